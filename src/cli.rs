@@ -1,120 +1,2364 @@
-use crate::core::{self, Candidate, CleanupResult, ScanConfig};
-use clap::Parser;
+use crate::core::daemon::{DaemonPass, DaemonStatus};
+use crate::core::doctor::{self, CheckStatus};
+use crate::core::history::Period;
+use crate::core::lastscan::{SavedScan, SavedSkip, ScanSettings, ScanTimings};
+use crate::core::policy::Policy;
+use crate::core::schedule::Frequency;
+use crate::core::summary::GroupSummary;
+use crate::core::watch::WatchSnapshot;
+use crate::core::{
+    self, AgeOverride, Candidate, CleanupOptions, CleanupOrder, CleanupResult, CleanupStrategy,
+    DevstripError, DevstripEvent, Excludes, ScanConfig, ScanProfile, SkippedPath, SymlinkPolicy,
+};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use human_bytes::human_bytes;
-use std::io::{self, IsTerminal, Write};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Once};
 use std::thread;
-use std::time::Duration;
-use std::{env, u32};
+use std::time::{Duration, Instant, SystemTime};
 
 pub fn run() {
     if let Err(err) = real_main() {
         eprintln!("Error: {}", err);
-        process::exit(1);
+        process::exit(err.exit_code());
     }
 }
 
-type Result<T> = std::result::Result<T, String>;
+type Result<T> = std::result::Result<T, CliError>;
+
+/// Why a run ended early. Core errors choose their own exit code; a run
+/// that completed but left failures behind exits with 1.
+#[derive(Debug)]
+enum CliError {
+    Core(DevstripError),
+    Failed(String),
+    /// `--check` found more reclaimable space than `--threshold`.
+    ThresholdExceeded(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Core(err) => err.exit_code(),
+            CliError::Failed(_) => 1,
+            CliError::ThresholdExceeded(_) => 3,
+        }
+    }
+}
+
+impl From<DevstripError> for CliError {
+    fn from(err: DevstripError) -> Self {
+        CliError::Core(err)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Core(err) => err.fmt(f),
+            CliError::Failed(message) | CliError::ThresholdExceeded(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Developer disk cleanup tool (CLI)", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    scan: ScanArgs,
+    #[command(flatten)]
+    clean: CleanArgs,
+    #[command(flatten)]
+    global: GlobalArgs,
+}
+
+/// Flags shared by every subcommand.
+#[derive(clap::Args, Debug)]
+struct GlobalArgs {
+    #[arg(long = "no-color", global = true)]
+    no_color: bool,
+    #[arg(long = "plain", global = true)]
+    plain: bool,
+    #[arg(long = "home", value_name = "PATH", global = true)]
+    home: Option<PathBuf>,
+    #[arg(long = "background", global = true)]
+    background: bool,
+    #[arg(
+        long = "columns",
+        value_name = "COLUMN",
+        value_enum,
+        value_delimiter = ',',
+        global = true
+    )]
+    columns: Vec<Column>,
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t = OutputFormat::Table,
+        global = true
+    )]
+    format: OutputFormat,
+    #[arg(
+        long = "sort",
+        value_name = "KEY",
+        value_enum,
+        default_value_t = ReportSort::Size,
+        global = true
+    )]
+    sort: ReportSort,
+    #[arg(long = "top", value_name = "N", global = true)]
+    top: Option<usize>,
+    #[arg(long = "notify", global = true)]
+    notify: bool,
+    #[arg(long = "progress", value_name = "FORMAT", value_enum, global = true)]
+    progress: Option<ProgressFormat>,
+    #[arg(long = "summary", global = true)]
+    summary: bool,
+}
+
+/// What to scan and which targets count as stale.
+#[derive(clap::Args, Debug)]
+struct ScanArgs {
     #[arg(long = "roots", value_name = "PATH", num_args = 1..)]
     roots: Vec<PathBuf>,
     #[arg(value_name = "PATH")]
     positional_roots: Vec<PathBuf>,
     #[arg(short = 'x', long = "exclude", value_name = "PATH")]
     excludes: Vec<PathBuf>,
+    #[arg(long = "exclude-glob", value_name = "GLOB", value_parser = parse_glob)]
+    exclude_globs: Vec<String>,
     #[arg(long = "min-age-days", default_value_t = 2)]
     min_age_days: u64,
+    #[arg(long = "age", value_name = "TARGET=AGE", value_parser = parse_age_override)]
+    ages: Vec<AgeOverride>,
     #[arg(long = "max-depth", default_value_t = 5)]
     max_depth: u32,
     #[arg(long = "keep-latest-derived", default_value_t = 1)]
     keep_latest_derived: usize,
     #[arg(long = "keep-latest-cache", default_value_t = 1)]
     keep_latest_cache: usize,
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+    #[arg(long = "preview-all", conflicts_with = "all")]
+    preview_all: bool,
+    #[arg(long = "cargo-sweep")]
+    cargo_sweep: bool,
+    #[arg(long = "fast", conflicts_with_all = ["preview_all", "metrics_file", "min_size"])]
+    fast: bool,
+    #[arg(long = "no-cache")]
+    no_cache: bool,
+    #[arg(long = "profile")]
+    profile: bool,
+    #[arg(long = "same-filesystem")]
+    same_filesystem: bool,
+    #[arg(
+        long = "skip-active-repos",
+        value_name = "DAYS",
+        num_args = 0..=1,
+        default_missing_value = "7"
+    )]
+    skip_active_repos: Option<u64>,
+    #[arg(long = "symlinks", value_name = "POLICY", default_value = "skip")]
+    symlinks: SymlinkPolicy,
+    #[arg(long = "metrics-file", value_name = "PATH")]
+    metrics_file: Option<PathBuf>,
+    #[arg(long = "save-scan", value_name = "FILE")]
+    save_scan: Option<PathBuf>,
+    #[arg(
+        long = "downloads-installers",
+        value_name = "DAYS",
+        num_args = 0..=1,
+        default_missing_value = "30"
+    )]
+    downloads_installers: Option<u64>,
+    #[arg(long = "category", value_name = "CATEGORY")]
+    categories: Vec<String>,
+    #[arg(long = "pattern", value_name = "NAME", value_parser = parse_pattern)]
+    patterns: Vec<String>,
+    #[arg(long = "min-size", value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+    #[arg(long = "check", requires = "threshold", conflicts_with = "preview_all")]
+    check: bool,
+    #[arg(
+        long = "threshold",
+        value_name = "SIZE",
+        value_parser = parse_size,
+        requires = "check"
+    )]
+    threshold: Option<u64>,
+}
+
+/// How targets are removed.
+#[derive(clap::Args, Debug)]
+struct CleanArgs {
     #[arg(short = 'y', long = "yes")]
     yes: bool,
     #[arg(long = "dry-run")]
     dry_run: bool,
-    #[arg(long = "no-color")]
-    no_color: bool,
-    #[arg(short = 'a', long = "all")]
-    all: bool,
+    #[arg(long = "sandboxed-delete")]
+    sandboxed_delete: bool,
+    #[arg(long = "trash", conflicts_with = "sandboxed_delete")]
+    trash: bool,
+    #[arg(long = "min-path-depth", value_name = "N")]
+    min_path_depth: Option<usize>,
+    #[arg(long = "size-cap", value_name = "GIB")]
+    size_cap: Option<u64>,
+    #[arg(long = "force-large")]
+    force_large: bool,
+    #[arg(long = "force")]
+    force: bool,
+    #[arg(long = "jobs", value_name = "N", default_value_t = core::DEFAULT_CLEANUP_WORKERS)]
+    jobs: usize,
+    #[arg(long = "order", value_name = "ORDER", default_value = "stalest")]
+    order: CleanupOrder,
+    #[arg(
+        long = "compress",
+        value_name = "CATEGORY",
+        num_args = 0..,
+        value_delimiter = ','
+    )]
+    compress: Option<Vec<String>>,
+    #[arg(
+        long = "free",
+        value_name = "SIZE",
+        value_parser = parse_size,
+        conflicts_with = "trash"
+    )]
+    free: Option<u64>,
+    /// Continue a cleanup that was stopped or crashed, instead of cleaning the saved scan
+    #[arg(long = "resume", conflicts_with_all = ["free", "compress"])]
+    resume: bool,
+    /// Clean the targets of a copy of a saved scan instead of the last one
+    #[arg(long = "from-scan", value_name = "FILE", conflicts_with = "resume")]
+    from_scan: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Scan for cleanup targets and save them for `report` and `clean`, without removing anything
+    Scan(ScanArgs),
+    /// Remove the targets the last `scan` saved
+    Clean(CleanArgs),
+    /// Show the targets the last `scan` saved
+    Report {
+        /// Write a standalone HTML page with the targets, charts and scan settings instead
+        #[arg(long = "html", value_name = "FILE")]
+        html: Option<PathBuf>,
+    },
+    /// Show the settings a scan with the given flags would use
+    Config(ScanArgs),
+    /// Check environment prerequisites and print remediation hints
+    Doctor,
+    /// Manage custom cleanup rules
+    Rules {
+        #[command(subcommand)]
+        action: RulesCommand,
+    },
+    /// Show what past cleanup runs removed, newest first
+    Log {
+        /// Number of entries to show
+        #[arg(long = "limit", default_value_t = 50)]
+        limit: usize,
+    },
+    /// Show how much space past cleanups reclaimed over time
+    History {
+        /// Group runs by day, week or month
+        #[arg(long = "by", value_name = "PERIOD", default_value = "month")]
+        by: Period,
+    },
+    /// Unpack an archive written by --compress back to its original location
+    Restore {
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+    },
+    /// Scan every --interval until stopped, optionally cleaning up after each scan
+    Daemon(DaemonArgs),
+    /// Show what the daemon last did and when it runs next
+    Status,
+    /// Rescan every --interval and report which targets grew or shrank
+    Watch(WatchArgs),
+    /// Compare two scans saved by `scan` or written by --format json
+    Diff {
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+    /// Run devstrip on a schedule through launchd, a systemd user timer or cron
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleCommand,
+    },
+}
+
+/// `devstrip watch`: the scan options pick what is tracked.
+#[derive(clap::Args, Debug)]
+struct WatchArgs {
+    /// Time between passes, e.g. 30m, 6h or 1d
+    #[arg(
+        long = "interval",
+        value_name = "DURATION",
+        value_parser = parse_interval,
+        default_value = "1h"
+    )]
+    interval: Duration,
+    /// Run one pass, compare it with the last one recorded, and exit
+    #[arg(long = "once")]
+    once: bool,
+    /// Leave out groups that changed by less than this
+    #[arg(
+        long = "min-change",
+        value_name = "SIZE",
+        value_parser = parse_size,
+        default_value = "10MB"
+    )]
+    min_change: u64,
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum ScheduleCommand {
+    /// Register a job that runs `devstrip scan`, or the arguments after `--`
+    Install(ScheduleArgs),
+    /// Unregister the job `install` set up
+    Remove,
+    /// Show the installed job and whether it is loaded
+    Status,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("frequency").required(true).args(["daily", "weekly"])))]
+struct ScheduleArgs {
+    /// Run every day at 03:00
+    #[arg(long = "daily")]
+    daily: bool,
+    /// Run every Sunday at 03:00
+    #[arg(long = "weekly")]
+    weekly: bool,
+    /// What the job runs instead of `scan`, e.g. `-- clean --yes --trash`
+    #[arg(last = true, value_name = "ARGS")]
+    args: Vec<String>,
+}
+
+/// `devstrip daemon`: the scan and cleanup options apply to every pass.
+#[derive(clap::Args, Debug)]
+struct DaemonArgs {
+    /// Time between passes, e.g. 30m, 6h or 1d
+    #[arg(
+        long = "interval",
+        value_name = "DURATION",
+        value_parser = parse_interval,
+        default_value = "24h"
+    )]
+    interval: Duration,
+    /// Also remove what each pass finds; needs --yes
+    #[arg(long = "clean", requires = "yes")]
+    clean: bool,
+    #[command(flatten)]
+    scan: ScanArgs,
+    #[command(flatten)]
+    cleanup: CleanArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum RulesCommand {
+    /// Report what the rules in a file would match, without deleting anything
+    Test {
+        #[arg(value_name = "RULE_FILE")]
+        rule_file: PathBuf,
+        #[arg(long = "roots", value_name = "PATH", num_args = 1..)]
+        roots: Vec<PathBuf>,
+        #[arg(short = 'x', long = "exclude", value_name = "PATH")]
+        excludes: Vec<PathBuf>,
+        #[arg(long = "min-age-days", default_value_t = 2)]
+        min_age_days: u64,
+        #[arg(long = "max-depth", default_value_t = 5)]
+        max_depth: u32,
+    },
 }
 
 fn real_main() -> Result<()> {
-    let args = Args::parse();
-    let styler = TerminalStyler::new(args.no_color);
-    let config = build_scan_config(&args)?;
-    let candidates = run_with_spinner("Scanning for cleanup candidates", &styler, {
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if args.command.is_some() {
+        reject_misplaced_flags(&matches);
+    }
+    let global = &args.global;
+    let mut styler = TerminalStyler::new(global.no_color, global.plain);
+    styler.quiet = global.format == OutputFormat::Json;
+    styler.json_progress = global.progress == Some(ProgressFormat::Json);
+    if global.background && !core::lower_current_thread() {
+        eprintln!(
+            "{}",
+            styler.warning("Could not lower the priority; continuing at normal priority.")
+        );
+    }
+    if let Some(home) = &global.home {
+        core::set_home_override(&expand_path(home))?;
+    }
+    match &args.command {
+        Some(Command::Scan(scan)) => run_scan(scan, global, &styler),
+        Some(Command::Clean(clean)) => run_clean(clean, global, &styler),
+        Some(Command::Report { html }) => run_report(html.as_deref(), global, &styler),
+        Some(Command::Config(scan)) => run_config(scan, global, &styler),
+        Some(Command::Doctor) => run_doctor(&styler),
+        Some(Command::Rules {
+            action:
+                RulesCommand::Test {
+                    rule_file,
+                    roots,
+                    excludes,
+                    min_age_days,
+                    max_depth,
+                },
+        }) => {
+            let exclude_paths = Excludes::new(core::normalize_paths(&expand_paths(excludes)));
+            let config = ScanConfig {
+                roots: core::default_roots(&expand_paths(roots), &exclude_paths)?,
+                min_age_days: *min_age_days,
+                max_depth: (*max_depth).max(1),
+                keep_latest_derived: 0,
+                keep_latest_cache: 0,
+                exclude_paths,
+                exclude_globs: Vec::new(),
+                cargo_sweep: false,
+                custom_rules: Vec::new(),
+                extra_patterns: Vec::new(),
+                age_overrides: Vec::new(),
+                downloads_min_age_days: None,
+                baseline: None,
+                same_filesystem: false,
+                active_repo_days: None,
+                symlinks: SymlinkPolicy::Skip,
+                size_cache: false,
+                lazy_sizes: false,
+                low_priority: global.background,
+                incremental: false,
+                profile: false,
+                keep_nested: false,
+            };
+            run_rules_test(&expand_path(rule_file), &config, &styler)
+        }
+        Some(Command::Log { limit }) => run_log(*limit, &styler),
+        Some(Command::History { by }) => run_history(*by, global, &styler),
+        Some(Command::Restore { archive }) => {
+            let restored = core::compress::restore(&expand_path(archive))?;
+            println!(
+                "{}",
+                styler.success(&format!("Restored {}", restored.display()))
+            );
+            Ok(())
+        }
+        Some(Command::Daemon(daemon)) => run_daemon(daemon, global, &styler),
+        Some(Command::Status) => run_status(global, &styler),
+        Some(Command::Watch(watch)) => run_watch(watch, global, &styler),
+        Some(Command::Diff { old, new }) => run_diff(old, new, global, &styler),
+        Some(Command::Schedule { action }) => match action {
+            ScheduleCommand::Install(schedule) => run_schedule_install(schedule, global, &styler),
+            ScheduleCommand::Remove => run_schedule_remove(&styler),
+            ScheduleCommand::Status => run_schedule_status(&styler),
+        },
+        None => run_once(&args.scan, &args.clean, global, &styler),
+    }
+}
+
+/// The flags of a run without a subcommand would be silently ignored in
+/// front of one, so they are refused there. Global flags go anywhere.
+fn reject_misplaced_flags(matches: &ArgMatches) {
+    let mut command = Args::command();
+    let misplaced = command.get_arguments().find(|arg| {
+        !arg.is_global_set()
+            && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+    });
+    let Some(arg) = misplaced else {
+        return;
+    };
+    let name = match arg.get_long() {
+        Some(long) => format!("--{}", long),
+        None => arg.get_id().to_string(),
+    };
+    command
+        .error(
+            ErrorKind::ArgumentConflict,
+            format!(
+                "{} cannot be given before a subcommand; pass it after the subcommand that takes it",
+                name
+            ),
+        )
+        .exit();
+}
+
+/// Scans, lists the targets, and cleans them after asking: what `devstrip`
+/// does without a subcommand.
+fn run_once(
+    scan: &ScanArgs,
+    clean: &CleanArgs,
+    global: &GlobalArgs,
+    styler: &TerminalStyler,
+) -> Result<()> {
+    if clean.resume || clean.from_scan.is_some() {
+        return run_clean(clean, global, styler);
+    }
+    if global.format == OutputFormat::Json && !clean.dry_run && !scan.preview_all && !scan.check {
+        return Err(DevstripError::config(
+            None,
+            "--format json only lists targets. Add --dry-run, or run `devstrip scan --format json` and then `devstrip clean`.",
+        )
+        .into());
+    }
+    // Held until the run ends, so a scheduled run and a GUI session never
+    // scan and delete at the same time.
+    let _lock = core::InstanceLock::acquire()?;
+    let policy = check_policy(scan, styler)?;
+    check_free_policy(clean, policy)?;
+    let Some(mut scanned) = scan_and_report(scan, global, clean.compress.as_deref(), styler)?
+    else {
+        return Ok(());
+    };
+    // A cleanup that needs no confirmation posts its own notification.
+    if clean.dry_run || !clean.yes || scan.threshold.is_some() || scanned.candidates.is_empty() {
+        notify(global, &scan_summary(&scanned.candidates), styler);
+    }
+    if let Some(threshold) = scan.threshold {
+        return check_threshold(&scanned.candidates, threshold, styler);
+    }
+    if let Some(target) = clean.free {
+        match select_for_free_space(
+            scanned.candidates,
+            target,
+            &scanned.roots,
+            clean,
+            global,
+            styler,
+        )? {
+            Some(selected) => scanned.candidates = selected,
+            None => return Ok(()),
+        }
+    }
+    if scanned.candidates.is_empty() {
+        return Ok(());
+    }
+    if clean.dry_run {
+        if !styler.quiet {
+            println!("{}", styler.dim("Dry-run: no files will be removed."));
+        }
+        return Ok(());
+    }
+    let use_trash = clean.trash || policy.map(|p| p.require_trash).unwrap_or(false);
+    let metrics = scan
+        .metrics_file
+        .as_deref()
+        .map(|path| (expand_path(path), scanned.duration));
+    note_pending_cleanup(styler);
+    match clean_candidates(scanned.candidates, clean, use_trash, metrics, styler)? {
+        Some(cleaned) => {
+            notify(global, &cleaned.summary(), styler);
+            cleaned.outcome()
+        }
+        None => Ok(()),
+    }
+}
+
+/// `devstrip scan`: lists the targets and saves them for `report` and
+/// `clean`. An empty result is saved too, so a later `clean` never works
+/// from an older scan.
+fn run_scan(scan: &ScanArgs, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let _lock = core::InstanceLock::acquire()?;
+    check_policy(scan, styler)?;
+    let Some(scanned) = scan_and_report(scan, global, None, styler)? else {
+        return Ok(());
+    };
+    core::lastscan::save(&scanned.roots, &scanned.candidates, Some(&scanned.settings))?;
+    if !scanned.candidates.is_empty() && !styler.quiet {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Saved {} target(s). Review them with `devstrip report`, remove them with `devstrip clean`.",
+                scanned.candidates.len()
+            ))
+        );
+    }
+    notify(global, &scan_summary(&scanned.candidates), styler);
+    if let Some(threshold) = scan.threshold {
+        return check_threshold(&scanned.candidates, threshold, styler);
+    }
+    Ok(())
+}
+
+/// `devstrip clean`: removes the targets of the last `devstrip scan`.
+/// Targets that changed since are skipped as in any cleanup; the ones
+/// removed are dropped from the saved scan.
+fn run_clean(clean: &CleanArgs, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    if global.format == OutputFormat::Json {
+        return Err(DevstripError::config(
+            None,
+            "--format json only lists targets; `devstrip report --format json` lists the saved scan.",
+        )
+        .into());
+    }
+    let _lock = core::InstanceLock::acquire()?;
+    let policy = core::policy::active_policy()?;
+    if let Some(policy) = policy {
+        println!("{}", styler.dim(&policy.summary()));
+    }
+    check_free_policy(clean, policy)?;
+    let require_trash = policy.is_some_and(|p| p.require_trash);
+    if clean.resume {
+        return resume_cleanup(clean, require_trash, global, styler);
+    }
+    let from_scan = clean.from_scan.as_deref().map(expand_path);
+    let saved = match &from_scan {
+        Some(file) => core::lastscan::load_from(file)?,
+        None => load_saved_scan()?,
+    };
+    if saved.candidates.is_empty() {
+        println!(
+            "{}",
+            styler.warning("The saved scan has no cleanup targets left.")
+        );
+        return Ok(());
+    }
+    println!(
+        "{}",
+        styler.dim(&match &from_scan {
+            Some(file) => format!(
+                "Targets in {}, saved by the scan of {}:",
+                file.display(),
+                saved_scan_time(&saved)
+            ),
+            None => format!("Targets saved by the scan of {}:", saved_scan_time(&saved)),
+        })
+    );
+    let mut candidates = match &from_scan {
+        Some(_) => rescan_saved(&saved, styler)?,
+        None => saved.candidates.clone(),
+    };
+    if candidates.is_empty() {
+        println!(
+            "{}",
+            styler.warning("None of the targets in the file are cleanup targets any more.")
+        );
+        return Ok(());
+    }
+    sort_report(&mut candidates, global);
+    if let Some(categories) = &clean.compress {
+        core::compress_instead_of_removing(&mut candidates, categories);
+    }
+    print_cli_report(top_of(&candidates, global, styler), &global.columns, styler);
+    if let Some(target) = clean.free {
+        match select_for_free_space(candidates, target, &saved.roots, clean, global, styler)? {
+            Some(selected) => candidates = selected,
+            None => return Ok(()),
+        }
+    }
+    if clean.dry_run {
+        println!("{}", styler.dim("Dry-run: no files will be removed."));
+        return Ok(());
+    }
+    let use_trash = clean.trash || require_trash;
+    note_pending_cleanup(styler);
+    let Some(cleaned) = clean_candidates(candidates, clean, use_trash, None, styler)? else {
+        return Ok(());
+    };
+    // The file given is left as it is; the saved scan still loses what
+    // was removed.
+    match from_scan {
+        Some(_) => {
+            if let Some(last) = core::lastscan::load()? {
+                forget_cleaned(&last, &cleaned.results)?;
+            }
+        }
+        None => forget_cleaned(&saved, &cleaned.results)?,
+    }
+    notify(global, &cleaned.summary(), styler);
+    cleaned.outcome()
+}
+
+/// The targets of a `--from-scan` file that a scan with its roots and
+/// settings finds again, listing the ones left out.
+fn rescan_saved(saved: &SavedScan, styler: &TerminalStyler) -> Result<Vec<Candidate>> {
+    let custom_rules = core::rules::load_user_rules()?;
+    let saved = saved.clone();
+    let rescanned = run_with_spinner("Checking the targets on disk", styler, move |_| {
+        Ok(saved.rescan(custom_rules)?)
+    })?;
+    for (path, reason) in &rescanned.dropped {
+        println!(
+            "{}",
+            styler.warning(&format!("Left out {}: {}", path.display(), reason))
+        );
+    }
+    Ok(rescanned.candidates)
+}
+
+/// `devstrip clean --resume`: cleans what a stopped or crashed cleanup
+/// left, checking each target again as any cleanup does.
+fn resume_cleanup(
+    clean: &CleanArgs,
+    require_trash: bool,
+    global: &GlobalArgs,
+    styler: &TerminalStyler,
+) -> Result<()> {
+    let Some(pending) = core::resume::load()? else {
+        println!(
+            "{}",
+            styler.warning("There is no interrupted cleanup to resume.")
+        );
+        return Ok(());
+    };
+    println!(
+        "{}",
+        styler.dim(&format!(
+            "Targets left by the cleanup of {}:",
+            short_time(&pending.started_at)
+        ))
+    );
+    let use_trash = pending.trash || clean.trash || require_trash;
+    let mut candidates = pending.into_candidates();
+    sort_report(&mut candidates, global);
+    print_cli_report(top_of(&candidates, global, styler), &global.columns, styler);
+    if clean.dry_run {
+        println!("{}", styler.dim("Dry-run: no files will be removed."));
+        return Ok(());
+    }
+    let Some(cleaned) = clean_candidates(candidates, clean, use_trash, None, styler)? else {
+        return Ok(());
+    };
+    if let Some(saved) = core::lastscan::load()? {
+        forget_cleaned(&saved, &cleaned.results)?;
+    }
+    notify(global, &cleaned.summary(), styler);
+    cleaned.outcome()
+}
+
+/// Drops the targets that `results` removed from the saved scan.
+fn forget_cleaned(saved: &SavedScan, results: &[CleanupResult]) -> Result<()> {
+    let removed: HashSet<&Path> = results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| r.candidate.path.as_path())
+        .collect();
+    let left: Vec<Candidate> = saved
+        .candidates
+        .iter()
+        .filter(|candidate| !removed.contains(candidate.path.as_path()))
+        .cloned()
+        .collect();
+    core::lastscan::save(&saved.roots, &left, saved.settings.as_ref())?;
+    Ok(())
+}
+
+/// Points out a cleanup that never finished before a new one replaces it.
+fn note_pending_cleanup(styler: &TerminalStyler) {
+    if let Ok(Some(pending)) = core::resume::load() {
+        println!(
+            "{}",
+            styler.warning(&format!(
+                "The cleanup of {} stopped with {} target(s) left; `devstrip clean --resume` continues it instead.",
+                short_time(&pending.started_at),
+                pending.candidates.len()
+            ))
+        );
+    }
+}
+
+/// `devstrip report`: lists the targets of the last `devstrip scan`
+/// without scanning again.
+fn run_report(html: Option<&Path>, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let mut saved = load_saved_scan()?;
+    if let Some(file) = html {
+        let file = expand_path(file);
+        sort_report(&mut saved.candidates, global);
+        core::html::write_html(&file, &saved, global.top)?;
+        if !styler.quiet {
+            println!(
+                "{}",
+                styler.success(&format!(
+                    "Wrote the report of {} target(s) to {}.",
+                    saved.candidates.len(),
+                    file.display()
+                ))
+            );
+        }
+        return Ok(());
+    }
+    if global.format == OutputFormat::Json {
+        sort_report(&mut saved.candidates, global);
+        let scanned_at = saved.scanned_at().unwrap_or_else(SystemTime::now);
+        println!(
+            "{}",
+            core::export::render_json(
+                top_of(&saved.candidates, global, styler),
+                &saved.roots,
+                scanned_at
+            )
+        );
+        return Ok(());
+    }
+    println!(
+        "{}",
+        styler.dim(&format!(
+            "Scan of {} from {}:",
+            saved
+                .roots
+                .iter()
+                .map(|root| root.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            saved_scan_time(&saved)
+        ))
+    );
+    if saved.candidates.is_empty() {
+        println!(
+            "{}",
+            styler.warning("The saved scan has no cleanup targets left.")
+        );
+        return Ok(());
+    }
+    sort_report(&mut saved.candidates, global);
+    print_cli_report(
+        top_of(&saved.candidates, global, styler),
+        &global.columns,
+        styler,
+    );
+    if global.summary {
+        print_group_summaries(&saved.candidates, &saved.roots, styler);
+    }
+    Ok(())
+}
+
+/// `devstrip config`: the settings a scan with the same flags would use,
+/// and where devstrip keeps its files.
+fn run_config(scan: &ScanArgs, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let config = build_scan_config(scan, global.background)?;
+    let or_none = |paths: &[PathBuf]| {
+        if paths.is_empty() {
+            "none".to_string()
+        } else {
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    };
+    let location = |path: Option<PathBuf>| {
+        path.map(|path| path.display().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+    let settings = [
+        ("Roots", or_none(&config.roots)),
+        ("Excludes", or_none(&config.exclude_paths)),
+        (
+            "Exclude globs",
+            if config.exclude_globs.is_empty() {
+                "none".to_string()
+            } else {
+                config.exclude_globs.join(", ")
+            },
+        ),
+        ("Minimum age", format!("{} day(s)", config.min_age_days)),
+        (
+            "Age overrides",
+            if config.age_overrides.is_empty() {
+                "none".to_string()
+            } else {
+                config
+                    .age_overrides
+                    .iter()
+                    .map(|age| format!("{}={}d", age.target, age.days))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+        ),
+        ("Maximum depth", config.max_depth.to_string()),
+        (
+            "Minimum size",
+            scan.min_size
+                .map(humanize_bytes)
+                .unwrap_or_else(|| "none".to_string()),
+        ),
+        (
+            "Keep latest",
+            format!(
+                "{} DerivedData, {} cache",
+                config.keep_latest_derived, config.keep_latest_cache
+            ),
+        ),
+        ("Symlinks", config.symlinks.label().to_string()),
+        (
+            "Categories",
+            if scan.categories.is_empty() {
+                "all".to_string()
+            } else {
+                scan.categories.join(", ")
+            },
+        ),
+        ("Custom rules", config.custom_rules.len().to_string()),
+        (
+            "Extra patterns",
+            if config.extra_patterns.is_empty() {
+                "none".to_string()
+            } else {
+                config.extra_patterns.join(", ")
+            },
+        ),
+        (
+            "Size cache",
+            if config.size_cache { "on" } else { "off" }.to_string(),
+        ),
+        ("Rules file", location(core::rules::rules_path())),
+        ("Config directory", location(core::config_dir())),
+        ("Data directory", location(core::data_dir())),
+        ("Saved scan", location(core::lastscan::last_scan_path())),
+    ];
+    let width = settings
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, value) in &settings {
+        println!("{} {}", styler.bold(&format!("{:<width$}", name)), value);
+    }
+    if let Some(policy) = core::policy::active_policy()? {
+        println!("{}", styler.dim(&policy.summary()));
+    }
+    Ok(())
+}
+
+/// Prints the managed policy in effect and refuses the deep scans it
+/// disables.
+/// Drops the candidates smaller than `--min-size`, saying how many.
+fn retain_min_size(candidates: &mut Vec<Candidate>, min_size: u64, styler: &TerminalStyler) {
+    let before = candidates.len();
+    candidates.retain(|candidate| candidate.size_bytes >= min_size);
+    if candidates.len() < before && !styler.quiet {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Left out {} target(s) smaller than {}.",
+                before - candidates.len(),
+                humanize_bytes(min_size)
+            ))
+        );
+    }
+}
+
+fn check_policy(scan: &ScanArgs, styler: &TerminalStyler) -> Result<Option<&'static Policy>> {
+    let policy = core::policy::active_policy()?;
+    if let Some(policy) = policy {
+        if !styler.quiet {
+            println!("{}", styler.dim(&policy.summary()));
+        }
+        if policy.disable_deep_scan && (scan.all || scan.preview_all) {
+            return Err(DevstripError::config(
+                Some(&policy.source),
+                "Deep scans (--all, --preview-all) are disabled by the managed policy.",
+            )
+            .into());
+        }
+    }
+    Ok(policy)
+}
+
+/// Refuses `--free` under a managed policy that sends removals to the
+/// Trash, as `--trash` is refused with it: nothing is freed until the Trash
+/// is emptied.
+fn check_free_policy(clean: &CleanArgs, policy: Option<&Policy>) -> Result<()> {
+    match policy {
+        Some(policy) if policy.require_trash && clean.free.is_some() => Err(DevstripError::config(
+            Some(&policy.source),
+            "--free cannot be used while the managed policy sends removals to the Trash, which frees nothing until it is emptied.",
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+fn load_saved_scan() -> Result<SavedScan> {
+    core::lastscan::load()?.ok_or_else(|| {
+        CliError::Failed("No scan has been saved yet; run `devstrip scan` first.".to_string())
+    })
+}
+
+fn saved_scan_time(saved: &SavedScan) -> String {
+    short_time(&saved.timestamp)
+}
+
+/// An RFC 3339 timestamp to the minute, as in `2026-10-16 20:04`.
+fn short_time(timestamp: &str) -> String {
+    let timestamp = timestamp.replace('T', " ");
+    timestamp.get(..16).unwrap_or(&timestamp).to_string()
+}
+
+fn now_rfc3339() -> String {
+    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+}
+
+/// A finished scan, its targets measured and listed.
+struct Scanned {
+    roots: Vec<PathBuf>,
+    candidates: Vec<Candidate>,
+    duration: Duration,
+    settings: ScanSettings,
+}
+
+/// What a scan found and ran into, before any of it is printed.
+struct RawScan {
+    candidates: Vec<Candidate>,
+    skipped: Vec<SkippedPath>,
+    unmatched: Vec<PathBuf>,
+    profile: Option<ScanProfile>,
+}
+
+fn scan_with_spinner(config: &ScanConfig, styler: &TerminalStyler) -> Result<RawScan> {
+    run_with_spinner("Scanning for cleanup candidates", styler, {
         let config = config.clone();
+        let json_progress = styler.json_progress;
         move |reporter| {
-            Ok(core::scan_with_callback(&config, |message| {
-                reporter.update(message)
-            }))
+            let mut skipped = Vec::new();
+            let mut unmatched = Vec::new();
+            let mut estimate = None;
+            let mut profile = None;
+            let mut json = json_progress.then(JsonProgress::default);
+            let candidates = core::scan_with_events(&config, |event| {
+                if let Some(json) = &mut json {
+                    json.scan_event(&event);
+                }
+                match &event {
+                    DevstripEvent::CandidateFound(candidate) => reporter.line(format!(
+                        "Found {:>10} {}",
+                        size_text(candidate),
+                        candidate.display_name()
+                    )),
+                    DevstripEvent::ScanEstimate(latest) => estimate = Some(latest.describe()),
+                    DevstripEvent::PathSkipped(path) => skipped.push(path.clone()),
+                    DevstripEvent::UnmatchedExclude { path } => unmatched.push(path.clone()),
+                    DevstripEvent::ScanProfile(collected) => profile = Some(collected.clone()),
+                    _ => {}
+                }
+                if let Some(text) = event.status_text() {
+                    match (&estimate, &event) {
+                        (Some(estimate), DevstripEvent::Scanning { .. }) => {
+                            reporter.update(format!("[{}] {}", estimate, text))
+                        }
+                        _ => reporter.update(text),
+                    }
+                }
+            });
+            skipped.sort_by(|a, b| a.path.cmp(&b.path));
+            skipped.dedup_by(|a, b| a.path == b.path);
+            Ok(RawScan {
+                candidates,
+                skipped,
+                unmatched,
+                profile,
+            })
         }
-    })?;
+    })
+}
 
-    if candidates.is_empty() {
+/// Scans with a spinner, then lists what it found. `None` for a preview
+/// scan, which only lists.
+fn scan_and_report(
+    scan: &ScanArgs,
+    global: &GlobalArgs,
+    compress: Option<&[String]>,
+    styler: &TerminalStyler,
+) -> Result<Option<Scanned>> {
+    let mut config = build_scan_config(scan, global.background)?;
+    if scan.preview_all {
+        config = config.deep_preview();
+    }
+    let metrics_file = scan.metrics_file.as_deref().map(expand_path);
+    let scan_started = Instant::now();
+    let RawScan {
+        mut candidates,
+        skipped,
+        unmatched,
+        profile,
+    } = scan_with_spinner(&config, styler)?;
+    let scan_duration = scan_started.elapsed();
+    if let Some(profile) = &profile {
+        print_scan_profile(profile, scan_duration, styler);
+    }
+    if !scan.categories.is_empty() {
+        let missing = core::retain_categories(&mut candidates, &scan.categories);
+        if !styler.quiet {
+            print_missing_categories(&missing, styler);
+        }
+    }
+    if let Some(min_size) = scan.min_size {
+        retain_min_size(&mut candidates, min_size, styler);
+    }
+    core::drop_nested_candidates(&mut candidates);
+    sort_report(&mut candidates, global);
+    if let Some(categories) = compress {
+        core::compress_instead_of_removing(&mut candidates, categories);
+    }
+    if let Some(path) = &metrics_file {
+        core::metrics::write_textfile(path, &candidates, &[], scan_duration)?;
+    }
+
+    if global.format == OutputFormat::Json {
+        if candidates.iter().any(|c| c.size_pending) {
+            candidates = measure_pending(candidates, styler)?;
+        }
+        println!(
+            "{}",
+            core::export::render_json(
+                top_of(&candidates, global, styler),
+                &config.roots,
+                SystemTime::now()
+            )
+        );
+    } else if scan.check {
+        if candidates.iter().any(|c| c.size_pending) {
+            candidates = measure_pending(candidates, styler)?;
+        }
+        print_category_summary(&candidates, styler);
+    } else if candidates.is_empty() {
         println!("{}", styler.warning("No safe cleanup targets were found."));
+        print_skipped_paths(&skipped, styler);
+        print_unmatched_excludes(&unmatched, styler);
+    } else if scan.preview_all {
+        print_deep_preview(top_of(&candidates, global, styler), &global.columns, styler);
+        print_skipped_paths(&skipped, styler);
+        print_unmatched_excludes(&unmatched, styler);
+    } else {
+        print_cli_report(top_of(&candidates, global, styler), &global.columns, styler);
+        print_skipped_paths(&skipped, styler);
+        print_unmatched_excludes(&unmatched, styler);
+        if candidates.iter().any(|c| c.size_pending) {
+            candidates = measure_pending(candidates, styler)?;
+        }
+        if global.summary {
+            print_group_summaries(&candidates, &config.roots, styler);
+        }
+    }
+    // The saved list is filtered, so the settings say by what.
+    let settings = ScanSettings {
+        categories: scan.categories.clone(),
+        min_size_bytes: scan.min_size,
+        ..ScanSettings::new(&config)
+    };
+    if let Some(file) = &scan.save_scan {
+        let file = expand_path(file);
+        let saved = SavedScan {
+            settings: Some(settings.clone()),
+            skipped: skipped.iter().map(SavedSkip::from).collect(),
+            timings: Some(ScanTimings::new(scan_duration, profile.as_ref())),
+            ..SavedScan::new(&config.roots, &candidates)
+        };
+        core::lastscan::write(&file, &saved)?;
+        if !styler.quiet {
+            println!(
+                "{}",
+                styler.dim(&format!("Saved the scan to {}.", file.display()))
+            );
+        }
+    }
+    if scan.preview_all && !candidates.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Scanned {
+        roots: config.roots,
+        candidates,
+        duration: scan_duration,
+        settings,
+    }))
+}
+
+/// `devstrip daemon`: scans every `--interval`, saving each scan as
+/// `devstrip scan` does and, with `--clean`, removing what it found. Every
+/// pass is logged to stdout and recorded in the status file that
+/// `devstrip status` reads. Meant to run under launchd, systemd or a
+/// similar service manager, which also restarts it.
+fn run_daemon(daemon: &DaemonArgs, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    if global.format == OutputFormat::Json {
+        return Err(DevstripError::config(
+            None,
+            "--format json only lists targets; `devstrip status --format json` reports on the daemon.",
+        )
+        .into());
+    }
+    if daemon.cleanup.resume || daemon.cleanup.from_scan.is_some() {
+        return Err(DevstripError::config(
+            None,
+            "--resume and --from-scan clean once; run `devstrip clean` with them instead.",
+        )
+        .into());
+    }
+    let _daemon_lock = core::InstanceLock::acquire_daemon()?;
+    let mut status = DaemonStatus {
+        pid: process::id(),
+        started_at: now_rfc3339(),
+        interval_secs: daemon.interval.as_secs(),
+        cleans: daemon.clean && !daemon.cleanup.dry_run,
+        ..DaemonStatus::default()
+    };
+    println!(
+        "{}",
+        styler.bold(&format!(
+            "devstrip daemon started (pid {}): {} every {}.",
+            status.pid,
+            if status.cleans {
+                "scanning and cleaning"
+            } else {
+                "scanning"
+            },
+            format_interval(status.interval_secs)
+        ))
+    );
+    loop {
+        let mut pass = DaemonPass {
+            started_at: now_rfc3339(),
+            ..DaemonPass::default()
+        };
+        if let Err(err) = daemon_pass(daemon, global, &mut pass, styler) {
+            pass.error = Some(err.to_string());
+        }
+        pass.finished_at = now_rfc3339();
+        let next = chrono::Duration::from_std(daemon.interval)
+            .ok()
+            .and_then(|interval| chrono::Local::now().checked_add_signed(interval));
+        status.passes += 1;
+        status.next_pass =
+            next.map(|next| next.to_rfc3339_opts(chrono::SecondsFormat::Secs, false));
+        println!(
+            "[{}] {} Next pass at {}.",
+            short_time(&pass.finished_at),
+            describe_pass(&pass),
+            status
+                .next_pass
+                .as_deref()
+                .map_or_else(|| "never".to_string(), short_time)
+        );
+        notify(global, &describe_pass(&pass), styler);
+        status.last_pass = Some(pass);
+        if let Err(err) = core::daemon::save_status(&status) {
+            eprintln!("{}", styler.warning(&err.to_string()));
+        }
+        thread::sleep(daemon.interval);
+    }
+}
+
+/// One scan of the daemon, then the cleanup if it was asked for.
+fn daemon_pass(
+    daemon: &DaemonArgs,
+    global: &GlobalArgs,
+    pass: &mut DaemonPass,
+    styler: &TerminalStyler,
+) -> Result<()> {
+    // Taken per pass, so a GUI session can work between passes.
+    let _lock = core::InstanceLock::acquire()?;
+    let policy = check_policy(&daemon.scan, styler)?;
+    check_free_policy(&daemon.cleanup, policy)?;
+    let compress = daemon.cleanup.compress.as_deref();
+    let Some(scanned) = scan_and_report(&daemon.scan, global, compress, styler)? else {
+        return Ok(());
+    };
+    core::lastscan::save(&scanned.roots, &scanned.candidates, Some(&scanned.settings))?;
+    pass.candidates = scanned.candidates.len();
+    pass.reclaimable_bytes = core::scan_total_size(&scanned.candidates);
+    if !daemon.clean || daemon.cleanup.dry_run || scanned.candidates.is_empty() {
+        return Ok(());
+    }
+    let mut candidates = scanned.candidates.clone();
+    if let Some(target) = daemon.cleanup.free {
+        match select_for_free_space(
+            candidates,
+            target,
+            &scanned.roots,
+            &daemon.cleanup,
+            global,
+            styler,
+        )? {
+            Some(selected) => candidates = selected,
+            None => return Ok(()),
+        }
+    }
+    let use_trash = daemon.cleanup.trash || policy.map(|p| p.require_trash).unwrap_or(false);
+    let metrics = daemon
+        .scan
+        .metrics_file
+        .as_deref()
+        .map(|path| (expand_path(path), scanned.duration));
+    let Some(cleaned) = clean_candidates(candidates, &daemon.cleanup, use_trash, metrics, styler)?
+    else {
+        return Ok(());
+    };
+    let removed: HashSet<&Path> = cleaned
+        .results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| r.candidate.path.as_path())
+        .collect();
+    pass.removed = removed.len();
+    pass.removed_bytes = cleaned
+        .results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| r.candidate.size_bytes)
+        .sum();
+    pass.failed = cleaned.results.len() - removed.len();
+    // The saved scan keeps only what is still there, as after `devstrip clean`.
+    let left: Vec<Candidate> = scanned
+        .candidates
+        .into_iter()
+        .filter(|candidate| !removed.contains(candidate.path.as_path()))
+        .collect();
+    core::lastscan::save(&scanned.roots, &left, Some(&scanned.settings))?;
+    cleaned.outcome()
+}
+
+/// `devstrip diff`: what appeared, disappeared, grew and shrank between
+/// two scans, and the totals per category.
+fn run_diff(old: &Path, new: &Path, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let old = core::diff::load_scan(&expand_path(old))?;
+    let new = core::diff::load_scan(&expand_path(new))?;
+    let diff = core::diff::diff_scans(old, new);
+    if global.format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&diff).unwrap_or_default()
+        );
         return Ok(());
     }
+    let category_width = diff.categories.keys().map(|c| c.len()).max().unwrap_or(0);
+    if !diff.appeared.is_empty() {
+        println!(
+            "{}",
+            styler.bold(&format!("Appeared ({}):", diff.appeared.len()))
+        );
+        for entry in &diff.appeared {
+            println!(
+                "  {} {:<width$} {}",
+                styler.warning(&format!(
+                    "{:>11}",
+                    format!("+{}", humanize_bytes(entry.size_bytes))
+                )),
+                entry.category,
+                entry.path.display(),
+                width = category_width
+            );
+        }
+    }
+    if !diff.disappeared.is_empty() {
+        println!(
+            "{}",
+            styler.bold(&format!("Disappeared ({}):", diff.disappeared.len()))
+        );
+        for entry in &diff.disappeared {
+            println!(
+                "  {} {:<width$} {}",
+                styler.success(&format!(
+                    "{:>11}",
+                    format!("-{}", humanize_bytes(entry.size_bytes))
+                )),
+                entry.category,
+                entry.path.display(),
+                width = category_width
+            );
+        }
+    }
+    for (title, entries) in [("Grew", &diff.grew), ("Shrank", &diff.shrank)] {
+        if entries.is_empty() {
+            continue;
+        }
+        println!(
+            "{}",
+            styler.bold(&format!("{} ({}):", title, entries.len()))
+        );
+        for entry in entries {
+            let change = size_change(entry.before, entry.after);
+            println!(
+                "  {} {:<width$} {} (now {})",
+                if entry.after > entry.before {
+                    styler.warning(&format!("{:>11}", change))
+                } else {
+                    styler.success(&format!("{:>11}", change))
+                },
+                entry.category,
+                entry.path.display(),
+                humanize_bytes(entry.after),
+                width = category_width
+            );
+        }
+    }
+    if diff.appeared.is_empty()
+        && diff.disappeared.is_empty()
+        && diff.grew.is_empty()
+        && diff.shrank.is_empty()
+    {
+        println!(
+            "{}",
+            styler.dim("The two scans found the same targets at the same sizes.")
+        );
+    }
+    println!("{}", styler.bold("By category:"));
+    for (category, totals) in &diff.categories {
+        println!(
+            "  {:<width$} {:>10} -> {:>10} ({})",
+            category,
+            humanize_bytes(totals.before),
+            humanize_bytes(totals.after),
+            size_change(totals.before, totals.after),
+            width = category_width
+        );
+    }
+    let totals = diff.totals();
+    println!(
+        "{}",
+        styler.bold(&format!(
+            "Total {} -> {} ({})",
+            humanize_bytes(totals.before),
+            humanize_bytes(totals.after),
+            size_change(totals.before, totals.after)
+        ))
+    );
+    Ok(())
+}
 
-    print_cli_report(&candidates, &styler);
+/// `+1.2 GiB`, `-300 MiB` or `0 B`.
+fn size_change(before: u64, after: u64) -> String {
+    match after.cmp(&before) {
+        std::cmp::Ordering::Greater => format!("+{}", humanize_bytes(after - before)),
+        std::cmp::Ordering::Less => format!("-{}", humanize_bytes(before - after)),
+        std::cmp::Ordering::Equal => humanize_bytes(0),
+    }
+}
 
-    if args.dry_run {
-        println!("{}", styler.dim("Dry-run: no files will be removed."));
+/// `devstrip watch`: rescans every `--interval` and reports how each
+/// group of targets, such as the `node_modules` under one root, changed
+/// since the last pass over the same roots. Passes are kept in the watch
+/// history, so a later run, or `--once` from a scheduler, picks up where
+/// the last one stopped.
+fn run_watch(watch: &WatchArgs, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    if global.format == OutputFormat::Json {
+        return Err(DevstripError::config(
+            None,
+            "--format json only lists targets; `devstrip watch` reports changes as text.",
+        )
+        .into());
+    }
+    let scan = &watch.scan;
+    let unsupported: Vec<&str> = [
+        ("--check", scan.check),
+        ("--threshold", scan.threshold.is_some()),
+        ("--save-scan", scan.save_scan.is_some()),
+        ("--metrics-file", scan.metrics_file.is_some()),
+        ("--preview-all", scan.preview_all),
+        ("--profile", scan.profile),
+    ]
+    .into_iter()
+    .filter_map(|(flag, given)| given.then_some(flag))
+    .collect();
+    if !unsupported.is_empty() {
+        return Err(DevstripError::config(
+            None,
+            format!(
+                "{} cannot be used with `devstrip watch`, which only compares passes; use `devstrip scan` for them.",
+                unsupported.join(", ")
+            ),
+        )
+        .into());
+    }
+    loop {
+        match watch_pass(watch, global, styler) {
+            Err(err) if !watch.once => eprintln!("{}", styler.warning(&err.to_string())),
+            result => result?,
+        }
+        if watch.once {
+            return Ok(());
+        }
+        thread::sleep(watch.interval);
+    }
+}
+
+fn watch_pass(watch: &WatchArgs, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let snapshot = {
+        let _lock = core::InstanceLock::acquire()?;
+        check_policy(&watch.scan, styler)?;
+        let config = build_scan_config(&watch.scan, global.background)?;
+        let mut candidates = scan_with_spinner(&config, styler)?.candidates;
+        if !watch.scan.categories.is_empty() {
+            core::retain_categories(&mut candidates, &watch.scan.categories);
+        }
+        core::drop_nested_candidates(&mut candidates);
+        if candidates.iter().any(|c| c.size_pending) {
+            candidates = measure_pending(candidates, styler)?;
+        }
+        if let Some(min_size) = watch.scan.min_size {
+            retain_min_size(&mut candidates, min_size, styler);
+        }
+        WatchSnapshot::new(&candidates, &config.roots)
+    };
+    let previous = core::watch::load_history()?
+        .into_iter()
+        .rev()
+        .find(|earlier| earlier.roots == snapshot.roots);
+    let Some(previous) = previous else {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Recorded a baseline of {} group(s), {} in total; changes show from the next pass.",
+                snapshot.groups.len(),
+                humanize_bytes(snapshot.total())
+            ))
+        );
+        return Ok(core::watch::record(&snapshot)?);
+    };
+    let since = since_text(&previous);
+    let changes = snapshot.changes_since(&previous, watch.min_change);
+    if changes.is_empty() {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Nothing changed by {} or more {}.",
+                humanize_bytes(watch.min_change),
+                since
+            ))
+        );
+    }
+    for change in &changes {
+        let amount = humanize_bytes(change.delta().unsigned_abs() as u64);
+        let line = if change.before == 0 {
+            styler.warning(&format!("{} appeared {} ({})", change.group, since, amount))
+        } else if change.after == 0 {
+            styler.success(&format!(
+                "{} is gone {} (was {})",
+                change.group, since, amount
+            ))
+        } else if change.delta() > 0 {
+            styler.warning(&format!(
+                "{} grew {} {} (now {})",
+                change.group,
+                amount,
+                since,
+                humanize_bytes(change.after)
+            ))
+        } else {
+            styler.success(&format!(
+                "{} shrank {} {} (now {})",
+                change.group,
+                amount,
+                since,
+                humanize_bytes(change.after)
+            ))
+        };
+        println!("{}", line);
+    }
+    let total = snapshot.total() as i128 - previous.total() as i128;
+    println!(
+        "{}",
+        styler.bold(&format!(
+            "In total {}, {}{} {}.",
+            humanize_bytes(snapshot.total()),
+            if total < 0 { "-" } else { "+" },
+            humanize_bytes(total.unsigned_abs() as u64),
+            since
+        ))
+    );
+    Ok(core::watch::record(&snapshot)?)
+}
+
+/// "since 14:05" for a pass earlier today, "since yesterday", or the date.
+fn since_text(previous: &WatchSnapshot) -> String {
+    let Some(taken_at) = previous.taken_at() else {
+        return "since the last pass".to_string();
+    };
+    match chrono::Local::now()
+        .date_naive()
+        .signed_duration_since(taken_at.date_naive())
+        .num_days()
+    {
+        0 => format!("since {}", taken_at.format("%H:%M")),
+        1 => "since yesterday".to_string(),
+        _ => format!("since {}", taken_at.format("%Y-%m-%d")),
+    }
+}
+
+/// Posts `body` as a desktop notification when `--notify` was given.
+fn notify(global: &GlobalArgs, body: &str, styler: &TerminalStyler) {
+    if global.notify && !core::notify::send("devstrip", body) {
+        eprintln!(
+            "{}",
+            styler
+                .warning("Could not post a desktop notification (on Linux, it needs notify-send).")
+        );
+    }
+}
+
+fn scan_summary(candidates: &[Candidate]) -> String {
+    if candidates.is_empty() {
+        return "Scan finished: no cleanup targets found.".to_string();
+    }
+    format!(
+        "Scan finished: {} target(s), {} reclaimable.",
+        candidates.len(),
+        humanize_bytes(core::scan_total_size(candidates))
+    )
+}
+
+fn describe_pass(pass: &DaemonPass) -> String {
+    let mut text = format!(
+        "{} target(s), {} reclaimable.",
+        pass.candidates,
+        humanize_bytes(pass.reclaimable_bytes)
+    );
+    if pass.removed > 0 || pass.failed > 0 {
+        text.push_str(&format!(
+            " Removed {} ({}), {} failed.",
+            pass.removed,
+            humanize_bytes(pass.removed_bytes),
+            pass.failed
+        ));
+    }
+    if let Some(error) = &pass.error {
+        text.push_str(&format!(" Stopped: {}", error));
+    }
+    text
+}
+
+/// `devstrip status`: what the daemon last did, from its status file.
+fn run_status(global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let running = core::daemon_running();
+    let Some(status) = core::daemon::load_status()? else {
+        if global.format == OutputFormat::Json {
+            println!("{}", serde_json::json!({ "running": running }));
+        } else {
+            println!("{}", styler.warning("No devstrip daemon has run yet."));
+        }
+        return Ok(());
+    };
+    if global.format == OutputFormat::Json {
+        let mut value = serde_json::to_value(&status).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.insert("running".to_string(), running.into());
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        );
         return Ok(());
     }
+    let mut settings = vec![
+        (
+            "Daemon",
+            if running {
+                format!("running (pid {})", status.pid)
+            } else {
+                format!("not running (was pid {})", status.pid)
+            },
+        ),
+        (
+            "Every",
+            format!(
+                "{}, {}",
+                format_interval(status.interval_secs),
+                if status.cleans {
+                    "scan and clean"
+                } else {
+                    "scan only"
+                }
+            ),
+        ),
+        ("Started", short_time(&status.started_at)),
+        ("Passes", status.passes.to_string()),
+    ];
+    if let Some(pass) = &status.last_pass {
+        settings.push((
+            "Last pass",
+            format!("{}: {}", short_time(&pass.finished_at), describe_pass(pass)),
+        ));
+    }
+    if let Some(next) = status.next_pass.as_deref().filter(|_| running) {
+        settings.push(("Next pass", short_time(next)));
+    }
+    let width = settings
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, value) in &settings {
+        println!("{} {}", styler.bold(&format!("{:<width$}", name)), value);
+    }
+    Ok(())
+}
+
+/// `devstrip schedule install`: the job runs this binary at background
+/// priority with the given arguments, checked here so that a typo fails
+/// now rather than every week in a log nobody reads.
+fn run_schedule_install(
+    schedule: &ScheduleArgs,
+    global: &GlobalArgs,
+    styler: &TerminalStyler,
+) -> Result<()> {
+    let mut args = schedule.args.clone();
+    if args.is_empty() {
+        args.push("scan".to_string());
+    }
+    let parsed = Args::try_parse_from(std::iter::once("devstrip".to_string()).chain(args.clone()))
+        .map_err(|err| {
+            DevstripError::config(
+                None,
+                format!(
+                    "The arguments to schedule are not valid: {}",
+                    err.to_string()
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .trim_start_matches("error: ")
+                ),
+            )
+        })?;
+    match &parsed.command {
+        Some(Command::Daemon(_)) | Some(Command::Schedule { .. }) => {
+            return Err(DevstripError::config(
+                None,
+                "A scheduled job runs once per period; schedule `scan` or `clean` instead.",
+            )
+            .into());
+        }
+        Some(Command::Clean(clean)) if !clean.yes && !clean.dry_run => {
+            return Err(DevstripError::config(None, "A scheduled cleanup needs --yes.").into());
+        }
+        None if !parsed.clean.yes && !parsed.clean.dry_run => {
+            return Err(DevstripError::config(None, "A scheduled cleanup needs --yes.").into());
+        }
+        _ => {}
+    }
+    let exe = env::current_exe().map_err(|err| DevstripError::Io {
+        path: None,
+        message: format!("Unable to locate the devstrip binary: {}", err),
+    })?;
+    let mut command = vec![exe.to_string_lossy().into_owned()];
+    command.push("--background".to_string());
+    command.push("--plain".to_string());
+    if let Some(home) = &global.home {
+        command.push("--home".to_string());
+        command.push(expand_path(home).to_string_lossy().into_owned());
+    }
+    command.extend(args);
+    let frequency = if schedule.daily {
+        Frequency::Daily
+    } else {
+        Frequency::Weekly
+    };
+    let installed = core::schedule::install(frequency, command)?;
+    println!(
+        "{}",
+        styler.success(&format!(
+            "Installed a {} {} running: {}",
+            installed.frequency.describe(),
+            installed.backend.describe(),
+            installed.command.join(" ")
+        ))
+    );
+    print_schedule_files(&installed, styler);
+    Ok(())
+}
+
+fn run_schedule_remove(styler: &TerminalStyler) -> Result<()> {
+    match core::schedule::remove()? {
+        Some(removed) => println!(
+            "{}",
+            styler.success(&format!("Removed the {}.", removed.backend.describe()))
+        ),
+        None => println!("{}", styler.warning("No devstrip schedule is installed.")),
+    }
+    Ok(())
+}
+
+fn run_schedule_status(styler: &TerminalStyler) -> Result<()> {
+    let Some(schedule) = core::schedule::load()? else {
+        println!("{}", styler.warning("No devstrip schedule is installed."));
+        return Ok(());
+    };
+    let state = if schedule.active() {
+        styler.success("loaded")
+    } else {
+        styler.warning("not loaded (run `devstrip schedule install` again)")
+    };
+    println!(
+        "{} {}, installed {}",
+        styler.bold(&format!(
+            "{} {}:",
+            capitalize(schedule.frequency.describe()),
+            schedule.backend.describe()
+        )),
+        state,
+        short_time(&schedule.installed_at)
+    );
+    println!("  runs: {}", schedule.command.join(" "));
+    print_schedule_files(&schedule, styler);
+    Ok(())
+}
+
+fn print_schedule_files(schedule: &core::schedule::Schedule, styler: &TerminalStyler) {
+    for file in &schedule.files {
+        println!("{}", styler.dim(&format!("  {}", file.display())));
+    }
+    if let Some(log) = &schedule.log {
+        println!("{}", styler.dim(&format!("  output: {}", log.display())));
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Narrows `candidates` for `--free`: the first ones in cleanup order that
+/// leave `target` bytes available on the filesystem of the first root.
+/// `None` when that much is free already.
+fn select_for_free_space(
+    candidates: Vec<Candidate>,
+    target: u64,
+    roots: &[PathBuf],
+    clean: &CleanArgs,
+    global: &GlobalArgs,
+    styler: &TerminalStyler,
+) -> Result<Option<Vec<Candidate>>> {
+    let Some(root) = roots.first().cloned().or_else(core::home_dir) else {
+        return Err(DevstripError::config(None, "--free needs a scan root to measure").into());
+    };
+    let Some(available) = core::available_space(&root) else {
+        return Err(DevstripError::config(
+            Some(&root),
+            format!("Unable to read the free space on {}", root.display()),
+        )
+        .into());
+    };
+    if available >= target {
+        if !styler.quiet {
+            println!(
+                "{}",
+                styler.success(&format!(
+                    "{} is already free on {}; nothing needs to be removed.",
+                    humanize_bytes(available),
+                    root.display()
+                ))
+            );
+        }
+        return Ok(None);
+    }
+    let needed = target - available;
+    // Only what is removed from the measured volume brings it closer to the
+    // target.
+    let (candidates, elsewhere): (Vec<Candidate>, Vec<Candidate>) = candidates
+        .into_iter()
+        .partition(|candidate| core::on_volume(&candidate.path, &root));
+    if !elsewhere.is_empty() && !styler.quiet {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Left out {} target(s) that are not on the same volume as {}.",
+                elsewhere.len(),
+                root.display()
+            ))
+        );
+    }
+    let total = candidates.len();
+    let selected = core::select_to_reclaim(candidates, needed, clean.order);
+    if styler.quiet {
+        return Ok(Some(selected));
+    }
+    let reclaimed = core::scan_total_size(&selected);
+    if reclaimed < needed {
+        println!(
+            "{}",
+            styler.warning(&format!(
+                "Removing every target frees {}, short of the {} needed for {} free on {}.",
+                humanize_bytes(reclaimed),
+                humanize_bytes(needed),
+                humanize_bytes(target),
+                root.display()
+            ))
+        );
+    } else {
+        println!(
+            "{}",
+            styler.bold(&format!(
+                "{} of {} target(s) ({}) bring {} from {} to {} free:",
+                selected.len(),
+                total,
+                humanize_bytes(reclaimed),
+                root.display(),
+                humanize_bytes(available),
+                humanize_bytes(target)
+            ))
+        );
+        print_cli_report(&selected, &global.columns, styler);
+    }
+    Ok(Some(selected))
+}
 
-    if !args.yes && !confirm_cleanup(&styler)? {
+/// `--check`: fails the run when more than `threshold` bytes could be
+/// reclaimed, so a build farm can gate on workspace debris.
+fn check_threshold(
+    candidates: &[Candidate],
+    threshold: u64,
+    styler: &TerminalStyler,
+) -> Result<()> {
+    let total = core::scan_total_size(candidates);
+    if total > threshold {
+        return Err(CliError::ThresholdExceeded(format!(
+            "{} reclaimable, above the {} threshold.",
+            humanize_bytes(total),
+            humanize_bytes(threshold)
+        )));
+    }
+    if !styler.quiet {
+        println!(
+            "{}",
+            styler.success(&format!(
+                "{} reclaimable, within the {} threshold.",
+                humanize_bytes(total),
+                humanize_bytes(threshold)
+            ))
+        );
+    }
+    Ok(())
+}
+
+/// The results of a cleanup that ran, already printed.
+struct Cleaned {
+    results: Vec<CleanupResult>,
+    failed: bool,
+    cancelled: bool,
+}
+
+impl Cleaned {
+    fn summary(&self) -> String {
+        let cleaned: Vec<&CleanupResult> = self.results.iter().filter(|r| r.success).collect();
+        let bytes: u64 = cleaned.iter().map(|r| r.candidate.size_bytes).sum();
+        let mut text = format!(
+            "Cleanup {}: cleaned {} target(s), {}.",
+            if self.cancelled {
+                "cancelled"
+            } else {
+                "finished"
+            },
+            cleaned.len(),
+            humanize_bytes(bytes)
+        );
+        let failed = self.results.len() - cleaned.len();
+        if failed > 0 {
+            text.push_str(&format!(" {} could not be cleaned.", failed));
+        }
+        text
+    }
+
+    /// How the run ends: cancelled, with failures, or successfully.
+    fn outcome(&self) -> Result<()> {
+        if self.cancelled {
+            return Err(DevstripError::Cancelled.into());
+        }
+        if self.failed {
+            return Err(CliError::Failed(
+                "One or more targets could not be removed.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Asks for confirmation unless `--yes` was given, then cleans `candidates`
+/// and prints what happened. `None` when the user declined.
+fn clean_candidates(
+    mut candidates: Vec<Candidate>,
+    clean: &CleanArgs,
+    use_trash: bool,
+    metrics: Option<(PathBuf, Duration)>,
+    styler: &TerminalStyler,
+) -> Result<Option<Cleaned>> {
+    let options = CleanupOptions {
+        dry_run: false,
+        sandboxed: clean.sandboxed_delete,
+        trash: use_trash,
+        min_path_depth: clean.min_path_depth,
+        size_cap_bytes: clean.size_cap.map(|gib| gib.saturating_mul(1 << 30)),
+        force_large: clean.force_large,
+        force: clean.force,
+        workers: clean.jobs.max(1),
+    };
+    // The size cap needs every size, including those a --fast scan left.
+    if candidates.iter().any(|c| c.size_pending) {
+        candidates = measure_pending(candidates, styler)?;
+    }
+    if let Some(err) = options.size_cap_error(&candidates) {
+        return Err(DevstripError::config(
+            None,
+            format!(
+                "{}. Review the list, then rerun with --force-large (or a higher --size-cap) to proceed.",
+                err
+            ),
+        )
+        .into());
+    }
+
+    if !clean.yes && !confirm_cleanup(styler)? {
         println!("Cleanup aborted.");
+        return Ok(None);
+    }
+
+    core::sort_for_cleanup(&mut candidates, clean.order);
+    let candidates: Vec<Arc<Candidate>> = candidates.into_iter().map(Arc::new).collect();
+    println!(
+        "{}",
+        styler.dim(&format!("Cleaning in {} order.", clean.order.label()))
+    );
+    let (results, cancelled) = cleanup_with_progress(&candidates, &options, styler);
+    if let Some((path, scan_duration)) = &metrics {
+        if let Err(err) = core::metrics::write_textfile(path, &candidates, &results, *scan_duration)
+        {
+            eprintln!("{}", styler.warning(&err.to_string()));
+        }
+    }
+
+    let success_count = results.iter().filter(|r| r.success).count();
+    let freed: u64 = results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| r.candidate.size_bytes)
+        .sum();
+    println!(
+        "{}",
+        styler.success(&if use_trash {
+            format!(
+                "Moved {} item(s) to the Trash ({}); empty the Trash to reclaim the space.",
+                success_count,
+                humanize_bytes(freed)
+            )
+        } else {
+            format!(
+                "Removed {} item(s); reclaimed approximately {}.",
+                success_count,
+                humanize_bytes(freed)
+            )
+        })
+    );
+
+    print_unlocked(&results, styler);
+    let (skipped, failures): (Vec<&CleanupResult>, Vec<&CleanupResult>) = results
+        .iter()
+        .filter(|r| !r.success)
+        .partition(|r| r.verification.is_some());
+    if !skipped.is_empty() {
+        println!(
+            "{}",
+            styler.warning("Skipped targets that changed since the scan:")
+        );
+        for skip in skipped {
+            let reason = skip
+                .error
+                .as_ref()
+                .map_or_else(|| "changed".to_string(), ToString::to_string);
+            println!("- {}: {}", skip.candidate.display_name(), reason);
+        }
+    }
+    if !failures.is_empty() {
+        println!(
+            "{}",
+            styler.error("Failed to remove the following targets:")
+        );
+        for failure in &failures {
+            let reason = failure
+                .error
+                .as_ref()
+                .map_or_else(|| "unknown error".to_string(), ToString::to_string);
+            println!("- {}: {}", failure.candidate.display_name(), reason);
+            if let Some(hint) = failure.error.as_ref().and_then(DevstripError::hint) {
+                println!("  {}", styler.dim(&format!("hint: {}", hint)));
+            }
+        }
+    }
+    if cancelled {
+        let remaining = candidates.len() - results.len();
+        if remaining > 0 {
+            println!(
+                "{}",
+                styler.warning(&format!(
+                    "Stopped before {} remaining target(s); `devstrip clean --resume` continues with them.",
+                    remaining
+                ))
+            );
+        }
+    }
+    let failed = !failures.is_empty();
+    Ok(Some(Cleaned {
+        results,
+        failed,
+        cancelled,
+    }))
+}
+
+fn print_deep_preview(candidates: &[Candidate], columns: &[Column], styler: &TerminalStyler) {
+    let (deep_only, baseline): (Vec<Candidate>, Vec<Candidate>) =
+        candidates.iter().cloned().partition(|c| c.deep_only);
+
+    println!("{}", styler.bold("Default scan"));
+    if baseline.is_empty() {
+        println!("{}", styler.dim("No candidates."));
+    } else {
+        print_cli_report(&baseline, columns, styler);
+    }
+    println!();
+    println!("{}", styler.bold("Only with --all"));
+    if deep_only.is_empty() {
+        println!("{}", styler.dim("--all would not add anything."));
+    } else {
+        print_cli_report(&deep_only, columns, styler);
+    }
+    println!(
+        "{}",
+        styler.dim("Preview only: nothing was removed. Run with --all to include the extra items.")
+    );
+}
+
+fn run_doctor(styler: &TerminalStyler) -> Result<()> {
+    let checks = doctor::run_checks();
+    let name_width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for check in &checks {
+        let label = match check.status {
+            CheckStatus::Pass => styler.success("[pass]"),
+            CheckStatus::Warn => styler.warning("[warn]"),
+            CheckStatus::Fail => styler.error("[fail]"),
+        };
+        let name = format!("{:<width$}", check.name, width = name_width);
+        println!("{} {} {}", label, styler.bold(&name), check.detail);
+        if let Some(hint) = &check.hint {
+            println!("       {}", styler.dim(&format!("hint: {}", hint)));
+        }
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .count();
+    if failures > 0 {
+        return Err(CliError::Failed(format!("{} check(s) failed.", failures)));
+    }
+    println!("{}", styler.success("All required checks passed."));
+    Ok(())
+}
+
+fn run_log(limit: usize, styler: &TerminalStyler) -> Result<()> {
+    let entries = core::audit::read_log()?;
+    if entries.is_empty() {
+        println!(
+            "{}",
+            styler.warning("No cleanup runs have been recorded yet.")
+        );
+        return Ok(());
+    }
+    let shown = entries.len().min(limit);
+    for entry in entries.iter().rev().take(limit) {
+        let outcome = if !entry.success {
+            styler.error("failed ")
+        } else if entry.dry_run {
+            styler.dim("dry-run")
+        } else {
+            styler.success("cleaned")
+        };
+        let timestamp = entry.timestamp.replace('T', " ");
+        let timestamp = timestamp.get(..16).unwrap_or(&timestamp);
+        println!(
+            "{} {} {:>10} {:<12} {}",
+            styler.dim(timestamp),
+            outcome,
+            humanize_bytes(entry.size_bytes),
+            entry.category,
+            entry.path.display()
+        );
+        if let Some(error) = &entry.error {
+            println!("{}", styler.dim(&format!("    {}", error)));
+        }
+    }
+    if shown < entries.len() {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Showing the latest {} of {} entries; use --limit to see more.",
+                shown,
+                entries.len()
+            ))
+        );
+    }
+    if let Some(path) = core::audit::audit_log_path() {
+        println!("{}", styler.dim(&format!("Audit log: {}", path.display())));
+    }
+    Ok(())
+}
+
+/// `devstrip history`: what cleanups reclaimed per period and in total.
+fn run_history(period: Period, global: &GlobalArgs, styler: &TerminalStyler) -> Result<()> {
+    let runs = core::history::read_history()?;
+    let periods = core::history::totals_by_period(&runs, period);
+    if global.format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&periods).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    if periods.is_empty() {
+        println!(
+            "{}",
+            styler.warning("No cleanup has reclaimed anything yet.")
+        );
         return Ok(());
     }
-
-    let results = cleanup_with_progress(&candidates, false, &styler);
-
-    let success_count = results.iter().filter(|r| r.success).count();
-    let freed: u64 = results
+    let width = periods
         .iter()
-        .filter(|r| r.success)
-        .map(|r| r.candidate.size_bytes)
-        .sum();
+        .map(|p| p.period.len())
+        .max()
+        .unwrap_or(0)
+        .max("Period".len());
+    println!(
+        "{}",
+        styler.bold(&format!(
+            "{:<width$} {:>11} {:>11} {:>5}",
+            "Period", "Reclaimed", "Cumulative", "Runs"
+        ))
+    );
+    let mut categories: BTreeMap<&str, u64> = BTreeMap::new();
+    for totals in &periods {
+        println!(
+            "{:<width$} {:>11} {:>11} {:>5}",
+            totals.period,
+            humanize_bytes(totals.reclaimed_bytes),
+            humanize_bytes(totals.cumulative_bytes),
+            totals.runs
+        );
+        for (category, bytes) in &totals.categories {
+            let sum = categories.entry(category).or_default();
+            *sum = sum.saturating_add(*bytes);
+        }
+    }
+    let mut categories: Vec<(&str, u64)> = categories.into_iter().collect();
+    categories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let category_width = categories.iter().map(|(c, _)| c.len()).max().unwrap_or(0);
+    println!("{}", styler.bold("By category:"));
+    for (category, bytes) in &categories {
+        println!(
+            "  {:<category_width$} {:>11}",
+            category,
+            humanize_bytes(*bytes)
+        );
+    }
+    let total = periods.last().map(|p| p.cumulative_bytes).unwrap_or(0);
+    let trash: u64 = periods.iter().map(|p| p.trash_bytes).sum();
+    let since = runs
+        .first()
+        .and_then(|run| run.timestamp.get(..10))
+        .unwrap_or_default();
     println!(
         "{}",
         styler.success(&format!(
-            "Removed {} item(s); reclaimed approximately {}.",
-            success_count,
-            humanize_bytes(freed)
+            "Reclaimed {} in {} cleanup run(s) since {}.",
+            humanize_bytes(total),
+            runs.len(),
+            since
         ))
     );
-
-    let failures: Vec<&CleanupResult> = results.iter().filter(|r| !r.success).collect();
-    if !failures.is_empty() {
+    if trash > 0 {
         println!(
             "{}",
-            styler.error("Failed to remove the following targets:")
+            styler.dim(&format!(
+                "{} of it went to the Trash and is only freed once the Trash is emptied.",
+                humanize_bytes(trash)
+            ))
         );
-        for failure in failures {
-            let reason = failure.error.as_deref().unwrap_or("unknown error");
-            println!("- {}: {}", failure.candidate.display_name(), reason);
-        }
-        return Err("One or more targets could not be removed.".to_string());
     }
+    Ok(())
+}
+
+fn run_rules_test(rule_file: &Path, config: &ScanConfig, styler: &TerminalStyler) -> Result<()> {
+    let rules = core::rules::load_rules(rule_file)?;
+    if rules.is_empty() {
+        return Err(DevstripError::config(
+            Some(rule_file),
+            format!("{} defines no [[rule]] entries.", rule_file.display()),
+        )
+        .into());
+    }
+    let reports = run_with_spinner("Testing custom rules", styler, {
+        let config = config.clone();
+        move |_| Ok(core::rules::test_rules(&rules, &config))
+    })?;
 
+    for report in &reports {
+        let flagged = report.matches.iter().filter(|m| m.flagged).count();
+        println!(
+            "{} {}",
+            styler.bold(&format!("Rule '{}'", report.rule.name)),
+            styler.dim(&format!(
+                "({} match(es), {} would be flagged, {})",
+                report.matches.len(),
+                flagged,
+                humanize_bytes(report.flagged_bytes())
+            ))
+        );
+        for warning in &report.warnings {
+            println!("  {}", styler.warning(&format!("warning: {}", warning)));
+        }
+        for rule_match in &report.matches {
+            let marker = if rule_match.flagged {
+                styler.success("[match]")
+            } else {
+                styler.dim("[skip] ")
+            };
+            println!(
+                "  {} {:>10} {}",
+                marker,
+                humanize_bytes(rule_match.size_bytes),
+                rule_match.path.display()
+            );
+            for diagnostic in &rule_match.diagnostics {
+                println!("          {}", styler.dim(diagnostic));
+            }
+        }
+    }
+    println!(
+        "{}",
+        styler.dim("Report only: nothing was deleted and the live rule file was not changed.")
+    );
+    if let Some(path) = core::rules::rules_path() {
+        println!(
+            "{}",
+            styler.dim(&format!("Live rules are read from {}", path.display()))
+        );
+    }
     Ok(())
 }
 
-fn build_scan_config(args: &Args) -> Result<ScanConfig> {
-    let mut roots = expand_paths(&args.roots);
-    roots.extend(expand_paths(&args.positional_roots));
+fn build_scan_config(args: &ScanArgs, low_priority: bool) -> Result<ScanConfig> {
+    let roots = root_args(args)?;
 
     let exclude_inputs = expand_paths(&args.excludes);
-    let exclude_paths = core::normalize_paths(&exclude_inputs);
+    let exclude_paths = Excludes::new(core::normalize_paths(&exclude_inputs));
     let resolved_roots = core::default_roots(&roots, &exclude_paths)?;
+    let custom_rules = core::rules::load_user_rules()?;
     if args.all {
         Ok(ScanConfig {
             roots: resolved_roots,
@@ -123,6 +2367,22 @@ fn build_scan_config(args: &Args) -> Result<ScanConfig> {
             keep_latest_derived: 0,
             keep_latest_cache: 0,
             exclude_paths,
+            exclude_globs: args.exclude_globs.clone(),
+            cargo_sweep: args.cargo_sweep,
+            custom_rules,
+            extra_patterns: args.patterns.clone(),
+            age_overrides: Vec::new(),
+            downloads_min_age_days: args.downloads_installers,
+            baseline: None,
+            same_filesystem: args.same_filesystem,
+            active_repo_days: args.skip_active_repos,
+            symlinks: args.symlinks,
+            size_cache: !args.no_cache,
+            lazy_sizes: args.fast,
+            low_priority,
+            incremental: false,
+            profile: args.profile,
+            keep_nested: true,
         })
     } else {
         Ok(ScanConfig {
@@ -132,6 +2392,22 @@ fn build_scan_config(args: &Args) -> Result<ScanConfig> {
             keep_latest_derived: args.keep_latest_derived,
             keep_latest_cache: args.keep_latest_cache,
             exclude_paths,
+            exclude_globs: args.exclude_globs.clone(),
+            cargo_sweep: args.cargo_sweep,
+            custom_rules,
+            extra_patterns: args.patterns.clone(),
+            age_overrides: args.ages.clone(),
+            downloads_min_age_days: args.downloads_installers,
+            baseline: None,
+            same_filesystem: args.same_filesystem,
+            active_repo_days: args.skip_active_repos,
+            symlinks: args.symlinks,
+            size_cache: !args.no_cache,
+            lazy_sizes: args.fast,
+            low_priority,
+            incremental: false,
+            profile: args.profile,
+            keep_nested: true,
         })
     }
 }
@@ -147,6 +2423,36 @@ fn expand_path(path: &Path) -> PathBuf {
     PathBuf::from(raw.as_ref())
 }
 
+/// The roots given on the command line. A `-` among them stands for the
+/// paths on stdin, one per line, so other tools can feed the scan.
+fn root_args(args: &ScanArgs) -> Result<Vec<PathBuf>> {
+    let mut roots = Vec::new();
+    for root in args.roots.iter().chain(&args.positional_roots) {
+        if root.as_os_str() != "-" {
+            roots.push(expand_path(root));
+            continue;
+        }
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|err| DevstripError::Io {
+                path: None,
+                message: format!("Failed to read roots from stdin: {}", err),
+            })?;
+        let before = roots.len();
+        roots.extend(
+            text.lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| expand_path(Path::new(line))),
+        );
+        if roots.len() == before {
+            return Err(DevstripError::config(None, "--roots - found no paths on stdin").into());
+        }
+    }
+    Ok(roots)
+}
+
 fn expand_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
     paths.iter().map(|p| expand_path(p)).collect()
 }
@@ -154,6 +2460,12 @@ fn expand_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
 struct TerminalStyler {
     use_color: bool,
     supports_animation: bool,
+    ascii_only: bool,
+    /// Stdout carries machine-readable output only, so there is no spinner
+    /// and no progress.
+    quiet: bool,
+    /// `--progress json`: progress also goes to stderr as JSON lines.
+    json_progress: bool,
 }
 
 impl TerminalStyler {
@@ -166,14 +2478,27 @@ impl TerminalStyler {
     const BLUE: &'static str = "\u{1b}[34m";
     const CYAN: &'static str = "\u{1b}[36m";
 
-    fn new(no_color: bool) -> Self {
+    fn new(no_color: bool, plain: bool) -> Self {
         let stdout_terminal = io::stdout().is_terminal();
         let env_no_color = env::var_os("NO_COLOR").is_some();
-        let use_color = !no_color && stdout_terminal && !env_no_color;
-        let supports_animation = stdout_terminal;
+        let dumb_terminal = env::var("TERM").map(|t| t == "dumb").unwrap_or(false);
+        let plain = plain || dumb_terminal || (stdout_terminal && !enable_virtual_terminal());
+        let use_color = !plain && !no_color && stdout_terminal && !env_no_color;
+        let supports_animation = !plain && stdout_terminal;
         Self {
             use_color,
             supports_animation,
+            ascii_only: plain,
+            quiet: false,
+            json_progress: false,
+        }
+    }
+
+    fn ellipsis(&self) -> &'static str {
+        if self.ascii_only {
+            "..."
+        } else {
+            "…"
         }
     }
 
@@ -219,17 +2544,54 @@ impl TerminalStyler {
     }
 }
 
+#[cfg(windows)]
+fn enable_virtual_terminal() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_OUTPUT_HANDLE,
+    };
+
+    // Rust already writes UTF-16 to console handles, so only ANSI escape
+    // processing has to be switched on; older consoles reject it and fall
+    // back to plain output.
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        if handle.is_null() || handle as isize == -1 {
+            return false;
+        }
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_virtual_terminal() -> bool {
+    true
+}
+
 struct StatusReporter {
     kind: ReporterKind,
 }
 
 enum ReporterKind {
-    Channel(mpsc::Sender<String>),
+    Channel(mpsc::Sender<StatusUpdate>),
     Print,
+    Silent,
+}
+
+/// What a background task sends the spinner: a new status to show next to
+/// it, or a line to print above it for good.
+enum StatusUpdate {
+    Status(String),
+    Line(String),
 }
 
 impl StatusReporter {
-    fn channel(tx: mpsc::Sender<String>) -> Self {
+    fn channel(tx: mpsc::Sender<StatusUpdate>) -> Self {
         Self {
             kind: ReporterKind::Channel(tx),
         }
@@ -241,14 +2603,35 @@ impl StatusReporter {
         }
     }
 
+    fn silent() -> Self {
+        Self {
+            kind: ReporterKind::Silent,
+        }
+    }
+
     fn update(&self, text: impl AsRef<str>) {
         match &self.kind {
             ReporterKind::Channel(tx) => {
-                let _ = tx.send(text.as_ref().to_string());
+                let _ = tx.send(StatusUpdate::Status(text.as_ref().to_string()));
+            }
+            ReporterKind::Print => {
+                println!("{}", text.as_ref());
+            }
+            ReporterKind::Silent => {}
+        }
+    }
+
+    /// Prints `text` on a line of its own, above the spinner when there is
+    /// one.
+    fn line(&self, text: impl AsRef<str>) {
+        match &self.kind {
+            ReporterKind::Channel(tx) => {
+                let _ = tx.send(StatusUpdate::Line(text.as_ref().to_string()));
             }
             ReporterKind::Print => {
                 println!("{}", text.as_ref());
             }
+            ReporterKind::Silent => {}
         }
     }
 }
@@ -258,6 +2641,9 @@ where
     T: Send + 'static,
     F: FnOnce(StatusReporter) -> Result<T> + Send + 'static,
 {
+    if styler.quiet {
+        return func(StatusReporter::silent());
+    }
     if !styler.supports_animation {
         println!("{}...", message);
         let reporter = StatusReporter::print();
@@ -266,7 +2652,7 @@ where
         return Ok(result);
     }
 
-    let (status_tx, status_rx) = mpsc::channel::<String>();
+    let (status_tx, status_rx) = mpsc::channel::<StatusUpdate>();
     let (result_tx, result_rx) = mpsc::channel::<Result<T>>();
     let message_owned = message.to_string();
 
@@ -282,10 +2668,15 @@ where
     let mut prev_len = 0usize;
 
     loop {
-        match status_rx.try_recv() {
-            Ok(update) => current = update,
-            Err(mpsc::TryRecvError::Empty) => {}
-            Err(mpsc::TryRecvError::Disconnected) => {}
+        while let Ok(update) = status_rx.try_recv() {
+            match update {
+                StatusUpdate::Status(text) => current = text,
+                StatusUpdate::Line(text) => {
+                    let padding = " ".repeat(prev_len.saturating_sub(text.len()));
+                    println!("\r{}{}", text, padding);
+                    prev_len = 0;
+                }
+            }
         }
 
         match result_rx.try_recv() {
@@ -302,7 +2693,9 @@ where
                 let padding = " ".repeat(prev_len.saturating_sub(final_text.len()));
                 print!("\r{}{}\n", final_text, padding);
                 let _ = io::stdout().flush();
-                return Err("Background task ended unexpectedly.".to_string());
+                return Err(CliError::Failed(
+                    "Background task ended unexpectedly.".to_string(),
+                ));
             }
         }
 
@@ -329,7 +2722,7 @@ fn truncate_status(text: &str) -> String {
     }
 }
 
-fn truncate_middle(text: &str, max_len: usize) -> String {
+fn truncate_middle(text: &str, max_len: usize, ellipsis: &str) -> String {
     if max_len == 0 {
         return String::new();
     }
@@ -337,90 +2730,620 @@ fn truncate_middle(text: &str, max_len: usize) -> String {
     if chars.len() <= max_len {
         return text.to_string();
     }
-    if max_len == 1 {
-        return "…".to_string();
+    let marker_len = ellipsis.chars().count();
+    if max_len <= marker_len {
+        return ellipsis.chars().take(max_len).collect();
     }
-    let head_len = (max_len - 1) / 2;
-    let tail_len = max_len - 1 - head_len;
+    let head_len = (max_len - marker_len) / 2;
+    let tail_len = max_len - marker_len - head_len;
     let mut result = String::new();
     result.extend(chars.iter().take(head_len));
-    result.push('…');
+    result.push_str(ellipsis);
     result.extend(chars.iter().skip(chars.len() - tail_len));
     result
 }
 
-fn print_cli_report(candidates: &[Candidate], styler: &TerminalStyler) {
-    let headers = [
-        styler.bold("#"),
-        styler.bold("Category"),
-        styler.bold("Size"),
-        styler.bold("Last Used"),
-        styler.bold("Reason"),
-        styler.bold("Path"),
-    ];
-    println!("{}", headers.join(" "));
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    #[default]
+    Table,
+    /// One JSON object with every target and a summary, and nothing else on
+    /// stdout.
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ProgressFormat {
+    /// One JSON object per scan or cleanup event on stderr.
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportSort {
+    /// Largest first.
+    Size,
+    /// Least recently used first.
+    Age,
+    /// By category, largest first within each.
+    Category,
+    Path,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    Index,
+    Category,
+    Size,
+    /// Sum of file lengths, next to the allocated `size`; only on request.
+    LogicalSize,
+    LastUsed,
+    Reason,
+    Path,
+}
+
+const ALL_COLUMNS: &[Column] = &[
+    Column::Index,
+    Column::Category,
+    Column::Size,
+    Column::LogicalSize,
+    Column::LastUsed,
+    Column::Reason,
+    Column::Path,
+];
+const DEFAULT_REASON_WIDTH: usize = 48;
+const MIN_REASON_WIDTH: usize = 16;
+const MIN_PATH_WIDTH: usize = 24;
+/// How many unreadable paths the scan summary lists before collapsing the rest.
+const MAX_SKIPPED_LISTED: usize = 10;
+
+struct ReportLayout {
+    columns: Vec<Column>,
+    category_width: usize,
+    size_width: usize,
+    logical_width: usize,
+    last_used_width: usize,
+    reason_width: usize,
+    path_width: Option<usize>,
+}
+
+impl ReportLayout {
+    fn new(candidates: &[Candidate], columns: &[Column], terminal_width: Option<usize>) -> Self {
+        let columns: Vec<Column> = if columns.is_empty() {
+            ALL_COLUMNS
+                .iter()
+                .copied()
+                .filter(|column| *column != Column::LogicalSize)
+                .collect()
+        } else {
+            ALL_COLUMNS
+                .iter()
+                .copied()
+                .filter(|column| columns.contains(column))
+                .collect()
+        };
+        let category_width = candidates
+            .iter()
+            .map(|c| c.category.len())
+            .max()
+            .map(|w| w.max(8))
+            .unwrap_or(8);
+        let size_width = candidates
+            .iter()
+            .map(|c| size_text(c).len())
+            .max()
+            .unwrap_or(6);
+        let logical_width = candidates
+            .iter()
+            .map(|c| logical_size_text(c).len())
+            .max()
+            .unwrap_or(6)
+            .max("Logical".len());
+        let last_used_width = 16usize;
+        let longest_reason = candidates
+            .iter()
+            .map(|c| c.reason.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut layout = Self {
+            columns,
+            category_width,
+            size_width,
+            logical_width,
+            last_used_width,
+            reason_width: longest_reason.min(DEFAULT_REASON_WIDTH),
+            path_width: None,
+        };
+
+        if let Some(total) = terminal_width {
+            // Fixed columns keep their natural width; the reason column
+            // shrinks first and the path takes whatever space is left.
+            let fixed: usize = layout
+                .columns
+                .iter()
+                .map(|column| match column {
+                    Column::Index => 4 + 1,
+                    Column::Category => layout.category_width + 1,
+                    Column::Size => layout.size_width + 1,
+                    Column::LogicalSize => layout.logical_width + 1,
+                    Column::LastUsed => layout.last_used_width + 1,
+                    Column::Reason => 1,
+                    Column::Path => 3,
+                })
+                .sum();
+            let available = total.saturating_sub(fixed);
+            let wants_path = layout.has(Column::Path);
+            if layout.has(Column::Reason) {
+                let path_reserve = if wants_path { MIN_PATH_WIDTH } else { 0 };
+                layout.reason_width = longest_reason
+                    .min(available.saturating_sub(path_reserve))
+                    .max(MIN_REASON_WIDTH.min(longest_reason));
+            }
+            if wants_path {
+                let used = if layout.has(Column::Reason) {
+                    layout.reason_width
+                } else {
+                    0
+                };
+                layout.path_width = Some(available.saturating_sub(used).max(MIN_PATH_WIDTH));
+            }
+        }
+
+        layout
+    }
+
+    fn has(&self, column: Column) -> bool {
+        self.columns.contains(&column)
+    }
+}
+
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        return Some(columns);
+    }
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+}
+
+/// Lists the directories the scan could not read, so a short report is not
+/// mistaken for a clean disk.
+fn print_skipped_paths(skipped: &[SkippedPath], styler: &TerminalStyler) {
+    if skipped.is_empty() {
+        return;
+    }
+    println!(
+        "{}",
+        styler.warning(&format!(
+            "{} path(s) could not be inspected:",
+            skipped.len()
+        ))
+    );
+    for path in skipped.iter().take(MAX_SKIPPED_LISTED) {
+        println!("- {} ({})", path.path.display(), path.kind);
+    }
+    if skipped.len() > MAX_SKIPPED_LISTED {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "... and {} more",
+                skipped.len() - MAX_SKIPPED_LISTED
+            ))
+        );
+    }
+    if let Some(hint) = core::skipped_paths_hint(skipped) {
+        println!("{}", styler.dim(hint));
+    }
+}
+
+/// Excludes are a safety net, so one that never matched (usually a typo or
+/// a renamed folder) is pointed out rather than silently ignored.
+fn print_unmatched_excludes(unmatched: &[PathBuf], styler: &TerminalStyler) {
+    for path in unmatched {
+        println!(
+            "{}",
+            styler.warning(&format!(
+                "exclude {} did not match any scanned path",
+                path.display()
+            ))
+        );
+    }
+}
+
+/// Orders the targets by `--sort`.
+fn sort_report(candidates: &mut [Candidate], global: &GlobalArgs) {
+    match global.sort {
+        ReportSort::Size => candidates.sort_by_key(|c| std::cmp::Reverse(c.size_bytes)),
+        ReportSort::Age => core::sort_for_cleanup(candidates, CleanupOrder::StalestFirst),
+        ReportSort::Category => candidates.sort_by(|a, b| {
+            a.category
+                .cmp(&b.category)
+                .then_with(|| b.size_bytes.cmp(&a.size_bytes))
+        }),
+        ReportSort::Path => candidates.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+}
+
+/// The first `--top` targets, which is all a report lists. Saved scans,
+/// summaries and cleanups still cover every target.
+fn top_of<'a>(
+    candidates: &'a [Candidate],
+    global: &GlobalArgs,
+    styler: &TerminalStyler,
+) -> &'a [Candidate] {
+    let top = match global.top {
+        Some(top) if top < candidates.len() => top,
+        _ => return candidates,
+    };
+    if !styler.quiet {
+        println!(
+            "{}",
+            styler.dim(&format!(
+                "Showing the first {} of {} target(s), {} in all.",
+                top,
+                candidates.len(),
+                humanize_bytes(core::scan_total_size(candidates))
+            ))
+        );
+    }
+    &candidates[..top]
+}
 
-    let category_width = candidates
+/// One line per category, largest first, for `--check`.
+fn print_category_summary(candidates: &[Candidate], styler: &TerminalStyler) {
+    let mut totals: Vec<(&str, usize, u64)> = Vec::new();
+    for candidate in candidates {
+        match totals
+            .iter_mut()
+            .find(|(category, _, _)| *category == candidate.category)
+        {
+            Some((_, count, size)) => {
+                *count += 1;
+                *size = size.saturating_add(candidate.size_bytes);
+            }
+            None => totals.push((&candidate.category, 1, candidate.size_bytes)),
+        }
+    }
+    totals.sort_by_key(|(_, _, size)| std::cmp::Reverse(*size));
+    let width = totals
         .iter()
-        .map(|c| c.category.len())
+        .map(|(category, _, _)| category.len())
         .max()
-        .map(|w| w.max(8))
-        .unwrap_or(8);
-    let size_width = candidates
+        .unwrap_or(0);
+    for (category, count, size) in totals {
+        let size_text = format!("{:>10}", humanize_bytes(size));
+        println!(
+            "{} {} {}",
+            styler.accent(&format!("{:<width$}", category)),
+            colorize_size(size, &size_text, styler),
+            styler.dim(&format!("{} target(s)", count))
+        );
+    }
+}
+
+/// `--summary`: the totals per category and per scan root, with the
+/// oldest target of each.
+fn print_group_summaries(candidates: &[Candidate], roots: &[PathBuf], styler: &TerminalStyler) {
+    for (title, groups) in [
+        ("By category", core::summary::by_category(candidates)),
+        ("By root", core::summary::by_root(candidates, roots)),
+    ] {
+        println!();
+        println!("{}", styler.bold(title));
+        print_group_summary(&groups, styler);
+    }
+}
+
+fn print_group_summary(groups: &[GroupSummary], styler: &TerminalStyler) {
+    let width = groups
         .iter()
-        .map(|c| humanize_bytes(c.size_bytes).len())
+        .map(|group| group.name.chars().count())
         .max()
-        .unwrap_or(6);
-    let last_width = 12usize;
-    let reason_width = 48usize;
+        .unwrap_or(0);
+    for group in groups {
+        let size_text = format!("{:>10}", humanize_bytes(group.size_bytes));
+        let oldest = match &group.oldest {
+            Some(oldest) => format!(
+                ", oldest {} {}",
+                oldest.last_used_str(),
+                oldest.display_name()
+            ),
+            None => String::new(),
+        };
+        println!(
+            "{} {} {}",
+            styler.accent(&format!("{:<width$}", group.name)),
+            colorize_size(group.size_bytes, &size_text, styler),
+            styler.dim(&format!("{:>4} target(s){}", group.count, oldest))
+        );
+    }
+}
 
-    for (idx, candidate) in candidates.iter().enumerate() {
-        let size_text = humanize_bytes(candidate.size_bytes);
-        let size_plain = format!("{:>width$}", size_text, width = size_width);
-        let size_colored = colorize_size(candidate.size_bytes, &size_plain, styler);
-        let category_text = format!("{:<width$}", candidate.category, width = category_width);
-        let category_colored = styler.accent(&category_text);
-        let index_label = styler.dim(&format!("[{:02}]", idx + 1));
-        let last_used_plain = format!("{:<width$}", candidate.last_used_str(), width = last_width,);
-        let last_used = styler.dim(&last_used_plain);
-        let reason_plain = truncate_middle(&candidate.reason, reason_width);
-        let reason = styler.dim(&reason_plain);
-        println!(
-            "{} {} {} {} {} -> {}",
-            index_label,
-            category_colored,
-            size_colored,
-            last_used,
-            reason,
-            candidate.display_name()
+fn print_missing_categories(missing: &[String], styler: &TerminalStyler) {
+    for category in missing {
+        println!(
+            "{}",
+            styler.warning(&format!("no targets were found in category {}", category))
         );
     }
+}
+
+fn print_scan_profile(profile: &ScanProfile, total: Duration, styler: &TerminalStyler) {
+    let mut lines = vec![styler.dim(&format!(
+        "Scan profile ({:.1} s in total; roots are walked in parallel):",
+        total.as_secs_f64()
+    ))];
+    for (title, entries) in [("Roots", &profile.roots), ("Detectors", &profile.detectors)] {
+        if entries.is_empty() {
+            continue;
+        }
+        lines.push(format!("  {}", title));
+        for entry in entries {
+            lines.push(format!(
+                "    {:>8.2} s {:>9} dirs {:>11} stat'ed  {}",
+                entry.wall_time.as_secs_f64(),
+                entry.directories,
+                human_bytes(entry.bytes_stated as f64),
+                entry.name
+            ));
+        }
+    }
+    // Keeps stdout to the JSON document when one is printed.
+    for line in lines {
+        if styler.quiet {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+fn print_cli_report(candidates: &[Candidate], columns: &[Column], styler: &TerminalStyler) {
+    let layout = ReportLayout::new(candidates, columns, terminal_width());
+
+    let mut headers = Vec::new();
+    for column in &layout.columns {
+        let header = match column {
+            Column::Index => format!("{:<4}", "#"),
+            Column::Category => format!("{:<width$}", "Category", width = layout.category_width),
+            Column::Size => format!("{:>width$}", "Size", width = layout.size_width),
+            Column::LogicalSize => {
+                format!("{:>width$}", "Logical", width = layout.logical_width)
+            }
+            Column::LastUsed => format!("{:<width$}", "Last Used", width = layout.last_used_width),
+            Column::Reason => format!("{:<width$}", "Reason", width = layout.reason_width),
+            Column::Path => "   Path".to_string(),
+        };
+        headers.push(styler.bold(&header));
+    }
+    println!("{}", headers.join(" "));
+
+    for (idx, candidate) in candidates.iter().enumerate() {
+        let mut cells = Vec::new();
+        for column in &layout.columns {
+            let cell = match column {
+                Column::Index => styler.dim(&format!("[{:02}]", idx + 1)),
+                Column::Category => styler.accent(&format!(
+                    "{:<width$}",
+                    candidate.category,
+                    width = layout.category_width
+                )),
+                Column::Size => {
+                    let size_plain = format!(
+                        "{:>width$}",
+                        size_text(candidate),
+                        width = layout.size_width
+                    );
+                    colorize_size(candidate.size_bytes, &size_plain, styler)
+                }
+                Column::LogicalSize => styler.dim(&format!(
+                    "{:>width$}",
+                    logical_size_text(candidate),
+                    width = layout.logical_width
+                )),
+                Column::LastUsed => styler.dim(&format!(
+                    "{:<width$}",
+                    candidate.last_used_str(),
+                    width = layout.last_used_width
+                )),
+                Column::Reason => {
+                    let reason =
+                        truncate_middle(&candidate.reason, layout.reason_width, styler.ellipsis());
+                    styler.dim(&format!("{:<width$}", reason, width = layout.reason_width))
+                }
+                Column::Path => {
+                    let name = candidate.display_name();
+                    let path = match layout.path_width {
+                        Some(width) => truncate_middle(&name, width, styler.ellipsis()),
+                        None => name,
+                    };
+                    let strategy_note = match &candidate.strategy {
+                        CleanupStrategy::Remove => String::new(),
+                        other => format!(" {}", styler.dim(&format!("({})", other.describe()))),
+                    };
+                    let recent_note = match &candidate.recently_opened {
+                        Some(ide) => format!(
+                            " {}",
+                            styler.warning(&format!("(recently opened in {})", ide))
+                        ),
+                        None => String::new(),
+                    };
+                    let incomplete_note = if candidate.size_incomplete {
+                        format!(
+                            " {}",
+                            styler.warning("(size incomplete: some folders unreadable)")
+                        )
+                    } else {
+                        String::new()
+                    };
+                    format!(
+                        "-> {}{}{}{}",
+                        path, strategy_note, recent_note, incomplete_note
+                    )
+                }
+            };
+            cells.push(cell);
+        }
+        println!("{}", cells.join(" "));
+    }
 
     let total = core::scan_total_size(candidates);
+    let pending = candidates.iter().filter(|c| c.size_pending).count();
+    let total_text = if pending > 0 {
+        format!(
+            "Reclaimable space: {} so far, {} target(s) still to measure",
+            humanize_bytes(total),
+            pending
+        )
+    } else {
+        format!("Reclaimable space: {}", humanize_bytes(total))
+    };
+    println!("{}", styler.bold(&total_text));
+}
+
+/// Sizes the candidates a `--fast` scan listed without one, printing each
+/// as it is measured.
+fn measure_pending(candidates: Vec<Candidate>, styler: &TerminalStyler) -> Result<Vec<Candidate>> {
+    let pending = candidates.iter().filter(|c| c.size_pending).count();
+    let message = format!("Measuring {} target(s)", pending);
+    let candidates = run_with_spinner(&message, styler, move |reporter| {
+        let mut candidates = candidates;
+        for candidate in candidates.iter_mut().filter(|c| c.size_pending) {
+            reporter.update(format!("Measuring: {}", candidate.display_name()));
+            core::measure_candidate(candidate);
+            reporter.line(format!(
+                "Measured {:>10} {}",
+                humanize_bytes(candidate.size_bytes),
+                candidate.display_name()
+            ));
+        }
+        Ok(candidates)
+    })?;
+    if !styler.quiet {
+        let total = core::scan_total_size(&candidates);
+        println!(
+            "{}",
+            styler.bold(&format!("Reclaimable space: {}", humanize_bytes(total)))
+        );
+    }
+    Ok(candidates)
+}
+
+fn size_text(candidate: &Candidate) -> String {
+    if candidate.size_pending {
+        "pending".to_string()
+    } else {
+        humanize_bytes(candidate.size_bytes)
+    }
+}
+
+fn logical_size_text(candidate: &Candidate) -> String {
+    if candidate.size_pending {
+        "pending".to_string()
+    } else {
+        humanize_bytes(candidate.logical_bytes)
+    }
+}
+
+/// How many unlocked paths are listed per target before the rest are
+/// summarised.
+const UNLOCKED_PATHS_SHOWN: usize = 5;
+
+/// Lists the read-only or immutable paths that had to be unlocked, so a
+/// permission someone set on purpose does not vanish unnoticed.
+fn print_unlocked(results: &[CleanupResult], styler: &TerminalStyler) {
+    let unlocked: Vec<&CleanupResult> = results.iter().filter(|r| !r.unlocked.is_empty()).collect();
+    if unlocked.is_empty() {
+        return;
+    }
     println!(
         "{}",
-        styler.bold(&format!("Reclaimable space: {}", humanize_bytes(total)))
+        styler.warning("Cleared read-only or immutable flags to remove:")
     );
+    for result in unlocked {
+        println!(
+            "- {}: {} path(s)",
+            result.candidate.display_name(),
+            result.unlocked.len()
+        );
+        for path in result.unlocked.iter().take(UNLOCKED_PATHS_SHOWN) {
+            println!("    {}", path.display());
+        }
+        if result.unlocked.len() > UNLOCKED_PATHS_SHOWN {
+            println!(
+                "    {}",
+                styler.dim(&format!(
+                    "and {} more",
+                    result.unlocked.len() - UNLOCKED_PATHS_SHOWN
+                ))
+            );
+        }
+    }
 }
 
+/// Set by the first Ctrl-C during a cleanup. Ctrl-C handlers can only be
+/// installed once per process, and a daemon cleans again and again.
+static CLEANUP_CANCEL: AtomicBool = AtomicBool::new(false);
+static CLEANING: AtomicBool = AtomicBool::new(false);
+static CTRLC_HANDLER: Once = Once::new();
+
+/// Runs the cleanup with a progress line. The first Ctrl-C stops it after
+/// the current item (or part-way through a large directory); a second one
+/// exits immediately. Returns whether the run was stopped.
 fn cleanup_with_progress(
-    candidates: &[Candidate],
-    dry_run: bool,
+    candidates: &[Arc<Candidate>],
+    options: &CleanupOptions,
     styler: &TerminalStyler,
-) -> Vec<CleanupResult> {
+) -> (Vec<CleanupResult>, bool) {
     if candidates.is_empty() {
-        return Vec::new();
+        return (Vec::new(), false);
     }
 
-    let results = core::cleanup_with_callback(candidates, dry_run, |progress| {
-        render_cleanup_progress(progress.index, progress.total, progress.candidate, styler);
+    CLEANUP_CANCEL.store(false, Ordering::Relaxed);
+    CLEANING.store(true, Ordering::Relaxed);
+    let stopping = styler.warning("Stopping cleanup; press Ctrl-C again to quit now.");
+    CTRLC_HANDLER.call_once(|| {
+        let _ = ctrlc::set_handler(move || {
+            if !CLEANING.load(Ordering::Relaxed) || CLEANUP_CANCEL.swap(true, Ordering::Relaxed) {
+                process::exit(DevstripError::Cancelled.exit_code());
+            }
+            eprintln!("\n{}", stopping);
+        });
+    });
+
+    let mut warnings = Vec::new();
+    let mut json = styler.json_progress.then(JsonProgress::default);
+    let results = core::cleanup_with_cancel(candidates, options, &CLEANUP_CANCEL, |event| {
+        if let Some(json) = &mut json {
+            json.cleanup_event(&event);
+        }
+        match event {
+            DevstripEvent::CleanupItemStarted {
+                index,
+                total,
+                candidate,
+            } => render_cleanup_progress(index, total, &candidate, styler),
+            DevstripEvent::Warning(message) => warnings.push(message),
+            _ => {}
+        }
     });
+    if let Some(json) = &json {
+        json.cleanup_finished(&results, CLEANUP_CANCEL.load(Ordering::Relaxed));
+    }
 
     if styler.supports_animation {
         println!();
     }
+    for warning in warnings {
+        eprintln!("{}", styler.warning(&warning));
+    }
 
-    results
+    CLEANING.store(false, Ordering::Relaxed);
+    (results, CLEANUP_CANCEL.load(Ordering::Relaxed))
 }
 
 fn render_cleanup_progress(
@@ -433,28 +3356,184 @@ fn render_cleanup_progress(
         let bar = render_progress_bar(index + 1, total, 28);
         let label = styler.bold(&format!("[{}]", bar));
         print!(
-            "\rCleaning {} {}/{} {}",
+            "\rCleaning {} {}/{} {} {}",
             label,
             index + 1,
             total,
+            styler.dim(&candidate.last_used_str()),
             candidate.display_name()
         );
         let _ = io::stdout().flush();
     } else {
         println!(
-            "Cleaning {}/{}: {}",
+            "Cleaning {}/{}: {} (last used {})",
             index + 1,
             total,
-            candidate.display_name()
+            candidate.display_name(),
+            candidate.last_used_str()
         );
     }
 }
 
+/// Directory updates in `--progress json` come at most this often; the
+/// other events are all sent.
+const JSON_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Turns scan and cleanup events into the JSON lines of `--progress json`,
+/// keeping the running totals they carry.
+#[derive(Default)]
+struct JsonProgress {
+    percent: Option<u8>,
+    bytes_found: u64,
+    bytes_freed: u64,
+    last_path: Option<Instant>,
+}
+
+impl JsonProgress {
+    fn scan_event(&mut self, event: &DevstripEvent) {
+        let line = match event {
+            DevstripEvent::ScanStarted { roots } => serde_json::json!({
+                "phase": "scan",
+                "event": "started",
+                "roots": roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>(),
+            }),
+            DevstripEvent::Scanning { path } => {
+                if self
+                    .last_path
+                    .is_some_and(|sent| sent.elapsed() < JSON_PROGRESS_INTERVAL)
+                {
+                    return;
+                }
+                self.last_path = Some(Instant::now());
+                serde_json::json!({
+                    "phase": "scan",
+                    "event": "progress",
+                    "path": path.display().to_string(),
+                    "percent": self.percent,
+                    "bytes_found": self.bytes_found,
+                })
+            }
+            DevstripEvent::ScanEstimate(estimate) => {
+                self.percent = Some(estimate.percent);
+                serde_json::json!({
+                    "phase": "scan",
+                    "event": "progress",
+                    "percent": estimate.percent,
+                    "remaining_secs": estimate.remaining.map(|left| left.as_secs()),
+                    "bytes_found": self.bytes_found,
+                })
+            }
+            DevstripEvent::CandidateFound(candidate) => {
+                self.bytes_found = self.bytes_found.saturating_add(candidate.size_bytes);
+                serde_json::json!({
+                    "phase": "scan",
+                    "event": "found",
+                    "path": candidate.path.display().to_string(),
+                    "category": candidate.category,
+                    "size_bytes": candidate.size_bytes,
+                    "percent": self.percent,
+                    "bytes_found": self.bytes_found,
+                })
+            }
+            DevstripEvent::PathSkipped(skipped) => serde_json::json!({
+                "phase": "scan",
+                "event": "skipped",
+                "path": skipped.path.display().to_string(),
+                "error": skipped.kind.to_string(),
+            }),
+            DevstripEvent::ScanFinished {
+                candidates,
+                cancelled,
+            } => serde_json::json!({
+                "phase": "scan",
+                "event": "finished",
+                "candidates": candidates,
+                "cancelled": cancelled,
+                "percent": if *cancelled { self.percent } else { Some(100) },
+                "bytes_found": self.bytes_found,
+            }),
+            DevstripEvent::Warning(message) | DevstripEvent::Error(message) => {
+                Self::message("scan", event, message)
+            }
+            _ => return,
+        };
+        Self::emit(line);
+    }
+
+    fn cleanup_event(&mut self, event: &DevstripEvent) {
+        let line = match event {
+            DevstripEvent::CleanupItemStarted {
+                index,
+                total,
+                candidate,
+            } => serde_json::json!({
+                "phase": "cleanup",
+                "event": "cleaning",
+                "item": index + 1,
+                "total": total,
+                "path": candidate.path.display().to_string(),
+                "size_bytes": candidate.size_bytes,
+                "percent": index * 100 / total.max(&1),
+                "bytes_freed": self.bytes_freed,
+            }),
+            DevstripEvent::CleanupItemFinished {
+                index,
+                total,
+                result,
+            } => {
+                if result.success {
+                    self.bytes_freed = self.bytes_freed.saturating_add(result.candidate.size_bytes);
+                }
+                serde_json::json!({
+                    "phase": "cleanup",
+                    "event": "cleaned",
+                    "item": index + 1,
+                    "total": total,
+                    "path": result.candidate.path.display().to_string(),
+                    "success": result.success,
+                    "error": result.error.as_ref().map(ToString::to_string),
+                    "hint": result.error.as_ref().and_then(DevstripError::hint),
+                    "percent": (index + 1) * 100 / total.max(&1),
+                    "bytes_freed": self.bytes_freed,
+                })
+            }
+            DevstripEvent::Warning(message) | DevstripEvent::Error(message) => {
+                Self::message("cleanup", event, message)
+            }
+            _ => return,
+        };
+        Self::emit(line);
+    }
+
+    fn cleanup_finished(&self, results: &[CleanupResult], cancelled: bool) {
+        Self::emit(serde_json::json!({
+            "phase": "cleanup",
+            "event": "finished",
+            "cleaned": results.iter().filter(|result| result.success).count(),
+            "total": results.len(),
+            "cancelled": cancelled,
+            "bytes_freed": self.bytes_freed,
+        }));
+    }
+
+    fn message(phase: &str, event: &DevstripEvent, message: &str) -> serde_json::Value {
+        let kind = match event {
+            DevstripEvent::Error(_) => "error",
+            _ => "warning",
+        };
+        serde_json::json!({ "phase": phase, "event": kind, "message": message })
+    }
+
+    fn emit(line: serde_json::Value) {
+        let _ = writeln!(io::stderr().lock(), "{}", line);
+    }
+}
+
 fn render_progress_bar(position: usize, total: usize, width: usize) -> String {
     if total == 0 || width == 0 {
         return String::new();
     }
-    let filled = ((position * width) + total - 1) / total;
+    let filled = (position * width).div_ceil(total);
     let filled = filled.min(width);
     let mut bar = String::new();
     bar.push_str(&"#".repeat(filled));
@@ -471,7 +3550,11 @@ fn confirm_cleanup(styler: &TerminalStyler) -> Result<bool> {
     let mut input = String::new();
     match io::stdin().read_line(&mut input) {
         Ok(_) => Ok(input.trim().eq_ignore_ascii_case("yes")),
-        Err(err) => Err(format!("Failed to read input: {}", err)),
+        Err(err) => Err(DevstripError::Io {
+            path: None,
+            message: format!("Failed to read input: {}", err),
+        }
+        .into()),
     }
 }
 
@@ -479,6 +3562,141 @@ fn humanize_bytes(size: u64) -> String {
     human_bytes(size as f64)
 }
 
+fn parse_glob(text: &str) -> std::result::Result<String, String> {
+    glob::Pattern::new(text)
+        .map(|_| text.to_string())
+        .map_err(|err| format!("invalid glob '{}': {}", text, err))
+}
+
+/// A `--pattern` is one directory name, matched exactly like the built-in
+/// patterns.
+fn parse_pattern(text: &str) -> std::result::Result<String, String> {
+    if text.is_empty() || text == "." || text == ".." {
+        return Err(format!("'{}' is not a directory name", text));
+    }
+    if text.contains('/') || text.contains('\\') {
+        return Err(format!(
+            "'{}' must be a single directory name, not a path",
+            text
+        ));
+    }
+    Ok(text.to_string())
+}
+
+/// The longest `--interval` accepted: a year.
+const MAX_INTERVAL_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Parses `--interval` values like `90s`, `30m`, `6h` or `1d`, up to a year.
+fn parse_interval(text: &str) -> std::result::Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid interval '{}' (expected e.g. 30m, 6h or 1d)", text))?;
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid interval '{}' (expected a number with s, m, h or d)",
+                text
+            ))
+        }
+    };
+    match number.checked_mul(scale) {
+        Some(0) => Err("the interval must be longer than zero".to_string()),
+        Some(secs) if secs <= MAX_INTERVAL_SECS => Ok(Duration::from_secs(secs)),
+        _ => Err(format!(
+            "the interval must be at most {}",
+            format_interval(MAX_INTERVAL_SECS)
+        )),
+    }
+}
+
+/// The largest whole unit of `secs`, as `--interval` takes it.
+fn format_interval(secs: u64) -> String {
+    match secs {
+        s if s % 86_400 == 0 => format!("{}d", s / 86_400),
+        s if s % 3_600 == 0 => format!("{}h", s / 3_600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Parses sizes like `500K`, `100MB` or `1.5GiB`: `KB`, `MB`, ... are
+/// powers of 1000, `KiB`, `MiB`, ... powers of 1024, and a bare number is
+/// bytes.
+fn parse_size(text: &str) -> std::result::Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}' (expected e.g. 100MB or 2GiB)", text))?;
+    let scale = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1e3,
+        "M" | "MB" => 1e6,
+        "G" | "GB" => 1e9,
+        "T" | "TB" => 1e12,
+        "KIB" => 1024.0,
+        "MIB" => 1024.0 * 1024.0,
+        "GIB" => 1024.0 * 1024.0 * 1024.0,
+        "TIB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        other => {
+            return Err(format!(
+                "unknown size unit '{}' (expected KB, MB, GB, TB or KiB, MiB, GiB, TiB)",
+                other
+            ))
+        }
+    };
+    Ok((number * scale) as u64)
+}
+
+/// The longest `--age` accepted, about a hundred years.
+const MAX_AGE_DAYS: u64 = 36_500;
+
+/// Parses `--age` values like `node_modules=30d` or `Xcode=2w`: a directory
+/// name or category, and an age in days (`30`, `30d`) or weeks (`2w`).
+fn parse_age_override(text: &str) -> std::result::Result<AgeOverride, String> {
+    let (target, age) = text
+        .split_once('=')
+        .ok_or_else(|| format!("invalid age '{}' (expected e.g. node_modules=30d)", text))?;
+    let target = target.trim();
+    if target.is_empty() {
+        return Err(format!("invalid age '{}': the target is empty", text));
+    }
+    let age = age.trim().to_ascii_lowercase();
+    let (number, scale) = if let Some(weeks) = age.strip_suffix('w') {
+        (weeks, 7)
+    } else {
+        (age.strip_suffix('d').unwrap_or(&age), 1)
+    };
+    let days: u64 = number.parse().map_err(|_| {
+        format!(
+            "invalid age '{}' for {} (expected days like 30d or weeks like 2w)",
+            age, target
+        )
+    })?;
+    match days.checked_mul(scale) {
+        Some(days) if days <= MAX_AGE_DAYS => Ok(AgeOverride {
+            target: target.to_string(),
+            days,
+        }),
+        _ => Err(format!(
+            "invalid age '{}' for {}: at most {} days",
+            age, target, MAX_AGE_DAYS
+        )),
+    }
+}
+
 fn colorize_size(size_bytes: u64, text: &str, styler: &TerminalStyler) -> String {
     if size_bytes >= 1_u64 << 40 {
         styler.accent(text)
@@ -1,15 +1,96 @@
 use chrono::{DateTime, Local, Utc};
-use std::collections::{HashSet, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub type CoreResult<T> = std::result::Result<T, String>;
-
+mod archives;
+mod attribution;
+pub mod audit;
+mod buildlocks;
+mod bulkstat;
+pub mod compress;
+pub mod daemon;
+pub mod diff;
+pub mod doctor;
+mod downloads;
+mod error;
+mod events;
+pub mod export;
+mod freespace;
+mod gems;
+mod git;
+mod gradle;
+mod handles;
+pub mod history;
+pub mod html;
+mod ignore;
+mod instance;
+mod jetbrains;
+mod job;
+mod kubernetes;
+pub mod lastscan;
+mod linux;
+mod logs;
+mod measure;
+pub mod metrics;
+pub mod notify;
+mod overrides;
+mod pathkey;
+pub mod policy;
+mod priority;
+mod profile;
+mod progress;
+mod rescan;
+pub mod resume;
+pub mod rules;
+pub mod sandbox;
+pub mod schedule;
+mod shell;
+mod sizecache;
+pub mod summary;
+mod sweep;
+mod tools;
+mod trash;
+mod unlock;
+mod verify;
+mod vscode;
+pub mod watch;
+mod windows;
+mod yarn;
+
+pub use error::DevstripError;
+pub use events::{skipped_paths_hint, DevstripEvent, SkippedPath};
+pub use freespace::{available_space, on_volume};
+pub use instance::{daemon_running, InstanceLock};
+use job::ScanControl;
+pub use job::{ScanJob, ScanProgress, ScanState};
+use overrides::Overrides;
+pub use priority::lower_current_thread;
+pub use profile::{ProfileEntry, ScanProfile};
+pub use progress::ScanEstimate;
+pub use verify::{CandidateId, ScanStamp, VerificationFailure, VerifiedCandidate};
+
+pub type CoreResult<T> = std::result::Result<T, DevstripError>;
+
+/// Candidates inside a project an IDE opened within this many days are
+/// marked as recently opened.
+pub const RECENT_WORKSPACE_DAYS: u64 = 14;
+/// A cleanup that frees more than this in total is refused unless forced,
+/// so a misconfigured deep scan cannot wipe out a whole workspace.
+pub const DEFAULT_SIZE_CAP_BYTES: u64 = 200 << 30;
+/// Candidates the frontends clean at the same time. Removing a tree waits
+/// on the disk for each entry, so a few at once finish much sooner.
+pub const DEFAULT_CLEANUP_WORKERS: usize = 4;
 pub const DEFAULT_HOME_PROJECT_DIRS: &[&str] = &["Projects", "workspace", "Work", "Developer"];
-const SKIP_DIR_NAMES: &[&str] = &[".git", ".hg", ".svn", ".idea", ".vscode", ".gradle"];
+const SKIP_DIR_NAMES: &[&str] = &[".git", ".hg", ".svn", ".idea", ".vscode"];
 const PROJECT_PATTERNS: &[&str] = &[
     "build",
     "dist",
@@ -22,6 +103,7 @@ const PROJECT_PATTERNS: &[&str] = &[
     ".mypy_cache",
     ".ruff_cache",
     ".tox",
+    ".flatpak-builder",
     ".eggs",
     "coverage",
     "__pycache__",
@@ -30,43 +112,238 @@ const PROJECT_PATTERNS: &[&str] = &[
     ".sass-cache",
     ".cache",
 ];
-const CACHE_TARGETS: &[(&str, &str, &str)] = &[
-    ("Library/Caches/pip", "Python", "pip cache"),
-    (".cache/pip", "Python", "pip cache"),
-    (".cache/pip-tools", "Python", "pip-tools cache"),
-    (".cache/pipenv", "Python", "pipenv cache"),
-    (".cache/pre-commit", "Python", "pre-commit cache"),
-    (".cache/matplotlib", "Python", "matplotlib cache"),
-    (".cache/pytest", "Python", "pytest cache"),
-    (".cache/ruff", "Python", "ruff cache"),
-    (".cache/uv", "Python", "uv cache"),
-    (".npm", "Node", "npm cache"),
-    ("Library/Caches/npm", "Node", "npm cache"),
-    ("Library/Caches/Yarn", "Node", "Yarn cache"),
-    (".cache/yarn", "Node", "Yarn cache"),
-    ("Library/Caches/CocoaPods", "CocoaPods", "CocoaPods cache"),
-    (".gradle/caches", "Gradle", "Gradle caches"),
-    (".gradle/daemon", "Gradle", "Gradle daemons"),
-    (".gradle/native", "Gradle", "Gradle native cache"),
+/// Generic output directory names that are only build artifacts when one
+/// of these manifests sits next to them; elsewhere they are often sources.
+const BUILD_DIR_MANIFESTS: &[(&str, &[&str])] = &[
+    ("target", &["Cargo.toml", "pom.xml", "build.sbt"]),
+    (
+        "build",
+        &[
+            "package.json",
+            "build.gradle",
+            "build.gradle.kts",
+            "settings.gradle",
+            "settings.gradle.kts",
+            "CMakeLists.txt",
+            "meson.build",
+            "setup.py",
+            "pyproject.toml",
+            "pubspec.yaml",
+        ],
+    ),
+    (
+        "dist",
+        &["package.json", "setup.py", "pyproject.toml", "Cargo.toml"],
+    ),
+    (
+        "out",
+        &[
+            "package.json",
+            "build.gradle",
+            "build.gradle.kts",
+            "CMakeLists.txt",
+        ],
+    ),
+];
+/// .NET output directories, which sit next to a project file with a
+/// per-project name (`App.csproj`) rather than a fixed manifest.
+const DOTNET_BUILD_DIRS: &[&str] = &["bin", "obj"];
+const DOTNET_PROJECT_EXTENSIONS: &[&str] = &["csproj", "fsproj", "vbproj"];
+const SYSTEM_CORE_DIR: &str = "/cores";
+/// `ET_CORE` and `MH_CORE`, the file types of ELF and Mach-O core dumps.
+const ELF_CORE_TYPE: u16 = 4;
+const MACHO_CORE_TYPE: u32 = 4;
+
+struct ProjectRule {
+    parent: Option<&'static str>,
+    name: &'static str,
+    manifest: &'static str,
+    category: &'static str,
+}
+
+const PROJECT_RULES: &[ProjectRule] = &[
+    ProjectRule {
+        parent: Some("vendor"),
+        name: "bundle",
+        manifest: "Gemfile",
+        category: "Ruby",
+    },
+    ProjectRule {
+        parent: None,
+        name: "_build",
+        manifest: "mix.exs",
+        category: "Elixir",
+    },
+    ProjectRule {
+        parent: None,
+        name: "deps",
+        manifest: "mix.exs",
+        category: "Elixir",
+    },
+    ProjectRule {
+        parent: None,
+        name: "zig-cache",
+        manifest: "build.zig",
+        category: "Zig",
+    },
+    ProjectRule {
+        parent: None,
+        name: ".zig-cache",
+        manifest: "build.zig",
+        category: "Zig",
+    },
+    ProjectRule {
+        parent: Some(".yarn"),
+        name: "cache",
+        manifest: ".yarnrc.yml",
+        category: "Node",
+    },
+    ProjectRule {
+        parent: Some(".yarn"),
+        name: "unplugged",
+        manifest: ".yarnrc.yml",
+        category: "Node",
+    },
+    ProjectRule {
+        parent: None,
+        name: "zig-out",
+        manifest: "build.zig",
+        category: "Zig",
+    },
+];
+/// Home-relative cache folders: (relative path, category, label, how they
+/// are cleaned). npm and VS Code recreate a missing cache folder only on
+/// some code paths, so theirs are emptied rather than removed.
+const CACHE_TARGETS: &[(&str, &str, &str, CleanupStrategy)] = &[
+    (
+        "Library/Caches/pip",
+        "Python",
+        "pip cache",
+        CleanupStrategy::Remove,
+    ),
+    (".cache/pip", "Python", "pip cache", CleanupStrategy::Remove),
+    (
+        ".cache/pip-tools",
+        "Python",
+        "pip-tools cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".cache/pipenv",
+        "Python",
+        "pipenv cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".cache/pre-commit",
+        "Python",
+        "pre-commit cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".cache/matplotlib",
+        "Python",
+        "matplotlib cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".cache/pytest",
+        "Python",
+        "pytest cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".cache/ruff",
+        "Python",
+        "ruff cache",
+        CleanupStrategy::Remove,
+    ),
+    (".cache/uv", "Python", "uv cache", CleanupStrategy::Remove),
+    (
+        ".bundle/cache",
+        "Ruby",
+        "Bundler cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".hex/packages",
+        "Elixir",
+        "Hex package cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".cache/zig",
+        "Zig",
+        "Zig global cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        "Library/Logs/DiagnosticReports",
+        "Diagnostics",
+        "Crash reports",
+        CleanupStrategy::Remove,
+    ),
+    (".npm", "Node", "npm cache", CleanupStrategy::EmptyDirectory),
+    (
+        "Library/Caches/npm",
+        "Node",
+        "npm cache",
+        CleanupStrategy::EmptyDirectory,
+    ),
+    (
+        "Library/Caches/Yarn",
+        "Node",
+        "Yarn cache",
+        CleanupStrategy::Remove,
+    ),
+    (".cache/yarn", "Node", "Yarn cache", CleanupStrategy::Remove),
+    (
+        "Library/Caches/CocoaPods",
+        "CocoaPods",
+        "CocoaPods cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".gradle/caches",
+        "Gradle",
+        "Gradle caches",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".gradle/daemon",
+        "Gradle",
+        "Gradle daemons",
+        CleanupStrategy::Remove,
+    ),
+    (
+        ".gradle/native",
+        "Gradle",
+        "Gradle native cache",
+        CleanupStrategy::Remove,
+    ),
     (
-        "Library/Caches/JetBrains",
-        "JetBrains",
-        "JetBrains IDE caches",
+        ".nuget/packages",
+        ".NET",
+        "NuGet package cache",
+        CleanupStrategy::Remove,
     ),
     (
         "Library/Application Support/Code/Cache",
         "VSCode",
         "VSCode cache",
+        CleanupStrategy::EmptyDirectory,
     ),
     (
         "Library/Application Support/Code/CachedData",
         "VSCode",
         "VSCode cached data",
+        CleanupStrategy::EmptyDirectory,
     ),
     (
         "Library/Application Support/Slack/Service Worker/CacheStorage",
         "Slack",
         "Slack cache",
+        CleanupStrategy::Remove,
     ),
 ];
 
@@ -77,16 +354,326 @@ pub struct ScanConfig {
     pub max_depth: u32,
     pub keep_latest_derived: usize,
     pub keep_latest_cache: usize,
-    pub exclude_paths: Vec<PathBuf>,
+    pub exclude_paths: Excludes,
+    /// Glob patterns such as `**/vendor/**`; the project walk skips the
+    /// paths they match.
+    pub exclude_globs: Vec<String>,
+    pub cargo_sweep: bool,
+    pub custom_rules: Vec<rules::CustomRule>,
+    /// Directory names flagged in projects like the built-in patterns
+    /// (`build`, `node_modules`, ...), such as `bazel-out`.
+    pub extra_patterns: Vec<String>,
+    /// Minimum ages for particular directory names or categories, such as
+    /// `node_modules=30` or `Xcode=7`, instead of `min_age_days`.
+    pub age_overrides: Vec<AgeOverride>,
+    /// Minimum age for installer artifacts in `~/Downloads`; `None` leaves
+    /// Downloads out of the scan entirely.
+    pub downloads_min_age_days: Option<u64>,
+    /// When set, the scan runs with this config's (looser) thresholds and
+    /// marks candidates the baseline thresholds would not have admitted as
+    /// `deep_only`, so one pass shows what the deep scan adds.
+    pub baseline: Option<ScanThresholds>,
+    /// Stay on the filesystem of each root: mount points below a root
+    /// (network shares, external volumes) are not descended into.
+    pub same_filesystem: bool,
+    /// Leave git repositories alone that have uncommitted changes or a
+    /// commit within this many days, so active projects keep warm caches.
+    pub active_repo_days: Option<u64>,
+    /// What the project walk does with symbolic links to directories.
+    pub symlinks: SymlinkPolicy,
+    /// Reuse the sizes an earlier scan measured for directories whose
+    /// modification time has not changed since.
+    pub size_cache: bool,
+    /// Find candidates without measuring the trees they cover; their sizes
+    /// are left pending for `measure_candidate`.
+    pub lazy_sizes: bool,
+    /// Run the scan at background CPU and I/O priority. `scan_with_events`
+    /// lowers the calling thread for good; a `ScanJob` only its own threads.
+    pub low_priority: bool,
+    /// Take the candidates of a root from the previous scan in this process
+    /// when the directories in its top levels have not changed since.
+    pub incremental: bool,
+    /// Time each root and detector and send the result as a `ScanProfile`
+    /// event just before `ScanFinished`.
+    pub profile: bool,
+    /// Leave in the candidates inside a tree another candidate removes
+    /// whole. For a frontend that filters the list further and calls
+    /// `drop_nested_candidates` after its own filters.
+    pub keep_nested: bool,
+}
+
+/// How the project walk treats a symbolic link to a directory, such as a
+/// `node_modules` or `target` that points into a shared cache volume.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Ignore symlinks entirely.
+    #[default]
+    Skip,
+    /// Report a symlink whose name marks a build or cache directory as a
+    /// candidate, without walking into it. Cleaning it removes the link
+    /// only; the directory it points to is left alone.
+    Report,
+    /// Like `Report`, and also walk into other symlinked directories. Each
+    /// directory is visited once, so link cycles end the walk.
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [SymlinkPolicy; 3] = [
+        SymlinkPolicy::Skip,
+        SymlinkPolicy::Report,
+        SymlinkPolicy::Follow,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SymlinkPolicy::Skip => "skip",
+            SymlinkPolicy::Report => "report",
+            SymlinkPolicy::Follow => "follow",
+        }
+    }
+
+    pub fn next(&self) -> SymlinkPolicy {
+        let index = Self::ALL.iter().position(|p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for SymlinkPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "report" => Ok(SymlinkPolicy::Report),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            other => Err(format!(
+                "unknown symlink policy '{}' (expected skip, report, or follow)",
+                other
+            )),
+        }
+    }
+}
+
+/// `--age TARGET=AGE`: targets whose directory is named `target`, or whose
+/// category is (in any case), must be unused for `days` days.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgeOverride {
+    pub target: String,
+    pub days: u64,
 }
 
 #[derive(Clone, Debug)]
+pub struct ScanThresholds {
+    pub min_age_days: u64,
+    pub max_depth: u32,
+    pub keep_latest_derived: usize,
+    pub keep_latest_cache: usize,
+}
+
+impl ScanConfig {
+    pub fn thresholds(&self) -> ScanThresholds {
+        ScanThresholds {
+            min_age_days: self.min_age_days,
+            max_depth: self.max_depth,
+            keep_latest_derived: self.keep_latest_derived,
+            keep_latest_cache: self.keep_latest_cache,
+        }
+    }
+
+    /// The same scan with deep (`--all`) thresholds, keeping the current
+    /// thresholds as the baseline for `deep_only` marking.
+    pub fn deep_preview(&self) -> ScanConfig {
+        ScanConfig {
+            min_age_days: 0,
+            max_depth: u32::MAX,
+            keep_latest_derived: 0,
+            keep_latest_cache: 0,
+            age_overrides: Vec::new(),
+            baseline: Some(self.thresholds()),
+            ..self.clone()
+        }
+    }
+
+    /// The `--age` days for a directory `name` in `category`. A match by
+    /// name wins over one by category.
+    pub(crate) fn age_override(&self, name: &str, category: &str) -> Option<u64> {
+        self.age_overrides
+            .iter()
+            .find(|age| age.target == name)
+            .or_else(|| {
+                self.age_overrides
+                    .iter()
+                    .find(|age| age.target.eq_ignore_ascii_case(category))
+            })
+            .map(|age| age.days)
+    }
+
+    /// The minimum age of the detectors that report a single category.
+    pub(crate) fn category_min_age_days(&self, category: &str) -> u64 {
+        self.age_overrides
+            .iter()
+            .find(|age| age.target.eq_ignore_ascii_case(category))
+            .map_or(self.min_age_days, |age| age.days)
+    }
+
+    /// Whether `candidate` is as old as an `--age` matching it asks. The
+    /// detectors already apply looser ages; stricter ones are applied here,
+    /// which covers detectors that check no age of their own too.
+    fn meets_age_override(&self, candidate: &Candidate) -> bool {
+        let name = candidate
+            .path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        match self
+            .age_override(name, &candidate.category)
+            .and_then(age_cutoff)
+        {
+            Some(limit) => candidate.last_used.is_none_or(|used| used < limit),
+            None => true,
+        }
+    }
+
+    /// Whether an entry last modified at `modified` would fall inside the
+    /// baseline age window, i.e. only the deep scan reports it.
+    pub(crate) fn too_recent_for_baseline(&self, modified: Option<SystemTime>) -> bool {
+        let cutoff = match &self.baseline {
+            Some(baseline) => age_cutoff(baseline.min_age_days),
+            None => return false,
+        };
+        matches!((cutoff, modified), (Some(limit), Some(mtime)) if mtime >= limit)
+    }
+
+    fn beyond_baseline_depth(&self, depth: u32) -> bool {
+        self.baseline
+            .as_ref()
+            .map(|baseline| depth > baseline.max_depth)
+            .unwrap_or(false)
+    }
+
+    fn kept_by_baseline(&self, index: usize, keep_of: fn(&ScanThresholds) -> usize) -> bool {
+        self.baseline
+            .as_ref()
+            .map(|baseline| index < keep_of(baseline))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Candidate {
     pub path: PathBuf,
+    /// Space allocated on disk, i.e. what a cleanup gives back.
     pub size_bytes: u64,
+    /// Sum of file lengths. Larger than `size_bytes` for sparse files,
+    /// smaller for lots of tiny files.
+    pub logical_bytes: u64,
     pub category: String,
     pub reason: String,
     pub last_used: Option<SystemTime>,
+    pub strategy: CleanupStrategy,
+    /// Set during a preview scan when only the deep thresholds admit it.
+    pub deep_only: bool,
+    /// The IDE that opened the workspace holding this candidate within the
+    /// last `RECENT_WORKSPACE_DAYS` days. Its caches are likely to be
+    /// rebuilt right after a cleanup.
+    pub recently_opened: Option<String>,
+    /// The path as it was when the scan finished; cleanup skips the
+    /// candidate if it has changed since.
+    pub scanned: Option<ScanStamp>,
+    /// Set by a scan with `lazy_sizes` until `measure_candidate` fills in
+    /// `size_bytes` and `logical_bytes`, which are zero meanwhile.
+    pub size_pending: bool,
+    /// Set when some directories below the path could not be read, so the
+    /// size leaves out whatever they hold.
+    #[serde(default)]
+    pub size_incomplete: bool,
+    /// The project rule that flagged the directory, which the check right
+    /// before cleanup repeats.
+    #[serde(default)]
+    pub matched_rule: Option<MatchedRule>,
+}
+
+/// How a project's build or cache directory was recognized.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchedRule {
+    /// A `PROJECT_RULES` entry: the directory name, and the directory it
+    /// sits in when the rule asks for one (`.yarn` for `.yarn/cache`).
+    Project {
+        name: String,
+        parent: Option<String>,
+    },
+    /// A generic build directory name (`target`, `build`, ...) next to the
+    /// manifest that makes it one.
+    BuildDir { name: String, manifest: String },
+    /// A .NET `bin` or `obj` directory next to a project file.
+    Dotnet { name: String, project: String },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CleanupStrategy {
+    #[default]
+    Remove,
+    Command {
+        program: String,
+        args: Vec<String>,
+    },
+    SweepCargoTarget {
+        cutoff: SystemTime,
+    },
+    PruneFilesOlderThan {
+        cutoff: SystemTime,
+    },
+    Compress,
+    TruncateHistory {
+        keep_lines: usize,
+    },
+    /// Delete everything inside the directory but keep the directory
+    /// itself, for tools that misbehave when their cache folder is gone.
+    EmptyDirectory,
+    /// Remove an installed gem version together with its gemspec, cached
+    /// package, native extensions and docs, as `gem uninstall` would.
+    RemoveGemVersion,
+    /// Remove a symbolic link and leave the directory it points to alone.
+    RemoveLink,
+}
+
+impl CleanupStrategy {
+    pub fn command(program: &str, args: &[&str]) -> Self {
+        CleanupStrategy::Command {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            CleanupStrategy::Remove => "remove".to_string(),
+            CleanupStrategy::Command { program, args } => {
+                let mut parts = vec![program.clone()];
+                parts.extend(args.iter().cloned());
+                format!("run `{}`", parts.join(" "))
+            }
+            CleanupStrategy::SweepCargoTarget { cutoff } => {
+                format!(
+                    "prune entries untouched since {}",
+                    format_system_time(*cutoff)
+                )
+            }
+            CleanupStrategy::PruneFilesOlderThan { cutoff } => {
+                format!("delete files older than {}", format_system_time(*cutoff))
+            }
+            CleanupStrategy::Compress => format!("compress to *{}", compress::ARCHIVE_SUFFIX),
+            CleanupStrategy::TruncateHistory { keep_lines } => {
+                format!("keep the last {} lines", keep_lines)
+            }
+            CleanupStrategy::EmptyDirectory => "empty, keep the folder".to_string(),
+            CleanupStrategy::RemoveGemVersion => {
+                "remove with its gemspec, extensions and docs".to_string()
+            }
+            CleanupStrategy::RemoveLink => "remove the link, keep its target".to_string(),
+        }
+    }
 }
 
 impl Candidate {
@@ -102,94 +689,705 @@ impl Candidate {
     }
 }
 
-pub struct CleanupResult {
-    pub candidate: Candidate,
-    pub success: bool,
-    pub error: Option<String>,
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CleanupOrder {
+    #[default]
+    StalestFirst,
+    LargestFirst,
+    AsListed,
 }
 
-pub struct CleanupProgress<'a> {
-    pub index: usize,
-    pub total: usize,
-    pub candidate: &'a Candidate,
+impl CleanupOrder {
+    pub const ALL: [CleanupOrder; 3] = [
+        CleanupOrder::StalestFirst,
+        CleanupOrder::LargestFirst,
+        CleanupOrder::AsListed,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CleanupOrder::StalestFirst => "stalest first",
+            CleanupOrder::LargestFirst => "largest first",
+            CleanupOrder::AsListed => "as listed",
+        }
+    }
+
+    pub fn next(&self) -> CleanupOrder {
+        let index = Self::ALL.iter().position(|o| o == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
 }
 
-pub fn scan(config: &ScanConfig) -> Vec<Candidate> {
-    scan_with_callback(config, |_| {})
+impl std::str::FromStr for CleanupOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "stalest" | "stalest-first" | "age" => Ok(CleanupOrder::StalestFirst),
+            "largest" | "largest-first" | "size" => Ok(CleanupOrder::LargestFirst),
+            "listed" | "as-listed" => Ok(CleanupOrder::AsListed),
+            other => Err(format!(
+                "unknown cleanup order '{}' (expected stalest, largest, or listed)",
+                other
+            )),
+        }
+    }
 }
 
-pub fn scan_with_cancel(config: &ScanConfig, cancel: &AtomicBool) -> Vec<Candidate> {
-    scan_with_callback_cancel(config, cancel, |_| {})
+#[derive(Clone, Debug)]
+pub struct CleanupResult {
+    /// The candidate as cleaned, re-measured right before; the one that was
+    /// passed in when it was not cleaned.
+    pub candidate: Arc<Candidate>,
+    pub success: bool,
+    /// Why the candidate was not cleaned; `hint` says what to do about it.
+    pub error: Option<DevstripError>,
+    /// Set when the candidate changed on disk since the scan and was left
+    /// alone.
+    pub verification: Option<VerificationFailure>,
+    /// Read-only or immutable paths that were unlocked so they could be
+    /// removed.
+    pub unlocked: Vec<PathBuf>,
 }
 
-pub fn scan_with_callback<F>(config: &ScanConfig, mut callback: F) -> Vec<Candidate>
-where
-    F: FnMut(&str),
-{
-    gather_candidates(config, &mut callback, None)
+pub fn scan(config: &ScanConfig) -> Vec<Candidate> {
+    scan_with_events(config, |_| {})
 }
 
-pub fn scan_with_callback_cancel<F>(
-    config: &ScanConfig,
-    cancel: &AtomicBool,
-    mut callback: F,
-) -> Vec<Candidate>
+pub fn scan_with_events<F>(config: &ScanConfig, mut on_event: F) -> Vec<Candidate>
 where
-    F: FnMut(&str),
+    F: FnMut(DevstripEvent),
 {
-    gather_candidates(config, &mut callback, Some(cancel))
+    on_event(DevstripEvent::ScanStarted {
+        roots: config.roots.clone(),
+    });
+    if config.low_priority {
+        priority::lower_current_thread();
+    }
+    let cache = sizecache::ScanCache::open(config.size_cache);
+    let lazy = measure::LazySizes::start(config.lazy_sizes);
+    let progress = progress::ProgressTracker::start(config);
+    let profiling = profile::Profiling::start(config.profile);
+    let mut reporter = |event: DevstripEvent| {
+        let estimate = progress.observe(&event);
+        on_event(event);
+        if let Some(estimate) = estimate {
+            on_event(DevstripEvent::ScanEstimate(estimate));
+        }
+    };
+    let candidates = gather_candidates(config, &mut reporter, None);
+    progress.finish(false);
+    drop(lazy);
+    cache.close(config.lazy_sizes);
+    for path in config.exclude_paths.unmatched() {
+        on_event(DevstripEvent::UnmatchedExclude { path });
+    }
+    if let Some(profiling) = profiling {
+        on_event(DevstripEvent::ScanProfile(profiling.finish()));
+    }
+    on_event(DevstripEvent::ScanFinished {
+        candidates: candidates.len(),
+        cancelled: false,
+    });
+    candidates
 }
 
-pub fn cleanup(candidates: &[Candidate], dry_run: bool) -> Vec<CleanupResult> {
-    cleanup_with_callback(candidates, dry_run, |_| {})
+#[derive(Clone, Debug, Default)]
+pub struct CleanupOptions {
+    pub dry_run: bool,
+    pub sandboxed: bool,
+    /// Move removed paths to the Trash instead of deleting them.
+    pub trash: bool,
+    /// Refuse paths shallower than this; `None` uses
+    /// `sandbox::DEFAULT_MIN_PATH_DEPTH`.
+    pub min_path_depth: Option<usize>,
+    /// Refuse a cleanup that frees more than this in total; `None` uses
+    /// `DEFAULT_SIZE_CAP_BYTES`.
+    pub size_cap_bytes: Option<u64>,
+    /// Go ahead even when the cleanup is larger than the size cap.
+    pub force_large: bool,
+    /// Clean candidates even while a running build or build daemon holds
+    /// a lock inside them.
+    pub force: bool,
+    /// Candidates cleaned at the same time; 0 and 1 clean one at a time.
+    pub workers: usize,
 }
 
-pub fn cleanup_with_callback<F>(
-    candidates: &[Candidate],
-    dry_run: bool,
-    mut callback: F,
+impl CleanupOptions {
+    /// Why cleaning `candidates` is refused as too large, if it is. Dry
+    /// runs delete nothing and are never refused. Candidates a quick scan
+    /// has not measured yet are refused even when forced, since the cap
+    /// cannot be checked without their sizes.
+    pub fn size_cap_error<C: Borrow<Candidate>>(&self, candidates: &[C]) -> Option<DevstripError> {
+        if self.dry_run {
+            return None;
+        }
+        let pending = candidates
+            .iter()
+            .filter(|candidate| (*candidate).borrow().size_pending)
+            .count();
+        if pending > 0 {
+            return Some(DevstripError::config(
+                None,
+                format!(
+                    "{} target(s) have not been measured yet, so the {} safety cap cannot be checked",
+                    pending,
+                    human_bytes::human_bytes(
+                        self.size_cap_bytes.unwrap_or(DEFAULT_SIZE_CAP_BYTES) as f64
+                    )
+                ),
+            ));
+        }
+        let cap = self.size_cap_bytes.unwrap_or(DEFAULT_SIZE_CAP_BYTES);
+        let total = scan_total_size(candidates);
+        if self.force_large || total <= cap {
+            return None;
+        }
+        Some(DevstripError::config(
+            None,
+            format!(
+                "This cleanup would free {}, more than the {} safety cap",
+                human_bytes::human_bytes(total as f64),
+                human_bytes::human_bytes(cap as f64)
+            ),
+        ))
+    }
+}
+
+pub fn cleanup(candidates: &[Arc<Candidate>], dry_run: bool) -> Vec<CleanupResult> {
+    let options = CleanupOptions {
+        dry_run,
+        ..CleanupOptions::default()
+    };
+    cleanup_with_events(candidates, &options, |_| {})
+}
+
+pub fn cleanup_with_events<F>(
+    candidates: &[Arc<Candidate>],
+    options: &CleanupOptions,
+    on_event: F,
+) -> Vec<CleanupResult>
+where
+    F: FnMut(DevstripEvent),
+{
+    cleanup_with_cancel(candidates, options, &AtomicBool::new(false), on_event)
+}
+
+/// Like `cleanup_with_events`, but stops once `cancel` is set: remaining
+/// candidates are left alone and a directory that is being removed stops
+/// part-way through. Only the candidates that were attempted get a result.
+/// With `options.workers` above one, that many candidates are cleaned at
+/// once; events still arrive in list order.
+pub fn cleanup_with_cancel<F>(
+    candidates: &[Arc<Candidate>],
+    options: &CleanupOptions,
+    cancel: &AtomicBool,
+    mut on_event: F,
 ) -> Vec<CleanupResult>
 where
-    F: FnMut(CleanupProgress<'_>),
+    F: FnMut(DevstripEvent),
 {
+    let dry_run = options.dry_run;
     let total = candidates.len();
     let mut results = Vec::with_capacity(total);
-    for (index, candidate) in candidates.iter().enumerate() {
-        callback(CleanupProgress {
-            index,
+    let (policy, policy_error) = match policy::active_policy() {
+        Ok(policy) => (policy, None),
+        Err(err) => (None, Some(err)),
+    };
+    let use_trash = options.trash || policy.map(|p| p.require_trash).unwrap_or(false);
+    let mut helper = None;
+    let mut helper_error = None;
+    if options.sandboxed && !use_trash && !dry_run {
+        match sandbox::DeleteHelper::spawn() {
+            Ok(spawned) => helper = Some(Mutex::new(spawned)),
+            Err(err) => {
+                on_event(DevstripEvent::Error(err.to_string()));
+                helper_error = Some(err);
+            }
+        }
+    }
+    let run = CleanupRun {
+        options,
+        policy,
+        policy_error,
+        // Checked up front by the frontends too; refusing every item here
+        // keeps a caller that skipped the check from deleting anything.
+        too_large: options
+            .size_cap_error(candidates)
+            .map(|err| DevstripError::config(None, format!("refused: {}", err))),
+        use_trash,
+        budget: Mutex::new(policy.and_then(|p| p.max_deletion_bytes)),
+        min_path_depth: options
+            .min_path_depth
+            .unwrap_or(sandbox::DEFAULT_MIN_PATH_DEPTH),
+        helper,
+        helper_error,
+        cancel,
+    };
+    let mut audit = audit::AuditLog::start(dry_run);
+    // A dry run removes nothing, so there is nothing to resume either.
+    let mut journal = None;
+    if !dry_run {
+        let (started, saved) = resume::Journal::start(candidates, use_trash);
+        if let Err(err) = saved {
+            on_event(DevstripEvent::Warning(err.to_string()));
+        }
+        journal = Some(started);
+    }
+
+    let workers = options.workers.clamp(1, total.max(1));
+    let mut started = 0;
+    if workers == 1 {
+        for (index, candidate) in candidates.iter().enumerate() {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            on_event(DevstripEvent::CleanupItemStarted {
+                index,
+                total,
+                candidate: Arc::clone(candidate),
+            });
+            started += 1;
+            if let Some(journal) = &mut journal {
+                journal.cleaning(index);
+            }
+            let result = run.clean(candidate);
+            if let Err(err) = audit.record(&result) {
+                on_event(DevstripEvent::Warning(err.to_string()));
+            }
+            report_cleaned(&mut on_event, index, total, result, &mut results);
+            if let Some(journal) = &mut journal {
+                journal.cleaned(results.len());
+            }
+        }
+    } else {
+        // Workers claim candidates in list order and report back here, where
+        // finished items are held until the ones before them are done.
+        let next = Mutex::new(0);
+        let (tx, rx) = mpsc::channel();
+        let lowered = priority::is_current_thread_lowered();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let (next, run) = (&next, &run);
+                scope.spawn(move || {
+                    if lowered {
+                        priority::lower_current_thread();
+                    }
+                    loop {
+                        let index = {
+                            let mut next = lock_ignoring_poison(next);
+                            if *next == total || cancel.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let _ = tx.send(CleanupProgress::Started(*next));
+                            *next += 1;
+                            *next - 1
+                        };
+                        let result = run.clean(&candidates[index]);
+                        let _ = tx.send(CleanupProgress::Finished(index, Box::new(result)));
+                    }
+                });
+            }
+            drop(tx);
+            let mut finished = BTreeMap::new();
+            for progress in rx {
+                match progress {
+                    CleanupProgress::Started(index) => {
+                        on_event(DevstripEvent::CleanupItemStarted {
+                            index,
+                            total,
+                            candidate: Arc::clone(&candidates[index]),
+                        });
+                        started += 1;
+                        if let Some(journal) = &mut journal {
+                            journal.cleaning(index);
+                        }
+                    }
+                    CleanupProgress::Finished(index, result) => {
+                        if let Err(err) = audit.record(&result) {
+                            on_event(DevstripEvent::Warning(err.to_string()));
+                        }
+                        finished.insert(index, *result);
+                        while let Some(result) = finished.remove(&results.len()) {
+                            let index = results.len();
+                            report_cleaned(&mut on_event, index, total, result, &mut results);
+                        }
+                        if let Some(journal) = &mut journal {
+                            journal.cleaned(results.len());
+                        }
+                    }
+                }
+            }
+        });
+    }
+    if started < total && cancel.load(Ordering::Relaxed) {
+        on_event(DevstripEvent::CleanupCancelled {
+            completed: started,
             total,
-            candidate,
         });
+    }
 
-        let (success, error) = if dry_run {
-            (true, None)
-        } else {
-            match delete_path(&candidate.path) {
-                Ok(_) => (true, None),
-                Err(err) => (false, Some(err.to_string())),
-            }
-        };
+    if !dry_run {
+        if let Err(err) = history::record_run(&results, use_trash) {
+            on_event(DevstripEvent::Warning(err.to_string()));
+        }
+    }
+    results
+}
+
+/// What a cleanup worker tells the thread that reports events.
+enum CleanupProgress {
+    Started(usize),
+    Finished(usize, Box<CleanupResult>),
+}
 
-        results.push(CleanupResult {
-            candidate: candidate.clone(),
-            success,
-            error,
+/// State one cleanup shares among the candidates it works through.
+struct CleanupRun<'a> {
+    options: &'a CleanupOptions,
+    policy: Option<&'static policy::Policy>,
+    policy_error: Option<DevstripError>,
+    too_large: Option<DevstripError>,
+    use_trash: bool,
+    /// What the managed policy still allows to be deleted.
+    budget: Mutex<Option<u64>>,
+    min_path_depth: usize,
+    /// The sandboxed helper deletes one path at a time.
+    helper: Option<Mutex<sandbox::DeleteHelper>>,
+    helper_error: Option<DevstripError>,
+    cancel: &'a AtomicBool,
+}
+
+impl CleanupRun<'_> {
+    fn clean(&self, candidate: &Arc<Candidate>) -> CleanupResult {
+        let refused = match candidate.strategy {
+            CleanupStrategy::Command { .. } => self
+                .options
+                .trash
+                .then(|| trash::unsupported_reason(&candidate.strategy))
+                .flatten()
+                .map(|reason| format!("refused: {}", reason)),
+            _ => sandbox::refused_reason(&candidate.path, self.min_path_depth)
+                .or_else(|| {
+                    self.options
+                        .sandboxed
+                        .then(|| sandbox::unsupported_reason(&candidate.strategy))
+                        .flatten()
+                })
+                .map(|reason| format!("refused: {}", reason)),
+        };
+        let blocked = self
+            .too_large
+            .clone()
+            .or_else(|| refused.map(|reason| DevstripError::file(&candidate.path, reason)))
+            .or_else(|| check_policy(candidate, self.policy, self.policy_error.as_ref()));
+        let (outcome, verification) = match blocked {
+            Some(err) => (Err(err), None),
+            None => match verify::verify(candidate) {
+                Err((failure, detail)) => (
+                    Err(DevstripError::file(
+                        &candidate.path,
+                        format!("{} since the scan: {}", failure, detail),
+                    )),
+                    Some(failure),
+                ),
+                Ok(verified) => (Ok(verified), None),
+            },
+        };
+        let outcome = outcome.and_then(|verified| {
+            let size = verified.candidate().size_bytes;
+            match lock_ignoring_poison(&self.budget).as_mut() {
+                Some(remaining) if size > *remaining => Err(DevstripError::config(
+                    None,
+                    format!(
+                        "managed policy allows at most {} per cleanup",
+                        human_bytes::human_bytes(
+                            self.policy.and_then(|p| p.max_deletion_bytes).unwrap_or(0) as f64
+                        )
+                    ),
+                )),
+                Some(remaining) => {
+                    *remaining -= size;
+                    Ok(verified)
+                }
+                None => Ok(verified),
+            }
         });
+
+        match outcome {
+            Err(err) => CleanupResult {
+                candidate: Arc::clone(candidate),
+                success: false,
+                error: Some(err),
+                verification,
+                unlocked: Vec::new(),
+            },
+            Ok(verified) => {
+                let mut unlocked = Vec::new();
+                let error = if self.options.dry_run {
+                    None
+                } else if let Some(err) = &self.helper_error {
+                    Some(err.clone())
+                } else if let Some(reason) = (!self.options.force)
+                    .then(|| buildlocks::active_build_reason(verified.candidate()))
+                    .flatten()
+                {
+                    Some(DevstripError::file(&candidate.path, reason))
+                } else if let Some(reason) = handles::in_use_reason(verified.candidate()) {
+                    Some(DevstripError::file(&candidate.path, reason))
+                } else {
+                    let mut helper = self.helper.as_ref().map(lock_ignoring_poison);
+                    apply_strategy(
+                        &verified,
+                        helper.as_deref_mut(),
+                        self.use_trash,
+                        self.cancel,
+                        &mut unlocked,
+                    )
+                    .err()
+                };
+                CleanupResult {
+                    success: error.is_none(),
+                    error,
+                    candidate: Arc::new(verified.into_candidate()),
+                    verification: None,
+                    unlocked,
+                }
+            }
+        }
     }
+}
 
-    results
+fn report_cleaned<F>(
+    on_event: &mut F,
+    index: usize,
+    total: usize,
+    result: CleanupResult,
+    results: &mut Vec<CleanupResult>,
+) where
+    F: FnMut(DevstripEvent),
+{
+    if let Some(err) = &result.error {
+        on_event(DevstripEvent::Error(format!(
+            "{}: {}",
+            result.candidate.display_name(),
+            err
+        )));
+    }
+    on_event(DevstripEvent::CleanupItemFinished {
+        index,
+        total,
+        result: result.clone(),
+    });
+    results.push(result);
+}
+
+fn lock_ignoring_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Why the managed policy forbids touching `candidate`, if it does. A policy
+/// that failed to load blocks everything, and one that requires the trash
+/// blocks what cannot go there.
+fn check_policy(
+    candidate: &Candidate,
+    policy: Option<&policy::Policy>,
+    policy_error: Option<&DevstripError>,
+) -> Option<DevstripError> {
+    if let Some(err) = policy_error {
+        return Some(err.clone());
+    }
+    let policy = policy?;
+    if policy.forbids(&candidate.category) {
+        return Some(DevstripError::config(
+            None,
+            format!(
+                "category {} is blocked by the managed policy",
+                candidate.category
+            ),
+        ));
+    }
+    policy
+        .require_trash
+        .then(|| trash::unsupported_reason(&candidate.strategy))
+        .flatten()
+        .map(|reason| {
+            DevstripError::config(
+                None,
+                format!(
+                    "blocked by the managed policy, which requires the Trash: {}",
+                    reason
+                ),
+            )
+        })
+}
+
+/// Switches candidates that would be deleted outright to in-place
+/// compression. An empty category list applies to every category.
+pub fn compress_instead_of_removing(candidates: &mut [Candidate], categories: &[String]) {
+    for candidate in candidates {
+        let selected = categories.is_empty()
+            || categories
+                .iter()
+                .any(|category| category.eq_ignore_ascii_case(&candidate.category));
+        if selected && candidate.strategy == CleanupStrategy::Remove {
+            candidate.strategy = CleanupStrategy::Compress;
+        }
+    }
+}
+
+/// The first candidates in cleanup `order` that together reclaim at least
+/// `bytes`, or all of them when they reclaim less.
+pub fn select_to_reclaim(
+    mut candidates: Vec<Candidate>,
+    bytes: u64,
+    order: CleanupOrder,
+) -> Vec<Candidate> {
+    sort_for_cleanup(&mut candidates, order);
+    let mut reclaimed = 0u64;
+    let count = candidates
+        .iter()
+        .take_while(|candidate| {
+            let needed = reclaimed < bytes;
+            reclaimed = reclaimed.saturating_add(candidate.size_bytes);
+            needed
+        })
+        .count();
+    candidates.truncate(count);
+    candidates
+}
+
+/// Keeps the candidates in one of `categories`, compared ignoring case, and
+/// returns the categories that none of them belonged to.
+pub fn retain_categories(candidates: &mut Vec<Candidate>, categories: &[String]) -> Vec<String> {
+    let mut matched = vec![false; categories.len()];
+    candidates.retain(|candidate| {
+        match categories
+            .iter()
+            .position(|category| category.eq_ignore_ascii_case(&candidate.category))
+        {
+            Some(index) => {
+                matched[index] = true;
+                true
+            }
+            None => false,
+        }
+    });
+    categories
+        .iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(category, _)| category.clone())
+        .collect()
+}
+
+/// Reorders candidates so that an interrupted cleanup has already handled the
+/// lowest-risk items. Candidates without a known modification time are
+/// treated as the riskiest and go last when ordering by staleness.
+pub fn sort_for_cleanup<C: Borrow<Candidate>>(candidates: &mut [C], order: CleanupOrder) {
+    match order {
+        CleanupOrder::StalestFirst => candidates.sort_by(|a, b| {
+            let (a, b) = (a.borrow(), b.borrow());
+            match (a.last_used, b.last_used) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+            .then_with(|| b.size_bytes.cmp(&a.size_bytes))
+        }),
+        CleanupOrder::LargestFirst => candidates.sort_by(|a, b| {
+            let (a, b) = (a.borrow(), b.borrow());
+            b.size_bytes
+                .cmp(&a.size_bytes)
+                .then_with(|| a.last_used.cmp(&b.last_used))
+        }),
+        CleanupOrder::AsListed => {}
+    }
 }
 
+pub const HOME_OVERRIDE_ENV: &str = "DEVSTRIP_HOME_DIR";
+
+static HOME_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// The home directory every detector works against: an override set with
+/// `set_home_override`, then `DEVSTRIP_HOME_DIR`, then `HOME` (or
+/// `USERPROFILE` on Windows).
 pub fn home_dir() -> Option<PathBuf> {
-    std::env::var_os("HOME").map(PathBuf::from)
+    if let Some(path) = HOME_OVERRIDE.get() {
+        return Some(path.clone());
+    }
+    std::env::var_os(HOME_OVERRIDE_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(login_home_dir)
+}
+
+/// The real user's home from `HOME`, ignoring any override. Safety checks
+/// use this so pointing devstrip at another tree never unprotects it.
+pub fn login_home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|value| !value.is_empty())
+        .or_else(|| {
+            cfg!(windows)
+                .then(|| std::env::var_os("USERPROFILE"))
+                .flatten()
+        })
+        .map(PathBuf::from)
+}
+
+/// Points `home_dir` at another tree (a mounted image, a container root, a
+/// test fixture) for the rest of the process. Only the first call wins.
+pub fn set_home_override(path: &Path) -> CoreResult<()> {
+    let resolved = canonicalize(path).map_err(|err| {
+        DevstripError::config(
+            Some(path),
+            format!("Invalid home override {}: {}", path.display(), err),
+        )
+    })?;
+    if !resolved.is_dir() {
+        return Err(DevstripError::config(
+            Some(path),
+            format!("Invalid home override {}: not a directory", path.display()),
+        ));
+    }
+    HOME_OVERRIDE
+        .set(resolved)
+        .map_err(|_| DevstripError::config(None, "Home override is already set"))
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return home_dir().map(|home| home.join("Library/Application Support/devstrip"));
+    }
+    if cfg!(windows) {
+        return home_dir().map(|home| windows::local_app_data(&home).join("devstrip"));
+    }
+    home_dir().map(|home| linux::data_home(&home).join("devstrip"))
 }
 
-pub fn default_roots(extra: &[PathBuf], excludes: &[PathBuf]) -> CoreResult<Vec<PathBuf>> {
+pub fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return data_dir();
+    }
+    if cfg!(windows) {
+        return std::env::var_os("APPDATA")
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join("AppData").join("Roaming")))
+            .map(|base| base.join("devstrip"));
+    }
+    home_dir().map(|home| linux::config_home(&home).join("devstrip"))
+}
+
+pub fn default_roots(extra: &[PathBuf], excludes: &Excludes) -> CoreResult<Vec<PathBuf>> {
     let mut roots = Vec::new();
-    roots.push(
-        std::env::current_dir()
-            .map_err(|e| format!("Unable to determine current directory: {}", e))?,
-    );
+    roots.push(std::env::current_dir().map_err(|err| DevstripError::Io {
+        path: None,
+        message: format!("Unable to determine current directory: {}", err),
+    })?);
 
     if let Some(home) = home_dir() {
         for name in DEFAULT_HOME_PROJECT_DIRS {
@@ -205,8 +1403,9 @@ pub fn default_roots(extra: &[PathBuf], excludes: &[PathBuf]) -> CoreResult<Vec<
     let mut unique = Vec::new();
     let mut seen = HashSet::new();
     for root in roots {
-        let resolved = fs::canonicalize(&root).unwrap_or(root.clone());
-        if seen.contains(&resolved) {
+        let resolved = canonicalize(&root).unwrap_or(root.clone());
+        let key = canonical_key(&resolved);
+        if seen.contains(&key) {
             continue;
         }
         if !resolved.exists() {
@@ -215,102 +1414,331 @@ pub fn default_roots(extra: &[PathBuf], excludes: &[PathBuf]) -> CoreResult<Vec<
         if is_excluded(&resolved, excludes) {
             continue;
         }
-        seen.insert(resolved.clone());
+        seen.insert(key);
         unique.push(resolved);
     }
 
     Ok(unique)
 }
 
-pub fn scan_total_size(candidates: &[Candidate]) -> u64 {
-    candidates.iter().map(|c| c.size_bytes).sum()
+/// Measures a candidate whose size a lazy scan left pending, noting
+/// whether every directory below it could be read.
+pub fn measure_candidate(candidate: &mut Candidate) {
+    if !candidate.size_pending {
+        return;
+    }
+    let cache = sizecache::ScanCache::open(false);
+    let usage = verify::current_usage(candidate);
+    candidate.size_bytes = usage.allocated;
+    candidate.logical_bytes = usage.logical;
+    candidate.size_pending = false;
+    candidate.size_incomplete = sizecache::unreadable_below(&candidate.path);
+    cache.close(false);
+}
+
+pub fn scan_total_size<C: Borrow<Candidate>>(candidates: &[C]) -> u64 {
+    candidates.iter().map(|c| c.borrow().size_bytes).sum()
 }
 
 fn gather_candidates<F>(
     config: &ScanConfig,
     reporter: &mut F,
-    cancel_flag: Option<&AtomicBool>,
+    control: Option<&ScanControl>,
 ) -> Vec<Candidate>
 where
-    F: FnMut(&str),
+    F: FnMut(DevstripEvent),
 {
-    let mut candidates = Vec::new();
-
-    if is_cancelled(cancel_flag) {
-        return candidates;
+    let mut candidates = gather_home_candidates(config, reporter, control);
+    if is_cancelled(control) {
+        return finalize_candidates(config, candidates);
     }
+    let (project_candidates, gradle_dists) =
+        gather_project_candidates(&config.roots, config, reporter, control);
+    candidates.extend(project_candidates);
+    candidates.extend(gather_cross_reference_candidates(
+        config,
+        &gradle_dists,
+        reporter,
+        control,
+    ));
+    finalize_candidates(config, candidates)
+}
 
-    let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
-
-    let derived = home.join("Library/Developer/Xcode/DerivedData");
+fn gather_home_candidates<F>(
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut candidates = Vec::new();
+
+    if is_cancelled(control) {
+        return candidates;
+    }
+
+    let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let mut detectors = profile::Detectors::default();
+
+    detectors.next("Xcode DerivedData");
+    let derived = home.join("Library/Developer/Xcode/DerivedData");
     candidates.extend(collect_keep_latest(
         &derived,
-        config.keep_latest_derived,
+        |t| t.keep_latest_derived,
         "Xcode",
         "Old DerivedData projects",
-        &config.exclude_paths,
+        config,
         reporter,
-        cancel_flag,
+        control,
     ));
 
+    detectors.next("Xcode archives");
     let archives = home.join("Library/Developer/Xcode/Archives");
-    candidates.extend(collect_keep_latest(
-        &archives,
-        config.keep_latest_derived,
-        "Xcode",
-        "Old Xcode archives",
-        &config.exclude_paths,
-        reporter,
-        cancel_flag,
+    candidates.extend(archives::collect_xcode_archives(
+        &archives, config, reporter, control,
     ));
 
+    detectors.next("CoreSimulator caches");
     let core_sim = home.join("Library/Developer/CoreSimulator/Caches");
     candidates.extend(collect_whole_directory(
         &core_sim,
         "Xcode",
         "CoreSimulator caches",
+        CleanupStrategy::Remove,
         &config.exclude_paths,
         reporter,
-        cancel_flag,
+        control,
     ));
 
-    let brew_cache = home.join("Library/Caches/Homebrew");
-    candidates.extend(collect_keep_latest(
-        &brew_cache,
-        config.keep_latest_cache,
-        "Homebrew",
-        "Homebrew download cache",
+    detectors.next("Homebrew and Cypress caches");
+    for caches in user_cache_dirs(&home) {
+        candidates.extend(collect_keep_latest(
+            &caches.join("Homebrew"),
+            |t| t.keep_latest_cache,
+            "Homebrew",
+            "Homebrew download cache",
+            config,
+            reporter,
+            control,
+        ));
+        candidates.extend(collect_keep_latest(
+            &caches.join("Cypress"),
+            |t| t.keep_latest_cache,
+            "Cypress",
+            "Old Cypress binary versions",
+            config,
+            reporter,
+            control,
+        ));
+    }
+
+    detectors.next("Podman storage");
+    candidates.extend(tools::collect_podman_storage(
+        &home,
+        &config.exclude_paths,
+        reporter,
+        control,
+    ));
+
+    detectors.next("Kubernetes caches");
+    candidates.extend(kubernetes::collect_kubernetes_caches(
+        &home,
+        &config.exclude_paths,
+        reporter,
+        control,
+    ));
+
+    detectors.next("JetBrains leftovers");
+    candidates.extend(jetbrains::collect_leftover_versions(
+        &home,
+        &config.exclude_paths,
+        reporter,
+        control,
+    ));
+
+    detectors.next("VS Code workspace storage");
+    candidates.extend(vscode::collect_orphaned_workspace_storage(
+        &home,
         &config.exclude_paths,
         reporter,
-        cancel_flag,
+        control,
     ));
+    detectors.next("VS Code extensions");
+    candidates.extend(vscode::collect_superseded_extensions(
+        &home,
+        &config.exclude_paths,
+        reporter,
+        control,
+    ));
+
+    detectors.next("Development logs");
+    candidates.extend(logs::collect_dev_logs(&home, config, reporter, control));
+    detectors.next("Shell clutter");
+    candidates.extend(shell::collect_shell_clutter(
+        &home, config, reporter, control,
+    ));
+
+    detectors.next("Installer downloads");
+    if let Some(min_age_days) = config.downloads_min_age_days {
+        candidates.extend(downloads::collect_installer_artifacts(
+            &home,
+            min_age_days,
+            &config.exclude_paths,
+            reporter,
+            control,
+        ));
+    }
+
+    detectors.next("Core dumps");
+    candidates.extend(collect_core_dumps(
+        Path::new(SYSTEM_CORE_DIR),
+        &config.exclude_paths,
+        reporter,
+        control,
+    ));
+
+    detectors.next("Ruby gems");
+    for gems_dir in gems::ruby_gem_dirs(&home) {
+        candidates.extend(gems::collect_stale_gem_versions(
+            &gems_dir, config, reporter, control,
+        ));
+        if is_cancelled(control) {
+            return candidates;
+        }
+    }
 
-    for (path, category, reason) in build_cache_targets(&home) {
+    detectors.next("Build caches");
+    for (path, category, reason, strategy) in build_cache_targets(&home) {
         candidates.extend(collect_whole_directory(
             &path,
             category,
             reason,
+            strategy,
             &config.exclude_paths,
             reporter,
-            cancel_flag,
+            control,
         ));
-        if is_cancelled(cancel_flag) {
+        if is_cancelled(control) {
             return candidates;
         }
     }
 
-    candidates.extend(collect_matching_dirs(
-        &config.roots,
-        "Project",
-        "Stale build or cache",
-        config.min_age_days,
-        config.max_depth,
-        &config.exclude_paths,
-        reporter,
-        cancel_flag,
-    ));
+    detectors.next("Sandboxed app caches");
+    if cfg!(target_os = "linux") {
+        for (path, category, reason) in linux::sandboxed_app_caches(&home, reporter) {
+            candidates.extend(collect_whole_directory(
+                &path,
+                category,
+                &reason,
+                CleanupStrategy::Remove,
+                &config.exclude_paths,
+                reporter,
+                control,
+            ));
+            if is_cancelled(control) {
+                return candidates;
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Returns the project candidates plus the Gradle wrapper distributions the
+/// visited projects reference.
+fn gather_project_candidates<F>(
+    roots: &[PathBuf],
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> (Vec<Candidate>, HashSet<String>)
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut candidates = Vec::new();
+    let mut gradle_dists = HashSet::new();
+    for root in roots {
+        if is_cancelled(control) {
+            break;
+        }
+        let _profile = profile::root(root);
+        let state = rescan::RootState::of(root, config);
+        let reused = state
+            .as_ref()
+            .filter(|_| config.incremental)
+            .and_then(rescan::RootState::reuse);
+        if let Some((found, dists)) = reused {
+            reporter(DevstripEvent::Scanning {
+                path: root.to_path_buf(),
+            });
+            candidates.extend(
+                found
+                    .into_iter()
+                    .map(|candidate| report_found(reporter, candidate)),
+            );
+            gradle_dists.extend(dists);
+            continue;
+        }
+        let mut root_dists = HashSet::new();
+        let found = collect_matching_dirs(
+            std::slice::from_ref(root),
+            "Project",
+            "Stale build or cache",
+            config,
+            &mut root_dists,
+            reporter,
+            control,
+        );
+        if let Some(state) = state.filter(|_| !is_cancelled(control)) {
+            state.record(&found, &root_dists);
+        }
+        candidates.extend(found);
+        gradle_dists.extend(root_dists);
+    }
+    (candidates, gradle_dists)
+}
 
+/// Home caches whose staleness depends on what the project scan found, so
+/// they can only be judged once every root has been walked.
+fn gather_cross_reference_candidates<F>(
+    config: &ScanConfig,
+    gradle_dists: &HashSet<String>,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    if is_cancelled(control) {
+        return Vec::new();
+    }
+    let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let _profile = profile::detector("Gradle wrapper distributions");
+    gradle::collect_unused_wrapper_dists(&home, gradle_dists, config, reporter, control)
+}
+
+fn finalize_candidates(config: &ScanConfig, candidates: Vec<Candidate>) -> Vec<Candidate> {
     let mut candidates = dedupe_candidates(candidates);
+    candidates.retain(|candidate| config.meets_age_override(candidate));
+    for candidate in &mut candidates {
+        measure::mark_pending(candidate);
+        candidate.size_incomplete |= sizecache::unreadable_below(&candidate.path);
+    }
+    if let Ok(Some(policy)) = policy::active_policy() {
+        candidates.retain(|candidate| !policy.forbids(&candidate.category));
+    }
+    if !config.keep_nested {
+        drop_nested_candidates(&mut candidates);
+    }
+    if let Some(home) = home_dir() {
+        tag_recently_opened(&mut candidates, &home);
+    }
+    // A candidate reused from an earlier walk keeps the stamp taken with its
+    // size.
+    for candidate in candidates.iter_mut().filter(|c| c.scanned.is_none()) {
+        candidate.scanned = ScanStamp::of(&candidate.path);
+    }
     candidates.sort_by(|a, b| match b.size_bytes.cmp(&a.size_bytes) {
         std::cmp::Ordering::Equal => match a.category.cmp(&b.category) {
             std::cmp::Ordering::Equal => a.display_name().cmp(&b.display_name()),
@@ -322,30 +1750,63 @@ where
     candidates
 }
 
+/// Marks the candidates inside a project that VS Code or a JetBrains IDE
+/// opened within `RECENT_WORKSPACE_DAYS` days. A workspace that is the home
+/// folder or above it is ignored, or every cache would be marked.
+fn tag_recently_opened(candidates: &mut [Candidate], home: &Path) {
+    let cutoff = match age_cutoff(RECENT_WORKSPACE_DAYS) {
+        Some(cutoff) if !candidates.is_empty() => cutoff,
+        _ => return,
+    };
+    let mut workspaces: Vec<(PathBuf, &str)> = jetbrains::recent_projects(home)
+        .into_iter()
+        .chain(
+            vscode::recent_workspaces(home)
+                .into_iter()
+                .map(|(path, opened)| (path, "VS Code", opened)),
+        )
+        .filter(|(_, _, opened)| *opened >= cutoff)
+        .map(|(path, ide, _)| (canonicalize(&path).unwrap_or(path), ide))
+        .filter(|(path, _)| !home.starts_with(path))
+        .collect();
+    // Nested workspaces: the innermost one names the IDE.
+    workspaces.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+    for candidate in candidates.iter_mut() {
+        candidate.recently_opened = workspaces
+            .iter()
+            .find(|(workspace, _)| candidate.path.starts_with(workspace))
+            .map(|(_, ide)| ide.to_string());
+    }
+}
+
 fn collect_keep_latest<F>(
     base: &Path,
-    keep: usize,
+    keep_of: fn(&ScanThresholds) -> usize,
     category: &str,
     reason: &str,
-    excludes: &[PathBuf],
+    config: &ScanConfig,
     reporter: &mut F,
-    cancel_flag: Option<&AtomicBool>,
+    control: Option<&ScanControl>,
 ) -> Vec<Candidate>
 where
-    F: FnMut(&str),
+    F: FnMut(DevstripEvent),
 {
+    let keep = keep_of(&config.thresholds());
+    let excludes = &config.exclude_paths;
     let mut results = Vec::new();
     if is_excluded(base, excludes) || !base.exists() {
         return results;
     }
-    reporter(&format!("Scanning: {}", base.display()));
-    if is_cancelled(cancel_flag) {
+    reporter(DevstripEvent::Scanning {
+        path: base.to_path_buf(),
+    });
+    if is_cancelled(control) {
         return results;
     }
 
-    let entries = match fs::read_dir(base) {
-        Ok(iter) => iter,
-        Err(_) => return results,
+    let entries = match read_dir_reporting(base, reporter) {
+        Some(iter) => iter,
+        None => return results,
     };
 
     let mut dated_dirs = Vec::new();
@@ -354,8 +1815,10 @@ where
         if is_excluded(&child, excludes) {
             continue;
         }
-        reporter(&format!("Scanning: {}", child.display()));
-        if is_cancelled(cancel_flag) {
+        reporter(DevstripEvent::Scanning {
+            path: child.to_path_buf(),
+        });
+        if is_cancelled(control) {
             break;
         }
         let metadata = match safe_metadata(&child) {
@@ -365,29 +1828,60 @@ where
         if !metadata.is_dir() {
             continue;
         }
-        if let Ok(modified) = metadata.modified() {
+        if let Some(modified) = last_used(&metadata) {
             dated_dirs.push((modified, child));
         }
     }
 
-    dated_dirs.sort_by(|a, b| b.0.cmp(&a.0));
+    dated_dirs.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+    let reason = match dated_dirs.first() {
+        Some((newest_mtime, newest)) if keep > 0 => {
+            let name = newest
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| newest.display().to_string());
+            let date = format_system_time(*newest_mtime);
+            let date = date.split(' ').next().unwrap_or(&date);
+            let others = keep.min(dated_dirs.len()) - 1;
+            if others > 0 {
+                format!(
+                    "{} (kept {} from {} and {} more)",
+                    reason, name, date, others
+                )
+            } else {
+                format!("{} (kept {} from {})", reason, name, date)
+            }
+        }
+        _ => reason.to_string(),
+    };
 
     for (index, (mtime, path)) in dated_dirs.into_iter().enumerate() {
         if index < keep {
             continue;
         }
-        let size = calculate_size(&path, cancel_flag);
-        if size == 0 {
+        let usage = measure_size(&path, control);
+        if usage.is_empty() {
             continue;
         }
-        results.push(Candidate {
-            path,
-            size_bytes: size,
-            category: category.to_string(),
-            reason: reason.to_string(),
-            last_used: Some(mtime),
-        });
-        if is_cancelled(cancel_flag) {
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path,
+                size_bytes: usage.allocated,
+                logical_bytes: usage.logical,
+                category: category.to_string(),
+                reason: reason.clone(),
+                last_used: Some(mtime),
+                strategy: CleanupStrategy::Remove,
+                deep_only: config.kept_by_baseline(index, keep_of),
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+        if is_cancelled(control) {
             break;
         }
     }
@@ -399,85 +1893,159 @@ fn collect_whole_directory<F>(
     path: &Path,
     category: &str,
     reason: &str,
-    excludes: &[PathBuf],
+    strategy: CleanupStrategy,
+    excludes: &Excludes,
     reporter: &mut F,
-    cancel_flag: Option<&AtomicBool>,
+    control: Option<&ScanControl>,
 ) -> Vec<Candidate>
 where
-    F: FnMut(&str),
+    F: FnMut(DevstripEvent),
 {
     if is_excluded(path, excludes) || !path.exists() {
         return Vec::new();
     }
-    reporter(&format!("Scanning: {}", path.display()));
-    if is_cancelled(cancel_flag) {
+    reporter(DevstripEvent::Scanning {
+        path: path.to_path_buf(),
+    });
+    if is_cancelled(control) {
+        return Vec::new();
+    }
+    if path.is_dir() && read_dir_reporting(path, reporter).is_none() {
         return Vec::new();
     }
-    let size = calculate_size(path, cancel_flag);
-    if size == 0 {
+    let usage = measure_size(path, control);
+    if usage.is_empty() {
         return Vec::new();
     }
     let metadata = safe_metadata(path);
-    let last_used = metadata.and_then(|meta| meta.modified().ok());
-    vec![Candidate {
-        path: path.to_path_buf(),
-        size_bytes: size,
-        category: category.to_string(),
-        reason: reason.to_string(),
-        last_used,
-    }]
+    let last_used = metadata.and_then(|meta| last_used(&meta));
+    vec![report_found(
+        reporter,
+        Candidate {
+            path: path.to_path_buf(),
+            size_bytes: usage.allocated,
+            logical_bytes: usage.logical,
+            category: category.to_string(),
+            reason: reason.to_string(),
+            last_used,
+            strategy,
+            deep_only: false,
+            recently_opened: None,
+            scanned: None,
+            size_pending: false,
+            size_incomplete: false,
+            matched_rule: None,
+        },
+    )]
 }
 
 fn collect_matching_dirs<F>(
     roots: &[PathBuf],
     category: &str,
     reason: &str,
-    min_age_days: u64,
-    max_depth: u32,
-    excludes: &[PathBuf],
+    config: &ScanConfig,
+    gradle_dists: &mut HashSet<String>,
     reporter: &mut F,
-    cancel_flag: Option<&AtomicBool>,
+    control: Option<&ScanControl>,
 ) -> Vec<Candidate>
 where
-    F: FnMut(&str),
+    F: FnMut(DevstripEvent),
 {
     let mut results = Vec::new();
-    let cutoff = if min_age_days == 0 {
-        None
-    } else {
-        SystemTime::now().checked_sub(Duration::from_secs(min_age_days * 86_400))
-    };
+    let max_depth = config.max_depth;
+    let excludes = &config.exclude_paths;
+    let glob_excludes = ignore::GlobExcludes::new(&config.exclude_globs);
 
-    let pattern_set: HashSet<&str> = PROJECT_PATTERNS.iter().copied().collect();
+    let pattern_set: HashSet<&str> = PROJECT_PATTERNS
+        .iter()
+        .copied()
+        .chain(config.extra_patterns.iter().map(String::as_str))
+        .collect();
     let skip_dirs: HashSet<&str> = SKIP_DIR_NAMES.iter().copied().collect();
+    // All roots, not just this batch: a root nested inside another one is
+    // walked on its own, with its own depth budget, so the outer walk
+    // stops there instead of covering it twice.
+    let all_roots: HashSet<&Path> = config.roots.iter().map(PathBuf::as_path).collect();
 
     for root in roots {
         if is_excluded(root, excludes) || !root.is_dir() {
             continue;
         }
-        reporter(&format!("Scanning: {}", root.display()));
-        if is_cancelled(cancel_flag) {
+        reporter(DevstripEvent::Scanning {
+            path: root.to_path_buf(),
+        });
+        if is_cancelled(control) {
             break;
         }
-
-        let mut queue: VecDeque<(PathBuf, u32)> = VecDeque::new();
-        queue.push_back((root.clone(), 0));
-
-        while let Some((current, depth)) = queue.pop_front() {
-            if depth > max_depth {
+        if let Some(days) = config.active_repo_days {
+            if git::enclosing_repo(root).is_some_and(|repo| git::recently_active(&repo, days)) {
                 continue;
             }
-            if is_excluded(&current, excludes) {
+        }
+        let root_device = config
+            .same_filesystem
+            .then(|| fs::metadata(root).ok().and_then(|meta| device_id(&meta)))
+            .flatten();
+
+        // Each queued directory carries the `.devstripignore` and
+        // `.devstrip.toml` files of the projects it sits in.
+        let mut queue: VecDeque<(PathBuf, u32, Vec<Rc<ignore::IgnoreFile>>, Overrides)> =
+            VecDeque::new();
+        queue.push_back((root.clone(), 0, Vec::new(), Overrides::default()));
+        // Canonical paths of the directories walked so far, so that followed
+        // links never visit a directory twice.
+        let mut visited = HashSet::new();
+
+        while let Some((current, depth, mut ignores, mut overrides)) = queue.pop_front() {
+            if depth > max_depth || (depth > 0 && all_roots.contains(current.as_path())) {
+                continue;
+            }
+            if is_excluded(&current, excludes) || glob_excludes.matches(&current) {
                 continue;
             }
-            reporter(&format!("Scanning: {}", current.display()));
-            if is_cancelled(cancel_flag) {
+            if config.symlinks == SymlinkPolicy::Follow
+                && !visited.insert(canonicalize(&current).unwrap_or(current.clone()))
+            {
+                continue;
+            }
+            reporter(DevstripEvent::Scanning {
+                path: current.to_path_buf(),
+            });
+            profile::count_directory(0);
+            if is_cancelled(control) {
                 break;
             }
+            if let Some(days) = config.active_repo_days {
+                if depth > 0
+                    && current.join(".git").exists()
+                    && git::recently_active(&current, days)
+                {
+                    continue;
+                }
+            }
+            if let Some(file) = ignore::IgnoreFile::load(&current) {
+                for warning in &file.invalid {
+                    reporter(DevstripEvent::Warning(warning.clone()));
+                }
+                ignores.push(Rc::new(file));
+            }
+            match overrides::ProjectOverrides::load(&current) {
+                Some(Ok(file)) => overrides.push(file),
+                Some(Err(warning)) => reporter(DevstripEvent::Warning(warning)),
+                None => {}
+            }
+            let ignored = |path: &Path| {
+                glob_excludes.matches(path) || ignores.iter().any(|file| file.matches(path))
+            };
+            let min_age_days = overrides.min_age_days().unwrap_or(config.min_age_days);
+            let cutoff = age_cutoff(min_age_days);
+            let cutoff_for = |name: &str, category: &str| {
+                age_cutoff(config.age_override(name, category).unwrap_or(min_age_days))
+            };
 
-            let entries = match fs::read_dir(&current) {
-                Ok(iter) => iter,
-                Err(_) => continue,
+            let entries = match read_dir_reporting(&current, reporter) {
+                Some(iter) => iter,
+                None => continue,
             };
 
             for entry in entries.flatten() {
@@ -486,13 +2054,111 @@ where
                     Err(_) => continue,
                 };
                 if file_type.is_symlink() {
+                    if config.symlinks == SymlinkPolicy::Skip {
+                        continue;
+                    }
+                    let link = entry.path();
+                    if is_excluded(&link, excludes) || ignored(&link) {
+                        continue;
+                    }
+                    let target = match canonicalize(&link) {
+                        Ok(target) if target.is_dir() => target,
+                        _ => continue,
+                    };
+                    if is_excluded(&target, excludes) {
+                        continue;
+                    }
+                    let metadata = match safe_metadata(&target) {
+                        Some(meta) => meta,
+                        None => continue,
+                    };
+                    if root_device.is_some() && device_id(&metadata) != root_device {
+                        continue;
+                    }
+                    let name = match link.file_name().and_then(|n| n.to_str()) {
+                        Some(n) => n,
+                        None => continue,
+                    };
+                    if let Some((category_text, reason_text, modified, matched_rule)) =
+                        classify_project_dir(
+                            name,
+                            &current,
+                            category,
+                            reason,
+                            &pattern_set,
+                            &cutoff_for,
+                            &target,
+                        )
+                    {
+                        // The candidate is the link, not the directory it
+                        // points to: that may lie outside the scan roots and
+                        // serve other projects, so cleanup only unlinks it.
+                        let holds_entries =
+                            fs::read_dir(&target).is_ok_and(|mut entries| entries.next().is_some());
+                        let link_meta = safe_metadata(&link);
+                        if let (true, Some(link_meta)) = (holds_entries, link_meta) {
+                            let usage = DiskUsage::of_file(&link_meta);
+                            keep_found(
+                                &mut results,
+                                reporter,
+                                &overrides,
+                                Candidate {
+                                    path: link,
+                                    size_bytes: usage.allocated,
+                                    logical_bytes: usage.logical,
+                                    category: category_text,
+                                    reason: format!(
+                                        "Symlink to {}, which is kept: {}",
+                                        target.display(),
+                                        reason_text
+                                    ),
+                                    last_used: modified,
+                                    strategy: CleanupStrategy::RemoveLink,
+                                    deep_only: config.beyond_baseline_depth(depth)
+                                        || config.too_recent_for_baseline(modified),
+                                    recently_opened: None,
+                                    scanned: None,
+                                    size_pending: false,
+                                    size_incomplete: false,
+                                    matched_rule,
+                                },
+                            );
+                        }
+                    } else if config.symlinks == SymlinkPolicy::Follow
+                        && depth < max_depth
+                        && !skip_dirs.contains(name)
+                    {
+                        queue.push_back((target, depth + 1, ignores.clone(), overrides.clone()));
+                    }
+                    if is_cancelled(control) {
+                        break;
+                    }
+                    continue;
+                }
+                if file_type.is_file() {
+                    let path = entry.path();
+                    if is_excluded(&path, excludes) || ignored(&path) {
+                        continue;
+                    }
+                    if let Some(mut candidate) = core_dump_candidate(&path, cutoff) {
+                        candidate.deep_only = config.beyond_baseline_depth(depth)
+                            || config.too_recent_for_baseline(candidate.last_used);
+                        keep_found(&mut results, reporter, &overrides, candidate);
+                    }
                     continue;
                 }
                 if !file_type.is_dir() {
                     continue;
                 }
                 let path = entry.path();
-                if is_excluded(&path, excludes) {
+                if is_excluded(&path, excludes) || ignored(&path) {
+                    continue;
+                }
+                let metadata = match safe_metadata(&path) {
+                    Some(meta) => meta,
+                    None => continue,
+                };
+                if root_device.is_some() && device_id(&metadata) != root_device {
                     continue;
                 }
                 let name = match path.file_name().and_then(|n| n.to_str()) {
@@ -503,41 +2169,118 @@ where
                 if skip_dirs.contains(name) {
                     continue;
                 }
+                if name == "gradle" {
+                    if let Some(dist) =
+                        gradle::wrapper_distribution(&path.join(gradle::WRAPPER_PROPERTIES))
+                    {
+                        gradle_dists.insert(dist);
+                    }
+                }
 
-                let metadata = match safe_metadata(&path) {
-                    Some(meta) => meta,
-                    None => continue,
-                };
-                let modified = metadata.modified().ok();
+                if config.cargo_sweep && name == "target" && current.join("Cargo.toml").is_file() {
+                    // Without an age limit every artifact is old enough; the
+                    // whole `target` is never removed once a sweep is asked for.
+                    let limit = cutoff.unwrap_or_else(SystemTime::now);
+                    if let Some(candidate) = sweep::collect_stale_artifacts(&path, limit) {
+                        keep_found(&mut results, reporter, &overrides, candidate);
+                    }
+                    if is_cancelled(control) {
+                        break;
+                    }
+                    continue;
+                }
 
-                if let Some(reason_text) =
-                    classify_project_dir(name, reason, &pattern_set, cutoff, modified)
+                if attribution::is_shared_cache_dir(name) {
+                    for mut candidate in attribution::attribute_children(
+                        &path, category, reason, excludes, cutoff, control,
+                    )
+                    .into_iter()
+                    .filter(|candidate| !ignored(&candidate.path))
+                    {
+                        candidate.deep_only = config.beyond_baseline_depth(depth)
+                            || config.too_recent_for_baseline(candidate.last_used);
+                        keep_found(&mut results, reporter, &overrides, candidate);
+                    }
+                    if is_cancelled(control) {
+                        break;
+                    }
+                    continue;
+                }
+
+                if let Some((category_text, reason_text, modified, matched_rule)) =
+                    classify_project_dir(
+                        name,
+                        &current,
+                        category,
+                        reason,
+                        &pattern_set,
+                        &cutoff_for,
+                        &path,
+                    )
                 {
-                    let size = calculate_size(&path, cancel_flag);
-                    if size > 0 {
-                        results.push(Candidate {
-                            path: path.clone(),
-                            size_bytes: size,
-                            category: category.to_string(),
-                            reason: reason_text,
-                            last_used: modified,
-                        });
+                    let usage = measure_size(&path, control);
+                    if !usage.is_empty() {
+                        keep_found(
+                            &mut results,
+                            reporter,
+                            &overrides,
+                            Candidate {
+                                path: path.clone(),
+                                size_bytes: usage.allocated,
+                                logical_bytes: usage.logical,
+                                category: category_text,
+                                reason: reason_text,
+                                last_used: modified,
+                                strategy: CleanupStrategy::Remove,
+                                deep_only: config.beyond_baseline_depth(depth)
+                                    || (name != "__pycache__"
+                                        && config.too_recent_for_baseline(modified)),
+                                recently_opened: None,
+                                scanned: None,
+                                size_pending: false,
+                                size_incomplete: false,
+                                matched_rule,
+                            },
+                        );
                     }
-                    if is_cancelled(cancel_flag) {
+                    if is_cancelled(control) {
+                        break;
+                    }
+                    continue;
+                }
+
+                let rule = overrides
+                    .match_rule(name, &current)
+                    .or_else(|| rules::match_custom_rule(&config.custom_rules, name, &current));
+                if let Some(rule) = rule {
+                    if let Some(mut candidate) = rules::rule_candidate(
+                        rule,
+                        &path,
+                        config,
+                        config
+                            .age_override(name, &rule.category)
+                            .unwrap_or(min_age_days),
+                        newest_use(&path, &metadata),
+                        control,
+                    ) {
+                        candidate.deep_only |= config.beyond_baseline_depth(depth);
+                        keep_found(&mut results, reporter, &overrides, candidate);
+                    }
+                    if is_cancelled(control) {
                         break;
                     }
                     continue;
                 }
 
                 if depth < max_depth {
-                    queue.push_back((path, depth + 1));
+                    queue.push_back((path, depth + 1, ignores.clone(), overrides.clone()));
                 }
             }
-            if is_cancelled(cancel_flag) {
+            if is_cancelled(control) {
                 break;
             }
         }
-        if is_cancelled(cancel_flag) {
+        if is_cancelled(control) {
             break;
         }
     }
@@ -545,36 +2288,357 @@ where
     results
 }
 
+/// Reports `candidate` and adds it to `results`, unless a `.devstrip.toml`
+/// of its project disables its category.
+fn keep_found<F>(
+    results: &mut Vec<Candidate>,
+    reporter: &mut F,
+    overrides: &Overrides,
+    candidate: Candidate,
+) where
+    F: FnMut(DevstripEvent),
+{
+    if !overrides.disables(&candidate.category) {
+        results.push(report_found(reporter, candidate));
+    }
+}
+
+/// The time before which something is old enough, or `None` when every age
+/// is. An age reaching back past the clock's range leaves nothing old enough.
+fn age_cutoff(min_age_days: u64) -> Option<SystemTime> {
+    if min_age_days == 0 {
+        return None;
+    }
+    let age = Duration::from_secs(min_age_days.saturating_mul(86_400));
+    Some(SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH))
+}
+
+/// Category, reason, last use and matched rule of the directory `name` in
+/// `parent` when it is a stale project artifact. `tree` is what the entry resolves to
+/// (the link target for a symlink); `cutoff` gives the age limit for a
+/// name and category.
 fn classify_project_dir(
     name: &str,
+    parent: &Path,
+    base_category: &str,
     base_reason: &str,
     pattern_set: &HashSet<&str>,
-    cutoff: Option<SystemTime>,
-    modified: Option<SystemTime>,
-) -> Option<String> {
+    cutoff: &dyn Fn(&str, &str) -> Option<SystemTime>,
+    tree: &Path,
+) -> Option<(String, String, Option<SystemTime>, Option<MatchedRule>)> {
     if name == "__pycache__" {
-        return Some(base_reason.to_string());
+        return Some((
+            base_category.to_string(),
+            base_reason.to_string(),
+            safe_metadata(tree).and_then(|meta| last_used(&meta)),
+            None,
+        ));
     }
 
-    let matches_named_pattern = pattern_set.contains(name) || name.ends_with(".egg-info");
-    if !matches_named_pattern {
+    let mut generic_name = false;
+    let (category, label, matched) = if let Some(rule) = match_project_rule(name, parent) {
+        let label = match rule.parent {
+            Some(parent_name) => format!("{}/{}", parent_name, name),
+            None => name.to_string(),
+        };
+        let matched = MatchedRule::Project {
+            name: name.to_string(),
+            parent: rule.parent.map(str::to_string),
+        };
+        (rule.category, label, Some(matched))
+    } else if let Some((_, manifests)) = BUILD_DIR_MANIFESTS.iter().find(|(dir, _)| *dir == name) {
+        let manifest = manifests
+            .iter()
+            .find(|manifest| parent.join(manifest).is_file())?;
+        generic_name = true;
+        let matched = MatchedRule::BuildDir {
+            name: name.to_string(),
+            manifest: manifest.to_string(),
+        };
+        (
+            base_category,
+            format!("{}, {}", name, manifest),
+            Some(matched),
+        )
+    } else if DOTNET_BUILD_DIRS.contains(&name) {
+        let project = dotnet_project_file(parent)?;
+        let label = format!("{}, {}", name, project);
+        let matched = MatchedRule::Dotnet {
+            name: name.to_string(),
+            project,
+        };
+        (base_category, label, Some(matched))
+    } else if pattern_set.contains(name) || name.ends_with(".egg-info") {
+        (base_category, name.to_string(), None)
+    } else {
+        return None;
+    };
+
+    let modified = safe_metadata(tree).and_then(|meta| newest_use(tree, &meta));
+    if let (Some(limit), Some(mtime)) = (cutoff(name, category), modified) {
+        if mtime >= limit {
+            return None;
+        }
+    }
+    if generic_name && git::has_tracked_files(&parent.join(name)) {
         return None;
     }
 
+    Some((
+        category.to_string(),
+        format!("{} ({})", base_reason, label),
+        modified,
+        matched,
+    ))
+}
+
+/// The name of a .NET project file (`App.csproj`, `Lib.fsproj`, ...) in
+/// `dir`, if there is one.
+fn dotnet_project_file(dir: &Path) -> Option<String> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            Path::new(name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| DOTNET_PROJECT_EXTENSIONS.contains(&ext))
+        })
+        .min()
+}
+
+fn match_project_rule(name: &str, parent: &Path) -> Option<&'static ProjectRule> {
+    PROJECT_RULES.iter().find(|rule| {
+        if rule.name != name {
+            return false;
+        }
+        let project_root = match rule.parent {
+            Some(parent_name) => {
+                if parent.file_name().and_then(|n| n.to_str()) != Some(parent_name) {
+                    return false;
+                }
+                match parent.parent() {
+                    Some(root) => root,
+                    None => return false,
+                }
+            }
+            None => parent,
+        };
+        if !project_root.join(rule.manifest).is_file() {
+            return false;
+        }
+        // Zero-install projects commit their Yarn cache; it is not ours to drop.
+        rule.parent != Some(".yarn") || !yarn::cache_committed(project_root)
+    })
+}
+
+fn collect_core_dumps<F>(
+    dir: &Path,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    if is_excluded(dir, excludes) || !dir.is_dir() {
+        return results;
+    }
+    reporter(DevstripEvent::Scanning {
+        path: dir.to_path_buf(),
+    });
+    let entries = match read_dir_reporting(dir, reporter) {
+        Some(entries) => entries,
+        None => return results,
+    };
+    for entry in entries.flatten() {
+        if is_cancelled(control) {
+            break;
+        }
+        let path = entry.path();
+        if is_excluded(&path, excludes) {
+            continue;
+        }
+        if let Some(candidate) = core_dump_candidate(&path, None) {
+            results.push(report_found(reporter, candidate));
+        }
+    }
+    results
+}
+
+/// Whether `header` starts an ELF or Mach-O core dump. Executables and
+/// libraries share the magic, so the file type decides: ELF's `e_type` at
+/// offset 16, in the byte order `EI_DATA` names, and Mach-O's `filetype`
+/// at offset 12.
+fn is_core_dump_header(header: &[u8; 20]) -> bool {
+    let elf_type = [header[16], header[17]];
+    match header {
+        [0x7f, b'E', b'L', b'F', _, 1, ..] => u16::from_le_bytes(elf_type) == ELF_CORE_TYPE,
+        [0x7f, b'E', b'L', b'F', _, 2, ..] => u16::from_be_bytes(elf_type) == ELF_CORE_TYPE,
+        [0xcf | 0xce, 0xfa, 0xed, 0xfe, ..] => {
+            u32::from_le_bytes([header[12], header[13], header[14], header[15]]) == MACHO_CORE_TYPE
+        }
+        _ => false,
+    }
+}
+
+fn core_dump_candidate(path: &Path, cutoff: Option<SystemTime>) -> Option<Candidate> {
+    let name = path.file_name()?.to_str()?;
+    let suffix_ok = match name.strip_prefix("core") {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix('.')
+            .map(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false),
+        None => false,
+    };
+    if !suffix_ok {
+        return None;
+    }
+
+    let metadata = safe_metadata(path)?;
+    if !metadata.is_file() || metadata.len() == 0 {
+        return None;
+    }
+    // Reading the header below bumps the access time, so a dump counts as
+    // old as when it was written.
+    let modified = metadata.modified().ok();
     if let (Some(limit), Some(mtime)) = (cutoff, modified) {
         if mtime >= limit {
             return None;
         }
     }
 
-    Some(format!("{} ({})", base_reason, name))
+    let mut header = [0u8; 20];
+    let mut file = fs::File::open(path).ok()?;
+    io::Read::read_exact(&mut file, &mut header).ok()?;
+    if !is_core_dump_header(&header) {
+        return None;
+    }
+
+    let usage = DiskUsage::of_file(&metadata);
+    Some(Candidate {
+        path: path.to_path_buf(),
+        size_bytes: usage.allocated,
+        logical_bytes: usage.logical,
+        category: "Diagnostics".to_string(),
+        reason: "Core dump".to_string(),
+        last_used: modified,
+        strategy: CleanupStrategy::Remove,
+        deep_only: false,
+        recently_opened: None,
+        scanned: None,
+        size_pending: false,
+        size_incomplete: false,
+        matched_rule: None,
+    })
+}
+
+fn split_gem_dir_name(dir_name: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = dir_name.split('-').collect();
+    let version_index = parts
+        .iter()
+        .position(|part| part.starts_with(|c: char| c.is_ascii_digit()))?;
+    if version_index == 0 {
+        return None;
+    }
+    Some((
+        parts[..version_index].join("-"),
+        parts[version_index..].join("-"),
+    ))
+}
+
+/// Splits the platform off a version from `split_gem_dir_name`:
+/// `1.15.4-x86_64-darwin` is a build of `1.15.4` for another platform, not
+/// a newer version of it. The platform is empty for a plain build.
+fn split_platform(version: &str) -> (String, String) {
+    match version.split_once('-') {
+        Some((version, platform)) => (version.to_string(), platform.to_string()),
+        None => (version.to_string(), String::new()),
+    }
+}
+
+/// One run of digits or of letters in a version. Letters mark a prerelease,
+/// so they sort below any number.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum VersionSegment<'a> {
+    Text(&'a str),
+    Number(u64),
+}
+
+fn version_segments(version: &str) -> Vec<VersionSegment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = version;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphanumeric()) {
+        rest = &rest[start..];
+        let digits = rest.starts_with(|c: char| c.is_ascii_digit());
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() || c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        segments.push(if digits {
+            VersionSegment::Number(rest[..end].parse().unwrap_or(u64::MAX))
+        } else {
+            VersionSegment::Text(&rest[..end])
+        });
+        rest = &rest[end..];
+    }
+    segments
+}
+
+/// Orders versions the way RubyGems does: missing segments count as zero
+/// and a segment with letters is a prerelease, so `7.1.0.rc1` comes before
+/// `7.1.0`, which equals `7.1`.
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let (left, right) = (version_segments(a), version_segments(b));
+    let zero = VersionSegment::Number(0);
+    (0..left.len().max(right.len()))
+        .map(|index| {
+            let l = left.get(index).unwrap_or(&zero);
+            let r = right.get(index).unwrap_or(&zero);
+            l.cmp(r)
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(std::cmp::Ordering::Equal)
+}
+
+fn report_found<F>(reporter: &mut F, candidate: Candidate) -> Candidate
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut candidate = candidate;
+    measure::mark_pending(&mut candidate);
+    reporter(DevstripEvent::CandidateFound(candidate.clone()));
+    candidate
+}
+
+/// `fs::read_dir` for the scanners: a directory that exists but cannot be
+/// read is reported as skipped instead of being dropped silently.
+fn read_dir_reporting<F>(dir: &Path, reporter: &mut F) -> Option<fs::ReadDir>
+where
+    F: FnMut(DevstripEvent),
+{
+    match fs::read_dir(dir) {
+        Ok(entries) => Some(entries),
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                reporter(DevstripEvent::PathSkipped(SkippedPath {
+                    path: dir.to_path_buf(),
+                    kind: err.kind(),
+                }));
+            }
+            None
+        }
+    }
 }
 
 fn dedupe_candidates(candidates: Vec<Candidate>) -> Vec<Candidate> {
     let mut seen = HashSet::new();
     let mut unique = Vec::with_capacity(candidates.len());
     for candidate in candidates {
-        let key = canonical_key(&candidate.path);
+        let key = candidate_key(&candidate);
         if seen.insert(key) {
             unique.push(candidate);
         }
@@ -582,92 +2646,512 @@ fn dedupe_candidates(candidates: Vec<Candidate>) -> Vec<Candidate> {
     unique
 }
 
+/// Drops the candidates inside a tree another candidate removes whole,
+/// which would otherwise be counted twice. Run it after every filter on
+/// the list: a nested candidate is only covered while its tree is kept.
+pub fn drop_nested_candidates(candidates: &mut Vec<Candidate>) {
+    let keys: Vec<PathBuf> = candidates.iter().map(candidate_key).collect();
+    let removed_trees: Vec<&PathBuf> = candidates
+        .iter()
+        .zip(&keys)
+        .filter(|(candidate, _)| {
+            matches!(
+                candidate.strategy,
+                CleanupStrategy::Remove
+                    | CleanupStrategy::EmptyDirectory
+                    | CleanupStrategy::RemoveGemVersion
+            )
+        })
+        .map(|(_, key)| key)
+        .collect();
+    let nested: Vec<bool> = keys
+        .iter()
+        .map(|key| {
+            removed_trees
+                .iter()
+                .any(|tree| key != *tree && key.starts_with(tree))
+        })
+        .collect();
+    let mut nested = nested.into_iter();
+    candidates.retain(|_| !nested.next().unwrap_or(false));
+}
+
+/// What two candidates for the same file have in common: the canonical
+/// path, Unicode-normalized, and lowercased on a case-insensitive volume.
 fn canonical_key(path: &Path) -> PathBuf {
-    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    let resolved = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    pathkey::key(&resolved, pathkey::case_insensitive(&resolved))
+}
+
+/// `canonical_key` of what cleaning `candidate` touches: the link itself for
+/// a symlink candidate rather than the directory it resolves to.
+fn candidate_key(candidate: &Candidate) -> PathBuf {
+    if candidate.strategy != CleanupStrategy::RemoveLink {
+        return canonical_key(&candidate.path);
+    }
+    entry_key(&candidate.path)
+}
+
+/// `canonical_key` that leaves the last component as it is, so a symlink
+/// keys as the link rather than what it points to.
+fn entry_key(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = canonicalize(parent).unwrap_or_else(|_| parent.to_path_buf());
+            pathkey::key(&parent.join(name), pathkey::case_insensitive(&parent))
+        }
+        _ => path.to_path_buf(),
+    }
 }
 
-fn build_cache_targets(home: &Path) -> Vec<(PathBuf, &'static str, &'static str)> {
-    CACHE_TARGETS
+/// `fs::canonicalize` without the Windows `\\?\` prefix, so the result
+/// can be compared with paths that were never canonicalized.
+fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(path).map(windows::strip_verbatim)
+}
+
+fn build_cache_targets(home: &Path) -> Vec<(PathBuf, &'static str, &'static str, CleanupStrategy)> {
+    let mut targets: Vec<_> = CACHE_TARGETS
         .iter()
-        .map(|(relative, category, reason)| (home.join(relative), *category, *reason))
-        .collect()
+        .map(|(relative, category, reason, strategy)| {
+            (
+                linux::resolve(home, relative),
+                *category,
+                *reason,
+                strategy.clone(),
+            )
+        })
+        .collect();
+    if cfg!(windows) {
+        targets.extend(windows::cache_targets(home));
+    }
+    if cfg!(target_os = "linux") {
+        targets.extend(linux::cache_targets(home));
+    }
+    targets
 }
 
-fn delete_path(path: &Path) -> io::Result<()> {
-    let metadata = match safe_metadata(path) {
-        Some(meta) => meta,
-        None => return Ok(()),
-    };
-    if metadata.is_dir() {
-        fs::remove_dir_all(path)
+/// Per-user cache folders that tools like Homebrew and Cypress download
+/// into: `~/Library/Caches`, plus `$XDG_CACHE_HOME` on Linux.
+fn user_cache_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![home.join("Library/Caches")];
+    if cfg!(target_os = "linux") {
+        dirs.push(linux::cache_home(home));
+    }
+    dirs
+}
+
+fn apply_strategy(
+    verified: &VerifiedCandidate,
+    helper: Option<&mut sandbox::DeleteHelper>,
+    use_trash: bool,
+    cancel: &AtomicBool,
+    unlocked: &mut Vec<PathBuf>,
+) -> CoreResult<()> {
+    let candidate = verified.candidate();
+    match &candidate.strategy {
+        CleanupStrategy::Remove | CleanupStrategy::RemoveLink if use_trash => {
+            trash::move_to_trash(&candidate.path)
+        }
+        // `delete_path` unlinks a symlink without following it.
+        CleanupStrategy::Remove | CleanupStrategy::RemoveLink => match helper {
+            Some(helper) => helper.delete(&candidate.path),
+            None => delete_path_cancellable(&candidate.path, cancel, unlocked)
+                .map_err(|err| io_error(&candidate.path, err)),
+        },
+        CleanupStrategy::Command { program, args } => tools::run_cleanup_command(program, args),
+        CleanupStrategy::SweepCargoTarget { cutoff } => {
+            sweep::prune_stale_artifacts(&candidate.path, *cutoff, cancel, |path| {
+                remove_entry(path, None, use_trash, cancel, unlocked)
+            })
+        }
+        CleanupStrategy::PruneFilesOlderThan { cutoff } => {
+            logs::prune_stale_files(&candidate.path, *cutoff, |path| {
+                if use_trash {
+                    trash::move_to_trash(path)
+                } else {
+                    fs::remove_file(path).map_err(|err| io_error(path, err))
+                }
+            })
+        }
+        CleanupStrategy::Compress => {
+            compress::compress_in_place(&candidate.path, use_trash).map(|_| ())
+        }
+        CleanupStrategy::TruncateHistory { keep_lines } => {
+            shell::truncate_history(&candidate.path, *keep_lines, use_trash)
+        }
+        CleanupStrategy::EmptyDirectory => {
+            empty_directory(&candidate.path, helper, use_trash, cancel, unlocked)
+        }
+        CleanupStrategy::RemoveGemVersion => {
+            let mut helper = helper;
+            for path in gems::version_paths(&candidate.path) {
+                remove_entry(&path, helper.as_deref_mut(), use_trash, cancel, unlocked)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Removes everything inside `dir` the way a `Remove` target would be
+/// removed (to the trash, through the helper, or directly), leaving `dir`
+/// itself in place.
+fn empty_directory(
+    dir: &Path,
+    mut helper: Option<&mut sandbox::DeleteHelper>,
+    use_trash: bool,
+    cancel: &AtomicBool,
+    unlocked: &mut Vec<PathBuf>,
+) -> CoreResult<()> {
+    let entries = fs::read_dir(dir).map_err(|err| io_error(dir, err))?;
+    for entry in entries {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(DevstripError::Cancelled);
+        }
+        let child = entry.map_err(|err| io_error(dir, err))?.path();
+        remove_entry(&child, helper.as_deref_mut(), use_trash, cancel, unlocked)?;
+    }
+    Ok(())
+}
+
+/// Removes `path` the way a `Remove` target is removed.
+fn remove_entry(
+    path: &Path,
+    helper: Option<&mut sandbox::DeleteHelper>,
+    use_trash: bool,
+    cancel: &AtomicBool,
+    unlocked: &mut Vec<PathBuf>,
+) -> CoreResult<()> {
+    if use_trash {
+        trash::move_to_trash(path)
+    } else if let Some(helper) = helper {
+        helper.delete(path)
     } else {
-        fs::remove_file(path)
+        delete_path_cancellable(path, cancel, unlocked).map_err(|err| io_error(path, err))
     }
 }
 
-fn safe_metadata(path: &Path) -> Option<fs::Metadata> {
-    fs::symlink_metadata(path).ok()
+/// Cleanup failures are listed next to the target, so the message is just
+/// the OS error.
+fn io_error(path: &Path, err: io::Error) -> DevstripError {
+    if err.kind() == io::ErrorKind::Interrupted {
+        return DevstripError::Cancelled;
+    }
+    DevstripError::io(path, &err, err.to_string())
 }
 
-fn calculate_size(path: &Path, cancel_flag: Option<&AtomicBool>) -> u64 {
-    let metadata = match safe_metadata(path) {
-        Some(meta) => meta,
-        None => return 0,
-    };
+fn delete_path(path: &Path) -> io::Result<()> {
+    delete_path_cancellable(path, &AtomicBool::new(false), &mut Vec::new())
+}
 
-    if !metadata.is_dir() {
-        return metadata.len();
+/// How many entries a cancellable removal deletes between checks of the
+/// cancel flag.
+const CANCEL_CHECK_INTERVAL: usize = 256;
+
+/// `delete_path` for a removal that may be stopped: directories are removed
+/// entry by entry, and an `Interrupted` error is returned once `cancel` is
+/// set. Whatever was already removed stays removed. Paths that had to be
+/// made writable first are added to `unlocked`.
+fn delete_path_cancellable(
+    path: &Path,
+    cancel: &AtomicBool,
+    unlocked: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    match safe_metadata(path) {
+        Some(meta) if meta.is_dir() => {
+            let mut removal = Removal {
+                root: path,
+                cancel,
+                removed: 0,
+                unlocked,
+            };
+            removal.remove_dir(path)
+        }
+        Some(_) => unlock::remove(path, false, path, unlocked),
+        None => Ok(()),
+    }
+}
+
+/// The state of one cancellable directory removal.
+struct Removal<'a> {
+    root: &'a Path,
+    cancel: &'a AtomicBool,
+    removed: usize,
+    unlocked: &'a mut Vec<PathBuf>,
+}
+
+impl Removal<'_> {
+    fn remove_dir(&mut self, path: &Path) -> io::Result<()> {
+        let entries = unlock::read_dir(path, self.unlocked)?;
+        // A directory swapped for a symlink after it was listed by its parent
+        // was opened through the link: unlink the link and leave what it
+        // points to alone.
+        if safe_metadata(path).is_none_or(|meta| !meta.is_dir()) {
+            drop(entries);
+            return unlock::remove(path, false, self.root, self.unlocked);
+        }
+        for entry in entries {
+            let entry = entry?;
+            // `DirEntry::file_type` does not follow symlinks, so a link to a
+            // directory is unlinked rather than descended into.
+            if entry.file_type()?.is_dir() {
+                self.remove_dir(&entry.path())?;
+            } else {
+                unlock::remove(&entry.path(), false, self.root, self.unlocked)?;
+            }
+            self.removed += 1;
+            if self.removed.is_multiple_of(CANCEL_CHECK_INTERVAL)
+                && self.cancel.load(Ordering::Relaxed)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "cleanup was cancelled",
+                ));
+            }
+        }
+        unlock::remove(path, true, self.root, self.unlocked)
     }
+}
+
+/// The device a file lives on; a different device than the root's marks a
+/// mount point.
+#[cfg(unix)]
+fn device_id(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+fn safe_metadata(path: &Path) -> Option<fs::Metadata> {
+    fs::symlink_metadata(path).ok()
+}
 
-    if is_cancelled(cancel_flag) {
-        return 0;
+/// When a file was last written or, if the filesystem keeps access times,
+/// last read: caches such as pip's are read long after they were written.
+/// Directories only count their modification time, since listing one
+/// (which every scan does) updates its access time. On a `noatime` mount
+/// the access time never advances, which leaves the modification time.
+fn last_used(meta: &fs::Metadata) -> Option<SystemTime> {
+    let modified = meta.modified().ok();
+    if !meta.is_file() {
+        return modified;
     }
+    let accessed = meta
+        .accessed()
+        .ok()
+        .filter(|accessed| *accessed <= SystemTime::now());
+    modified.max(accessed)
+}
 
-    let mut total = 0u64;
-    let mut stack = vec![path.to_path_buf()];
-    while let Some(current) = stack.pop() {
+/// How many levels below a candidate, and how many entries at most,
+/// `newest_use` inspects.
+const AGE_PROBE_DEPTH: u32 = 3;
+const AGE_PROBE_ENTRIES: usize = 20_000;
+
+/// When anything in the tree at `dir` was last used: the newest `last_used`
+/// of its entries down to `AGE_PROBE_DEPTH` levels. The directory's own
+/// time only counts when it is empty or too large to probe fully, since
+/// tools touch the top level without using what is inside (npm does so to
+/// `node_modules` on every install check).
+fn newest_use(dir: &Path, meta: &fs::Metadata) -> Option<SystemTime> {
+    let own = last_used(meta);
+    if !meta.is_dir() {
+        return own;
+    }
+    let mut newest = None;
+    let mut seen = 0usize;
+    let mut stack = vec![(dir.to_path_buf(), 1u32)];
+    while let Some((current, depth)) = stack.pop() {
         let entries = match fs::read_dir(&current) {
             Ok(entries) => entries,
             Err(_) => continue,
         };
         for entry in entries.flatten() {
-            let entry_path = entry.path();
-            let entry_meta = match safe_metadata(&entry_path) {
-                Some(meta) => meta,
-                None => continue,
-            };
-            if entry_meta.file_type().is_symlink() {
-                continue;
-            }
-            if is_cancelled(cancel_flag) {
-                return total;
+            seen += 1;
+            if seen > AGE_PROBE_ENTRIES {
+                return newest.max(own);
             }
-            if entry_meta.is_dir() {
-                stack.push(entry_path);
-            } else {
-                total = total.saturating_add(entry_meta.len());
+            let entry_meta = match entry.metadata() {
+                Ok(meta) => meta,
+                Err(_) => continue,
+            };
+            newest = newest.max(last_used(&entry_meta));
+            if entry_meta.is_dir() && depth < AGE_PROBE_DEPTH {
+                stack.push((entry.path(), depth + 1));
             }
         }
     }
+    newest.or(own)
+}
 
-    total
+/// Allocated and logical size of a file or tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct DiskUsage {
+    allocated: u64,
+    logical: u64,
 }
 
-fn is_cancelled(flag: Option<&AtomicBool>) -> bool {
-    flag.map(|f| f.load(Ordering::Relaxed)).unwrap_or(false)
+impl DiskUsage {
+    fn of_file(meta: &fs::Metadata) -> DiskUsage {
+        DiskUsage {
+            allocated: allocated_bytes(meta),
+            logical: meta.len(),
+        }
+    }
+
+    fn add(self, other: DiskUsage) -> DiskUsage {
+        DiskUsage {
+            allocated: self.allocated.saturating_add(other.allocated),
+            logical: self.logical.saturating_add(other.logical),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.allocated == 0 && self.logical == 0
+    }
 }
 
-pub fn is_excluded(path: &Path, excludes: &[PathBuf]) -> bool {
-    let resolved = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
-    excludes
-        .iter()
-        .any(|exclude| resolved == *exclude || resolved.starts_with(exclude))
+/// Bytes the filesystem has reserved for a file, from `st_blocks`, so sparse
+/// files count only the blocks they use.
+#[cfg(unix)]
+fn allocated_bytes(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.blocks().saturating_mul(512)
+}
+
+#[cfg(not(unix))]
+fn allocated_bytes(meta: &fs::Metadata) -> u64 {
+    meta.len()
+}
+
+fn calculate_size(path: &Path, control: Option<&ScanControl>) -> u64 {
+    measure_size(path, control).allocated
+}
+
+fn measure_size(path: &Path, control: Option<&ScanControl>) -> DiskUsage {
+    let metadata = match safe_metadata(path) {
+        Some(meta) => meta,
+        None => return DiskUsage::default(),
+    };
+
+    if !metadata.is_dir() {
+        return DiskUsage::of_file(&metadata);
+    }
+
+    if is_cancelled(control) {
+        return DiskUsage::default();
+    }
+    if let Some(usage) = measure::deferred_usage(path) {
+        return usage;
+    }
+
+    measure::tree_usage(path, control)
+}
+
+fn is_cancelled(control: Option<&ScanControl>) -> bool {
+    control.map(|c| c.checkpoint()).unwrap_or(false)
+}
+
+/// The `--exclude` paths of one scan, and those that have kept at least
+/// one path out of it, including roots `default_roots` left out before it
+/// started. Clones share the matches, so every part of a scan records into
+/// the same set and no scan sees another's.
+#[derive(Clone, Default)]
+pub struct Excludes {
+    paths: Vec<PathBuf>,
+    matched: Arc<Mutex<BTreeSet<PathBuf>>>,
+}
+
+impl Excludes {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Excludes {
+            paths,
+            matched: Arc::default(),
+        }
+    }
+
+    /// Counts `excludes` as matched by this scan.
+    pub(crate) fn mark_matched(&self, excludes: &[PathBuf]) {
+        lock_ignoring_poison(&self.matched).extend(excludes.iter().cloned());
+    }
+
+    /// The excludes at or below `root` this scan has matched so far.
+    pub(crate) fn matched_within(&self, root: &Path) -> Vec<PathBuf> {
+        lock_ignoring_poison(&self.matched)
+            .iter()
+            .filter(|exclude| exclude.starts_with(root))
+            .cloned()
+            .collect()
+    }
+
+    /// The excludes that have not excluded anything, checked when the scan
+    /// ends. The matches are forgotten, so scanning again with the same
+    /// excludes starts afresh.
+    pub fn unmatched(&self) -> Vec<PathBuf> {
+        let seen = std::mem::take(&mut *lock_ignoring_poison(&self.matched));
+        self.paths
+            .iter()
+            .filter(|exclude| !seen.contains(*exclude))
+            .cloned()
+            .collect()
+    }
+}
+
+impl std::ops::Deref for Excludes {
+    type Target = [PathBuf];
+
+    fn deref(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+/// Only the paths; the matches change while a scan runs.
+impl std::fmt::Debug for Excludes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.paths.fmt(f)
+    }
+}
+
+impl From<Vec<PathBuf>> for Excludes {
+    fn from(paths: Vec<PathBuf>) -> Self {
+        Excludes::new(paths)
+    }
+}
+
+impl FromIterator<PathBuf> for Excludes {
+    fn from_iter<I: IntoIterator<Item = PathBuf>>(paths: I) -> Self {
+        Excludes::new(paths.into_iter().collect())
+    }
+}
+
+pub fn is_excluded(path: &Path, excludes: &Excludes) -> bool {
+    if excludes.is_empty() {
+        return false;
+    }
+    let resolved = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    // Excludes are compared the way the volume holding `path` compares
+    // names, so `--exclude ~/projects/app` also covers `~/Projects/App` on
+    // a case-insensitive disk.
+    let fold_case = pathkey::case_insensitive(&resolved);
+    let resolved = pathkey::key(&resolved, fold_case);
+    let matched = excludes.iter().find(|exclude| {
+        let exclude = pathkey::key(exclude, fold_case);
+        resolved == exclude || resolved.starts_with(&exclude)
+    });
+    if let Some(exclude) = matched {
+        excludes.mark_matched(std::slice::from_ref(exclude));
+    }
+    matched.is_some()
 }
 
 pub fn normalize_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
     paths
         .iter()
-        .map(|path| match fs::canonicalize(path) {
+        .map(|path| match canonicalize(path) {
             Ok(resolved) => resolved,
             Err(_) => path.clone(),
         })
@@ -0,0 +1,169 @@
+use super::{
+    format_system_time, is_cancelled, is_excluded, last_used, measure_size, report_found,
+    safe_metadata, Candidate, CleanupStrategy, DevstripEvent, ScanConfig, ScanControl,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const ARCHIVE_EXTENSION: &str = "xcarchive";
+
+/// Keeps the newest `keep` archives of every app separately. Xcode files
+/// archives under per-day folders, so grouping has to look inside them.
+pub(crate) fn collect_xcode_archives<F>(
+    base: &Path,
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let keep = config.keep_latest_derived;
+    let excludes = &config.exclude_paths;
+    let mut results = Vec::new();
+    if is_excluded(base, excludes) || !base.is_dir() {
+        return results;
+    }
+    reporter(DevstripEvent::Scanning {
+        path: base.to_path_buf(),
+    });
+
+    let mut by_app: BTreeMap<String, Vec<(SystemTime, PathBuf)>> = BTreeMap::new();
+    for archive in archive_paths(base) {
+        if is_cancelled(control) {
+            return results;
+        }
+        if is_excluded(&archive, excludes) {
+            continue;
+        }
+        let modified = match safe_metadata(&archive).and_then(|meta| last_used(&meta)) {
+            Some(modified) => modified,
+            None => continue,
+        };
+        by_app
+            .entry(app_name(&archive))
+            .or_default()
+            .push((modified, archive));
+    }
+
+    for (app, mut archives) in by_app {
+        archives.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+        let kept_note = archives.first().filter(|_| keep > 0).map(|(mtime, path)| {
+            let date = format_system_time(*mtime);
+            let date = date.split(' ').next().unwrap_or(&date).to_string();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            format!(" (kept {} from {})", name, date)
+        });
+        for (index, (mtime, path)) in archives.into_iter().enumerate().skip(keep) {
+            reporter(DevstripEvent::Scanning { path: path.clone() });
+            let usage = measure_size(&path, control);
+            if !usage.is_empty() {
+                results.push(report_found(
+                    reporter,
+                    Candidate {
+                        path,
+                        size_bytes: usage.allocated,
+                        logical_bytes: usage.logical,
+                        category: "Xcode".to_string(),
+                        reason: format!(
+                            "Old {} archive{}",
+                            app,
+                            kept_note.as_deref().unwrap_or("")
+                        ),
+                        last_used: Some(mtime),
+                        strategy: CleanupStrategy::Remove,
+                        deep_only: config.kept_by_baseline(index, |t| t.keep_latest_derived),
+                        recently_opened: None,
+                        scanned: None,
+                        size_pending: false,
+                        size_incomplete: false,
+                        matched_rule: None,
+                    },
+                ));
+            }
+            if is_cancelled(control) {
+                return results;
+            }
+        }
+    }
+    results
+}
+
+fn archive_paths(base: &Path) -> Vec<PathBuf> {
+    let mut archives = Vec::new();
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(_) => return archives,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        if is_archive(&path) {
+            archives.push(path);
+            continue;
+        }
+        if let Ok(day) = fs::read_dir(&path) {
+            archives.extend(
+                day.flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| is_archive(path)),
+            );
+        }
+    }
+    archives
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext == ARCHIVE_EXTENSION)
+        .unwrap_or(false)
+}
+
+/// The scheme name from the archive's Info.plist, falling back to the file
+/// name with Xcode's " <date>, <time>" suffix removed.
+fn app_name(archive: &Path) -> String {
+    if let Some(name) = fs::read_to_string(archive.join("Info.plist"))
+        .ok()
+        .and_then(|plist| plist_string(&plist, "Name"))
+    {
+        return name;
+    }
+    let stem = archive
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut end = stem.len();
+    for (index, _) in stem.match_indices(' ') {
+        if stem[index + 1..]
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_digit())
+            .unwrap_or(false)
+            && stem[index..].contains(',')
+        {
+            end = index;
+            break;
+        }
+    }
+    stem[..end].to_string()
+}
+
+fn plist_string(plist: &str, key: &str) -> Option<String> {
+    let marker = format!("<key>{}</key>", key);
+    let rest = &plist[plist.find(&marker)? + marker.len()..];
+    let rest = rest.trim_start().strip_prefix("<string>")?;
+    let value = &rest[..rest.find("</string>")?];
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
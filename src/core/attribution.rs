@@ -0,0 +1,149 @@
+use super::{
+    is_cancelled, is_excluded, measure_size, newest_use, safe_metadata, Candidate, CleanupStrategy,
+    Excludes, ScanControl,
+};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Well-known children of a shared `.cache` directory: (name, category, label).
+const DOT_CACHE_TOOLS: &[(&str, &str, &str)] = &[
+    ("uv", "Python", "uv cache"),
+    ("pip", "Python", "pip cache"),
+    ("pypoetry", "Python", "Poetry cache"),
+    ("pre-commit", "Python", "pre-commit cache"),
+    ("matplotlib", "Python", "matplotlib cache"),
+    ("ruff", "Python", "ruff cache"),
+    ("mypy", "Python", "mypy cache"),
+    ("huggingface", "ML", "Hugging Face cache"),
+    ("torch", "ML", "PyTorch cache"),
+    ("yarn", "Node", "Yarn cache"),
+    ("node-gyp", "Node", "node-gyp headers"),
+    ("ms-playwright", "Node", "Playwright browsers"),
+    ("puppeteer", "Node", "Puppeteer browsers"),
+    ("electron", "Node", "Electron downloads"),
+    ("typescript", "Node", "TypeScript cache"),
+    ("eslint", "Node", "ESLint cache"),
+    ("babel-loader", "Node", "babel-loader cache"),
+    ("webpack", "Node", "webpack cache"),
+    ("parcel", "Node", "Parcel cache"),
+    ("Cypress", "Cypress", "Cypress binaries"),
+    ("go-build", "Go", "Go build cache"),
+    ("golangci-lint", "Go", "golangci-lint cache"),
+    ("bazel", "Bazel", "Bazel output base"),
+    ("sccache", "Rust", "sccache cache"),
+    ("zig", "Zig", "Zig cache"),
+    ("deno", "Deno", "Deno cache"),
+    ("JetBrains", "JetBrains", "JetBrains cache"),
+];
+
+/// Regenerable children of a `.gradle` directory. Anything else (notably
+/// `wrapper`, which holds the pinned distributions) is left alone.
+const DOT_GRADLE_ENTRIES: &[(&str, &str)] = &[
+    ("caches", "Gradle caches"),
+    ("daemon", "Gradle daemons"),
+    ("native", "Gradle native cache"),
+    ("configuration-cache", "Gradle configuration cache"),
+    ("buildOutputCleanup", "Gradle build output state"),
+    ("kotlin", "Kotlin compiler state"),
+    ("vcs-1", "Gradle VCS checkouts"),
+];
+
+/// Whether a matched directory is a shared cache whose children should be
+/// reported individually rather than as one blob.
+pub(crate) fn is_shared_cache_dir(name: &str) -> bool {
+    name == ".cache" || name == ".gradle"
+}
+
+/// Splits a shared cache directory into one candidate per attributable
+/// child, each age-checked on its own.
+pub(crate) fn attribute_children(
+    dir: &Path,
+    base_category: &str,
+    base_reason: &str,
+    excludes: &Excludes,
+    cutoff: Option<SystemTime>,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate> {
+    let mut results = Vec::new();
+    let dir_name = match dir.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return results,
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return results,
+    };
+    for entry in entries.flatten() {
+        if is_cancelled(control) {
+            break;
+        }
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        if is_excluded(&path, excludes) {
+            continue;
+        }
+        let child = entry.file_name().to_string_lossy().into_owned();
+        let (category, label) = match attribute(dir_name, &child) {
+            Some((category, label)) => (category.to_string(), label),
+            None if dir_name == ".cache" => (
+                base_category.to_string(),
+                format!("{} ({}/{})", base_reason, dir_name, child),
+            ),
+            None => continue,
+        };
+        let modified = safe_metadata(&path).and_then(|meta| newest_use(&path, &meta));
+        if let (Some(limit), Some(mtime)) = (cutoff, modified) {
+            if mtime >= limit {
+                continue;
+            }
+        }
+        let usage = measure_size(&path, control);
+        if usage.is_empty() {
+            continue;
+        }
+        results.push(Candidate {
+            path,
+            size_bytes: usage.allocated,
+            logical_bytes: usage.logical,
+            category,
+            reason: label,
+            last_used: modified,
+            strategy: CleanupStrategy::Remove,
+            deep_only: false,
+            recently_opened: None,
+            scanned: None,
+            size_pending: false,
+            size_incomplete: false,
+            matched_rule: None,
+        });
+    }
+    results
+}
+
+fn attribute(dir_name: &str, child: &str) -> Option<(&'static str, String)> {
+    match dir_name {
+        ".cache" => DOT_CACHE_TOOLS
+            .iter()
+            .find(|(name, _, _)| *name == child)
+            .map(|(_, category, label)| (*category, format!("{} (.cache/{})", label, child))),
+        ".gradle" => {
+            if let Some((_, label)) = DOT_GRADLE_ENTRIES.iter().find(|(name, _)| *name == child) {
+                return Some(("Gradle", format!("{} (.gradle/{})", label, child)));
+            }
+            // Per-version build state such as `.gradle/8.5`.
+            if child.starts_with(|c: char| c.is_ascii_digit())
+                && child.chars().all(|c| c.is_ascii_digit() || c == '.')
+            {
+                return Some((
+                    "Gradle",
+                    format!("Gradle {} build state (.gradle/{})", child, child),
+                ));
+            }
+            None
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,114 @@
+use super::{data_dir, CleanupResult, CoreResult, DevstripError};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+
+/// One cleanup attempt as recorded in the audit log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// RFC 3339 local time of the run the entry belongs to.
+    pub timestamp: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub category: String,
+    pub strategy: String,
+    pub dry_run: bool,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn audit_log_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(AUDIT_FILE))
+}
+
+/// Appends each cleanup result to the audit log as soon as it is known, so
+/// a run that is killed part way still leaves a line for every target it
+/// finished. The file is only ever opened for appending, so earlier runs are
+/// never rewritten.
+pub(crate) struct AuditLog {
+    /// RFC 3339 local time the run started, shared by all its entries.
+    timestamp: String,
+    dry_run: bool,
+    /// Set once a write fails; the rest of the run is not logged, so the
+    /// failure is reported only once.
+    failed: bool,
+}
+
+impl AuditLog {
+    pub(crate) fn start(dry_run: bool) -> Self {
+        AuditLog {
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            dry_run,
+            failed: false,
+        }
+    }
+
+    /// Appends the line for `result`.
+    pub(crate) fn record(&mut self, result: &CleanupResult) -> CoreResult<()> {
+        if self.failed {
+            return Ok(());
+        }
+        let written = self.append(result);
+        self.failed = written.is_err();
+        written
+    }
+
+    fn append(&self, result: &CleanupResult) -> CoreResult<()> {
+        let path = audit_log_path().ok_or_else(|| DevstripError::Io {
+            path: None,
+            message: "Unable to locate the data directory".to_string(),
+        })?;
+        let entry = AuditEntry {
+            timestamp: self.timestamp.clone(),
+            path: result.candidate.path.clone(),
+            size_bytes: result.candidate.size_bytes,
+            category: result.candidate.category.clone(),
+            strategy: result.candidate.strategy.describe(),
+            dry_run: self.dry_run,
+            success: result.success,
+            error: result.error.as_ref().map(ToString::to_string),
+        };
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|err| DevstripError::file(&path, err.to_string()))?;
+        line.push('\n');
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+            file.write_all(line.as_bytes())
+        };
+        write().map_err(|err| {
+            DevstripError::io(
+                &path,
+                &err,
+                format!("Unable to write audit log {}: {}", path.display(), err),
+            )
+        })
+    }
+}
+
+/// Every entry in the audit log, oldest first. Lines that fail to parse are
+/// skipped so one damaged line does not hide the rest of the history.
+pub fn read_log() -> CoreResult<Vec<AuditEntry>> {
+    let path = match audit_log_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Vec::new()),
+    };
+    let text = fs::read_to_string(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!("Unable to read audit log {}: {}", path.display(), err),
+        )
+    })?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
@@ -0,0 +1,159 @@
+use super::tools::find_in_path;
+use super::{home_dir, safe_metadata, Candidate, CleanupStrategy};
+use std::collections::VecDeque;
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+/// Lock files cargo holds with `flock` for the length of a build.
+const CARGO_LOCKS: &[&str] = &[".cargo-lock", ".package-cache"];
+/// JVM build tools lock with `fcntl`, which a probe from here cannot see,
+/// so one of their lock files written this recently counts as a running
+/// build instead.
+const JVM_LOCK_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// How deep below a candidate lock files are looked for, and how many
+/// entries are visited at most.
+const LOCK_PROBE_DEPTH: usize = 4;
+const LOCK_PROBE_ENTRIES: usize = 20_000;
+
+/// Why `candidate` must not be cleaned right now: a build or build daemon
+/// is working inside it, and deleting under it corrupts its state. Checked
+/// for the strategies that delete files a build could be using.
+pub(crate) fn active_build_reason(candidate: &Candidate) -> Option<String> {
+    if !matches!(
+        candidate.strategy,
+        CleanupStrategy::Remove
+            | CleanupStrategy::EmptyDirectory
+            | CleanupStrategy::SweepCargoTarget { .. }
+    ) {
+        return None;
+    }
+    let path = &candidate.path;
+    match safe_metadata(path) {
+        Some(meta) if meta.is_dir() => {}
+        _ => return None,
+    }
+    if let Some(project) = path.parent().filter(|dir| dir.join("build.sbt").is_file()) {
+        if project.join("project/target/active.json").is_file() {
+            return Some("build in progress: an sbt server is running for this project".into());
+        }
+    }
+    if let Some(pid) = home_dir()
+        .map(|home| home.join(".gradle"))
+        .filter(|gradle| path.starts_with(gradle))
+        .and_then(|gradle| running_gradle_daemon(&gradle))
+    {
+        return Some(format!(
+            "build in progress: a Gradle daemon is running (pid {})",
+            pid
+        ));
+    }
+    let lock = find_held_lock(path)?;
+    let relative = lock.strip_prefix(path).unwrap_or(&lock);
+    Some(format!(
+        "build in progress: {} is locked",
+        relative.display()
+    ))
+}
+
+/// The first lock file under `dir` that a running build holds.
+fn find_held_lock(dir: &Path) -> Option<PathBuf> {
+    let recent = SystemTime::now().checked_sub(JVM_LOCK_WINDOW)?;
+    let mut pending = VecDeque::from([(dir.to_path_buf(), 0usize)]);
+    let mut visited = 0usize;
+    while let Some((current, depth)) = pending.pop_front() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            visited += 1;
+            if visited > LOCK_PROBE_ENTRIES {
+                return None;
+            }
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                if depth + 1 < LOCK_PROBE_DEPTH {
+                    pending.push_back((path, depth + 1));
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let held = if CARGO_LOCKS.contains(&name.as_ref()) {
+                flock_held(&path)
+            } else if is_jvm_lock(&path, &name) {
+                entry
+                    .metadata()
+                    .and_then(|meta| meta.modified())
+                    .is_ok_and(|modified| modified >= recent)
+            } else {
+                false
+            };
+            if held {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Gradle keeps `*.lock` files throughout `.gradle` folders; sbt has
+/// `sbt.boot.lock`.
+fn is_jvm_lock(path: &Path, name: &str) -> bool {
+    name == "sbt.boot.lock"
+        || (name.ends_with(".lock")
+            && path
+                .components()
+                .any(|component| component.as_os_str() == ".gradle"))
+}
+
+/// Whether another process holds an exclusive lock on `path`. The shared
+/// lock taken to find out is released right away.
+fn flock_held(path: &Path) -> bool {
+    match File::open(path).map(|file| file.try_lock_shared()) {
+        Ok(Err(TryLockError::WouldBlock)) => true,
+        Ok(_) | Err(_) => false,
+    }
+}
+
+/// The pid of a live Gradle daemon, found through the
+/// `daemon/<version>/daemon-<pid>.out.log` files each daemon writes.
+fn running_gradle_daemon(gradle: &Path) -> Option<u32> {
+    if cfg!(windows) || find_in_path("ps").is_none() {
+        return None;
+    }
+    let versions = fs::read_dir(gradle.join("daemon")).ok()?;
+    versions
+        .flatten()
+        .filter_map(|version| fs::read_dir(version.path()).ok())
+        .flat_map(|logs| logs.flatten())
+        .filter_map(|log| {
+            let name = log.file_name();
+            name.to_str()?
+                .strip_prefix("daemon-")?
+                .strip_suffix(".out.log")?
+                .parse::<u32>()
+                .ok()
+        })
+        .find(|pid| is_java_process(*pid))
+}
+
+/// Whether `pid` is a running Java process; a finished daemon's pid may
+/// since have been reused by something else.
+fn is_java_process(pid: u32) -> bool {
+    Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "comm="])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).contains("java")
+        })
+}
@@ -0,0 +1,227 @@
+use super::job::ScanControl;
+use super::{is_cancelled, DiskUsage};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What one directory holds directly.
+#[derive(Default)]
+pub(crate) struct Listing {
+    pub(crate) files: DiskUsage,
+    pub(crate) subdirs: Vec<PathBuf>,
+    /// False when some entries could not be read, or the scan was
+    /// cancelled part-way through.
+    pub(crate) complete: bool,
+}
+
+/// Lists `dir` for sizing: the usage of its files and its subdirectories.
+/// Symlinks are skipped. Entries are read in batches where the platform
+/// has an API for it; `None` when the directory cannot be read at all.
+pub(crate) fn list(dir: &Path, control: Option<&ScanControl>) -> Option<Listing> {
+    #[cfg(target_os = "macos")]
+    if let Some(listing) = macos::list(dir, control) {
+        return Some(listing);
+    }
+    list_entries(dir, control)
+}
+
+/// `read_dir` with the metadata of each entry taken from the directory
+/// handle: on Linux a `statx` relative to the open directory instead of a
+/// lookup of the whole path, on Windows the data the listing already
+/// returned. Directories need no metadata at all.
+fn list_entries(dir: &Path, control: Option<&ScanControl>) -> Option<Listing> {
+    let entries = fs::read_dir(dir).ok()?;
+    let mut listing = Listing {
+        complete: true,
+        ..Listing::default()
+    };
+    for entry in entries {
+        if is_cancelled(control) {
+            listing.complete = false;
+            break;
+        }
+        let Ok(entry) = entry else {
+            listing.complete = false;
+            continue;
+        };
+        let Ok(file_type) = entry.file_type() else {
+            listing.complete = false;
+            continue;
+        };
+        if file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            listing.subdirs.push(entry.path());
+            continue;
+        }
+        match entry.metadata() {
+            Ok(meta) => listing.files = listing.files.add(DiskUsage::of_file(&meta)),
+            Err(_) => listing.complete = false,
+        }
+    }
+    Some(listing)
+}
+
+/// `getattrlistbulk`, which returns the name, type and sizes of many
+/// entries per call instead of one `lstat` each.
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::{is_cancelled, DiskUsage, Listing, ScanControl};
+    use std::ffi::{CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    const ATTR_CMN_ERROR: libc::attrgroup_t = 0x2000_0000;
+    const VDIR: u32 = 2;
+    const VLNK: u32 = 5;
+    const BUFFER_WORDS: usize = 16 * 1024;
+
+    // Layout of one entry, packed in attribute order: the returned
+    // attribute set comes first, then the error, name, type, and the file
+    // sizes (placeholders for entries that are not files).
+    const RETURNED_FILE_ATTRS: usize = 16;
+    const ERROR: usize = 24;
+    const NAME: usize = 28;
+    const OBJ_TYPE: usize = 36;
+    const ALLOC_SIZE: usize = 40;
+    const DATA_LENGTH: usize = 48;
+    const ENTRY_SIZE: usize = 56;
+
+    struct Fd(libc::c_int);
+
+    impl Drop for Fd {
+        fn drop(&mut self) {
+            // SAFETY: the descriptor was opened by `list` and is closed once.
+            unsafe { libc::close(self.0) };
+        }
+    }
+
+    /// `None` when the directory cannot be opened or the volume does not
+    /// support bulk listing.
+    pub(super) fn list(dir: &Path, control: Option<&ScanControl>) -> Option<Listing> {
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `path` is a valid NUL-terminated string.
+        let fd = unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return None;
+        }
+        let fd = Fd(fd);
+        let mut request = libc::attrlist {
+            bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+            reserved: 0,
+            commonattr: libc::ATTR_CMN_RETURNED_ATTRS
+                | ATTR_CMN_ERROR
+                | libc::ATTR_CMN_NAME
+                | libc::ATTR_CMN_OBJTYPE,
+            volattr: 0,
+            dirattr: 0,
+            fileattr: libc::ATTR_FILE_ALLOCSIZE | libc::ATTR_FILE_DATALENGTH,
+            forkattr: 0,
+        };
+        // u64 words keep the buffer aligned for the call.
+        let mut buffer = vec![0u64; BUFFER_WORDS];
+        let mut listing = Listing {
+            complete: true,
+            ..Listing::default()
+        };
+        let mut first_call = true;
+        loop {
+            if is_cancelled(control) {
+                listing.complete = false;
+                break;
+            }
+            // SAFETY: `request` and `buffer` outlive the call, and the size
+            // passed is the buffer's size in bytes.
+            let count = unsafe {
+                libc::getattrlistbulk(
+                    fd.0,
+                    (&mut request as *mut libc::attrlist).cast(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len() * 8,
+                    libc::FSOPT_PACK_INVAL_ATTRS as u64,
+                )
+            };
+            if count < 0 {
+                if first_call {
+                    return None;
+                }
+                listing.complete = false;
+                break;
+            }
+            first_call = false;
+            if count == 0 {
+                break;
+            }
+            // SAFETY: the buffer is initialized and `buffer.len() * 8` bytes
+            // long.
+            let bytes: &[u8] =
+                unsafe { std::slice::from_raw_parts(buffer.as_ptr().cast(), buffer.len() * 8) };
+            let mut offset = 0;
+            for _ in 0..count {
+                let Some(entry) = bytes.get(offset..) else {
+                    listing.complete = false;
+                    break;
+                };
+                let length = read_u32(entry, 0) as usize;
+                if length < ENTRY_SIZE || entry.len() < length {
+                    listing.complete = false;
+                    break;
+                }
+                offset += length;
+                add_entry(dir, &entry[..length], &mut listing);
+            }
+        }
+        Some(listing)
+    }
+
+    fn add_entry(dir: &Path, entry: &[u8], listing: &mut Listing) {
+        if read_u32(entry, ERROR) != 0 {
+            listing.complete = false;
+            return;
+        }
+        match read_u32(entry, OBJ_TYPE) {
+            VLNK => {}
+            VDIR => {
+                let start = NAME as i64 + i64::from(read_u32(entry, NAME) as i32);
+                let length = read_u32(entry, NAME + 4) as usize;
+                // The name is NUL-terminated, and the length counts the NUL.
+                let name = usize::try_from(start)
+                    .ok()
+                    .and_then(|start| entry.get(start..start + length.saturating_sub(1)));
+                match name {
+                    Some(name) if !name.is_empty() => {
+                        listing.subdirs.push(dir.join(OsStr::from_bytes(name)))
+                    }
+                    _ => listing.complete = false,
+                }
+            }
+            _ => {
+                let returned = read_u32(entry, RETURNED_FILE_ATTRS);
+                let size = |attr: libc::attrgroup_t, at: usize| {
+                    if returned & attr == 0 {
+                        0
+                    } else {
+                        u64::try_from(read_i64(entry, at)).unwrap_or(0)
+                    }
+                };
+                listing.files = listing.files.add(DiskUsage {
+                    allocated: size(libc::ATTR_FILE_ALLOCSIZE, ALLOC_SIZE),
+                    logical: size(libc::ATTR_FILE_DATALENGTH, DATA_LENGTH),
+                });
+            }
+        }
+    }
+
+    fn read_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_ne_bytes(bytes[at..at + 4].try_into().unwrap_or_default())
+    }
+
+    fn read_i64(bytes: &[u8], at: usize) -> i64 {
+        i64::from_ne_bytes(bytes[at..at + 8].try_into().unwrap_or_default())
+    }
+}
@@ -0,0 +1,154 @@
+use super::{trash, CoreResult, DevstripError};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+pub const ARCHIVE_SUFFIX: &str = ".tar.zst";
+const COMPRESSION_LEVEL: i32 = 10;
+
+/// Where `compress_in_place` puts the archive for `path`.
+pub fn archive_path(path: &Path) -> Option<PathBuf> {
+    let mut name = path.file_name()?.to_os_string();
+    name.push(ARCHIVE_SUFFIX);
+    Some(path.with_file_name(name))
+}
+
+/// Packs `path` into a zstd-compressed tarball next to it and removes the
+/// original once the archive is complete, or moves it to the trash with
+/// `use_trash`. Symlinks are stored as links.
+pub(crate) fn compress_in_place(path: &Path, use_trash: bool) -> CoreResult<PathBuf> {
+    let archive = archive_path(path)
+        .ok_or_else(|| DevstripError::file(path, format!("Invalid path: {}", path.display())))?;
+    if archive.exists() {
+        return Err(DevstripError::file(
+            &archive,
+            format!("{} already exists", archive.display()),
+        ));
+    }
+    let mut temp_name = archive.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = archive.with_file_name(temp_name);
+
+    if let Err(err) = write_archive(path, &temp) {
+        let _ = fs::remove_file(&temp);
+        return Err(DevstripError::io(
+            path,
+            &err,
+            format!("Unable to compress {}: {}", path.display(), err),
+        ));
+    }
+    fs::rename(&temp, &archive).map_err(|err| {
+        DevstripError::io(
+            &archive,
+            &err,
+            format!("Unable to write {}: {}", archive.display(), err),
+        )
+    })?;
+    if use_trash {
+        return trash::move_to_trash(path).map(|_| archive);
+    }
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    removed.map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!(
+                "Archived to {} but could not remove the original: {}",
+                archive.display(),
+                err
+            ),
+        )
+    })?;
+    Ok(archive)
+}
+
+fn write_archive(source: &Path, target: &Path) -> io::Result<()> {
+    let name = source
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let file = BufWriter::new(File::create(target)?);
+    let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    if source.is_dir() {
+        builder.append_dir_all(name, source)?;
+    } else {
+        builder.append_path_with_name(source, name)?;
+    }
+    let encoder = builder.into_inner()?;
+    let mut file = encoder.finish()?;
+    io::Write::flush(&mut file)?;
+    file.get_ref().sync_all()
+}
+
+/// Unpacks an archive made by `compress_in_place` back to its original
+/// location and deletes the archive. Refuses to overwrite an existing path.
+pub fn restore(archive: &Path) -> CoreResult<PathBuf> {
+    let file_name = archive
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| {
+            DevstripError::file(
+                archive,
+                format!("Invalid archive path: {}", archive.display()),
+            )
+        })?;
+    let original_name = file_name
+        .strip_suffix(ARCHIVE_SUFFIX)
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| {
+            DevstripError::file(
+                archive,
+                format!("{} is not a {} archive", archive.display(), ARCHIVE_SUFFIX),
+            )
+        })?;
+    let original = archive.with_file_name(original_name);
+    if original.exists() {
+        return Err(DevstripError::file(
+            &original,
+            format!("{} already exists", original.display()),
+        ));
+    }
+    let parent = original
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let unpacked = File::open(archive)
+        .and_then(zstd::Decoder::new)
+        .and_then(|decoder| {
+            let mut tar = tar::Archive::new(decoder);
+            tar.set_preserve_permissions(true);
+            tar.set_preserve_mtime(true);
+            tar.unpack(&parent)
+        });
+    unpacked.map_err(|err| {
+        DevstripError::io(
+            archive,
+            &err,
+            format!("Unable to restore {}: {}", archive.display(), err),
+        )
+    })?;
+    if !original.exists() {
+        return Err(DevstripError::file(
+            archive,
+            format!("{} did not contain {}", archive.display(), original_name),
+        ));
+    }
+    fs::remove_file(archive).map_err(|err| {
+        DevstripError::io(
+            archive,
+            &err,
+            format!(
+                "Restored {} but could not remove the archive: {}",
+                original.display(),
+                err
+            ),
+        )
+    })?;
+    Ok(original)
+}
@@ -0,0 +1,100 @@
+use super::{data_dir, CoreResult, DevstripError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STATUS_FILE: &str = "daemon-status.json";
+
+/// What `devstrip daemon` is doing, rewritten after every pass so that
+/// `devstrip status` can report it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    /// RFC 3339 local time the daemon started.
+    pub started_at: String,
+    pub interval_secs: u64,
+    /// Whether each pass also removes what it found.
+    pub cleans: bool,
+    pub passes: u64,
+    #[serde(default)]
+    pub last_pass: Option<DaemonPass>,
+    /// RFC 3339 local time of the next pass.
+    #[serde(default)]
+    pub next_pass: Option<String>,
+}
+
+/// One scan, and cleanup if enabled, of the daemon.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DaemonPass {
+    pub started_at: String,
+    pub finished_at: String,
+    pub candidates: usize,
+    pub reclaimable_bytes: u64,
+    pub removed: usize,
+    pub removed_bytes: u64,
+    pub failed: usize,
+    /// Why the pass stopped early, e.g. because a GUI session was scanning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn status_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(STATUS_FILE))
+}
+
+pub fn save_status(status: &DaemonStatus) -> CoreResult<()> {
+    let path = status_path().ok_or_else(|| DevstripError::Io {
+        path: None,
+        message: "Unable to locate the data directory".to_string(),
+    })?;
+    let text = serde_json::to_vec_pretty(status)
+        .map_err(|err| DevstripError::file(&path, err.to_string()))?;
+    let temp = path.with_extension("json.tmp");
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&temp, &text)?;
+        fs::rename(&temp, &path)
+    };
+    write().map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!(
+                "Unable to save the daemon status to {}: {}",
+                path.display(),
+                err
+            ),
+        )
+    })
+}
+
+/// The status the daemon last saved, or `None` when no daemon has run.
+pub fn load_status() -> CoreResult<Option<DaemonStatus>> {
+    let path = match status_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let text = fs::read(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    serde_json::from_slice(&text)
+        .map(Some)
+        .map_err(|err| damaged(&path, &err))
+}
+
+fn damaged(path: &Path, err: &serde_json::Error) -> DevstripError {
+    DevstripError::file(
+        path,
+        format!(
+            "The daemon status {} is damaged ({}); it is rewritten after the next pass.",
+            path.display(),
+            err
+        ),
+    )
+}
@@ -0,0 +1,133 @@
+use super::{CoreResult, DevstripError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A target as both the saved scan (`last-scan.json`) and `--format json`
+/// record it; the other fields of either are ignored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiffEntry {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub category: String,
+}
+
+#[derive(Deserialize)]
+struct ScanFile {
+    candidates: Vec<DiffEntry>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChangedEntry {
+    pub path: PathBuf,
+    pub category: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CategoryTotals {
+    pub before: u64,
+    pub after: u64,
+}
+
+/// What changed between two scans, each list with the largest size first.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ScanDiff {
+    pub appeared: Vec<DiffEntry>,
+    pub disappeared: Vec<DiffEntry>,
+    pub grew: Vec<ChangedEntry>,
+    pub shrank: Vec<ChangedEntry>,
+    pub categories: BTreeMap<String, CategoryTotals>,
+}
+
+impl ScanDiff {
+    pub fn totals(&self) -> CategoryTotals {
+        self.categories
+            .values()
+            .fold(CategoryTotals::default(), |acc, totals| CategoryTotals {
+                before: acc.before.saturating_add(totals.before),
+                after: acc.after.saturating_add(totals.after),
+            })
+    }
+}
+
+/// Reads the targets of a saved scan or a `--format json` export.
+pub fn load_scan(path: &Path) -> CoreResult<Vec<DiffEntry>> {
+    let text = fs::read(path).map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    serde_json::from_slice::<ScanFile>(&text)
+        .map(|file| file.candidates)
+        .map_err(|err| {
+            DevstripError::config(
+                Some(path),
+                format!(
+                    "{} is not a devstrip scan (a saved scan or `--format json` output): {}",
+                    path.display(),
+                    err
+                ),
+            )
+        })
+}
+
+pub fn diff_scans(old: Vec<DiffEntry>, new: Vec<DiffEntry>) -> ScanDiff {
+    let mut diff = ScanDiff::default();
+    for entry in &old {
+        let totals = diff.categories.entry(entry.category.clone()).or_default();
+        totals.before = totals.before.saturating_add(entry.size_bytes);
+    }
+    for entry in &new {
+        let totals = diff.categories.entry(entry.category.clone()).or_default();
+        totals.after = totals.after.saturating_add(entry.size_bytes);
+    }
+    let mut before: HashMap<PathBuf, DiffEntry> = old
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    for entry in new {
+        match before.remove(&entry.path) {
+            None => diff.appeared.push(entry),
+            Some(earlier) => {
+                let changed = ChangedEntry {
+                    path: entry.path,
+                    category: entry.category,
+                    before: earlier.size_bytes,
+                    after: entry.size_bytes,
+                };
+                if changed.after > changed.before {
+                    diff.grew.push(changed);
+                } else if changed.after < changed.before {
+                    diff.shrank.push(changed);
+                }
+            }
+        }
+    }
+    diff.disappeared = before.into_values().collect();
+    diff.appeared.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    diff.disappeared.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    diff.grew.sort_by(|a, b| {
+        (b.after - b.before)
+            .cmp(&(a.after - a.before))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    diff.shrank.sort_by(|a, b| {
+        (b.before - b.after)
+            .cmp(&(a.before - a.after))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    diff
+}
@@ -0,0 +1,182 @@
+use super::tools::find_in_path;
+use super::{data_dir, default_roots, home_dir, Excludes};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Clone, Debug)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+}
+
+const EXTERNAL_TOOLS: &[(&str, &str)] = &[
+    ("docker", "Docker image and build cache cleanup"),
+    ("podman", "Podman storage cleanup"),
+    ("brew", "Homebrew cleanup"),
+    ("xcrun", "Simulator cleanup via simctl"),
+    ("lsof", "Skipping targets that are still open"),
+];
+
+pub fn run_checks() -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    let home = home_dir();
+    checks.push(check_home(home.as_deref()));
+    if let Some(home) = &home {
+        checks.push(check_full_disk_access(home));
+    }
+    checks.push(check_roots());
+    checks.push(check_data_dir());
+    for (tool, purpose) in EXTERNAL_TOOLS {
+        checks.push(check_tool(tool, purpose));
+    }
+    checks
+}
+
+fn check_home(home: Option<&Path>) -> DoctorCheck {
+    match home {
+        Some(path) if path.is_dir() => DoctorCheck::new(
+            "Home directory",
+            CheckStatus::Pass,
+            format!("Resolved to {}", path.display()),
+        ),
+        Some(path) => DoctorCheck::new(
+            "Home directory",
+            CheckStatus::Fail,
+            format!("{} does not exist or is not a directory", path.display()),
+        )
+        .with_hint("Set HOME to your user directory before running devstrip."),
+        None => DoctorCheck::new("Home directory", CheckStatus::Fail, "HOME is not set")
+            .with_hint("Set HOME to your user directory before running devstrip."),
+    }
+}
+
+fn check_full_disk_access(home: &Path) -> DoctorCheck {
+    if !cfg!(target_os = "macos") {
+        return DoctorCheck::new(
+            "Full Disk Access",
+            CheckStatus::Pass,
+            "Not required on this platform",
+        );
+    }
+    let protected = home.join("Library/Safari");
+    if !protected.exists() {
+        return DoctorCheck::new(
+            "Full Disk Access",
+            CheckStatus::Warn,
+            "Unable to determine (no protected folder to probe)",
+        );
+    }
+    match fs::read_dir(&protected) {
+        Ok(_) => DoctorCheck::new("Full Disk Access", CheckStatus::Pass, "Granted"),
+        Err(err) => DoctorCheck::new(
+            "Full Disk Access",
+            CheckStatus::Warn,
+            format!("Protected folders are not readable: {}", err),
+        )
+        .with_hint(
+            "Grant Full Disk Access to your terminal (or devstrip) in System Settings > Privacy & Security.",
+        ),
+    }
+}
+
+fn check_roots() -> DoctorCheck {
+    match default_roots(&[], &Excludes::default()) {
+        Ok(roots) if roots.is_empty() => DoctorCheck::new(
+            "Scan roots",
+            CheckStatus::Warn,
+            "No default scan roots exist",
+        )
+        .with_hint("Pass project directories explicitly with --roots."),
+        Ok(roots) => {
+            let unreadable: Vec<String> = roots
+                .iter()
+                .filter(|root| fs::read_dir(root).is_err())
+                .map(|root| root.display().to_string())
+                .collect();
+            if unreadable.is_empty() {
+                DoctorCheck::new(
+                    "Scan roots",
+                    CheckStatus::Pass,
+                    format!("{} default root(s) readable", roots.len()),
+                )
+            } else {
+                DoctorCheck::new(
+                    "Scan roots",
+                    CheckStatus::Fail,
+                    format!("Unreadable: {}", unreadable.join(", ")),
+                )
+                .with_hint("Check directory permissions or grant Full Disk Access.")
+            }
+        }
+        Err(err) => DoctorCheck::new("Scan roots", CheckStatus::Fail, err.to_string())
+            .with_hint("Run devstrip from an existing directory."),
+    }
+}
+
+fn check_data_dir() -> DoctorCheck {
+    let dir = match data_dir() {
+        Some(dir) => dir,
+        None => {
+            return DoctorCheck::new(
+                "State directory",
+                CheckStatus::Fail,
+                "Unable to resolve a data directory",
+            )
+            .with_hint("Set HOME (or XDG_DATA_HOME on Linux).")
+        }
+    };
+    let probe = dir.join(".doctor-probe");
+    let outcome = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe));
+    match outcome {
+        Ok(_) => DoctorCheck::new(
+            "State directory",
+            CheckStatus::Pass,
+            format!("{} is writable", dir.display()),
+        ),
+        Err(err) => DoctorCheck::new(
+            "State directory",
+            CheckStatus::Fail,
+            format!("{} is not writable: {}", dir.display(), err),
+        )
+        .with_hint("Fix ownership of the directory or remove it so devstrip can recreate it."),
+    }
+}
+
+fn check_tool(tool: &str, purpose: &str) -> DoctorCheck {
+    let name = format!("Tool: {}", tool);
+    match find_in_path(tool) {
+        Some(path) => DoctorCheck::new(&name, CheckStatus::Pass, path.display().to_string()),
+        None => DoctorCheck::new(
+            &name,
+            CheckStatus::Warn,
+            format!("Not found on PATH; {} is unavailable", purpose),
+        ),
+    }
+}
@@ -0,0 +1,104 @@
+use super::{
+    age_cutoff, is_cancelled, is_excluded, last_used, read_dir_reporting, report_found,
+    safe_metadata, Candidate, CleanupStrategy, DevstripEvent, DiskUsage, Excludes, ScanControl,
+};
+use std::path::Path;
+
+const INSTALLER_EXTENSIONS: &[&str] = &["dmg", "pkg", "iso", "appimage", "xip"];
+/// Archives only count when the name looks like a toolchain or SDK bundle.
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "tar", "gz", "tgz", "xz"];
+const SDK_KEYWORDS: &[&str] = &[
+    "sdk",
+    "jdk",
+    "ndk",
+    "xcode",
+    "commandlinetools",
+    "android-studio",
+    "toolchain",
+    "platform-tools",
+];
+
+/// Looks only at the top level of `~/Downloads`; anything the user sorted
+/// into subfolders is left alone.
+pub(crate) fn collect_installer_artifacts<F>(
+    home: &Path,
+    min_age_days: u64,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    let downloads = home.join("Downloads");
+    if is_excluded(&downloads, excludes) || !downloads.is_dir() {
+        return results;
+    }
+    reporter(DevstripEvent::Scanning {
+        path: downloads.clone(),
+    });
+    let entries = match read_dir_reporting(&downloads, reporter) {
+        Some(entries) => entries,
+        None => return results,
+    };
+    let cutoff = age_cutoff(min_age_days);
+
+    for entry in entries.flatten() {
+        if is_cancelled(control) {
+            break;
+        }
+        let path = entry.path();
+        if is_excluded(&path, excludes) {
+            continue;
+        }
+        let kind = match installer_kind(&path) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let metadata = match safe_metadata(&path) {
+            Some(meta) if meta.is_file() && meta.len() > 0 => meta,
+            _ => continue,
+        };
+        let modified = last_used(&metadata);
+        if let (Some(limit), Some(mtime)) = (cutoff, modified) {
+            if mtime >= limit {
+                continue;
+            }
+        }
+        let usage = DiskUsage::of_file(&metadata);
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path,
+                size_bytes: usage.allocated,
+                logical_bytes: usage.logical,
+                category: "Downloads".to_string(),
+                reason: format!("Downloaded {}", kind),
+                last_used: modified,
+                strategy: CleanupStrategy::Remove,
+                deep_only: false,
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+    }
+    results
+}
+
+fn installer_kind(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    let extension = name.rsplit('.').next()?;
+    if INSTALLER_EXTENSIONS.contains(&extension) {
+        return Some("installer image");
+    }
+    if ARCHIVE_EXTENSIONS.contains(&extension)
+        && SDK_KEYWORDS.iter().any(|keyword| name.contains(keyword))
+    {
+        return Some("SDK archive");
+    }
+    None
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Everything the core can fail with. Each variant keeps the path or tool
+/// it is about, so the CLI can pick an exit code and the GUI can suggest
+/// what to do next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DevstripError {
+    /// Reading or changing the filesystem failed.
+    Io {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// The operating system refused access to `path`.
+    Permission { path: PathBuf, message: String },
+    /// The user stopped the operation before it finished.
+    Cancelled,
+    /// A rule file, policy or command-line setting is invalid.
+    Config {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// An external program (a cleanup command, the deletion helper) could
+    /// not be run or reported a failure.
+    ExternalTool { tool: String, message: String },
+    /// Another devstrip process holds the instance lock at `lock`.
+    AlreadyRunning { lock: PathBuf, message: String },
+}
+
+impl DevstripError {
+    /// Wraps an I/O error on `path`. "Permission denied" becomes
+    /// `Permission` so it can be told apart from other failures.
+    pub fn io(path: &Path, err: &io::Error, message: impl Into<String>) -> Self {
+        let message = message.into();
+        if err.kind() == io::ErrorKind::PermissionDenied {
+            DevstripError::Permission {
+                path: path.to_path_buf(),
+                message,
+            }
+        } else {
+            DevstripError::Io {
+                path: Some(path.to_path_buf()),
+                message,
+            }
+        }
+    }
+
+    /// A filesystem problem that is not an `io::Error`, such as a target
+    /// that already exists.
+    pub fn file(path: &Path, message: impl Into<String>) -> Self {
+        DevstripError::Io {
+            path: Some(path.to_path_buf()),
+            message: message.into(),
+        }
+    }
+
+    pub fn config(path: Option<&Path>, message: impl Into<String>) -> Self {
+        DevstripError::Config {
+            path: path.map(Path::to_path_buf),
+            message: message.into(),
+        }
+    }
+
+    pub fn tool(tool: impl Into<String>, message: impl Into<String>) -> Self {
+        DevstripError::ExternalTool {
+            tool: tool.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The file or directory the error is about, if there is one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            DevstripError::Io { path, .. } | DevstripError::Config { path, .. } => path.as_deref(),
+            DevstripError::Permission { path, .. } => Some(path),
+            DevstripError::AlreadyRunning { lock, .. } => Some(lock),
+            DevstripError::Cancelled | DevstripError::ExternalTool { .. } => None,
+        }
+    }
+
+    /// Process exit status for the CLI, following the BSD `sysexits.h`
+    /// conventions (130 is what shells report for Ctrl-C).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DevstripError::Io { .. } => 74,
+            DevstripError::Permission { .. } => 77,
+            DevstripError::Cancelled => 130,
+            DevstripError::Config { .. } => 78,
+            DevstripError::ExternalTool { .. } => 69,
+            DevstripError::AlreadyRunning { .. } => 75,
+        }
+    }
+
+    /// A short suggestion for the user, shown after the error in the GUI.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            DevstripError::Permission { path, .. } if cfg!(target_os = "macos") => Some(format!(
+                "Grant devstrip Full Disk Access in System Settings > Privacy & Security, or check the permissions of {}.",
+                path.display()
+            )),
+            DevstripError::Permission { path, .. } => {
+                Some(format!("Check the permissions of {}.", path.display()))
+            }
+            DevstripError::Config {
+                path: Some(path), ..
+            } => Some(format!("Fix or remove {} and try again.", path.display())),
+            DevstripError::ExternalTool { tool, .. } => Some(format!(
+                "Run `devstrip doctor` to check that {} is installed and working.",
+                tool
+            )),
+            DevstripError::AlreadyRunning { lock, .. } => Some(format!(
+                "Wait for the other scan or cleanup to finish; the lock is {}.",
+                lock.display()
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DevstripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DevstripError::Io { message, .. }
+            | DevstripError::Permission { message, .. }
+            | DevstripError::Config { message, .. }
+            | DevstripError::ExternalTool { message, .. }
+            | DevstripError::AlreadyRunning { message, .. } => f.write_str(message),
+            DevstripError::Cancelled => f.write_str("Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for DevstripError {}
@@ -0,0 +1,117 @@
+use super::{Candidate, CleanupResult, ScanEstimate, ScanProfile};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A directory the scan could not read, so nothing below it was considered.
+#[derive(Clone, Debug)]
+pub struct SkippedPath {
+    pub path: PathBuf,
+    pub kind: io::ErrorKind,
+}
+
+impl SkippedPath {
+    pub fn is_permission_denied(&self) -> bool {
+        self.kind == io::ErrorKind::PermissionDenied
+    }
+}
+
+/// What to do about `skipped`, shown under the "could not be inspected"
+/// list. Only permission problems have a remedy worth suggesting.
+pub fn skipped_paths_hint(skipped: &[SkippedPath]) -> Option<&'static str> {
+    if !skipped.iter().any(SkippedPath::is_permission_denied) {
+        return None;
+    }
+    Some(if cfg!(target_os = "macos") {
+        "Grant devstrip Full Disk Access in System Settings > Privacy & Security, then scan again."
+    } else {
+        "Check the permissions of these directories, or exclude them from the scan."
+    })
+}
+
+/// Everything a frontend needs to follow a scan or cleanup as it happens.
+/// Both the CLI and the GUI render from this stream.
+#[derive(Clone, Debug)]
+pub enum DevstripEvent {
+    ScanStarted {
+        roots: Vec<PathBuf>,
+    },
+    Scanning {
+        path: PathBuf,
+    },
+    CandidateFound(Candidate),
+    /// Sent each time the scan gets another percent further, when an
+    /// earlier scan of the same roots says how far there is to go.
+    ScanEstimate(ScanEstimate),
+    PathSkipped(SkippedPath),
+    /// An exclude that kept nothing out of the scan, most likely a typo or
+    /// a folder that was renamed. Sent just before `ScanFinished`.
+    UnmatchedExclude {
+        path: PathBuf,
+    },
+    /// Where the scan spent its time, when `ScanConfig::profile` is set.
+    /// Sent just before `ScanFinished`.
+    ScanProfile(ScanProfile),
+    ScanFinished {
+        candidates: usize,
+        cancelled: bool,
+    },
+    CleanupItemStarted {
+        index: usize,
+        total: usize,
+        candidate: Arc<Candidate>,
+    },
+    CleanupItemFinished {
+        index: usize,
+        total: usize,
+        result: CleanupResult,
+    },
+    /// The cleanup was stopped before `total - completed` candidates.
+    CleanupCancelled {
+        completed: usize,
+        total: usize,
+    },
+    Warning(String),
+    Error(String),
+}
+
+impl DevstripEvent {
+    pub fn status_text(&self) -> Option<String> {
+        match self {
+            DevstripEvent::ScanStarted { roots } => {
+                Some(format!("Scanning {} root(s)", roots.len()))
+            }
+            DevstripEvent::Scanning { path } => Some(format!("Scanning: {}", path.display())),
+            DevstripEvent::CandidateFound(_) => None,
+            DevstripEvent::ScanEstimate(_) => None,
+            DevstripEvent::PathSkipped(_) => None,
+            DevstripEvent::UnmatchedExclude { .. } => None,
+            DevstripEvent::ScanProfile(_) => None,
+            DevstripEvent::ScanFinished {
+                candidates,
+                cancelled,
+            } => Some(if *cancelled {
+                format!("Scan cancelled with {} candidate(s)", candidates)
+            } else {
+                format!("Scan finished with {} candidate(s)", candidates)
+            }),
+            DevstripEvent::CleanupItemStarted {
+                index,
+                total,
+                candidate,
+            } => Some(format!(
+                "Cleaning {}/{}: {}",
+                index + 1,
+                total,
+                candidate.display_name()
+            )),
+            DevstripEvent::CleanupItemFinished { .. } => None,
+            DevstripEvent::CleanupCancelled { completed, total } => Some(format!(
+                "Cleanup stopped after {}/{} item(s)",
+                completed, total
+            )),
+            DevstripEvent::Warning(message) => Some(format!("Warning: {}", message)),
+            DevstripEvent::Error(message) => Some(format!("Error: {}", message)),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use super::Candidate;
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Serialize;
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    candidates: Vec<JsonCandidate<'a>>,
+    summary: JsonSummary<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonCandidate<'a> {
+    path: &'a Path,
+    size_bytes: u64,
+    logical_bytes: u64,
+    category: &'a str,
+    reason: &'a str,
+    /// RFC 3339 local time, `null` when unknown.
+    last_used: Option<String>,
+    strategy: String,
+    size_pending: bool,
+    size_incomplete: bool,
+}
+
+#[derive(Serialize)]
+struct JsonSummary<'a> {
+    /// RFC 3339 local time the scan finished.
+    scanned_at: String,
+    roots: &'a [PathBuf],
+    candidates: usize,
+    total_bytes: u64,
+    logical_bytes: u64,
+    categories: BTreeMap<&'a str, JsonCategory>,
+}
+
+#[derive(Default, Serialize)]
+struct JsonCategory {
+    candidates: usize,
+    size_bytes: u64,
+}
+
+/// The candidates of a scan of `roots` that finished at `scanned_at`, with
+/// a summary, as one pretty-printed JSON object for scripts and dashboards.
+pub fn render_json<C: Borrow<Candidate>>(
+    candidates: &[C],
+    roots: &[PathBuf],
+    scanned_at: SystemTime,
+) -> String {
+    let mut categories: BTreeMap<&str, JsonCategory> = BTreeMap::new();
+    let mut total_bytes = 0u64;
+    let mut logical_bytes = 0u64;
+    let entries = candidates
+        .iter()
+        .map(|candidate| {
+            let candidate = candidate.borrow();
+            let category = categories.entry(candidate.category.as_str()).or_default();
+            category.candidates += 1;
+            category.size_bytes = category.size_bytes.saturating_add(candidate.size_bytes);
+            total_bytes = total_bytes.saturating_add(candidate.size_bytes);
+            logical_bytes = logical_bytes.saturating_add(candidate.logical_bytes);
+            JsonCandidate {
+                path: &candidate.path,
+                size_bytes: candidate.size_bytes,
+                logical_bytes: candidate.logical_bytes,
+                category: &candidate.category,
+                reason: &candidate.reason,
+                last_used: candidate.last_used.map(rfc3339),
+                strategy: candidate.strategy.describe(),
+                size_pending: candidate.size_pending,
+                size_incomplete: candidate.size_incomplete,
+            }
+        })
+        .collect();
+    let report = JsonReport {
+        candidates: entries,
+        summary: JsonSummary {
+            scanned_at: rfc3339(scanned_at),
+            roots,
+            candidates: candidates.len(),
+            total_bytes,
+            logical_bytes,
+            categories,
+        },
+    };
+    serde_json::to_string_pretty(&report).unwrap_or_default()
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false)
+}
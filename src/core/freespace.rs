@@ -0,0 +1,82 @@
+use std::path::Path;
+
+/// Space available to this user on the filesystem that holds `path`, or
+/// `None` when it cannot be determined.
+pub fn available_space(path: &Path) -> Option<u64> {
+    os_available_space(path)
+}
+
+/// Whether removing `path` frees space on the filesystem that holds
+/// `volume`. A symlink counts where the link itself lives, and a path that
+/// cannot be read (a Docker image, say) is on no volume.
+pub fn on_volume(path: &Path, volume: &Path) -> bool {
+    os_on_volume(path, volume)
+}
+
+#[cfg(unix)]
+fn os_on_volume(path: &Path, volume: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| std::fs::symlink_metadata(path).map(|meta| meta.dev()).ok();
+    matches!((device(path), device(volume)), (Some(a), Some(b)) if a == b)
+}
+
+#[cfg(windows)]
+fn os_on_volume(path: &Path, volume: &Path) -> bool {
+    use std::path::Component;
+
+    let drive = |path: &Path| match path.components().next() {
+        Some(Component::Prefix(prefix)) => {
+            Some(prefix.as_os_str().to_string_lossy().to_ascii_lowercase())
+        }
+        _ => None,
+    };
+    matches!((drive(path), drive(volume)), (Some(a), Some(b)) if a == b)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn os_on_volume(_path: &Path, _volume: &Path) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn os_available_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(windows)]
+fn os_available_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated; the totals we do not need may be null.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn os_available_space(_path: &Path) -> Option<u64> {
+    None
+}
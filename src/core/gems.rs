@@ -0,0 +1,159 @@
+use super::{
+    age_cutoff, compare_versions, is_cancelled, is_excluded, last_used, measure_size, report_found,
+    safe_metadata, split_gem_dir_name, split_platform, Candidate, CleanupStrategy, DevstripEvent,
+    DiskUsage, ScanConfig, ScanControl,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The `gems` folders of the rbenv, rvm and `~/.gem` gem homes. Each sits
+/// next to the `specifications`, `cache`, `extensions` and `doc` folders of
+/// the same home.
+pub(crate) fn ruby_gem_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for version in child_dirs(&home.join(".rbenv/versions")) {
+        for abi in child_dirs(&version.join("lib/ruby/gems")) {
+            dirs.push(abi.join("gems"));
+        }
+    }
+    for gemset in child_dirs(&home.join(".rvm/gems")) {
+        dirs.push(gemset.join("gems"));
+    }
+    for abi in child_dirs(&home.join(".gem/ruby")) {
+        dirs.push(abi.join("gems"));
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+fn child_dirs(path: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|child| child.is_dir())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Installed gem versions with a newer install of the same gem for the same
+/// platform, once they are older than the Ruby minimum age.
+pub(crate) fn collect_stale_gem_versions<F>(
+    gems_dir: &Path,
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let excludes = &config.exclude_paths;
+    let mut results = Vec::new();
+    if is_excluded(gems_dir, excludes) {
+        return results;
+    }
+    reporter(DevstripEvent::Scanning {
+        path: gems_dir.to_path_buf(),
+    });
+    let cutoff = age_cutoff(config.category_min_age_days("Ruby"));
+
+    let mut by_gem: HashMap<(String, String), Vec<(String, PathBuf)>> = HashMap::new();
+    for path in child_dirs(gems_dir) {
+        let dir_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(n) => n.to_string(),
+            None => continue,
+        };
+        if let Some((name, version)) = split_gem_dir_name(&dir_name) {
+            let (version, platform) = split_platform(&version);
+            by_gem
+                .entry((name, platform))
+                .or_default()
+                .push((version, path));
+        }
+    }
+
+    for ((name, _), mut versions) in by_gem {
+        if versions.len() < 2 {
+            continue;
+        }
+        versions.sort_by(|a, b| compare_versions(&b.0, &a.0));
+        let newest = versions[0].0.clone();
+        for (_, path) in versions.into_iter().skip(1) {
+            if is_excluded(&path, excludes) {
+                continue;
+            }
+            if is_cancelled(control) {
+                return results;
+            }
+            let modified = safe_metadata(&path).and_then(|meta| last_used(&meta));
+            if let (Some(limit), Some(mtime)) = (cutoff, modified) {
+                if mtime >= limit {
+                    continue;
+                }
+            }
+            let usage = version_usage(&path, control);
+            if usage.is_empty() {
+                continue;
+            }
+            results.push(report_found(
+                reporter,
+                Candidate {
+                    path,
+                    size_bytes: usage.allocated,
+                    logical_bytes: usage.logical,
+                    category: "Ruby".to_string(),
+                    reason: format!("Superseded gem version ({} {} installed)", name, newest),
+                    last_used: modified,
+                    strategy: CleanupStrategy::RemoveGemVersion,
+                    deep_only: config.too_recent_for_baseline(modified),
+                    recently_opened: None,
+                    scanned: None,
+                    size_pending: false,
+                    size_incomplete: false,
+                    matched_rule: None,
+                },
+            ));
+        }
+    }
+
+    results
+}
+
+/// Everything `gem uninstall` removes for the version unpacked at
+/// `gem_dir`: its gemspec, the cached package, built native extensions and
+/// generated docs, then `gem_dir` itself. The gemspec goes first, so a
+/// removal stopped halfway never leaves RubyGems listing a gem whose files
+/// are gone.
+pub(crate) fn version_paths(gem_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let full_name = gem_dir.file_name().and_then(|name| name.to_str());
+    let gem_home = gem_dir.parent().and_then(Path::parent);
+    if let (Some(full_name), Some(gem_home)) = (full_name, gem_home) {
+        paths.push(
+            gem_home
+                .join("specifications")
+                .join(format!("{}.gemspec", full_name)),
+        );
+        paths.push(gem_home.join("cache").join(format!("{}.gem", full_name)));
+        for platform in child_dirs(&gem_home.join("extensions")) {
+            for abi in child_dirs(&platform) {
+                paths.push(abi.join(full_name));
+            }
+        }
+        paths.push(gem_home.join("doc").join(full_name));
+    }
+    paths.retain(|path| safe_metadata(path).is_some());
+    paths.push(gem_dir.to_path_buf());
+    paths
+}
+
+/// The space removing the version at `gem_dir` frees.
+pub(crate) fn version_usage(gem_dir: &Path, control: Option<&ScanControl>) -> DiskUsage {
+    version_paths(gem_dir)
+        .iter()
+        .fold(DiskUsage::default(), |acc, path| {
+            acc.add(measure_size(path, control))
+        })
+}
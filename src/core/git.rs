@@ -0,0 +1,56 @@
+use super::home_dir;
+use super::tools::{find_in_path, run_tool};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The repository `path` belongs to, if any: the nearest ancestor (or
+/// `path` itself) with a `.git` directory or worktree file. A repository at
+/// the home directory or above (a dotfiles checkout) does not count.
+pub(crate) fn enclosing_repo(path: &Path) -> Option<PathBuf> {
+    let home = home_dir();
+    path.ancestors()
+        .take_while(|dir| home.as_deref().is_none_or(|home| !home.starts_with(dir)))
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Whether the repository at `repo` is actively developed: it has
+/// uncommitted changes or a commit within the last `days` days. Without
+/// `git` on the PATH no repository is considered active.
+pub(crate) fn recently_active(repo: &Path, days: u64) -> bool {
+    if find_in_path("git").is_none() {
+        return false;
+    }
+    let repo_arg = repo.to_string_lossy();
+    let status = match run_tool("git", &["-C", &repo_arg, "status", "--porcelain"]) {
+        Some(status) => status,
+        None => return false,
+    };
+    if !status.trim().is_empty() {
+        return true;
+    }
+    let committed = match run_tool("git", &["-C", &repo_arg, "log", "-1", "--format=%ct"])
+        .and_then(|out| out.trim().parse::<u64>().ok())
+    {
+        Some(seconds) => seconds,
+        None => return false,
+    };
+    let committed = UNIX_EPOCH + Duration::from_secs(committed);
+    let window = Duration::from_secs(days.saturating_mul(86_400));
+    SystemTime::now()
+        .duration_since(committed)
+        .map(|age| age < window)
+        .unwrap_or(true)
+}
+
+/// Whether the repository `dir` belongs to tracks any file inside it, which
+/// makes a `build` or `dist` folder source rather than output. Outside a
+/// repository, or without `git` on the PATH, nothing counts as tracked.
+pub(crate) fn has_tracked_files(dir: &Path) -> bool {
+    if enclosing_repo(dir).is_none() || find_in_path("git").is_none() {
+        return false;
+    }
+    let dir_arg = dir.to_string_lossy();
+    run_tool("git", &["-C", &dir_arg, "ls-files", "--", "."])
+        .is_some_and(|files| !files.trim().is_empty())
+}
@@ -0,0 +1,102 @@
+use super::{
+    age_cutoff, is_cancelled, is_excluded, last_used, measure_size, read_dir_reporting,
+    report_found, safe_metadata, Candidate, CleanupStrategy, DevstripEvent, ScanConfig,
+    ScanControl,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+pub(crate) const WRAPPER_PROPERTIES: &str = "wrapper/gradle-wrapper.properties";
+
+/// Reads the distribution a project's wrapper pins, e.g. `gradle-8.5-bin`,
+/// which is also the directory name under `~/.gradle/wrapper/dists`.
+pub(crate) fn wrapper_distribution(properties: &Path) -> Option<String> {
+    let text = fs::read_to_string(properties).ok()?;
+    let url = text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "distributionUrl").then(|| value.trim().to_string())
+    })?;
+    let file = url.rsplit('/').next()?;
+    let name = file.strip_suffix(".zip").unwrap_or(file);
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Flags wrapper distributions that no scanned project references. Nothing
+/// is flagged when no wrapper was seen at all, since then the scan gives no
+/// evidence about which versions are still needed.
+pub(crate) fn collect_unused_wrapper_dists<F>(
+    home: &Path,
+    referenced: &HashSet<String>,
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    let dists = home.join(".gradle/wrapper/dists");
+    if referenced.is_empty() || is_excluded(&dists, &config.exclude_paths) || !dists.is_dir() {
+        return results;
+    }
+    reporter(DevstripEvent::Scanning {
+        path: dists.clone(),
+    });
+    let entries = match read_dir_reporting(&dists, reporter) {
+        Some(entries) => entries,
+        None => return results,
+    };
+    let cutoff = age_cutoff(config.category_min_age_days("Gradle"));
+
+    for entry in entries.flatten() {
+        if is_cancelled(control) {
+            break;
+        }
+        let path = entry.path();
+        if is_excluded(&path, &config.exclude_paths) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if referenced.contains(&name) {
+            continue;
+        }
+        let metadata = match safe_metadata(&path) {
+            Some(meta) if meta.is_dir() => meta,
+            _ => continue,
+        };
+        let modified = last_used(&metadata);
+        if let (Some(limit), Some(mtime)) = (cutoff, modified) {
+            if mtime >= limit {
+                continue;
+            }
+        }
+        let usage = measure_size(&path, control);
+        if usage.is_empty() {
+            continue;
+        }
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path,
+                size_bytes: usage.allocated,
+                logical_bytes: usage.logical,
+                category: "Gradle".to_string(),
+                reason: format!("Unreferenced Gradle wrapper ({})", name),
+                last_used: modified,
+                strategy: CleanupStrategy::Remove,
+                deep_only: config.too_recent_for_baseline(modified),
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+    }
+    results
+}
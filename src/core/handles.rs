@@ -0,0 +1,78 @@
+use super::tools::find_in_path;
+use super::{safe_metadata, Candidate, CleanupStrategy};
+use std::path::Path;
+use std::process::Command;
+
+/// At most this many processes are named in an "in use by" message.
+const MAX_REPORTED: usize = 3;
+
+/// Why `candidate` must not be touched right now: a process (a build, an
+/// IDE, a daemon) still has a file inside it open. Only strategies that
+/// replace the whole path are checked; pruning old files next to a log an
+/// IDE is writing is fine. Without `lsof` nothing is reported.
+pub(crate) fn in_use_reason(candidate: &Candidate) -> Option<String> {
+    if !matches!(
+        candidate.strategy,
+        CleanupStrategy::Remove
+            | CleanupStrategy::Compress
+            | CleanupStrategy::EmptyDirectory
+            | CleanupStrategy::RemoveGemVersion
+    ) {
+        return None;
+    }
+    let owners = open_handle_owners(&candidate.path);
+    if owners.is_empty() {
+        return None;
+    }
+    let mut names: Vec<String> = owners
+        .iter()
+        .take(MAX_REPORTED)
+        .map(|(pid, command)| format!("{} (pid {})", command, pid))
+        .collect();
+    if owners.len() > MAX_REPORTED {
+        names.push(format!("{} more", owners.len() - MAX_REPORTED));
+    }
+    Some(format!("in use by {}", names.join(", ")))
+}
+
+/// Processes other than this one holding a file at or under `path` open.
+fn open_handle_owners(path: &Path) -> Vec<(u32, String)> {
+    let meta = match safe_metadata(path) {
+        Some(meta) if !meta.file_type().is_symlink() => meta,
+        _ => return Vec::new(),
+    };
+    if find_in_path("lsof").is_none() {
+        return Vec::new();
+    }
+    let mut command = Command::new("lsof");
+    command.args(["-w", "-F", "pc"]);
+    if meta.is_dir() {
+        command.arg("+D");
+    } else {
+        command.arg("--");
+    }
+    // The exit status is not useful: lsof also fails when nothing has the
+    // path open, or when it could not stat some unrelated mount.
+    match command.arg(path).output() {
+        Ok(output) => parse_lsof_owners(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Parses `lsof -F pc`: a `p<pid>` line starts each process and the
+/// following `c<command>` line names it.
+fn parse_lsof_owners(output: &str) -> Vec<(u32, String)> {
+    let own_pid = std::process::id();
+    let mut owners: Vec<(u32, String)> = Vec::new();
+    let mut pid = None;
+    for line in output.lines() {
+        if let Some(value) = line.strip_prefix('p') {
+            pid = value.trim().parse::<u32>().ok();
+        } else if let (Some(command), Some(current)) = (line.strip_prefix('c'), pid) {
+            if current != own_pid && !owners.iter().any(|(seen, _)| *seen == current) {
+                owners.push((current, command.to_string()));
+            }
+        }
+    }
+    owners
+}
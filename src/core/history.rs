@@ -0,0 +1,183 @@
+use super::{data_dir, CleanupResult, CoreResult, DevstripError};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// What one cleanup run reclaimed. Unlike the audit log, which keeps every
+/// target, the history keeps one line per run, so it stays small enough
+/// to sum up on every `devstrip history`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryRun {
+    /// RFC 3339 local time of the run.
+    pub timestamp: String,
+    pub targets: usize,
+    pub reclaimed_bytes: u64,
+    /// Whether the targets went to the Trash, which frees nothing until
+    /// it is emptied.
+    #[serde(default)]
+    pub trash: bool,
+    pub categories: BTreeMap<String, u64>,
+}
+
+/// How `devstrip history` groups runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    fn key(self, time: &DateTime<Local>) -> String {
+        match self {
+            Period::Day => time.format("%Y-%m-%d").to_string(),
+            Period::Week => time.format("%G-W%V").to_string(),
+            Period::Month => time.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Period {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "day" | "daily" => Ok(Period::Day),
+            "week" | "weekly" => Ok(Period::Week),
+            "month" | "monthly" => Ok(Period::Month),
+            other => Err(format!(
+                "unknown period '{}' (expected day, week, or month)",
+                other
+            )),
+        }
+    }
+}
+
+/// The runs of one period, with the running total up to its end.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PeriodTotals {
+    pub period: String,
+    pub runs: usize,
+    pub targets: usize,
+    pub reclaimed_bytes: u64,
+    pub cumulative_bytes: u64,
+    pub trash_bytes: u64,
+    pub categories: BTreeMap<String, u64>,
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// Appends the run to the history when it cleaned anything.
+pub(crate) fn record_run(results: &[CleanupResult], trash: bool) -> CoreResult<()> {
+    let mut run = HistoryRun {
+        timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        targets: 0,
+        reclaimed_bytes: 0,
+        trash,
+        categories: BTreeMap::new(),
+    };
+    for result in results.iter().filter(|result| result.success) {
+        let size = result.candidate.size_bytes;
+        run.targets += 1;
+        run.reclaimed_bytes = run.reclaimed_bytes.saturating_add(size);
+        let category = run
+            .categories
+            .entry(result.candidate.category.clone())
+            .or_default();
+        *category = category.saturating_add(size);
+    }
+    if run.targets == 0 {
+        return Ok(());
+    }
+    let path = history_path().ok_or_else(|| DevstripError::Io {
+        path: None,
+        message: "Unable to locate the data directory".to_string(),
+    })?;
+    let mut line =
+        serde_json::to_string(&run).map_err(|err| DevstripError::file(&path, err.to_string()))?;
+    line.push('\n');
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(line.as_bytes())
+    };
+    write().map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!(
+                "Unable to write the cleanup history {}: {}",
+                path.display(),
+                err
+            ),
+        )
+    })
+}
+
+/// Every recorded run, oldest first. Lines that fail to parse are skipped,
+/// as in the audit log.
+pub fn read_history() -> CoreResult<Vec<HistoryRun>> {
+    let path = match history_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Vec::new()),
+    };
+    let text = fs::read_to_string(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!(
+                "Unable to read the cleanup history {}: {}",
+                path.display(),
+                err
+            ),
+        )
+    })?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Sums `runs` per `period`, oldest period first.
+pub fn totals_by_period(runs: &[HistoryRun], period: Period) -> Vec<PeriodTotals> {
+    let mut periods: BTreeMap<String, PeriodTotals> = BTreeMap::new();
+    for run in runs {
+        let Ok(time) = DateTime::parse_from_rfc3339(&run.timestamp) else {
+            continue;
+        };
+        let key = period.key(&time.with_timezone(&Local));
+        let totals = periods.entry(key.clone()).or_insert_with(|| PeriodTotals {
+            period: key,
+            ..PeriodTotals::default()
+        });
+        totals.runs += 1;
+        totals.targets += run.targets;
+        totals.reclaimed_bytes = totals.reclaimed_bytes.saturating_add(run.reclaimed_bytes);
+        if run.trash {
+            totals.trash_bytes = totals.trash_bytes.saturating_add(run.reclaimed_bytes);
+        }
+        for (category, bytes) in &run.categories {
+            let sum = totals.categories.entry(category.clone()).or_default();
+            *sum = sum.saturating_add(*bytes);
+        }
+    }
+    let mut cumulative = 0u64;
+    periods
+        .into_values()
+        .map(|mut totals| {
+            cumulative = cumulative.saturating_add(totals.reclaimed_bytes);
+            totals.cumulative_bytes = cumulative;
+            totals
+        })
+        .collect()
+}
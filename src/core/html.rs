@@ -0,0 +1,287 @@
+use super::lastscan::{SavedScan, ScanSettings};
+use super::summary::{self, GroupSummary};
+use super::{scan_total_size, Candidate, CoreResult, DevstripError};
+use human_bytes::human_bytes;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+const STYLE: &str = "
+body { font: 14px/1.4 -apple-system, 'Segoe UI', sans-serif; margin: 2em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.meta { color: #666; margin-top: 0; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 4px 10px; text-align: left; border-bottom: 1px solid #e4e4e4; }
+th { background: #f4f4f4; }
+table.sortable th { cursor: pointer; user-select: none; }
+table.sortable th::after { content: ' \\2195'; color: #aaa; }
+td.number { text-align: right; white-space: nowrap; }
+td.path { font-family: ui-monospace, Menlo, monospace; word-break: break-all; }
+.chart { margin-bottom: 2em; max-width: 60em; }
+.bar-row { display: flex; align-items: center; margin: 3px 0; }
+.bar-label { width: 12em; flex: none; overflow: hidden; text-overflow: ellipsis; }
+.bar-track { flex: 1; background: #f0f0f0; height: 16px; margin: 0 10px; }
+.bar { background: #4a7bd0; height: 100%; }
+.bar-value { width: 12em; flex: none; color: #555; }
+";
+
+/// Sorts a table by the column whose header was clicked, by each cell's
+/// `data-sort` when it has one; a second click reverses the order.
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable').forEach(function (table) {
+  table.querySelectorAll('th').forEach(function (th, column) {
+    th.addEventListener('click', function () {
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      var numeric = th.dataset.type === 'number';
+      var ascending = th.dataset.order !== 'asc';
+      th.dataset.order = ascending ? 'asc' : 'desc';
+      rows.sort(function (a, b) {
+        var x = a.cells[column], y = b.cells[column];
+        x = x.dataset.sort !== undefined ? x.dataset.sort : x.textContent;
+        y = y.dataset.sort !== undefined ? y.dataset.sort : y.textContent;
+        var order = numeric ? Number(x) - Number(y) : x.localeCompare(y);
+        return ascending ? order : -order;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+";
+
+/// A standalone page for `devstrip report --html`: the totals per category
+/// as a chart, the totals per root, every target (or the first `top`) in a
+/// sortable table, and the settings of the scan when it recorded them.
+pub fn render_html(scan: &SavedScan, top: Option<usize>) -> String {
+    let candidates = &scan.candidates;
+    let total = scan_total_size(candidates);
+    let roots = scan
+        .roots
+        .iter()
+        .map(|root| root.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut page = String::new();
+    let _ = write!(
+        page,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>devstrip report</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>devstrip report</h1>\n<p class=\"meta\">Scan of {} from {}: {} target(s), {} reclaimable.</p>\n",
+        STYLE,
+        escape(&roots),
+        escape(&scan.timestamp.replace('T', " ")),
+        candidates.len(),
+        human_bytes(total as f64)
+    );
+
+    let categories = summary::by_category(candidates);
+    page.push_str("<h2>By category</h2>\n<div class=\"chart\">\n");
+    let largest = categories.first().map_or(0, |group| group.size_bytes);
+    for group in &categories {
+        let percent = if largest == 0 {
+            0.0
+        } else {
+            group.size_bytes as f64 * 100.0 / largest as f64
+        };
+        let _ = writeln!(
+            page,
+            "<div class=\"bar-row\"><span class=\"bar-label\">{}</span>\
+             <span class=\"bar-track\"><div class=\"bar\" style=\"width: {:.1}%\"></div></span>\
+             <span class=\"bar-value\">{}, {} target(s)</span></div>",
+            escape(&group.name),
+            percent,
+            human_bytes(group.size_bytes as f64),
+            group.count
+        );
+    }
+    page.push_str("</div>\n");
+
+    page.push_str("<h2>By root</h2>\n");
+    push_groups(
+        &mut page,
+        "Root",
+        &summary::by_root(candidates, &scan.roots),
+    );
+
+    page.push_str("<h2>Targets</h2>\n");
+    let listed = top.map_or(candidates.len(), |top| top.min(candidates.len()));
+    if listed < candidates.len() {
+        let _ = writeln!(
+            page,
+            "<p class=\"meta\">The first {} of {} target(s).</p>",
+            listed,
+            candidates.len()
+        );
+    }
+    push_targets(&mut page, &candidates[..listed]);
+
+    page.push_str("<h2>Configuration</h2>\n");
+    match &scan.settings {
+        Some(settings) => push_settings(&mut page, settings),
+        None => page.push_str(
+            "<p class=\"meta\">This scan did not record its settings; scan again to include them.</p>\n",
+        ),
+    }
+
+    let _ = write!(page, "<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+    page
+}
+
+/// Writes the page for `scan` to `path`.
+pub fn write_html(path: &Path, scan: &SavedScan, top: Option<usize>) -> CoreResult<()> {
+    fs::write(path, render_html(scan, top)).map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to write the report to {}: {}", path.display(), err),
+        )
+    })
+}
+
+fn push_groups(page: &mut String, title: &str, groups: &[GroupSummary]) {
+    let _ = writeln!(
+        page,
+        "<table class=\"sortable\">\n<thead><tr><th>{}</th><th data-type=\"number\">Targets</th>\
+         <th data-type=\"number\">Size</th><th data-type=\"number\">Oldest</th><th>Oldest target</th></tr></thead>\n<tbody>",
+        title
+    );
+    for group in groups {
+        let oldest = group.oldest.as_ref();
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\" data-sort=\"{}\">{}</td>\
+             <td class=\"number\" data-sort=\"{}\">{}</td><td class=\"path\">{}</td></tr>",
+            escape(&group.name),
+            group.count,
+            group.size_bytes,
+            human_bytes(group.size_bytes as f64),
+            oldest.map_or(0, last_used_secs),
+            oldest.map_or_else(|| "-".to_string(), Candidate::last_used_str),
+            escape(&oldest.map(Candidate::display_name).unwrap_or_default())
+        );
+    }
+    page.push_str("</tbody>\n</table>\n");
+}
+
+fn push_targets(page: &mut String, candidates: &[Candidate]) {
+    page.push_str(
+        "<table class=\"sortable\">\n<thead><tr><th>Category</th><th data-type=\"number\">Size</th>\
+         <th data-type=\"number\">Last used</th><th>Reason</th><th>Path</th></tr></thead>\n<tbody>\n",
+    );
+    for candidate in candidates {
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td class=\"number\" data-sort=\"{}\">{}</td>\
+             <td class=\"number\" data-sort=\"{}\">{}</td><td>{}</td><td class=\"path\">{}</td></tr>",
+            escape(&candidate.category),
+            candidate.size_bytes,
+            human_bytes(candidate.size_bytes as f64),
+            last_used_secs(candidate),
+            candidate.last_used_str(),
+            escape(&candidate.reason),
+            escape(&candidate.display_name())
+        );
+    }
+    page.push_str("</tbody>\n</table>\n");
+}
+
+fn push_settings(page: &mut String, settings: &ScanSettings) {
+    let list = |items: &[String]| {
+        if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+    let days =
+        |days: Option<u64>| days.map_or_else(|| "off".to_string(), |d| format!("{} day(s)", d));
+    let rows = [
+        ("Minimum age", format!("{} day(s)", settings.min_age_days)),
+        (
+            "Age overrides",
+            list(
+                &settings
+                    .age_overrides
+                    .iter()
+                    .map(|age| format!("{}={}d", age.target, age.days))
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        ("Maximum depth", settings.max_depth.to_string()),
+        (
+            "Keep latest",
+            format!(
+                "{} DerivedData, {} cache",
+                settings.keep_latest_derived, settings.keep_latest_cache
+            ),
+        ),
+        (
+            "Excludes",
+            list(
+                &settings
+                    .exclude_paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        ("Exclude globs", list(&settings.exclude_globs)),
+        ("Extra patterns", list(&settings.extra_patterns)),
+        ("Custom rules", list(&settings.custom_rules)),
+        (
+            "Downloads installers",
+            days(settings.downloads_min_age_days),
+        ),
+        ("Skip active repos", days(settings.active_repo_days)),
+        ("Cargo sweep", settings.cargo_sweep.to_string()),
+        ("Same filesystem", settings.same_filesystem.to_string()),
+        ("Symlinks", settings.symlinks.clone()),
+        (
+            "Categories",
+            if settings.categories.is_empty() {
+                "all".to_string()
+            } else {
+                settings.categories.join(", ")
+            },
+        ),
+        (
+            "Minimum size",
+            settings
+                .min_size_bytes
+                .map_or_else(|| "none".to_string(), |size| human_bytes(size as f64)),
+        ),
+    ];
+    page.push_str("<table>\n<tbody>\n");
+    for (name, value) in rows {
+        let _ = writeln!(
+            page,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            name,
+            escape(&value)
+        );
+    }
+    page.push_str("</tbody>\n</table>\n");
+}
+
+fn last_used_secs(candidate: &Candidate) -> u64 {
+    candidate
+        .last_used
+        .and_then(|used| used.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
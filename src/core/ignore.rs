@@ -0,0 +1,111 @@
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) const IGNORE_FILE: &str = ".devstripignore";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// The patterns of one `.devstripignore`, relative to the directory holding
+/// it. Like `.gitignore`, a pattern without a slash matches at any depth
+/// (`build`), one with a slash is anchored to the project root
+/// (`packages/*/dist`), and `**` spans directories.
+#[derive(Debug)]
+pub(crate) struct IgnoreFile {
+    root: PathBuf,
+    patterns: Vec<Pattern>,
+    /// Lines that are not valid glob patterns, for a scan warning.
+    pub(crate) invalid: Vec<String>,
+}
+
+impl IgnoreFile {
+    /// Reads `dir/.devstripignore`, if there is one.
+    pub(crate) fn load(dir: &Path) -> Option<IgnoreFile> {
+        let text = fs::read_to_string(dir.join(IGNORE_FILE)).ok()?;
+        let mut patterns = Vec::new();
+        let mut invalid = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let trimmed = line.trim_end_matches('/');
+            let glob = match trimmed.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if trimmed.contains('/') => trimmed.to_string(),
+                None => format!("**/{}", trimmed),
+            };
+            match Pattern::new(&glob) {
+                Ok(pattern) => patterns.push(pattern),
+                Err(err) => invalid.push(format!(
+                    "{}: ignoring pattern {:?}: {}",
+                    dir.join(IGNORE_FILE).display(),
+                    line,
+                    err
+                )),
+            }
+        }
+        Some(IgnoreFile {
+            root: dir.to_path_buf(),
+            patterns,
+            invalid,
+        })
+    }
+
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => return false,
+        };
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_path_with(relative, MATCH_OPTIONS))
+    }
+
+    pub(crate) fn path(&self) -> PathBuf {
+        self.root.join(IGNORE_FILE)
+    }
+}
+
+/// `--exclude-glob` patterns, matched against whole paths during the
+/// project walk. A pattern ending in `/**` also covers the directory
+/// itself, so `**/vendor/**` keeps `vendor` from being flagged too.
+#[derive(Debug, Default)]
+pub(crate) struct GlobExcludes {
+    patterns: Vec<Pattern>,
+}
+
+impl GlobExcludes {
+    /// Compiles `globs`, leaving out any that are not valid patterns.
+    pub(crate) fn new(globs: &[String]) -> GlobExcludes {
+        let patterns = globs
+            .iter()
+            .flat_map(|glob| {
+                let parent = glob.strip_suffix("/**").map(str::to_string);
+                std::iter::once(glob.clone()).chain(parent)
+            })
+            .filter_map(|glob| Pattern::new(&glob).ok())
+            .collect();
+        GlobExcludes { patterns }
+    }
+
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_path_with(path, MATCH_OPTIONS))
+    }
+}
+
+/// The `.devstripignore` above `path` that protects it, found by walking up
+/// from its parent. Used to re-check a candidate outside of a scan.
+pub(crate) fn protecting_file(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .filter_map(IgnoreFile::load)
+        .find(|file| file.matches(path))
+        .map(|file| file.path())
+}
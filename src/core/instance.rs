@@ -0,0 +1,121 @@
+use super::{data_dir, linux, CoreResult, DevstripError};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+const LOCK_FILE: &str = "devstrip.lock";
+const DAEMON_LOCK_FILE: &str = "daemon.lock";
+
+/// Held while a scan or cleanup runs, so a scheduled run and a GUI session
+/// never work on the same candidates at once. The lock is advisory and
+/// released by the operating system when the process exits, so a crashed
+/// run never leaves it stuck.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Takes the lock, or fails with `AlreadyRunning` naming the process
+    /// that holds it.
+    pub fn acquire() -> CoreResult<Self> {
+        Self::acquire_file(LOCK_FILE, "Another devstrip instance is running")
+    }
+
+    /// Held for the life of `devstrip daemon`, so only one daemon runs per
+    /// user. Each pass still takes the instance lock while it works.
+    pub fn acquire_daemon() -> CoreResult<Self> {
+        Self::acquire_file(DAEMON_LOCK_FILE, "A devstrip daemon is already running")
+    }
+
+    fn acquire_file(name: &str, busy: &str) -> CoreResult<Self> {
+        let dir = lock_dir().ok_or_else(|| DevstripError::Io {
+            path: None,
+            message: "Unable to locate a directory for the instance lock".to_string(),
+        })?;
+        fs::create_dir_all(&dir).map_err(|err| {
+            DevstripError::io(
+                &dir,
+                &err,
+                format!("Unable to create {}: {}", dir.display(), err),
+            )
+        })?;
+        let path = dir.join(name);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|err| {
+                DevstripError::io(
+                    &path,
+                    &err,
+                    format!("Unable to open {}: {}", path.display(), err),
+                )
+            })?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut owner = String::new();
+                let _ = file.read_to_string(&mut owner);
+                let message = match owner.trim().parse::<u32>() {
+                    Ok(pid) => format!("{} (pid {}).", busy, pid),
+                    Err(_) => format!("{}.", busy),
+                };
+                return Err(DevstripError::AlreadyRunning {
+                    lock: path,
+                    message,
+                });
+            }
+            // Some network filesystems cannot lock at all; running
+            // unguarded beats not running.
+            Err(TryLockError::Error(err)) if err.kind() == io::ErrorKind::Unsupported => {}
+            Err(TryLockError::Error(err)) => {
+                return Err(DevstripError::io(
+                    &path,
+                    &err,
+                    format!("Unable to lock {}: {}", path.display(), err),
+                ));
+            }
+        }
+        let _ = file
+            .set_len(0)
+            .and_then(|_| writeln!(file, "{}", std::process::id()));
+        Ok(InstanceLock { _file: file, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Whether a `devstrip daemon` is running for this user.
+pub fn daemon_running() -> bool {
+    let Some(path) = lock_dir().map(|dir| dir.join(DAEMON_LOCK_FILE)) else {
+        return false;
+    };
+    match File::open(&path) {
+        Ok(file) => matches!(file.try_lock_shared(), Err(TryLockError::WouldBlock)),
+        Err(_) => false,
+    }
+}
+
+/// The per-user runtime directory: `$XDG_RUNTIME_DIR` on Linux and the
+/// per-user `$TMPDIR` on macOS, falling back to the data directory.
+fn lock_dir() -> Option<PathBuf> {
+    let runtime = if cfg!(target_os = "linux") {
+        linux::runtime_dir()
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("TMPDIR")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    } else {
+        None
+    };
+    match runtime {
+        Some(dir) => Some(dir.join("devstrip")),
+        None => data_dir(),
+    }
+}
@@ -0,0 +1,342 @@
+use super::{
+    is_cancelled, is_excluded, last_used, linux, measure_size, report_found, safe_metadata,
+    Candidate, CleanupStrategy, DevstripEvent, Excludes, ScanControl,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Directory prefix used under the JetBrains folders, and the app bundle
+/// name the IDE installs as.
+const PRODUCTS: &[(&str, &str)] = &[
+    ("IntelliJIdea", "IntelliJ IDEA"),
+    ("IdeaIC", "IntelliJ IDEA CE"),
+    ("PyCharm", "PyCharm"),
+    ("PyCharmCE", "PyCharm CE"),
+    ("WebStorm", "WebStorm"),
+    ("GoLand", "GoLand"),
+    ("CLion", "CLion"),
+    ("Rider", "Rider"),
+    ("RubyMine", "RubyMine"),
+    ("PhpStorm", "PhpStorm"),
+    ("DataGrip", "DataGrip"),
+    ("DataSpell", "DataSpell"),
+    ("RustRover", "RustRover"),
+    ("AppCode", "AppCode"),
+    ("Aqua", "Aqua"),
+];
+
+const APP_DIRS: &[&str] = &["/Applications"];
+/// Where each IDE version lists the projects it opened; Rider calls them
+/// solutions.
+const RECENT_PROJECT_FILES: &[&str] =
+    &["options/recentProjects.xml", "options/recentSolutions.xml"];
+const TOOLBOX_APPS: &str = "Library/Application Support/JetBrains/Toolbox/apps";
+
+struct VersionDir {
+    path: PathBuf,
+    version: (u32, u32),
+}
+
+/// Reports per-version JetBrains folders for IDE versions that are no longer
+/// in use: every version older than the newest installed one of each
+/// product, and every version with no installed app. In the caches folder the current version
+/// is reported too, since caches are always safe to rebuild.
+pub(crate) fn collect_leftover_versions<F>(
+    home: &Path,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    let mut roots = vec![
+        (home.join("Library/Application Support/JetBrains"), false),
+        (home.join("Library/Caches/JetBrains"), true),
+    ];
+    if cfg!(target_os = "linux") {
+        roots.push((linux::config_home(home).join("JetBrains"), false));
+        roots.push((linux::data_home(home).join("JetBrains"), false));
+        roots.push((linux::cache_home(home).join("JetBrains"), true));
+    }
+    for (base, is_cache) in roots {
+        if is_excluded(&base, excludes) || !base.is_dir() {
+            continue;
+        }
+        reporter(DevstripEvent::Scanning { path: base.clone() });
+        for (product, mut versions) in version_dirs(&base) {
+            if is_cancelled(control) {
+                return results;
+            }
+            versions.sort_by_key(|dir| std::cmp::Reverse(dir.version));
+            let installed = installed_versions(home, &product);
+            let is_installed = |version: (u32, u32)| {
+                installed
+                    .as_ref()
+                    .is_none_or(|versions| versions.contains(&version))
+            };
+            let newest = versions
+                .iter()
+                .map(|dir| dir.version)
+                .find(|version| is_installed(*version));
+            for dir in versions {
+                if is_excluded(&dir.path, excludes) {
+                    continue;
+                }
+                let reason = if !is_installed(dir.version) {
+                    format!(
+                        "{} {} (IDE not installed)",
+                        product,
+                        format_version(dir.version)
+                    )
+                } else if let Some(newest) = newest.filter(|newest| *newest > dir.version) {
+                    format!(
+                        "{} {} (superseded by {})",
+                        product,
+                        format_version(dir.version),
+                        format_version(newest)
+                    )
+                } else if is_cache {
+                    format!("{} {} caches", product, format_version(dir.version))
+                } else {
+                    continue;
+                };
+                let usage = measure_size(&dir.path, control);
+                if usage.is_empty() {
+                    continue;
+                }
+                let last_used = safe_metadata(&dir.path).and_then(|meta| last_used(&meta));
+                results.push(report_found(
+                    reporter,
+                    Candidate {
+                        path: dir.path,
+                        size_bytes: usage.allocated,
+                        logical_bytes: usage.logical,
+                        category: "JetBrains".to_string(),
+                        reason,
+                        last_used,
+                        strategy: CleanupStrategy::Remove,
+                        deep_only: false,
+                        recently_opened: None,
+                        scanned: None,
+                        size_pending: false,
+                        size_incomplete: false,
+                        matched_rule: None,
+                    },
+                ));
+            }
+        }
+    }
+    results
+}
+
+/// Projects the installed JetBrains IDEs remember opening, with the IDE
+/// name and when the project was last opened or activated.
+pub(crate) fn recent_projects(home: &Path) -> Vec<(PathBuf, &'static str, SystemTime)> {
+    let mut bases = vec![home.join("Library/Application Support/JetBrains")];
+    if cfg!(target_os = "linux") {
+        bases.push(linux::config_home(home).join("JetBrains"));
+    }
+    let mut projects = Vec::new();
+    for base in bases {
+        for (product, versions) in version_dirs(&base) {
+            let ide = PRODUCTS
+                .iter()
+                .find(|(prefix, _)| *prefix == product)
+                .map_or("JetBrains IDE", |(_, name)| *name);
+            for dir in versions {
+                for file in RECENT_PROJECT_FILES {
+                    let text = match fs::read_to_string(dir.path.join(file)) {
+                        Ok(text) => text,
+                        Err(_) => continue,
+                    };
+                    for (path, opened) in parse_recent_projects(&text, home) {
+                        projects.push((path, ide, opened));
+                    }
+                }
+            }
+        }
+    }
+    projects
+}
+
+/// Reads the `<entry key="...">` items of a `recentProjects.xml`, taking the
+/// later of `activationTimestamp` and `projectOpenTimestamp` (milliseconds
+/// since the epoch). `$USER_HOME$` in a key stands for `home`.
+fn parse_recent_projects(text: &str, home: &Path) -> Vec<(PathBuf, SystemTime)> {
+    const ENTRY: &str = "<entry key=\"";
+    let mut projects = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(ENTRY) {
+        rest = &rest[start + ENTRY.len()..];
+        let key = match rest.find('"') {
+            Some(end) => unescape_xml(&rest[..end]),
+            None => break,
+        };
+        let block = &rest[..rest.find("</entry>").unwrap_or(rest.len())];
+        let opened = ["activationTimestamp", "projectOpenTimestamp"]
+            .iter()
+            .filter_map(|name| option_value(block, name)?.parse::<u64>().ok())
+            .max();
+        let Some(millis) = opened else {
+            continue;
+        };
+        let path = match key.strip_prefix("$USER_HOME$") {
+            Some(relative) => home.join(relative.trim_start_matches('/')),
+            None => PathBuf::from(key),
+        };
+        projects.push((path, UNIX_EPOCH + Duration::from_millis(millis)));
+    }
+    projects
+}
+
+/// The value of `<option name="{name}" value="..."/>` inside `block`.
+fn option_value<'a>(block: &'a str, name: &str) -> Option<&'a str> {
+    let marker = format!("name=\"{}\" value=\"", name);
+    let start = block.find(&marker)? + marker.len();
+    let end = block[start..].find('"')?;
+    Some(&block[start..start + end])
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn version_dirs(base: &Path) -> BTreeMap<String, Vec<VersionDir>> {
+    let mut products: BTreeMap<String, Vec<VersionDir>> = BTreeMap::new();
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(_) => return products,
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some((product, version)) = split_product_version(&name) {
+            if PRODUCTS.iter().any(|(prefix, _)| *prefix == product) {
+                products
+                    .entry(product.to_string())
+                    .or_default()
+                    .push(VersionDir {
+                        path: entry.path(),
+                        version,
+                    });
+            }
+        }
+    }
+    products
+}
+
+/// Splits names like `PyCharm2024.1` into the product and its year.release.
+fn split_product_version(name: &str) -> Option<(&str, (u32, u32))> {
+    let start = name.find(|c: char| c.is_ascii_digit())?;
+    let (product, version) = name.split_at(start);
+    let (year, release) = version.split_once('.')?;
+    if product.is_empty() || year.len() != 4 {
+        return None;
+    }
+    Some((product, (year.parse().ok()?, release.parse().ok()?)))
+}
+
+fn format_version(version: (u32, u32)) -> String {
+    format!("{}.{}", version.0, version.1)
+}
+
+/// The versions of `product` with an installed app, or `None` when that
+/// cannot be told and every version counts as installed: outside macOS, or
+/// when an app's version cannot be read.
+fn installed_versions(home: &Path, product: &str) -> Option<Vec<(u32, u32)>> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    let (_, app_name) = PRODUCTS.iter().find(|(prefix, _)| *prefix == product)?;
+    let wanted = app_name.to_ascii_lowercase();
+    let mut bundles = Vec::new();
+    for dir in APP_DIRS.iter().map(PathBuf::from) {
+        find_bundles(&dir, &wanted, 0, &mut bundles);
+    }
+    find_bundles(&home.join("Applications"), &wanted, 0, &mut bundles);
+    // Toolbox keeps each build in apps/<product>/<channel>/<build>/.
+    find_bundles(&home.join(TOOLBOX_APPS), &wanted, 3, &mut bundles);
+    bundles.iter().map(|app| bundle_version(app)).collect()
+}
+
+/// Collects the `.app` bundles named after `wanted` in `dir`, looking
+/// `depth` folders further down.
+fn find_bundles(dir: &Path, wanted: &str, depth: usize, bundles: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_ascii_lowercase();
+        let named = name.starts_with(wanted) || name.replace('-', " ").starts_with(wanted);
+        if named && name.ends_with(".app") {
+            bundles.push(entry.path());
+        } else if depth > 0 {
+            find_bundles(&entry.path(), wanted, depth - 1, bundles);
+        }
+    }
+}
+
+/// The year.release of the IDE in an app bundle, from its `Info.plist`,
+/// its `build.txt`, or the Toolbox build folder it sits in.
+fn bundle_version(app: &Path) -> Option<(u32, u32)> {
+    let plist = fs::read_to_string(app.join("Contents/Info.plist")).unwrap_or_default();
+    if let Some(version) = plist_string(&plist, "CFBundleShortVersionString") {
+        if let Some(version) = parse_release(version) {
+            return Some(version);
+        }
+    }
+    if let Ok(build) = fs::read_to_string(app.join("Contents/Resources/build.txt")) {
+        if let Some(version) = parse_build(&build) {
+            return Some(version);
+        }
+    }
+    let folder = app.parent()?.file_name()?.to_str()?;
+    parse_build(folder)
+}
+
+/// The `<string>` following `<key>{key}</key>` in an XML property list.
+fn plist_string<'a>(plist: &'a str, key: &str) -> Option<&'a str> {
+    let marker = format!("<key>{}</key>", key);
+    let rest = &plist[plist.find(&marker)? + marker.len()..];
+    let start = rest.find("<string>")? + "<string>".len();
+    let end = rest[start..].find("</string>")?;
+    Some(rest[start..start + end].trim())
+}
+
+/// Reads a marketing version such as `2024.1.2`.
+fn parse_release(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let year = parts.next()?;
+    let release = parts.next()?;
+    if year.len() != 4 {
+        return None;
+    }
+    Some((year.parse().ok()?, release.parse().ok()?))
+}
+
+/// Reads a build number such as `PC-241.14494.241`, whose branch `241`
+/// stands for 2024.1.
+fn parse_build(build: &str) -> Option<(u32, u32)> {
+    let build = build.trim();
+    let number = build.split_once('-').map_or(build, |(_, number)| number);
+    let branch = number.split('.').next()?;
+    if branch.len() != 3 {
+        return None;
+    }
+    let branch: u32 = branch.parse().ok()?;
+    Some((2000 + branch / 10, branch % 10))
+}
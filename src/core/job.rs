@@ -0,0 +1,286 @@
+use super::{
+    finalize_candidates, gather_cross_reference_candidates, gather_home_candidates,
+    gather_project_candidates, measure, priority, profile, progress, sizecache, Candidate,
+    DevstripEvent, ScanConfig,
+};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanState {
+    Running,
+    Paused,
+    Cancelling,
+    Finished,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScanProgress {
+    pub state: ScanState,
+    pub directories_scanned: u64,
+    pub candidates_found: usize,
+    pub current_path: Option<String>,
+}
+
+/// Shared state between a `ScanJob` handle and its worker threads. Workers
+/// call `checkpoint` wherever the scan used to poll a cancel flag, which
+/// also parks them while the job is paused.
+pub(crate) struct ScanControl {
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+    paused: Mutex<bool>,
+    resume_signal: Condvar,
+    directories_scanned: AtomicU64,
+    candidates_found: AtomicUsize,
+    current_path: Mutex<Option<String>>,
+    sink: Option<EventSink>,
+    progress: progress::ProgressTracker,
+}
+
+type EventSink = Box<dyn Fn(DevstripEvent) + Send + Sync>;
+
+impl ScanControl {
+    fn new(sink: Option<EventSink>, progress: progress::ProgressTracker) -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            paused: Mutex::new(false),
+            resume_signal: Condvar::new(),
+            directories_scanned: AtomicU64::new(0),
+            candidates_found: AtomicUsize::new(0),
+            current_path: Mutex::new(None),
+            sink,
+            progress,
+        }
+    }
+
+    pub(crate) fn checkpoint(&self) -> bool {
+        let mut paused = lock(&self.paused);
+        while *paused && !self.cancelled.load(Ordering::Relaxed) {
+            paused = self
+                .resume_signal
+                .wait(paused)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn record_event(&self, event: DevstripEvent) {
+        match &event {
+            DevstripEvent::Scanning { path } => {
+                self.directories_scanned.fetch_add(1, Ordering::Relaxed);
+                *lock(&self.current_path) = Some(path.display().to_string());
+            }
+            DevstripEvent::CandidateFound(_) => {
+                self.candidates_found.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        let estimate = self.progress.observe(&event);
+        if let Some(sink) = &self.sink {
+            sink(event);
+            if let Some(estimate) = estimate {
+                sink(DevstripEvent::ScanEstimate(estimate));
+            }
+        }
+    }
+}
+
+/// A scan running on background worker threads. The home-directory detectors
+/// run on one worker while the project roots are shared among the others.
+pub struct ScanJob {
+    control: Arc<ScanControl>,
+    handle: Mutex<Option<JoinHandle<Vec<Candidate>>>>,
+}
+
+impl ScanJob {
+    pub fn start(config: ScanConfig) -> Self {
+        Self::spawn(config, None)
+    }
+
+    /// Like `start`, but forwards every scan event to `on_event` from the
+    /// worker threads as it happens.
+    pub fn start_with_events<F>(config: ScanConfig, on_event: F) -> Self
+    where
+        F: Fn(DevstripEvent) + Send + Sync + 'static,
+    {
+        Self::spawn(config, Some(Box::new(on_event)))
+    }
+
+    fn spawn(config: ScanConfig, sink: Option<EventSink>) -> Self {
+        let progress = progress::ProgressTracker::start(&config);
+        let control = Arc::new(ScanControl::new(sink, progress));
+        let handle = thread::spawn({
+            let control = control.clone();
+            move || run_workers(&config, &control)
+        });
+        Self {
+            control,
+            handle: Mutex::new(Some(handle)),
+        }
+    }
+
+    pub fn pause(&self) {
+        if self.control.finished.load(Ordering::Relaxed) {
+            return;
+        }
+        *lock(&self.control.paused) = true;
+    }
+
+    pub fn resume(&self) {
+        *lock(&self.control.paused) = false;
+        self.control.resume_signal.notify_all();
+    }
+
+    pub fn cancel(&self) -> bool {
+        let already = self.control.cancelled.swap(true, Ordering::Relaxed);
+        self.control.resume_signal.notify_all();
+        !already
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.control.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *lock(&self.control.paused) && !self.is_cancelled()
+    }
+
+    pub fn progress(&self) -> ScanProgress {
+        let state = if self.control.finished.load(Ordering::Relaxed) {
+            ScanState::Finished
+        } else if self.is_cancelled() {
+            ScanState::Cancelling
+        } else if self.is_paused() {
+            ScanState::Paused
+        } else {
+            ScanState::Running
+        };
+        ScanProgress {
+            state,
+            directories_scanned: self.control.directories_scanned.load(Ordering::Relaxed),
+            candidates_found: self.control.candidates_found.load(Ordering::Relaxed),
+            current_path: lock(&self.control.current_path).clone(),
+        }
+    }
+
+    /// Blocks until the workers finish and returns the collected candidates.
+    /// Later calls return an empty list.
+    pub fn wait(&self) -> Vec<Candidate> {
+        let handle = lock(&self.handle).take();
+        match handle {
+            Some(handle) => handle.join().unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Drop for ScanJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn run_workers(config: &ScanConfig, control: &ScanControl) -> Vec<Candidate> {
+    let worker_count = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .saturating_sub(1)
+        .clamp(1, config.roots.len().max(1));
+
+    let mut root_batches: Vec<Vec<PathBuf>> = vec![Vec::new(); worker_count];
+    for (index, root) in config.roots.iter().enumerate() {
+        root_batches[index % worker_count].push(root.clone());
+    }
+
+    control.record_event(DevstripEvent::ScanStarted {
+        roots: config.roots.clone(),
+    });
+    lower_if(config.low_priority);
+    let cache = sizecache::ScanCache::open(config.size_cache);
+    let lazy = measure::LazySizes::start(config.lazy_sizes);
+    let profiling = profile::Profiling::start(config.profile);
+
+    let deferred = measure::current();
+    let sizes = sizecache::current();
+
+    let candidates = thread::scope(|scope| {
+        let home_worker = scope.spawn(|| {
+            lower_if(config.low_priority);
+            measure::defer_for(deferred.clone());
+            sizecache::use_for(sizes.clone());
+            let mut reporter = |event: DevstripEvent| control.record_event(event);
+            gather_home_candidates(config, &mut reporter, Some(control))
+        });
+
+        let root_workers: Vec<_> = root_batches
+            .iter()
+            .filter(|batch| !batch.is_empty())
+            .map(|batch| {
+                let (deferred, sizes) = (deferred.clone(), sizes.clone());
+                scope.spawn(move || {
+                    lower_if(config.low_priority);
+                    measure::defer_for(deferred);
+                    sizecache::use_for(sizes);
+                    let mut reporter = |event: DevstripEvent| control.record_event(event);
+                    gather_project_candidates(batch, config, &mut reporter, Some(control))
+                })
+            })
+            .collect();
+
+        let mut candidates = home_worker.join().unwrap_or_default();
+        let mut gradle_dists = HashSet::new();
+        for worker in root_workers {
+            let (found, dists) = worker.join().unwrap_or_default();
+            candidates.extend(found);
+            gradle_dists.extend(dists);
+        }
+        let mut reporter = |event: DevstripEvent| control.record_event(event);
+        candidates.extend(gather_cross_reference_candidates(
+            config,
+            &gradle_dists,
+            &mut reporter,
+            Some(control),
+        ));
+        candidates
+    });
+
+    let candidates = finalize_candidates(config, candidates);
+    drop(lazy);
+    let cancelled = control.cancelled.load(Ordering::Relaxed);
+    control.progress.finish(cancelled);
+    cache.close(cancelled || config.lazy_sizes);
+    // A cancelled scan may simply not have reached an exclude yet.
+    let unmatched = config.exclude_paths.unmatched();
+    if !cancelled {
+        for path in unmatched {
+            control.record_event(DevstripEvent::UnmatchedExclude { path });
+        }
+    }
+    if let Some(profiling) = profiling {
+        control.record_event(DevstripEvent::ScanProfile(profiling.finish()));
+    }
+    control.finished.store(true, Ordering::Relaxed);
+    control.record_event(DevstripEvent::ScanFinished {
+        candidates: candidates.len(),
+        cancelled,
+    });
+    candidates
+}
+
+/// Scan threads are lowered one by one: the job's own thread and each
+/// worker, so the caller of `ScanJob` keeps its priority.
+fn lower_if(low_priority: bool) {
+    if low_priority {
+        priority::lower_current_thread();
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,244 @@
+use super::tools::{find_in_path, run_tool};
+use super::{
+    collect_whole_directory, is_cancelled, report_found, Candidate, CleanupStrategy, DevstripEvent,
+    Excludes, ScanControl,
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const MINIKUBE_CACHE: &str = ".minikube/cache";
+const KIND_NODE_IMAGE: &str = "kindest/node";
+
+/// Local Kubernetes tooling caches: minikube's download cache, kind node
+/// images no container uses anymore, and k3d image volumes whose cluster
+/// was deleted.
+pub(crate) fn collect_kubernetes_caches<F>(
+    home: &Path,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    // minikube has no command that drops only its download cache (`delete
+    // --purge` also removes clusters), and everything in it is re-downloaded
+    // on demand, so the directory is removed directly.
+    let mut results = collect_whole_directory(
+        &home.join(MINIKUBE_CACHE),
+        "Kubernetes",
+        "minikube image and ISO cache",
+        CleanupStrategy::Remove,
+        excludes,
+        reporter,
+        control,
+    );
+    if find_in_path("docker").is_none() || is_cancelled(control) {
+        return results;
+    }
+    if find_in_path("kind").is_some() {
+        results.extend(unused_kind_images(reporter));
+    }
+    if find_in_path("k3d").is_some() && !is_cancelled(control) {
+        results.extend(orphaned_k3d_volumes(reporter));
+    }
+    results
+}
+
+fn unused_kind_images<F>(reporter: &mut F) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    let listing = match run_tool(
+        "docker",
+        &[
+            "images",
+            KIND_NODE_IMAGE,
+            "--no-trunc",
+            "--format",
+            "{{.ID}}\t{{.Repository}}:{{.Tag}}",
+        ],
+    ) {
+        Some(listing) => listing,
+        None => return results,
+    };
+    let Some(in_use) = images_in_use() else {
+        return results;
+    };
+
+    for line in listing.lines() {
+        let (id, reference) = match line.split_once('\t') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        if in_use.contains(id) {
+            continue;
+        }
+        let size = run_tool("docker", &["image", "inspect", "--format", "{{.Size}}", id])
+            .and_then(|out| out.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        if size == 0 {
+            continue;
+        }
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path: PathBuf::from(format!("docker://image/{}", reference)),
+                size_bytes: size,
+                logical_bytes: size,
+                category: "Kubernetes".to_string(),
+                reason: format!("kind node image {} (no cluster uses it)", reference),
+                last_used: None,
+                strategy: CleanupStrategy::command("docker", &["image", "rm", id]),
+                deep_only: false,
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+    }
+    results
+}
+
+/// The full IDs (`sha256:...`) of the images behind every container,
+/// stopped ones included. Comparing IDs rather than names keeps an image
+/// a container was started from by tag, digest or short ID. `None` when
+/// Docker cannot be asked, so nothing is reported as unused.
+fn images_in_use() -> Option<HashSet<String>> {
+    let containers = run_tool("docker", &["ps", "--all", "--quiet", "--no-trunc"])?;
+    let containers: Vec<&str> = containers
+        .lines()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect();
+    if containers.is_empty() {
+        return Some(HashSet::new());
+    }
+    let mut args = vec!["container", "inspect", "--format", "{{.Image}}"];
+    args.extend(containers);
+    let images = run_tool("docker", &args)?;
+    Some(images.lines().map(|line| line.trim().to_string()).collect())
+}
+
+fn orphaned_k3d_volumes<F>(reporter: &mut F) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    let clusters = match run_tool("k3d", &["cluster", "list", "--output", "json"])
+        .and_then(|out| k3d_cluster_names(&out))
+    {
+        Some(clusters) => clusters,
+        None => return results,
+    };
+    let listing = match run_tool(
+        "docker",
+        &[
+            "volume",
+            "ls",
+            "--filter",
+            "label=app=k3d",
+            "--format",
+            "{{.Name}}\t{{.Label \"k3d.cluster\"}}",
+        ],
+    ) {
+        Some(listing) => listing,
+        None => return results,
+    };
+    let sizes = run_tool("docker", &["system", "df", "--verbose", "--format", "json"])
+        .map(|out| docker_volume_sizes(&out))
+        .unwrap_or_default();
+
+    for line in listing.lines() {
+        let (volume, cluster) = match line.split_once('\t') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        if cluster.is_empty() || clusters.contains(cluster) {
+            continue;
+        }
+        let size = sizes
+            .iter()
+            .find(|(name, _)| name == volume)
+            .map(|(_, size)| *size)
+            .unwrap_or(0);
+        if size == 0 {
+            continue;
+        }
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path: PathBuf::from(format!("docker://volume/{}", volume)),
+                size_bytes: size,
+                logical_bytes: size,
+                category: "Kubernetes".to_string(),
+                reason: format!("k3d image volume for deleted cluster {}", cluster),
+                last_used: None,
+                strategy: CleanupStrategy::command("docker", &["volume", "rm", volume]),
+                deep_only: false,
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+    }
+    results
+}
+
+fn k3d_cluster_names(json: &str) -> Option<HashSet<String>> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    Some(
+        value
+            .as_array()?
+            .iter()
+            .filter_map(|cluster| cluster.get("name").and_then(|v| v.as_str()))
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Volume sizes from `docker system df --verbose`, which only reports them
+/// as human-readable strings such as `1.2GB`.
+fn docker_volume_sizes(json: &str) -> Vec<(String, u64)> {
+    let value: serde_json::Value = match serde_json::from_str(json) {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+    value
+        .get("Volumes")
+        .and_then(|v| v.as_array())
+        .map(|volumes| {
+            volumes
+                .iter()
+                .filter_map(|volume| {
+                    let name = volume.get("Name")?.as_str()?;
+                    let size = parse_docker_size(volume.get("Size")?.as_str()?)?;
+                    Some((name.to_string(), size))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_docker_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((number * scale) as u64)
+}
@@ -0,0 +1,373 @@
+use super::rules::CustomRule;
+use super::{
+    canonicalize, data_dir, entry_key, scan, AgeOverride, Candidate, CleanupStrategy, CoreResult,
+    DevstripError, Excludes, ProfileEntry, ScanConfig, ScanProfile, SkippedPath,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const LAST_SCAN_FILE: &str = "last-scan.json";
+/// The version of the saved scan format. Files from before it was
+/// versioned read as version 0.
+pub const SCAN_FORMAT_VERSION: u32 = 1;
+
+/// The result of the last `devstrip scan`, kept so that `devstrip report`
+/// and `devstrip clean` work from it without scanning again. `--save-scan`
+/// writes the same with the settings, skipped paths and timings too.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedScan {
+    #[serde(default)]
+    pub version: u32,
+    /// RFC 3339 local time the scan finished.
+    pub timestamp: String,
+    pub roots: Vec<PathBuf>,
+    pub candidates: Vec<Candidate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<ScanSettings>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SavedSkip>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<ScanTimings>,
+}
+
+/// The settings that decided what the scan reported.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanSettings {
+    pub min_age_days: u64,
+    pub max_depth: u32,
+    pub keep_latest_derived: usize,
+    pub keep_latest_cache: usize,
+    pub exclude_paths: Vec<PathBuf>,
+    pub exclude_globs: Vec<String>,
+    pub extra_patterns: Vec<String>,
+    pub age_overrides: Vec<AgeOverride>,
+    /// Names of the custom rules that were loaded.
+    pub custom_rules: Vec<String>,
+    pub downloads_min_age_days: Option<u64>,
+    pub active_repo_days: Option<u64>,
+    pub cargo_sweep: bool,
+    pub same_filesystem: bool,
+    pub symlinks: String,
+    /// The `--category` filter; targets in other categories were left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// The `--min-size` filter; smaller targets were left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size_bytes: Option<u64>,
+}
+
+impl ScanSettings {
+    pub fn new(config: &ScanConfig) -> Self {
+        ScanSettings {
+            min_age_days: config.min_age_days,
+            max_depth: config.max_depth,
+            keep_latest_derived: config.keep_latest_derived,
+            keep_latest_cache: config.keep_latest_cache,
+            exclude_paths: config.exclude_paths.to_vec(),
+            exclude_globs: config.exclude_globs.clone(),
+            extra_patterns: config.extra_patterns.clone(),
+            age_overrides: config.age_overrides.clone(),
+            custom_rules: config
+                .custom_rules
+                .iter()
+                .map(|rule| rule.name.clone())
+                .collect(),
+            downloads_min_age_days: config.downloads_min_age_days,
+            active_repo_days: config.active_repo_days,
+            cargo_sweep: config.cargo_sweep,
+            same_filesystem: config.same_filesystem,
+            symlinks: config.symlinks.label().to_string(),
+            categories: Vec::new(),
+            min_size_bytes: None,
+        }
+    }
+}
+
+/// The targets of a scan file that a fresh scan with the file's roots and
+/// settings reports again, for `devstrip clean --from-scan`.
+pub struct Rescanned {
+    /// The targets as found now: classified, sized, stamped and given a
+    /// cleanup strategy by this scan, not by the file.
+    pub candidates: Vec<Candidate>,
+    /// The targets of the file left out, with why.
+    pub dropped: Vec<(PathBuf, String)>,
+}
+
+/// A directory the scan could not read.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedSkip {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl From<&SkippedPath> for SavedSkip {
+    fn from(skipped: &SkippedPath) -> Self {
+        SavedSkip {
+            path: skipped.path.clone(),
+            error: skipped.kind.to_string(),
+        }
+    }
+}
+
+/// How long the scan took, and where, when it ran with `--profile`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanTimings {
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<SavedProfileEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detectors: Vec<SavedProfileEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedProfileEntry {
+    pub name: String,
+    pub wall_time_ms: u64,
+    pub directories: u64,
+    pub bytes_stated: u64,
+}
+
+impl ScanTimings {
+    pub fn new(duration: Duration, profile: Option<&ScanProfile>) -> Self {
+        let entries = |entries: &[ProfileEntry]| {
+            entries
+                .iter()
+                .map(|entry| SavedProfileEntry {
+                    name: entry.name.clone(),
+                    wall_time_ms: entry.wall_time.as_millis() as u64,
+                    directories: entry.directories,
+                    bytes_stated: entry.bytes_stated,
+                })
+                .collect()
+        };
+        ScanTimings {
+            duration_ms: duration.as_millis() as u64,
+            roots: profile.map(|p| entries(&p.roots)).unwrap_or_default(),
+            detectors: profile.map(|p| entries(&p.detectors)).unwrap_or_default(),
+        }
+    }
+}
+
+impl SavedScan {
+    /// A scan of `roots` that finished now, without the details.
+    pub fn new(roots: &[PathBuf], candidates: &[Candidate]) -> Self {
+        SavedScan {
+            version: SCAN_FORMAT_VERSION,
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            roots: roots.to_vec(),
+            candidates: candidates.to_vec(),
+            settings: None,
+            skipped: Vec::new(),
+            timings: None,
+        }
+    }
+
+    /// Scans the file's roots again with its settings and keeps the targets
+    /// both list. A scan file can be edited or come from another machine, so
+    /// nothing in it is cleaned as written: paths outside its roots and
+    /// cleanup commands are refused, and everything else is taken from the
+    /// new scan. `custom_rules` are the user's rules; only those the file
+    /// names are applied.
+    pub fn rescan(&self, custom_rules: Vec<CustomRule>) -> CoreResult<Rescanned> {
+        let settings = self.settings.as_ref().ok_or_else(|| {
+            DevstripError::config(
+                None,
+                "The scan file does not record its scan settings, so its targets cannot be checked again; save a new one with --save-scan.",
+            )
+        })?;
+        let roots: Vec<PathBuf> = self
+            .roots
+            .iter()
+            .filter_map(|root| canonicalize(root).ok())
+            .filter(|root| root.is_dir())
+            .collect();
+        let mut dropped = Vec::new();
+        let mut wanted = HashMap::new();
+        for candidate in &self.candidates {
+            let path = &candidate.path;
+            let refused = if let CleanupStrategy::Command { .. } = candidate.strategy {
+                Some("cleanup commands are not run from a scan file")
+            } else if !path.is_absolute() || !roots.iter().any(|root| path.starts_with(root)) {
+                Some("outside the roots of the scan")
+            } else {
+                None
+            };
+            match refused {
+                Some(reason) => dropped.push((path.clone(), reason.to_string())),
+                None => {
+                    wanted.insert(entry_key(path), path.clone());
+                }
+            }
+        }
+        if wanted.is_empty() {
+            return Ok(Rescanned {
+                candidates: Vec::new(),
+                dropped,
+            });
+        }
+
+        let config = ScanConfig {
+            roots,
+            min_age_days: settings.min_age_days,
+            max_depth: settings.max_depth,
+            keep_latest_derived: settings.keep_latest_derived,
+            keep_latest_cache: settings.keep_latest_cache,
+            exclude_paths: Excludes::new(settings.exclude_paths.clone()),
+            exclude_globs: settings.exclude_globs.clone(),
+            cargo_sweep: settings.cargo_sweep,
+            custom_rules: custom_rules
+                .into_iter()
+                .filter(|rule| settings.custom_rules.contains(&rule.name))
+                .collect(),
+            extra_patterns: settings.extra_patterns.clone(),
+            age_overrides: settings.age_overrides.clone(),
+            downloads_min_age_days: settings.downloads_min_age_days,
+            baseline: None,
+            same_filesystem: settings.same_filesystem,
+            active_repo_days: settings.active_repo_days,
+            symlinks: settings.symlinks.parse().unwrap_or_default(),
+            size_cache: true,
+            lazy_sizes: false,
+            low_priority: false,
+            incremental: false,
+            profile: false,
+            keep_nested: false,
+        };
+        let candidates: Vec<Candidate> = scan(&config)
+            .into_iter()
+            .filter(|candidate| !matches!(candidate.strategy, CleanupStrategy::Command { .. }))
+            .filter(|candidate| wanted.remove(&entry_key(&candidate.path)).is_some())
+            .collect();
+        dropped.extend(
+            wanted
+                .into_values()
+                .map(|path| (path, "no longer a cleanup target".to_string())),
+        );
+        Ok(Rescanned {
+            candidates,
+            dropped,
+        })
+    }
+
+    pub fn scanned_at(&self) -> Option<SystemTime> {
+        chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(SystemTime::from)
+    }
+}
+
+pub fn last_scan_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(LAST_SCAN_FILE))
+}
+
+/// Replaces the saved scan with `candidates`, and the settings that found
+/// them when they are known.
+pub fn save(
+    roots: &[PathBuf],
+    candidates: &[Candidate],
+    settings: Option<&ScanSettings>,
+) -> CoreResult<()> {
+    let path = last_scan_path().ok_or_else(|| DevstripError::Io {
+        path: None,
+        message: "Unable to locate the data directory".to_string(),
+    })?;
+    let scan = SavedScan {
+        settings: settings.cloned(),
+        ..SavedScan::new(roots, candidates)
+    };
+    write(&path, &scan)
+}
+
+/// Writes `scan` to `path`, as `--save-scan` does.
+pub fn write(path: &Path, scan: &SavedScan) -> CoreResult<()> {
+    let text =
+        serde_json::to_vec(scan).map_err(|err| DevstripError::file(path, err.to_string()))?;
+    let temp = path.with_extension("json.tmp");
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&temp, &text)?;
+        fs::rename(&temp, path)
+    };
+    write().map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to save the scan to {}: {}", path.display(), err),
+        )
+    })
+}
+
+/// The saved scan, or `None` when nothing was saved yet.
+pub fn load() -> CoreResult<Option<SavedScan>> {
+    let path = match last_scan_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let text = fs::read(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!("Unable to read the saved scan {}: {}", path.display(), err),
+        )
+    })?;
+    let scan: SavedScan = serde_json::from_slice(&text).map_err(|err| damaged(&path, &err))?;
+    check_version(&path, &scan)?;
+    Ok(Some(scan))
+}
+
+/// Refuses a scan written by a newer devstrip, whose format this one does
+/// not know.
+fn check_version(path: &Path, scan: &SavedScan) -> CoreResult<()> {
+    if scan.version <= SCAN_FORMAT_VERSION {
+        return Ok(());
+    }
+    Err(DevstripError::config(
+        Some(path),
+        format!(
+            "{} was saved in scan format {}, newer than the {} this devstrip reads; update devstrip.",
+            path.display(),
+            scan.version,
+            SCAN_FORMAT_VERSION
+        ),
+    ))
+}
+
+/// A saved scan that was copied elsewhere, for `devstrip clean --from-scan`.
+pub fn load_from(path: &Path) -> CoreResult<SavedScan> {
+    let text = fs::read(path).map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    let scan = serde_json::from_slice(&text).map_err(|err| {
+        DevstripError::config(
+            Some(path),
+            format!(
+                "{} is not a saved scan (a copy of {} or a --save-scan file): {}",
+                path.display(),
+                LAST_SCAN_FILE,
+                err
+            ),
+        )
+    })?;
+    check_version(path, &scan)?;
+    Ok(scan)
+}
+
+fn damaged(path: &Path, err: &serde_json::Error) -> DevstripError {
+    DevstripError::file(
+        path,
+        format!(
+            "The saved scan {} is damaged ({}); run `devstrip scan` again.",
+            path.display(),
+            err
+        ),
+    )
+}
@@ -0,0 +1,137 @@
+use super::{login_home_dir, read_dir_reporting, CleanupStrategy, DevstripEvent};
+use std::path::{Path, PathBuf};
+
+/// Caches Electron apps keep next to their settings under
+/// `$XDG_CONFIG_HOME`: (relative path, category, label, how it is cleaned).
+const CONFIG_HOME_CACHES: &[(&str, &str, &str, CleanupStrategy)] = &[
+    (
+        "Code/Cache",
+        "VSCode",
+        "VSCode cache",
+        CleanupStrategy::EmptyDirectory,
+    ),
+    (
+        "Code/CachedData",
+        "VSCode",
+        "VSCode cached data",
+        CleanupStrategy::EmptyDirectory,
+    ),
+    (
+        "Code/CachedExtensionVSIXs",
+        "VSCode",
+        "VSCode extension downloads",
+        CleanupStrategy::Remove,
+    ),
+    (
+        "Slack/Service Worker/CacheStorage",
+        "Slack",
+        "Slack cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        "discord/Cache",
+        "Discord",
+        "Discord cache",
+        CleanupStrategy::Remove,
+    ),
+];
+
+pub(crate) fn cache_home(home: &Path) -> PathBuf {
+    base_dir("XDG_CACHE_HOME", home, ".cache")
+}
+
+pub(crate) fn data_home(home: &Path) -> PathBuf {
+    base_dir("XDG_DATA_HOME", home, ".local/share")
+}
+
+pub(crate) fn config_home(home: &Path) -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", home, ".config")
+}
+
+/// `$XDG_RUNTIME_DIR`, the per-user directory for sockets and locks that
+/// only live as long as the login session.
+pub(crate) fn runtime_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+/// An XDG base directory for `home`. Relative values are ignored, as the
+/// spec requires, and so is a value outside `home` once `--home` points
+/// devstrip at another tree: it would belong to the caller, not to it.
+fn base_dir(var: &str, home: &Path, default: &str) -> PathBuf {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .filter(|path| path.starts_with(home) || login_home_dir().as_deref() == Some(home))
+        .unwrap_or_else(|| home.join(default))
+}
+
+/// A home-relative path such as `.cache/pip`, with the `.cache`,
+/// `.config` and `.local/share` prefixes resolved against the XDG base
+/// directories on Linux.
+pub(crate) fn resolve(home: &Path, relative: &str) -> PathBuf {
+    if !cfg!(target_os = "linux") {
+        return home.join(relative);
+    }
+    if let Some(rest) = relative.strip_prefix(".cache/") {
+        cache_home(home).join(rest)
+    } else if let Some(rest) = relative.strip_prefix(".config/") {
+        config_home(home).join(rest)
+    } else if let Some(rest) = relative.strip_prefix(".local/share/") {
+        data_home(home).join(rest)
+    } else {
+        home.join(relative)
+    }
+}
+
+/// The Linux-only cache targets for `home`.
+pub(crate) fn cache_targets(
+    home: &Path,
+) -> Vec<(PathBuf, &'static str, &'static str, CleanupStrategy)> {
+    let config = config_home(home);
+    CONFIG_HOME_CACHES
+        .iter()
+        .map(|(relative, category, reason, strategy)| {
+            (config.join(relative), *category, *reason, strategy.clone())
+        })
+        .collect()
+}
+
+/// The private cache of every sandboxed app: `~/snap/<name>/common/.cache`
+/// for Snap and `~/.var/app/<id>/cache` for Flatpak.
+pub(crate) fn sandboxed_app_caches<F>(
+    home: &Path,
+    reporter: &mut F,
+) -> Vec<(PathBuf, &'static str, String)>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut caches = Vec::new();
+    let layouts = [
+        (home.join("snap"), "common/.cache", "Snap", "snap cache"),
+        (
+            home.join(".var/app"),
+            "cache",
+            "Flatpak",
+            "Flatpak app cache",
+        ),
+    ];
+    for (base, relative, category, label) in layouts {
+        let entries = match read_dir_reporting(&base, reporter) {
+            Some(entries) => entries,
+            None => continue,
+        };
+        for entry in entries.flatten() {
+            if !entry.file_type().is_ok_and(|ft| ft.is_dir()) {
+                continue;
+            }
+            let cache = entry.path().join(relative);
+            if cache.is_dir() {
+                let app = entry.file_name().to_string_lossy().into_owned();
+                caches.push((cache, category, format!("{} ({})", label, app)));
+            }
+        }
+    }
+    caches
+}
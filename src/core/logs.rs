@@ -0,0 +1,122 @@
+use super::{
+    age_cutoff, is_cancelled, is_excluded, report_found, safe_metadata, Candidate, CleanupStrategy,
+    CoreResult, DevstripEvent, DiskUsage, ScanConfig, ScanControl,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Home-relative log folders written by developer tools. Gradle daemon logs
+/// are not listed because `.gradle/daemon` is already a whole-directory target.
+const LOG_DIRS: &[(&str, &str)] = &[
+    ("Library/Logs/CoreSimulator", "Xcode simulator logs"),
+    ("Library/Logs/JetBrains", "JetBrains IDE logs"),
+    ("Library/Logs/Google", "Android Studio logs"),
+    ("Library/Logs/gradle", "Gradle logs"),
+    (".npm/_logs", "npm debug logs"),
+];
+
+pub(crate) fn collect_dev_logs<F>(
+    home: &Path,
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let cutoff = age_cutoff(config.category_min_age_days("Logs")).unwrap_or_else(SystemTime::now);
+    let excludes = &config.exclude_paths;
+    let mut results = Vec::new();
+    for (relative, label) in LOG_DIRS {
+        let dir = home.join(relative);
+        if is_excluded(&dir, excludes) || !dir.is_dir() {
+            continue;
+        }
+        reporter(DevstripEvent::Scanning { path: dir.clone() });
+        if is_cancelled(control) {
+            return results;
+        }
+        let stale = stale_files(&dir, cutoff);
+        let usage = stale
+            .iter()
+            .filter_map(|path| safe_metadata(path))
+            .fold(DiskUsage::default(), |acc, meta| {
+                acc.add(DiskUsage::of_file(&meta))
+            });
+        if usage.is_empty() {
+            continue;
+        }
+        let mtimes: Vec<SystemTime> = stale
+            .iter()
+            .filter_map(|path| safe_metadata(path).and_then(|meta| meta.modified().ok()))
+            .collect();
+        let last_used = mtimes.iter().max().copied();
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path: dir,
+                size_bytes: usage.allocated,
+                logical_bytes: usage.logical,
+                category: "Logs".to_string(),
+                reason: format!("Old {} ({} files)", label, stale.len()),
+                last_used,
+                strategy: CleanupStrategy::PruneFilesOlderThan { cutoff },
+                deep_only: config.too_recent_for_baseline(last_used),
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+    }
+    results
+}
+
+/// Removes each stale file with `remove`, carrying on past failures and
+/// returning the first.
+pub(crate) fn prune_stale_files<F>(dir: &Path, cutoff: SystemTime, mut remove: F) -> CoreResult<()>
+where
+    F: FnMut(&Path) -> CoreResult<()>,
+{
+    let mut first_error = None;
+    for path in stale_files(dir, cutoff) {
+        if let Err(err) = remove(&path) {
+            first_error.get_or_insert(err);
+        }
+    }
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn stale_files(dir: &Path, cutoff: SystemTime) -> Vec<PathBuf> {
+    let mut stale = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let meta = match safe_metadata(&path) {
+                Some(meta) => meta,
+                None => continue,
+            };
+            if meta.file_type().is_symlink() {
+                continue;
+            }
+            if meta.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if matches!(meta.modified(), Ok(mtime) if mtime < cutoff) {
+                stale.push(path);
+            }
+        }
+    }
+    stale
+}
@@ -0,0 +1,330 @@
+use super::job::ScanControl;
+use super::{
+    bulkstat, is_cancelled, priority, profile, sizecache, Candidate, CleanupStrategy, DiskUsage,
+};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+/// Directories read on the calling thread before helpers are brought in,
+/// so the many small candidates never pay for spawning threads.
+const SERIAL_DIRS: usize = 256;
+/// Size threads running at once across all measurements. Sizing is bound
+/// by the disk rather than the CPU: more requests in flight keep a
+/// spinning disk's queue full and hide the latency of network volumes.
+const MAX_SIZE_THREADS: usize = 16;
+static SIZE_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// The trees a lazy scan only probed for a file, to be measured once the
+/// list is shown. Each lazy scan has its own, set on every thread working
+/// for it, so a measurement running beside a quick scan is never deferred.
+#[derive(Default)]
+pub(crate) struct Deferred {
+    dirs: Mutex<Vec<PathBuf>>,
+}
+
+thread_local! {
+    /// The deferred trees of the lazy scan the calling thread works for.
+    static DEFERRING: RefCell<Option<Arc<Deferred>>> = const { RefCell::new(None) };
+}
+
+/// Defers measuring on the calling thread for the length of one scan.
+pub(crate) struct LazySizes {
+    outer: Option<Arc<Deferred>>,
+}
+
+impl LazySizes {
+    pub(crate) fn start(enabled: bool) -> Option<LazySizes> {
+        if !enabled {
+            return None;
+        }
+        let deferred = Arc::new(Deferred::default());
+        let outer = DEFERRING.with_borrow_mut(|current| current.replace(deferred));
+        Some(LazySizes { outer })
+    }
+}
+
+impl Drop for LazySizes {
+    fn drop(&mut self) {
+        DEFERRING.set(self.outer.take());
+    }
+}
+
+/// The deferred trees of the calling thread's scan, for its worker threads
+/// to record into.
+pub(crate) fn current() -> Option<Arc<Deferred>> {
+    DEFERRING.with_borrow(|current| current.clone())
+}
+
+/// Makes the calling worker thread defer measuring into `deferred`.
+pub(crate) fn defer_for(deferred: Option<Arc<Deferred>>) {
+    DEFERRING.set(deferred);
+}
+
+/// What a lazy scan reports for the tree at `dir` instead of its size: a
+/// single byte when the tree holds any data at all, so the scanners still
+/// drop empty trees.
+pub(crate) fn deferred_usage(dir: &Path) -> Option<DiskUsage> {
+    let deferred = current()?;
+    if !holds_data(dir) {
+        return Some(DiskUsage::default());
+    }
+    lock_dirs(&deferred).push(dir.to_path_buf());
+    Some(DiskUsage {
+        allocated: 1,
+        logical: 1,
+    })
+}
+
+/// Marks `candidate` as pending when its size includes a deferred tree.
+pub(crate) fn mark_pending(candidate: &mut Candidate) {
+    if candidate.size_pending || matches!(candidate.strategy, CleanupStrategy::Command { .. }) {
+        return;
+    }
+    let Some(deferred) = current() else {
+        return;
+    };
+    if lock_dirs(&deferred)
+        .iter()
+        .any(|dir| dir.starts_with(&candidate.path))
+    {
+        candidate.size_pending = true;
+        candidate.size_bytes = 0;
+        candidate.logical_bytes = 0;
+    }
+}
+
+/// Whether any file below `dir` takes up space.
+fn holds_data(dir: &Path) -> bool {
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else if meta.is_file() && !DiskUsage::of_file(&meta).is_empty() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn lock_dirs(deferred: &Deferred) -> MutexGuard<'_, Vec<PathBuf>> {
+    deferred
+        .dirs
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Allocated and logical size of everything below `dir`. Symlinks are not
+/// followed. Large trees are shared among helper threads.
+pub(crate) fn tree_usage(dir: &Path, control: Option<&ScanControl>) -> DiskUsage {
+    let mut total = DiskUsage::default();
+    let mut stack = vec![dir.to_path_buf()];
+    let mut read = 0;
+    let mut walk = sizecache::Walk::start();
+    while let Some(current) = stack.pop() {
+        if !read_into(&current, &mut stack, &mut total, &mut walk, control) {
+            return total;
+        }
+        read += 1;
+        if read == SERIAL_DIRS && stack.len() > 1 {
+            return total.add(shared_usage(stack, control));
+        }
+    }
+    total
+}
+
+/// Reads one directory, adding its files to `total` and queueing its
+/// subdirectories; a directory this scan listed already, or one unchanged
+/// since an earlier scan, is taken from the scan cache instead, and one
+/// that cannot be read in full is noted there. Returns false once the scan
+/// is cancelled.
+fn read_into(
+    dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    total: &mut DiskUsage,
+    walk: &mut sizecache::Walk,
+    control: Option<&ScanControl>,
+) -> bool {
+    if let Some((files, subdirs)) = walk.listed(dir) {
+        *total = total.add(files);
+        stack.extend(subdirs);
+        return true;
+    }
+    let stamp = walk.stamp(dir);
+    if let Some((files, subdirs)) = stamp.as_ref().and_then(|s| walk.lookup(dir, s)) {
+        *total = total.add(files);
+        stack.extend(subdirs);
+        return true;
+    }
+    let Some(listing) = bulkstat::list(dir, control) else {
+        // A directory removed since it was queued holds nothing to miss.
+        if fs::symlink_metadata(dir).is_ok() {
+            walk.unreadable(dir);
+        }
+        return true;
+    };
+    profile::count_directory(listing.files.logical);
+    *total = total.add(listing.files);
+    if !listing.complete && is_cancelled(control) {
+        return false;
+    }
+    if listing.complete {
+        walk.record(dir, stamp, listing.files, &listing.subdirs);
+    } else {
+        walk.unreadable(dir);
+    }
+    stack.extend(listing.subdirs);
+    true
+}
+
+/// Directories waiting to be read, and how many threads are reading one.
+struct Pending {
+    dirs: Vec<PathBuf>,
+    busy: usize,
+}
+
+struct SharedWalk<'a> {
+    pending: Mutex<Pending>,
+    more: Condvar,
+    /// Threads waiting for work, readable without taking the lock.
+    idle: AtomicUsize,
+    control: Option<&'a ScanControl>,
+}
+
+/// Measures the trees in `dirs` with as many threads as the global budget
+/// allows; the calling thread always takes part.
+fn shared_usage(dirs: Vec<PathBuf>, control: Option<&ScanControl>) -> DiskUsage {
+    let helpers = reserve_threads(
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .max(4)
+            - 1,
+    );
+    let walk = SharedWalk {
+        pending: Mutex::new(Pending { dirs, busy: 0 }),
+        more: Condvar::new(),
+        idle: AtomicUsize::new(0),
+        control,
+    };
+    // Helpers share the priority, the profile section and the size cache
+    // of the thread that asked for the size.
+    let lowered = priority::is_current_thread_lowered();
+    let counters = profile::current();
+    let sizes = sizecache::current();
+    let total = thread::scope(|scope| {
+        let workers: Vec<_> = (0..helpers)
+            .map(|_| {
+                let (walk, counters, sizes) = (&walk, counters.clone(), sizes.clone());
+                scope.spawn(move || {
+                    if lowered {
+                        priority::lower_current_thread();
+                    }
+                    profile::work_for(counters);
+                    sizecache::use_for(sizes);
+                    walk.run()
+                })
+            })
+            .collect();
+        let own = walk.run();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_default())
+            .fold(own, DiskUsage::add)
+    });
+    SIZE_THREADS.fetch_sub(helpers, Ordering::Relaxed);
+    total
+}
+
+/// Claims up to `wanted` helper threads from the global budget.
+fn reserve_threads(wanted: usize) -> usize {
+    let mut granted = 0;
+    let _ = SIZE_THREADS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |in_use| {
+        granted = wanted.min(MAX_SIZE_THREADS.saturating_sub(in_use));
+        Some(in_use + granted)
+    });
+    granted
+}
+
+impl SharedWalk<'_> {
+    fn run(&self) -> DiskUsage {
+        let mut total = DiskUsage::default();
+        let mut walk = sizecache::Walk::start();
+        while let Some(dir) = self.next_dir() {
+            let mut stack = vec![dir];
+            let mut cancelled = false;
+            while let Some(current) = stack.pop() {
+                if !read_into(&current, &mut stack, &mut total, &mut walk, self.control) {
+                    cancelled = true;
+                    break;
+                }
+                if stack.len() > 1 {
+                    self.share(&mut stack);
+                }
+            }
+            self.finish(cancelled);
+        }
+        total
+    }
+
+    /// The next directory to walk, or `None` once every directory has been
+    /// read (or the scan was cancelled).
+    fn next_dir(&self) -> Option<PathBuf> {
+        let mut pending = self.lock();
+        loop {
+            if let Some(dir) = pending.dirs.pop() {
+                pending.busy += 1;
+                return Some(dir);
+            }
+            if pending.busy == 0 {
+                return None;
+            }
+            self.idle.fetch_add(1, Ordering::Relaxed);
+            pending = self
+                .more
+                .wait(pending)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            self.idle.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Hands all but one of the queued directories to idle threads.
+    fn share(&self, stack: &mut Vec<PathBuf>) {
+        if self.idle.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut pending = self.lock();
+        if pending.dirs.is_empty() {
+            pending.dirs.extend(stack.drain(1..));
+            self.more.notify_all();
+        }
+    }
+
+    fn finish(&self, cancelled: bool) {
+        let mut pending = self.lock();
+        pending.busy -= 1;
+        if cancelled {
+            pending.dirs.clear();
+        }
+        if pending.busy == 0 || !pending.dirs.is_empty() {
+            self.more.notify_all();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Pending> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Candidate, CleanupResult, CoreResult, DevstripError};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Writes node_exporter textfile-collector metrics for one run. The file is
+/// written next to `path` and renamed into place so the collector never
+/// reads a partial file.
+pub fn write_textfile<C: Borrow<Candidate>>(
+    path: &Path,
+    candidates: &[C],
+    results: &[CleanupResult],
+    scan_duration: Duration,
+) -> CoreResult<()> {
+    let text = render_textfile(candidates, results, scan_duration);
+    let file_name = path.file_name().ok_or_else(|| {
+        DevstripError::config(
+            Some(path),
+            format!("Invalid metrics file path: {}", path.display()),
+        )
+    })?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    fs::write(&temp, text)
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|err| {
+            DevstripError::io(
+                path,
+                &err,
+                format!("Unable to write metrics to {}: {}", path.display(), err),
+            )
+        })
+}
+
+pub fn render_textfile<C: Borrow<Candidate>>(
+    candidates: &[C],
+    results: &[CleanupResult],
+    scan_duration: Duration,
+) -> String {
+    let mut by_category: BTreeMap<&str, (u64, usize)> = BTreeMap::new();
+    for candidate in candidates {
+        let candidate = candidate.borrow();
+        let entry = by_category.entry(candidate.category.as_str()).or_default();
+        entry.0 = entry.0.saturating_add(candidate.size_bytes);
+        entry.1 += 1;
+    }
+    let cleaned: u64 = results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| r.candidate.size_bytes)
+        .fold(0u64, |acc, size| acc.saturating_add(size));
+    let failures = results.iter().filter(|r| !r.success).count();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    header(
+        &mut out,
+        "devstrip_reclaimable_bytes",
+        "Bytes reclaimable per category in the last scan.",
+    );
+    for (category, (bytes, _)) in &by_category {
+        let _ = writeln!(
+            out,
+            "devstrip_reclaimable_bytes{{category=\"{}\"}} {}",
+            escape_label(category),
+            bytes
+        );
+    }
+    header(
+        &mut out,
+        "devstrip_candidates",
+        "Cleanup candidates per category in the last scan.",
+    );
+    for (category, (_, count)) in &by_category {
+        let _ = writeln!(
+            out,
+            "devstrip_candidates{{category=\"{}\"}} {}",
+            escape_label(category),
+            count
+        );
+    }
+    header(
+        &mut out,
+        "devstrip_cleaned_bytes",
+        "Bytes removed by the last cleanup.",
+    );
+    let _ = writeln!(out, "devstrip_cleaned_bytes {}", cleaned);
+    header(
+        &mut out,
+        "devstrip_cleanup_failures",
+        "Candidates that failed to clean in the last cleanup.",
+    );
+    let _ = writeln!(out, "devstrip_cleanup_failures {}", failures);
+    header(
+        &mut out,
+        "devstrip_scan_duration_seconds",
+        "Wall-clock duration of the last scan.",
+    );
+    let _ = writeln!(
+        out,
+        "devstrip_scan_duration_seconds {:.3}",
+        scan_duration.as_secs_f64()
+    );
+    header(
+        &mut out,
+        "devstrip_last_run_timestamp_seconds",
+        "Unix time when these metrics were written.",
+    );
+    let _ = writeln!(out, "devstrip_last_run_timestamp_seconds {}", timestamp);
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
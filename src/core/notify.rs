@@ -0,0 +1,33 @@
+use super::tools::find_in_path;
+use std::process::{Command, Stdio};
+
+/// Posts a desktop notification: through Notification Center on macOS and
+/// `notify-send` elsewhere on unix. `false` when it could not be posted.
+pub fn send(title: &str, body: &str) -> bool {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        ));
+        command
+    } else if cfg!(unix) && find_in_path("notify-send").is_some() {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=devstrip", title, body]);
+        command
+    } else {
+        return false;
+    };
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,77 @@
+use super::rules::{self, CustomRule};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+pub(crate) const OVERRIDES_FILE: &str = ".devstrip.toml";
+
+/// The settings of one `.devstrip.toml`, for the project holding it and
+/// everything below it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProjectOverrides {
+    /// Replaces `--min-age-days` in this project.
+    #[serde(default)]
+    min_age_days: Option<u64>,
+    /// Categories never reported in this project, in any case.
+    #[serde(default)]
+    disable_categories: Vec<String>,
+    /// Extra directories to clean in this project, written like the
+    /// `[[rule]]` tables of `rules.toml`.
+    #[serde(default, rename = "rule")]
+    rules: Vec<CustomRule>,
+}
+
+impl ProjectOverrides {
+    /// Reads `dir/.devstrip.toml`, if there is one. A file that does not
+    /// parse is returned as a warning for the scan and otherwise ignored.
+    pub(crate) fn load(dir: &Path) -> Option<Result<ProjectOverrides, String>> {
+        let path = dir.join(OVERRIDES_FILE);
+        let text = fs::read_to_string(&path).ok()?;
+        let parsed = toml::from_str::<ProjectOverrides>(&text)
+            .map_err(|err| err.to_string())
+            .and_then(|overrides| {
+                for rule in &overrides.rules {
+                    rules::validate_rule(rule)?;
+                }
+                Ok(overrides)
+            });
+        Some(
+            parsed.map_err(|err| {
+                format!("{}: ignoring the file: {}", path.display(), err.trim_end())
+            }),
+        )
+    }
+}
+
+/// The `.devstrip.toml` files of the projects a directory sits in,
+/// outermost first. Inner files win for the minimum age and their rules
+/// are tried first; disabled categories add up.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Overrides(Vec<Rc<ProjectOverrides>>);
+
+impl Overrides {
+    pub(crate) fn push(&mut self, overrides: ProjectOverrides) {
+        self.0.push(Rc::new(overrides));
+    }
+
+    pub(crate) fn min_age_days(&self) -> Option<u64> {
+        self.0.iter().rev().find_map(|file| file.min_age_days)
+    }
+
+    pub(crate) fn disables(&self, category: &str) -> bool {
+        self.0.iter().any(|file| {
+            file.disable_categories
+                .iter()
+                .any(|disabled| disabled.eq_ignore_ascii_case(category))
+        })
+    }
+
+    pub(crate) fn match_rule(&self, name: &str, parent: &Path) -> Option<&CustomRule> {
+        self.0
+            .iter()
+            .rev()
+            .find_map(|file| rules::match_custom_rule(&file.rules, name, parent))
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use unicode_normalization::UnicodeNormalization;
+
+/// Whether each device seen so far compares names case-insensitively.
+static CASE_INSENSITIVE_DEVICES: OnceLock<Mutex<HashMap<u64, bool>>> = OnceLock::new();
+
+/// The form of an already canonical `path` that two spellings of the same
+/// file share: Unicode NFC, since macOS hands out decomposed names, and
+/// lowercase when `fold_case` is set.
+pub(crate) fn key(path: &Path, fold_case: bool) -> PathBuf {
+    let text = match path.to_str() {
+        Some(text) => text,
+        None => return path.to_path_buf(),
+    };
+    let normalized: String = text.nfc().collect();
+    if fold_case {
+        PathBuf::from(normalized.to_lowercase())
+    } else {
+        PathBuf::from(normalized)
+    }
+}
+
+/// Whether the filesystem holding `path` treats `Projects` and `projects`
+/// as the same name. Windows volumes are assumed to; elsewhere it is probed
+/// once per device by looking the path up with the case of one of its
+/// components flipped. Linux folders with the casefold attribute set are
+/// not told apart from the rest of their volume.
+pub(crate) fn case_insensitive(path: &Path) -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    let id = match file_id(path) {
+        Some(id) => id,
+        None => return false,
+    };
+    let devices = CASE_INSENSITIVE_DEVICES.get_or_init(Default::default);
+    if let Some(known) = devices.lock().ok().and_then(|map| map.get(&id.0).copied()) {
+        return known;
+    }
+    let insensitive = path
+        .ancestors()
+        .find_map(|ancestor| {
+            let name = ancestor.file_name()?.to_str()?;
+            // The name is looked up in the parent directory, which has to
+            // be on the same volume for the answer to apply to `path`.
+            if file_id(ancestor.parent()?).map(|(device, _)| device) != Some(id.0) {
+                return Some(false);
+            }
+            let flipped: String = name.chars().map(flip_case).collect();
+            (flipped != name).then(|| {
+                let twin = file_id(&ancestor.with_file_name(flipped));
+                twin.is_some() && twin == file_id(ancestor)
+            })
+        })
+        .unwrap_or(false);
+    if let Ok(mut map) = devices.lock() {
+        map.insert(id.0, insensitive);
+    }
+    insensitive
+}
+
+fn flip_case(c: char) -> char {
+    if c.is_lowercase() {
+        c.to_uppercase().next().unwrap_or(c)
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Device and inode of `path`, without following a final symlink.
+#[cfg(unix)]
+pub(crate) fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path)
+        .ok()
+        .map(|meta| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,112 @@
+use super::{CoreResult, DevstripError};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// System-wide policy locations, checked in order. Only administrators can
+/// write there, and nothing a user passes can point devstrip elsewhere.
+#[cfg(not(windows))]
+const POLICY_PATHS: &[&str] = &[
+    "/Library/Application Support/devstrip/policy.toml",
+    "/etc/devstrip/policy.toml",
+];
+#[cfg(windows)]
+const POLICY_PATHS: &[&str] = &[r"C:\ProgramData\devstrip\policy.toml"];
+
+static ACTIVE_POLICY: OnceLock<CoreResult<Option<Policy>>> = OnceLock::new();
+
+/// Constraints an administrator enforces on every run.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Categories that are never reported or cleaned.
+    #[serde(default)]
+    pub forbidden_categories: Vec<String>,
+    /// Removed targets always go to the Trash.
+    #[serde(default)]
+    pub require_trash: bool,
+    /// Upper bound on the bytes one cleanup run may remove.
+    #[serde(default)]
+    pub max_deletion_bytes: Option<u64>,
+    /// Refuse `--all`, `--preview-all` and the GUI deep scan.
+    #[serde(default)]
+    pub disable_deep_scan: bool,
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+impl Policy {
+    pub fn forbids(&self, category: &str) -> bool {
+        self.forbidden_categories
+            .iter()
+            .any(|forbidden| forbidden.eq_ignore_ascii_case(category))
+    }
+
+    /// One-line description for the frontends' "policy active" notice.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.forbidden_categories.is_empty() {
+            parts.push(format!(
+                "blocked categories: {}",
+                self.forbidden_categories.join(", ")
+            ));
+        }
+        if self.require_trash {
+            parts.push("removals go to the Trash".to_string());
+        }
+        if let Some(max) = self.max_deletion_bytes {
+            parts.push(format!(
+                "at most {} per cleanup",
+                human_bytes::human_bytes(max as f64)
+            ));
+        }
+        if self.disable_deep_scan {
+            parts.push("deep scan disabled".to_string());
+        }
+        if parts.is_empty() {
+            parts.push("no restrictions".to_string());
+        }
+        format!(
+            "Managed policy active ({}): {}",
+            self.source.display(),
+            parts.join("; ")
+        )
+    }
+}
+
+pub fn load_policy(path: &Path) -> CoreResult<Policy> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to read policy {}: {}", path.display(), err),
+        )
+    })?;
+    let mut policy: Policy = toml::from_str(&text).map_err(|err| {
+        DevstripError::config(
+            Some(path),
+            format!("Invalid policy {}: {}", path.display(), err),
+        )
+    })?;
+    policy.source = path.to_path_buf();
+    Ok(policy)
+}
+
+/// The installed policy, read once per process. A policy file that exists
+/// but cannot be read is an error rather than "no policy", so a broken
+/// install never silently lifts the constraints.
+pub fn active_policy() -> CoreResult<Option<&'static Policy>> {
+    ACTIVE_POLICY
+        .get_or_init(|| {
+            POLICY_PATHS
+                .iter()
+                .map(Path::new)
+                .find(|path| path.exists())
+                .map(load_policy)
+                .transpose()
+        })
+        .as_ref()
+        .map(Option::as_ref)
+        .map_err(Clone::clone)
+}
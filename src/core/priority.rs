@@ -0,0 +1,64 @@
+use std::cell::Cell;
+
+thread_local! {
+    static LOWERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Puts the calling thread at background priority for both CPU and disk,
+/// so a scheduled scan or a low-impact GUI scan does not slow down
+/// interactive work or drain the battery: nice 19 and the idle I/O class
+/// on Linux, the background QoS class on macOS (which also throttles its
+/// disk access), background mode on Windows. Threads the scan starts from
+/// a lowered thread are lowered too. Returns whether it took effect.
+pub fn lower_current_thread() -> bool {
+    if LOWERED.get() {
+        return true;
+    }
+    let lowered = lower_os_thread();
+    LOWERED.set(lowered);
+    lowered
+}
+
+pub(crate) fn is_current_thread_lowered() -> bool {
+    LOWERED.get()
+}
+
+#[cfg(target_os = "linux")]
+fn lower_os_thread() -> bool {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+    // On Linux both apply to the calling thread only when `who` is 0.
+    // SAFETY: plain syscalls on the calling thread with constant arguments.
+    let niced = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } == 0;
+    let idle_io = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    } == 0;
+    niced || idle_io
+}
+
+#[cfg(target_os = "macos")]
+fn lower_os_thread() -> bool {
+    // SAFETY: changes the QoS class of the calling thread only.
+    unsafe { libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_BACKGROUND, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn lower_os_thread() -> bool {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+    };
+    // SAFETY: the pseudo handle always refers to the calling thread.
+    unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) != 0 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn lower_os_thread() -> bool {
+    // SAFETY: lowers the priority of the calling process.
+    unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) == 0 }
+}
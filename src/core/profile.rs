@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Where one root or detector of a scan spent its time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub wall_time: Duration,
+    /// Directories listed, both to find candidates and to size them.
+    pub directories: u64,
+    /// Total size of the files whose metadata was read.
+    pub bytes_stated: u64,
+}
+
+/// Where a scan spent its time, slowest first. Roots are walked in
+/// parallel, so their times can add up to more than the scan took.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanProfile {
+    pub roots: Vec<ProfileEntry>,
+    pub detectors: Vec<ProfileEntry>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COLLECTED: Mutex<ScanProfile> = Mutex::new(ScanProfile {
+    roots: Vec::new(),
+    detectors: Vec::new(),
+});
+
+thread_local! {
+    /// Counters of the section the calling thread works for.
+    static CURRENT: RefCell<Option<Arc<Counters>>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+pub(crate) struct Counters {
+    directories: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Collects a profile for the length of one scan.
+pub(crate) struct Profiling {
+    _private: (),
+}
+
+impl Profiling {
+    pub(crate) fn start(enabled: bool) -> Option<Profiling> {
+        if !enabled {
+            return None;
+        }
+        *lock() = ScanProfile::default();
+        ENABLED.store(true, Ordering::Relaxed);
+        Some(Profiling { _private: () })
+    }
+
+    pub(crate) fn finish(self) -> ScanProfile {
+        let mut profile = std::mem::take(&mut *lock());
+        for entries in [&mut profile.roots, &mut profile.detectors] {
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.wall_time));
+        }
+        profile
+    }
+}
+
+impl Drop for Profiling {
+    fn drop(&mut self) {
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Root,
+    Detector,
+}
+
+/// Counts the time and work of the calling thread towards one root or
+/// detector until it is dropped.
+pub(crate) struct Section {
+    kind: Kind,
+    name: String,
+    started: Instant,
+    counters: Arc<Counters>,
+    outer: Option<Arc<Counters>>,
+}
+
+pub(crate) fn root(root: &Path) -> Option<Section> {
+    section(Kind::Root, root.display().to_string())
+}
+
+pub(crate) fn detector(name: &str) -> Option<Section> {
+    section(Kind::Detector, name.to_string())
+}
+
+fn section(kind: Kind, name: String) -> Option<Section> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let counters = Arc::new(Counters::default());
+    let outer = CURRENT.with_borrow_mut(|current| current.replace(counters.clone()));
+    Some(Section {
+        kind,
+        name,
+        started: Instant::now(),
+        counters,
+        outer,
+    })
+}
+
+impl Drop for Section {
+    fn drop(&mut self) {
+        CURRENT.set(self.outer.take());
+        let mut profile = lock();
+        let entries = match self.kind {
+            Kind::Root => &mut profile.roots,
+            Kind::Detector => &mut profile.detectors,
+        };
+        // A detector that runs once per cache folder adds up to one entry.
+        let index = match entries.iter().position(|entry| entry.name == self.name) {
+            Some(index) => index,
+            None => {
+                entries.push(ProfileEntry {
+                    name: self.name.clone(),
+                    ..ProfileEntry::default()
+                });
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index];
+        entry.wall_time += self.started.elapsed();
+        entry.directories += self.counters.directories.load(Ordering::Relaxed);
+        entry.bytes_stated += self.counters.bytes.load(Ordering::Relaxed);
+    }
+}
+
+/// Runs the detectors of a scan one after another, each in its own section.
+#[derive(Default)]
+pub(crate) struct Detectors {
+    current: Option<Section>,
+}
+
+impl Detectors {
+    /// Ends the section of the previous detector and starts one for `name`.
+    pub(crate) fn next(&mut self, name: &str) {
+        self.current = None;
+        self.current = detector(name);
+    }
+}
+
+/// Records a directory listed by the calling thread, with the total size of
+/// the files in it that were stat'ed.
+pub(crate) fn count_directory(bytes_stated: u64) {
+    CURRENT.with_borrow(|current| {
+        if let Some(counters) = current {
+            counters.directories.fetch_add(1, Ordering::Relaxed);
+            counters.bytes.fetch_add(bytes_stated, Ordering::Relaxed);
+        }
+    });
+}
+
+/// The counters of the calling thread, for helper threads to work for.
+pub(crate) fn current() -> Option<Arc<Counters>> {
+    CURRENT.with_borrow(|current| current.clone())
+}
+
+/// Makes the calling helper thread count towards `counters`.
+pub(crate) fn work_for(counters: Option<Arc<Counters>>) {
+    CURRENT.set(counters);
+}
+
+fn lock() -> MutexGuard<'static, ScanProfile> {
+    COLLECTED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,129 @@
+use super::{sizecache, DevstripEvent, ScanConfig};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const STATS_FILE: &str = "scans.json";
+/// Estimates made this early in a scan swing too much to be worth showing.
+const MIN_PERCENT_FOR_ETA: u64 = 5;
+
+/// How far a scan has come, judged by how many directories the last scan
+/// with the same roots and settings went through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanEstimate {
+    pub percent: u8,
+    pub remaining: Option<Duration>,
+}
+
+impl ScanEstimate {
+    /// "40%, about 2 min left"
+    pub fn describe(&self) -> String {
+        match self.remaining {
+            Some(remaining) if remaining.as_secs() >= 90 => format!(
+                "{}%, about {} min left",
+                self.percent,
+                (remaining.as_secs() + 30) / 60
+            ),
+            Some(remaining) => format!(
+                "{}%, about {} s left",
+                self.percent,
+                remaining.as_secs().max(1)
+            ),
+            None => format!("{}%", self.percent),
+        }
+    }
+}
+
+/// Counts the directories a scan goes through and turns them into
+/// estimates. The count of a finished scan is kept in the cache directory
+/// for the next one; without it, or for an incremental rescan, there are no
+/// estimates.
+pub(crate) struct ProgressTracker {
+    key: String,
+    stats_file: Option<PathBuf>,
+    expected: Option<u64>,
+    started: Instant,
+    scanned: AtomicU64,
+    reported: AtomicU64,
+}
+
+impl ProgressTracker {
+    pub(crate) fn start(config: &ScanConfig) -> Self {
+        let key = format!(
+            "{:?}",
+            (
+                &config.roots,
+                config.max_depth,
+                &config.exclude_paths,
+                config.same_filesystem,
+                config.symlinks,
+            )
+        );
+        let stats_file = config
+            .size_cache
+            .then(|| sizecache::cache_dir().map(|dir| dir.join(STATS_FILE)))
+            .flatten();
+        let expected = stats_file
+            .as_ref()
+            .filter(|_| !config.incremental)
+            .and_then(|file| read_stats(file).remove(&key))
+            .filter(|count| *count > 0);
+        Self {
+            key,
+            stats_file: stats_file.filter(|_| !config.incremental),
+            expected,
+            started: Instant::now(),
+            scanned: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+        }
+    }
+
+    /// A new estimate once `event` moves the scan past the next percent.
+    pub(crate) fn observe(&self, event: &DevstripEvent) -> Option<ScanEstimate> {
+        if !matches!(event, DevstripEvent::Scanning { .. }) {
+            return None;
+        }
+        let scanned = self.scanned.fetch_add(1, Ordering::Relaxed) + 1;
+        let expected = self.expected?;
+        // Held below 100 until the scan says it is finished, as this scan
+        // may go through more directories than the last one.
+        let percent = (scanned * 100 / expected).min(99);
+        if self.reported.fetch_max(percent, Ordering::Relaxed) >= percent {
+            return None;
+        }
+        let remaining = (percent >= MIN_PERCENT_FOR_ETA).then(|| {
+            let left = expected.saturating_sub(scanned) as f64;
+            self.started.elapsed().mul_f64(left / scanned as f64)
+        });
+        Some(ScanEstimate {
+            percent: percent as u8,
+            remaining,
+        })
+    }
+
+    /// Remembers how many directories a scan that ran to the end went
+    /// through.
+    pub(crate) fn finish(&self, cancelled: bool) {
+        let Some(file) = self.stats_file.as_ref().filter(|_| !cancelled) else {
+            return;
+        };
+        let mut stats = read_stats(file);
+        stats.insert(self.key.clone(), self.scanned.load(Ordering::Relaxed));
+        let Some(parent) = file.parent() else {
+            return;
+        };
+        let temp = file.with_extension("json.tmp");
+        let _ = fs::create_dir_all(parent)
+            .and_then(|_| fs::write(&temp, serde_json::to_vec(&stats).unwrap_or_default()))
+            .and_then(|_| fs::rename(&temp, file));
+    }
+}
+
+fn read_stats(file: &Path) -> HashMap<String, u64> {
+    fs::read(file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,157 @@
+use super::{Candidate, Excludes, ScanConfig, ScanStamp};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Directory levels below a root whose modification times make up its
+/// fingerprint: the root lists the projects and a project lists its build
+/// outputs, so one appearing or going away changes the fingerprint.
+const FINGERPRINT_DEPTH: usize = 2;
+/// Roots with more directories than this in those levels are always
+/// walked again.
+const FINGERPRINT_MAX_DIRS: usize = 20_000;
+
+/// What the project walk found below one root, and the state of the root
+/// when the walk started.
+struct RootScan {
+    settings: String,
+    fingerprint: u64,
+    candidates: Vec<Candidate>,
+    gradle_dists: HashSet<String>,
+    /// Excludes the walk kept paths out with.
+    matched_excludes: Vec<PathBuf>,
+}
+
+/// The last walk of each root in this process.
+static PREVIOUS: Mutex<BTreeMap<PathBuf, RootScan>> = Mutex::new(BTreeMap::new());
+
+/// A root as it is right before it is walked.
+pub(crate) struct RootState {
+    root: PathBuf,
+    settings: String,
+    fingerprint: u64,
+    /// The scan's excludes, which record what its walk matched.
+    excludes: Excludes,
+}
+
+impl RootState {
+    /// `None` when the root is too large to fingerprint, or the scan
+    /// leaves sizes for later and its candidates cannot be kept.
+    pub(crate) fn of(root: &Path, config: &ScanConfig) -> Option<RootState> {
+        if config.lazy_sizes {
+            return None;
+        }
+        Some(RootState {
+            root: root.to_path_buf(),
+            settings: settings_key(config),
+            fingerprint: fingerprint(root, config)?,
+            excludes: config.exclude_paths.clone(),
+        })
+    }
+
+    /// The candidates and Gradle distributions the previous walk of this
+    /// root found, if neither the root nor the settings changed since.
+    /// Candidates removed in the meantime are left out. The excludes that
+    /// walk matched count as matched by this scan too.
+    pub(crate) fn reuse(&self) -> Option<(Vec<Candidate>, HashSet<String>)> {
+        let previous = lock();
+        let scan = previous.get(&self.root).filter(|scan| {
+            scan.settings == self.settings && scan.fingerprint == self.fingerprint
+        })?;
+        let candidates = scan
+            .candidates
+            .iter()
+            .filter(|candidate| fs::symlink_metadata(&candidate.path).is_ok())
+            .cloned()
+            .collect();
+        self.excludes.mark_matched(&scan.matched_excludes);
+        Some((candidates, scan.gradle_dists.clone()))
+    }
+
+    /// Keeps a finished walk of the root for the next rescan. Each candidate
+    /// is stamped now, next to the size this walk measured, so a rescan
+    /// that reuses the size hands on the stamp that goes with it.
+    pub(crate) fn record(self, candidates: &[Candidate], gradle_dists: &HashSet<String>) {
+        let matched_excludes = self.excludes.matched_within(&self.root);
+        let candidates = candidates
+            .iter()
+            .map(|candidate| Candidate {
+                scanned: ScanStamp::of(&candidate.path),
+                ..candidate.clone()
+            })
+            .collect();
+        lock().insert(
+            self.root,
+            RootScan {
+                settings: self.settings,
+                fingerprint: self.fingerprint,
+                candidates,
+                gradle_dists: gradle_dists.clone(),
+                matched_excludes,
+            },
+        );
+    }
+}
+
+/// The options that decide what the walk of a root finds.
+fn settings_key(config: &ScanConfig) -> String {
+    format!(
+        "{:?}",
+        (
+            &config.roots,
+            config.min_age_days,
+            config.max_depth,
+            config.keep_latest_derived,
+            config.keep_latest_cache,
+            &config.exclude_paths,
+            config.cargo_sweep,
+            &config.custom_rules,
+            &config.baseline,
+            config.same_filesystem,
+            config.active_repo_days,
+            config.symlinks,
+        )
+    )
+}
+
+/// Hash of the names and modification times of the directories in the top
+/// levels of `root`. Other scan roots nested inside it are left out, as
+/// their own walks cover them.
+fn fingerprint(root: &Path, config: &ScanConfig) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut dirs = vec![(root.to_path_buf(), 0)];
+    let mut seen = 0;
+    while let Some((dir, depth)) = dirs.pop() {
+        seen += 1;
+        if seen > FINGERPRINT_MAX_DIRS {
+            return None;
+        }
+        let meta = fs::symlink_metadata(&dir).ok();
+        dir.hash(&mut hasher);
+        meta.and_then(|meta| meta.modified().ok()).hash(&mut hasher);
+        if depth == FINGERPRINT_DEPTH {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut children: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .map(|entry| entry.path())
+            .filter(|path| !config.roots.contains(path))
+            .collect();
+        children.sort();
+        dirs.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+    }
+    Some(hasher.finish())
+}
+
+fn lock() -> MutexGuard<'static, BTreeMap<PathBuf, RootScan>> {
+    PREVIOUS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
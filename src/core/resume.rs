@@ -0,0 +1,217 @@
+use super::verify::current_usage;
+use super::{data_dir, Candidate, CoreResult, DevstripError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const PENDING_FILE: &str = "pending-cleanup.json";
+/// Appended to while the cleanup runs, next to the targets written once
+/// when it starts: one line per target started (`cleaning <index>`) and
+/// per step of the targets with a result (`done <count>`).
+const JOURNAL_FILE: &str = "pending-cleanup.log";
+
+/// The targets a cleanup had not finished when it was stopped or the
+/// process died, kept so that `devstrip clean --resume` can continue
+/// without scanning again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingCleanup {
+    /// RFC 3339 local time the cleanup started.
+    pub started_at: String,
+    pub trash: bool,
+    pub candidates: Vec<Candidate>,
+    /// Targets that were being removed at the time; what is left of them
+    /// no longer looks like the scan saw it.
+    #[serde(default)]
+    pub interrupted: Vec<PathBuf>,
+}
+
+impl PendingCleanup {
+    /// The targets to clean again. Interrupted ones are measured anew and
+    /// keep their scan stamp apart from what removing a part changes, so
+    /// the check before cleanup still catches a target replaced since the
+    /// scan.
+    pub fn into_candidates(self) -> Vec<Candidate> {
+        let interrupted = self.interrupted;
+        self.candidates
+            .into_iter()
+            .map(|mut candidate| {
+                if interrupted.contains(&candidate.path) {
+                    candidate.scanned = candidate
+                        .scanned
+                        .map(|scanned| scanned.after_partial_removal(&candidate.path));
+                    let usage = current_usage(&candidate);
+                    candidate.size_bytes = usage.allocated;
+                    candidate.logical_bytes = usage.logical;
+                }
+                candidate
+            })
+            .collect()
+    }
+
+    /// Replays `journal` onto the targets the cleanup started with. A line
+    /// cut short by a crash is ignored.
+    fn replay(mut self, journal: &str) -> Self {
+        let mut done = 0;
+        let mut cleaning = BTreeSet::new();
+        let whole_lines = journal
+            .split_inclusive('\n')
+            .filter_map(|line| line.strip_suffix('\n'));
+        for line in whole_lines {
+            let Some((event, index)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(index) = index.parse::<usize>() else {
+                continue;
+            };
+            match event {
+                "cleaning" => {
+                    cleaning.insert(index);
+                }
+                "done" => {
+                    done = index.max(done);
+                    cleaning = cleaning.split_off(&done);
+                }
+                _ => {}
+            }
+        }
+        let done = done.min(self.candidates.len());
+        self.interrupted.extend(
+            cleaning
+                .iter()
+                .filter_map(|index| self.candidates.get(*index))
+                .map(|candidate| candidate.path.clone()),
+        );
+        self.candidates.drain(..done);
+        self
+    }
+}
+
+pub fn pending_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(PENDING_FILE))
+}
+
+/// The interrupted cleanup, or `None` when the last one finished.
+pub fn load() -> CoreResult<Option<PendingCleanup>> {
+    let path = match pending_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let text = fs::read(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    let pending: PendingCleanup = serde_json::from_slice(&text).map_err(|err| {
+        DevstripError::file(
+            &path,
+            format!(
+                "The interrupted cleanup in {} is damaged ({}); delete it and scan again.",
+                path.display(),
+                err
+            ),
+        )
+    })?;
+    let journal = fs::read_to_string(path.with_file_name(JOURNAL_FILE)).unwrap_or_default();
+    let pending = pending.replay(&journal);
+    Ok((!pending.candidates.is_empty()).then_some(pending))
+}
+
+/// Keeps the pending files in step with a running cleanup: every target is
+/// written once when it starts, and the journal next to it grows by a line
+/// whenever a target is started or more of them have their result.
+pub(crate) struct Journal {
+    path: Option<PathBuf>,
+    log: Option<File>,
+    total: usize,
+}
+
+impl Journal {
+    pub(crate) fn start(candidates: &[Arc<Candidate>], trash: bool) -> (Self, CoreResult<()>) {
+        let mut journal = Journal {
+            path: pending_path(),
+            log: None,
+            total: candidates.len(),
+        };
+        let saved = journal.save(candidates, trash);
+        (journal, saved)
+    }
+
+    pub(crate) fn cleaning(&mut self, index: usize) {
+        self.append(&format!("cleaning {}", index));
+    }
+
+    /// Records that the first `done` targets have their result.
+    pub(crate) fn cleaned(&mut self, done: usize) {
+        if done < self.total {
+            self.append(&format!("done {}", done));
+            return;
+        }
+        if self.log.take().is_some() {
+            if let Some(path) = &self.path {
+                let _ = fs::remove_file(path);
+                let _ = fs::remove_file(path.with_file_name(JOURNAL_FILE));
+            }
+        }
+    }
+
+    /// Writes the targets, after emptying the journal of an earlier
+    /// cleanup so it is never replayed onto them.
+    fn save(&mut self, candidates: &[Arc<Candidate>], trash: bool) -> CoreResult<()> {
+        let path = self.path.as_ref().ok_or_else(|| DevstripError::Io {
+            path: None,
+            message: "Unable to locate the data directory".to_string(),
+        })?;
+        let write = || -> std::io::Result<Option<File>> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let log = File::create(path.with_file_name(JOURNAL_FILE))?;
+            if candidates.is_empty() {
+                return match fs::remove_file(path) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+                    _ => Ok(None),
+                };
+            }
+            let pending = PendingCleanup {
+                started_at: chrono::Local::now()
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                trash,
+                candidates: candidates
+                    .iter()
+                    .map(|candidate| Candidate::clone(candidate))
+                    .collect(),
+                interrupted: Vec::new(),
+            };
+            let text = serde_json::to_vec(&pending)?;
+            let temp = path.with_extension("json.tmp");
+            fs::write(&temp, &text)?;
+            fs::rename(&temp, path)?;
+            Ok(Some(log))
+        };
+        self.log = write().map_err(|err| {
+            DevstripError::io(
+                path,
+                &err,
+                format!(
+                    "Unable to record the cleanup in {}: {}",
+                    path.display(),
+                    err
+                ),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Appends `line` in a single write, so a crash leaves at most a
+    /// partial last line.
+    fn append(&mut self, line: &str) {
+        if let Some(log) = &mut self.log {
+            let _ = log.write_all(format!("{}\n", line).as_bytes());
+        }
+    }
+}
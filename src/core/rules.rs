@@ -0,0 +1,321 @@
+use super::sandbox::protected_reason;
+use super::{
+    age_cutoff, calculate_size, config_dir, is_excluded, measure_size, newest_use, safe_metadata,
+    Candidate, CleanupStrategy, CoreResult, DevstripError, ScanConfig, ScanControl,
+    PROJECT_PATTERNS, SKIP_DIR_NAMES,
+};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const RULES_FILE: &str = "rules.toml";
+const BROAD_RULE_THRESHOLD: usize = 25;
+
+/// A user-defined project directory rule, loaded from `[[rule]]` tables.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRule {
+    pub name: String,
+    pub directory: String,
+    #[serde(default)]
+    pub manifest: Option<String>,
+    #[serde(default = "default_category")]
+    pub category: String,
+    #[serde(default)]
+    pub min_age_days: Option<u64>,
+    /// Empty matching directories instead of removing them.
+    #[serde(default)]
+    pub keep_directory: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<CustomRule>,
+}
+
+fn default_category() -> String {
+    "Custom".to_string()
+}
+
+impl CustomRule {
+    fn reason(&self) -> String {
+        format!("Custom rule: {}", self.name)
+    }
+
+    fn cutoff(&self, default_min_age_days: u64) -> Option<SystemTime> {
+        age_cutoff(self.min_age_days.unwrap_or(default_min_age_days))
+    }
+
+    fn strategy(&self) -> CleanupStrategy {
+        if self.keep_directory {
+            CleanupStrategy::EmptyDirectory
+        } else {
+            CleanupStrategy::Remove
+        }
+    }
+
+    fn manifest_present(&self, parent: &Path) -> bool {
+        match &self.manifest {
+            Some(manifest) => parent.join(manifest).exists(),
+            None => true,
+        }
+    }
+}
+
+/// Location of the live rule file that every scan picks up.
+pub fn rules_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(RULES_FILE))
+}
+
+pub fn load_rules(path: &Path) -> CoreResult<Vec<CustomRule>> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    let file: RuleFile = toml::from_str(&text).map_err(|err| {
+        DevstripError::config(
+            Some(path),
+            format!("Invalid rule file {}: {}", path.display(), err),
+        )
+    })?;
+    for rule in &file.rules {
+        validate_rule(rule).map_err(|err| {
+            DevstripError::config(Some(path), format!("{}: {}", path.display(), err))
+        })?;
+    }
+    Ok(file.rules)
+}
+
+/// Loads the live rule file, treating a missing file as "no custom rules".
+pub fn load_user_rules() -> CoreResult<Vec<CustomRule>> {
+    match rules_path() {
+        Some(path) if path.exists() => load_rules(&path),
+        _ => Ok(Vec::new()),
+    }
+}
+
+pub(crate) fn validate_rule(rule: &CustomRule) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("rule name must not be empty".to_string());
+    }
+    let directory = rule.directory.as_str();
+    if directory.is_empty() || directory == "." || directory == ".." {
+        return Err(format!(
+            "rule '{}' has an invalid directory name",
+            rule.name
+        ));
+    }
+    if directory.contains('/') || directory.contains('\\') {
+        return Err(format!(
+            "rule '{}': directory must be a single name, not a path",
+            rule.name
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn match_custom_rule<'a>(
+    rules: &'a [CustomRule],
+    name: &str,
+    parent: &Path,
+) -> Option<&'a CustomRule> {
+    rules
+        .iter()
+        .find(|rule| rule.directory == name && rule.manifest_present(parent))
+}
+
+/// `min_age_days` applies to rules that set no minimum age of their own.
+pub(crate) fn rule_candidate(
+    rule: &CustomRule,
+    path: &Path,
+    config: &ScanConfig,
+    min_age_days: u64,
+    modified: Option<SystemTime>,
+    control: Option<&ScanControl>,
+) -> Option<Candidate> {
+    if let (Some(limit), Some(mtime)) = (rule.cutoff(min_age_days), modified) {
+        if mtime >= limit {
+            return None;
+        }
+    }
+    let usage = measure_size(path, control);
+    if usage.is_empty() {
+        return None;
+    }
+    Some(Candidate {
+        path: path.to_path_buf(),
+        size_bytes: usage.allocated,
+        logical_bytes: usage.logical,
+        category: rule.category.clone(),
+        reason: rule.reason(),
+        last_used: modified,
+        strategy: rule.strategy(),
+        deep_only: rule.min_age_days.is_none() && config.too_recent_for_baseline(modified),
+        recently_opened: None,
+        scanned: None,
+        size_pending: false,
+        size_incomplete: false,
+        matched_rule: None,
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleMatch {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub last_used: Option<SystemTime>,
+    /// Whether a live scan would report this directory.
+    pub flagged: bool,
+    pub diagnostics: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleReport {
+    pub rule: CustomRule,
+    pub matches: Vec<RuleMatch>,
+    pub warnings: Vec<String>,
+}
+
+impl RuleReport {
+    pub fn flagged_bytes(&self) -> u64 {
+        self.matches
+            .iter()
+            .filter(|m| m.flagged)
+            .map(|m| m.size_bytes)
+            .fold(0u64, |acc, size| acc.saturating_add(size))
+    }
+}
+
+/// Walks the configured roots looking only for directories named by
+/// `rules`, without deleting anything. Unlike a live scan, near misses
+/// (missing manifest, too recent, protected) are kept with a diagnostic.
+pub fn test_rules(rules: &[CustomRule], config: &ScanConfig) -> Vec<RuleReport> {
+    let mut reports: Vec<RuleReport> = rules
+        .iter()
+        .map(|rule| RuleReport {
+            rule: rule.clone(),
+            matches: Vec::new(),
+            warnings: static_warnings(rule),
+        })
+        .collect();
+
+    for root in &config.roots {
+        if is_excluded(root, &config.exclude_paths) || !root.is_dir() {
+            continue;
+        }
+        let mut queue: VecDeque<(PathBuf, u32)> = VecDeque::new();
+        queue.push_back((root.clone(), 0));
+        while let Some((current, depth)) = queue.pop_front() {
+            let entries = match fs::read_dir(&current) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+                    continue;
+                }
+                let path = entry.path();
+                let name = match path.file_name().and_then(|n| n.to_str()) {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                };
+                if SKIP_DIR_NAMES.contains(&name.as_str()) {
+                    continue;
+                }
+                let mut matched = false;
+                for report in reports.iter_mut() {
+                    if report.rule.directory == name {
+                        matched = true;
+                        report
+                            .matches
+                            .push(diagnose(&report.rule, &path, &current, config));
+                    }
+                }
+                if !matched && depth < config.max_depth {
+                    queue.push_back((path, depth + 1));
+                }
+            }
+        }
+    }
+
+    for report in reports.iter_mut() {
+        let flagged = report.matches.iter().filter(|m| m.flagged).count();
+        if flagged > BROAD_RULE_THRESHOLD {
+            report.warnings.push(format!(
+                "matches {} directories; the rule may be too broad",
+                flagged
+            ));
+        }
+        if report.matches.is_empty() {
+            report
+                .warnings
+                .push("matched nothing under the scanned roots".to_string());
+        }
+    }
+    reports
+}
+
+fn static_warnings(rule: &CustomRule) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if rule.manifest.is_none() {
+        warnings
+            .push("no manifest is required, so every directory with this name matches".to_string());
+    }
+    if PROJECT_PATTERNS.contains(&rule.directory.as_str()) {
+        warnings.push(format!(
+            "'{}' is already a built-in pattern; built-in matches take precedence",
+            rule.directory
+        ));
+    }
+    warnings
+}
+
+fn diagnose(rule: &CustomRule, path: &Path, parent: &Path, config: &ScanConfig) -> RuleMatch {
+    let mut diagnostics = Vec::new();
+    let mut flagged = true;
+    let modified = safe_metadata(path).and_then(|meta| newest_use(path, &meta));
+
+    if is_excluded(path, &config.exclude_paths) {
+        flagged = false;
+        diagnostics.push("skipped: excluded".to_string());
+    }
+    if let Some(manifest) = &rule.manifest {
+        if rule.manifest_present(parent) {
+            diagnostics.push(format!("manifest {} found", manifest));
+        } else {
+            flagged = false;
+            diagnostics.push(format!("skipped: no {} next to it", manifest));
+        }
+    }
+    if let (Some(limit), Some(mtime)) = (rule.cutoff(config.min_age_days), modified) {
+        if mtime >= limit {
+            flagged = false;
+            diagnostics.push("skipped: modified too recently".to_string());
+        }
+    }
+    if let Some(reason) = protected_reason(path) {
+        flagged = false;
+        diagnostics.push(format!("skipped: {}", reason));
+    }
+
+    let size_bytes = calculate_size(path, None);
+    if size_bytes == 0 {
+        flagged = false;
+        diagnostics.push("skipped: empty".to_string());
+    }
+
+    RuleMatch {
+        path: path.to_path_buf(),
+        size_bytes,
+        last_used: modified,
+        flagged,
+        diagnostics,
+    }
+}
@@ -0,0 +1,296 @@
+use super::{delete_path, home_dir, login_home_dir, CleanupStrategy, CoreResult, DevstripError};
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Hidden argument that turns the devstrip binary into the deletion helper.
+pub const HELPER_FLAG: &str = "--devstrip-delete-helper";
+const HELPER_NAME: &str = "the deletion helper";
+
+#[cfg(not(windows))]
+const PROTECTED_TREES: &[&str] = &[
+    "/System", "/usr", "/bin", "/sbin", "/etc", "/boot", "/dev", "/proc", "/lib",
+];
+#[cfg(not(windows))]
+const PROTECTED_EXACT: &[&str] = &[
+    "/",
+    "/Library",
+    "/Applications",
+    "/Users",
+    "/home",
+    "/private",
+    "/private/tmp",
+    "/private/var",
+    "/tmp",
+    "/var",
+    "/opt",
+];
+#[cfg(windows)]
+const PROTECTED_TREES: &[&str] = &[
+    r"C:\Windows",
+    r"C:\Program Files",
+    r"C:\Program Files (x86)",
+    r"C:\ProgramData",
+];
+#[cfg(windows)]
+const PROTECTED_EXACT: &[&str] = &[r"C:\", r"C:\Users"];
+const PROTECTED_HOME_ENTRIES: &[&str] = &[
+    "Library",
+    "Library/Application Support",
+    "Library/Caches",
+    "Library/Developer",
+    "Applications",
+    "Desktop",
+    "Documents",
+    "Downloads",
+    "Movies",
+    "Music",
+    "Pictures",
+    ".cache",
+    ".config",
+    ".local",
+    ".local/share",
+    ".ssh",
+    "AppData",
+    "AppData/Local",
+    "AppData/LocalLow",
+    "AppData/Roaming",
+];
+
+pub fn helper_requested() -> bool {
+    env::args_os()
+        .nth(1)
+        .map(|arg| arg == HELPER_FLAG)
+        .unwrap_or(false)
+}
+
+/// Entry point of the helper process. Reads NUL-terminated paths from stdin,
+/// re-checks each against the protected-path rules, deletes it, and answers
+/// with one `ok` or `err <reason>` line per path.
+pub fn run_helper() -> i32 {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+        match input.read_until(0, &mut buffer) {
+            Ok(0) => return 0,
+            Ok(_) => {}
+            Err(_) => return 1,
+        }
+        if buffer.last() == Some(&0) {
+            buffer.pop();
+        }
+        let path = path_from_bytes(&buffer);
+        let reply = match protected_reason(&path) {
+            Some(reason) => format!("err refused: {}", reason),
+            None => match delete_path(&path) {
+                Ok(_) => "ok".to_string(),
+                Err(err) => format!("err {}", err),
+            },
+        };
+        let written = writeln!(output, "{}", reply.replace('\n', " ")).and_then(|_| output.flush());
+        if written.is_err() {
+            return 1;
+        }
+    }
+}
+
+/// Paths with fewer components than this (`/usr`, `/Volumes`, ...) are never
+/// cleaned unless the caller lowers the limit.
+pub const DEFAULT_MIN_PATH_DEPTH: usize = 2;
+
+/// The guard every cleanup passes through, whatever produced the candidate:
+/// the protected-path rules plus a minimum depth below `/`.
+pub(crate) fn refused_reason(path: &Path, min_depth: usize) -> Option<String> {
+    if let Some(reason) = protected_reason(path) {
+        return Some(reason);
+    }
+    let depth = resolve_parent(path)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count();
+    (depth < min_depth).then(|| {
+        format!(
+            "path is {} level(s) below the filesystem root; at least {} required",
+            depth, min_depth
+        )
+    })
+}
+
+/// Why a sandboxed cleanup refuses `strategy`. The helper only removes
+/// whole paths; sweeping, pruning, compressing or truncating would change
+/// files from the scanner process, outside the helper's checks.
+pub(crate) fn unsupported_reason(strategy: &CleanupStrategy) -> Option<String> {
+    match strategy {
+        CleanupStrategy::Remove
+        | CleanupStrategy::EmptyDirectory
+        | CleanupStrategy::RemoveGemVersion
+        | CleanupStrategy::RemoveLink
+        | CleanupStrategy::Command { .. } => None,
+        other => Some(format!(
+            "sandboxed deletion only removes whole paths (this target: {})",
+            other.describe()
+        )),
+    }
+}
+
+/// Returns why `path` must never be deleted, or `None` when it is allowed.
+pub fn protected_reason(path: &Path) -> Option<String> {
+    if !path.is_absolute() {
+        return Some("path is not absolute".to_string());
+    }
+    let resolved = resolve_parent(path);
+
+    for tree in PROTECTED_TREES {
+        if resolved.starts_with(tree) {
+            return Some(format!("{} is a system directory", tree));
+        }
+    }
+    if PROTECTED_EXACT
+        .iter()
+        .any(|exact| resolved == Path::new(exact))
+    {
+        return Some(format!("{} is a protected directory", resolved.display()));
+    }
+
+    for home in [home_dir(), login_home_dir()].into_iter().flatten() {
+        let home = super::canonicalize(&home).unwrap_or(home);
+        if home.starts_with(&resolved) {
+            return Some("path is the home directory or one of its parents".to_string());
+        }
+        if PROTECTED_HOME_ENTRIES
+            .iter()
+            .any(|entry| resolved == home.join(entry))
+        {
+            return Some(format!("{} is a protected home folder", resolved.display()));
+        }
+    }
+
+    None
+}
+
+fn resolve_parent(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => super::canonicalize(parent)
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(bytes.to_vec()))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(OsString::from(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+pub(crate) struct DeleteHelper {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl DeleteHelper {
+    pub(crate) fn spawn() -> CoreResult<Self> {
+        let exe = env::current_exe().map_err(|err| {
+            DevstripError::tool(
+                HELPER_NAME,
+                format!("Unable to locate devstrip executable: {}", err),
+            )
+        })?;
+        let mut command = Command::new(exe);
+        if let Some(home) = super::HOME_OVERRIDE.get() {
+            // The helper re-derives protected folders, so it must see the
+            // same home as the scanner even when `--home` was used.
+            command.env(super::HOME_OVERRIDE_ENV, home);
+        }
+        let mut child = command
+            .arg(HELPER_FLAG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| {
+                DevstripError::tool(
+                    HELPER_NAME,
+                    format!("Unable to start deletion helper: {}", err),
+                )
+            })?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().ok_or_else(|| {
+            DevstripError::tool(HELPER_NAME, "Deletion helper has no output pipe")
+        })?;
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    pub(crate) fn delete(&mut self, path: &Path) -> CoreResult<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| DevstripError::tool(HELPER_NAME, "deletion helper is closed"))?;
+        let mut request = path_to_bytes(path);
+        request.push(0);
+        stdin
+            .write_all(&request)
+            .and_then(|_| stdin.flush())
+            .map_err(|err| {
+                DevstripError::tool(HELPER_NAME, format!("deletion helper unavailable: {}", err))
+            })?;
+
+        let mut reply = String::new();
+        match self.stdout.read_line(&mut reply) {
+            Ok(0) => Err(DevstripError::tool(
+                HELPER_NAME,
+                "deletion helper exited unexpectedly",
+            )),
+            Ok(_) => {
+                let reply = reply.trim_end();
+                match reply.strip_prefix("err ") {
+                    Some(reason) => Err(DevstripError::file(path, reason)),
+                    None if reply == "ok" => Ok(()),
+                    None => Err(DevstripError::tool(
+                        HELPER_NAME,
+                        format!("unexpected helper reply: {}", reply),
+                    )),
+                }
+            }
+            Err(err) => Err(DevstripError::tool(
+                HELPER_NAME,
+                format!("deletion helper unavailable: {}", err),
+            )),
+        }
+    }
+}
+
+impl Drop for DeleteHelper {
+    fn drop(&mut self) {
+        self.stdin.take();
+        let _ = self.child.wait();
+    }
+}
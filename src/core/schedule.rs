@@ -0,0 +1,466 @@
+use super::tools::{find_in_path, run_cleanup_command, run_tool};
+use super::{data_dir, home_dir, linux, CoreResult, DevstripError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const SCHEDULE_FILE: &str = "schedule.json";
+const LOG_FILE: &str = "schedule.log";
+const LAUNCHD_LABEL: &str = "com.ruzhila.devstrip";
+const SYSTEMD_UNIT: &str = "devstrip";
+/// Trails the crontab line devstrip owns, so it can be found and replaced.
+const CRON_MARKER: &str = "# devstrip schedule";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+}
+
+impl Frequency {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Frequency::Daily => "daily",
+            Frequency::Weekly => "weekly",
+        }
+    }
+}
+
+/// Where the job is registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Launchd,
+    Systemd,
+    Cron,
+}
+
+impl Backend {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Backend::Launchd => "launchd agent",
+            Backend::Systemd => "systemd user timer",
+            Backend::Cron => "crontab entry",
+        }
+    }
+}
+
+/// The installed job, recorded next to the saved scan so that `schedule
+/// status` and `schedule remove` know what `schedule install` did.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Schedule {
+    pub backend: Backend,
+    pub frequency: Frequency,
+    /// The program and arguments the job runs.
+    pub command: Vec<String>,
+    /// Files written for the job; empty for cron.
+    pub files: Vec<PathBuf>,
+    /// Where the job's output goes, when the backend does not keep it.
+    pub log: Option<PathBuf>,
+    /// RFC 3339 local time of the installation.
+    pub installed_at: String,
+}
+
+impl Schedule {
+    /// Whether the backend still has the job loaded.
+    pub fn active(&self) -> bool {
+        match self.backend {
+            Backend::Launchd => run_tool("launchctl", &["list", LAUNCHD_LABEL]).is_some(),
+            Backend::Systemd => {
+                run_tool("systemctl", &["--user", "is-active", &timer_name()]).is_some()
+            }
+            Backend::Cron => read_crontab()
+                .map(|table| table.lines().any(|line| line.ends_with(CRON_MARKER)))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// The backend `install` would use here: launchd on macOS, a systemd user
+/// timer where the user manager answers, cron otherwise.
+pub fn detect_backend() -> CoreResult<Backend> {
+    if cfg!(target_os = "macos") {
+        return Ok(Backend::Launchd);
+    }
+    if cfg!(unix) {
+        if find_in_path("systemctl").is_some()
+            && run_tool("systemctl", &["--user", "show-environment"]).is_some()
+        {
+            return Ok(Backend::Systemd);
+        }
+        if find_in_path("crontab").is_some() {
+            return Ok(Backend::Cron);
+        }
+        return Err(DevstripError::config(
+            None,
+            "Neither a systemd user session nor crontab is available to schedule devstrip.",
+        ));
+    }
+    Err(DevstripError::config(
+        None,
+        "Scheduling is supported on macOS and Linux; on Windows, run `devstrip scan` from Task Scheduler.",
+    ))
+}
+
+pub fn schedule_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(SCHEDULE_FILE))
+}
+
+/// Registers `command` to run at `frequency`, replacing an earlier job.
+pub fn install(frequency: Frequency, command: Vec<String>) -> CoreResult<Schedule> {
+    let backend = detect_backend()?;
+    if let Some(previous) = load()? {
+        unregister(&previous)?;
+    }
+    let log = data_dir().map(|dir| dir.join(LOG_FILE));
+    let mut schedule = Schedule {
+        backend,
+        frequency,
+        command,
+        files: Vec::new(),
+        log: None,
+        installed_at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+    };
+    match backend {
+        Backend::Launchd => {
+            let log = log.ok_or_else(no_data_dir)?;
+            let plist = launch_agents_dir()?.join(format!("{}.plist", LAUNCHD_LABEL));
+            write_file(&plist, &launchd_plist(&schedule, &log))?;
+            if let Some(dir) = log.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            run_cleanup_command(
+                "launchctl",
+                &["load".to_string(), "-w".to_string(), path_arg(&plist)],
+            )?;
+            schedule.files.push(plist);
+            schedule.log = Some(log);
+        }
+        Backend::Systemd => {
+            let dir = systemd_user_dir()?;
+            let service = dir.join(format!("{}.service", SYSTEMD_UNIT));
+            let timer = dir.join(timer_name());
+            write_file(&service, &systemd_service(&schedule))?;
+            write_file(&timer, &systemd_timer(frequency))?;
+            schedule.files = vec![service, timer];
+            systemctl(&["daemon-reload"])?;
+            systemctl(&["enable", "--now", &timer_name()])?;
+        }
+        Backend::Cron => {
+            let log = log.ok_or_else(no_data_dir)?;
+            if let Some(dir) = log.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let mut lines = crontab_without_marker()?;
+            lines.push(cron_line(&schedule, &log));
+            write_crontab(&lines)?;
+            schedule.log = Some(log);
+        }
+    }
+    save(&schedule)?;
+    Ok(schedule)
+}
+
+/// Unregisters the installed job; `None` when there was none.
+pub fn remove() -> CoreResult<Option<Schedule>> {
+    let Some(schedule) = load()? else {
+        return Ok(None);
+    };
+    unregister(&schedule)?;
+    if let Some(path) = schedule_path() {
+        let _ = fs::remove_file(path);
+    }
+    Ok(Some(schedule))
+}
+
+/// The installed job, or `None` when `schedule install` was not run.
+pub fn load() -> CoreResult<Option<Schedule>> {
+    let path = match schedule_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let text = fs::read(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    serde_json::from_slice(&text).map(Some).map_err(|err| {
+        DevstripError::file(
+            &path,
+            format!(
+                "The schedule record {} is damaged ({}); run `devstrip schedule install` again.",
+                path.display(),
+                err
+            ),
+        )
+    })
+}
+
+fn save(schedule: &Schedule) -> CoreResult<()> {
+    let path = schedule_path().ok_or_else(no_data_dir)?;
+    let text = serde_json::to_vec_pretty(schedule)
+        .map_err(|err| DevstripError::file(&path, err.to_string()))?;
+    write_file(&path, &String::from_utf8_lossy(&text))
+}
+
+fn unregister(schedule: &Schedule) -> CoreResult<()> {
+    match schedule.backend {
+        Backend::Launchd => {
+            for plist in &schedule.files {
+                // Fails when the agent is not loaded, which is fine here.
+                let _ = run_tool("launchctl", &["unload", "-w", &path_arg(plist)]);
+            }
+        }
+        Backend::Systemd => {
+            let _ = run_tool("systemctl", &["--user", "disable", "--now", &timer_name()]);
+        }
+        Backend::Cron => write_crontab(&crontab_without_marker()?)?,
+    }
+    for file in &schedule.files {
+        match fs::remove_file(file) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(DevstripError::io(
+                    file,
+                    &err,
+                    format!("Unable to remove {}: {}", file.display(), err),
+                ))
+            }
+        }
+    }
+    if schedule.backend == Backend::Systemd {
+        let _ = run_tool("systemctl", &["--user", "daemon-reload"]);
+    }
+    Ok(())
+}
+
+fn launch_agents_dir() -> CoreResult<PathBuf> {
+    home_dir()
+        .map(|home| home.join("Library/LaunchAgents"))
+        .ok_or_else(no_data_dir)
+}
+
+fn systemd_user_dir() -> CoreResult<PathBuf> {
+    home_dir()
+        .map(|home| linux::config_home(&home).join("systemd/user"))
+        .ok_or_else(no_data_dir)
+}
+
+fn timer_name() -> String {
+    format!("{}.timer", SYSTEMD_UNIT)
+}
+
+fn systemctl(args: &[&str]) -> CoreResult<()> {
+    let args: Vec<String> = std::iter::once("--user")
+        .chain(args.iter().copied())
+        .map(str::to_string)
+        .collect();
+    run_cleanup_command("systemctl", &args)
+}
+
+fn launchd_plist(schedule: &Schedule, log: &Path) -> String {
+    let arguments: String = schedule
+        .command
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect();
+    let weekday = match schedule.frequency {
+        Frequency::Daily => String::new(),
+        Frequency::Weekly => {
+            "        <key>Weekday</key>\n        <integer>0</integer>\n".to_string()
+        }
+    };
+    let log = xml_escape(&log.to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>StartCalendarInterval</key>
+    <dict>
+{weekday}        <key>Hour</key>
+        <integer>3</integer>
+        <key>Minute</key>
+        <integer>0</integer>
+    </dict>
+    <key>ProcessType</key>
+    <string>Background</string>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+    )
+}
+
+fn systemd_service(schedule: &Schedule) -> String {
+    let exec: Vec<String> = schedule
+        .command
+        .iter()
+        .map(|arg| systemd_quote(arg))
+        .collect();
+    format!(
+        "[Unit]\nDescription=devstrip scheduled run\n\n[Service]\nType=oneshot\nExecStart={}\n",
+        exec.join(" ")
+    )
+}
+
+fn systemd_timer(frequency: Frequency) -> String {
+    format!(
+        "[Unit]\nDescription=Run devstrip {}\n\n[Timer]\nOnCalendar={}\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        frequency.describe(),
+        frequency.describe()
+    )
+}
+
+fn cron_line(schedule: &Schedule, log: &Path) -> String {
+    let when = match schedule.frequency {
+        Frequency::Daily => "0 3 * * *",
+        Frequency::Weekly => "0 3 * * 0",
+    };
+    let command: Vec<String> = schedule
+        .command
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect();
+    // cron turns an unescaped % into a newline.
+    format!(
+        "{} {} >> {} 2>&1 {}",
+        when,
+        command.join(" "),
+        shell_quote(&log.to_string_lossy()),
+        CRON_MARKER
+    )
+    .replace('%', "\\%")
+}
+
+/// The user's crontab, empty when they have none yet. Any other failure of
+/// `crontab -l` is an error, so a table that could not be read is never
+/// written back without its lines.
+fn read_crontab() -> CoreResult<String> {
+    let output = Command::new("crontab")
+        .arg("-l")
+        .output()
+        .map_err(|err| DevstripError::tool("crontab", format!("failed to run crontab: {}", err)))?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Vixie cron, cronie and the BSDs all report a missing table this way.
+    if stderr.contains("no crontab for") {
+        return Ok(String::new());
+    }
+    Err(DevstripError::tool(
+        "crontab",
+        format!(
+            "crontab -l exited with {}: {}; the crontab was left unchanged",
+            output.status,
+            stderr.trim()
+        ),
+    ))
+}
+
+fn crontab_without_marker() -> CoreResult<Vec<String>> {
+    Ok(read_crontab()?
+        .lines()
+        .filter(|line| !line.ends_with(CRON_MARKER))
+        .map(str::to_string)
+        .collect())
+}
+
+fn write_crontab(lines: &[String]) -> CoreResult<()> {
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    let run = || -> std::io::Result<std::process::Output> {
+        let mut child = Command::new("crontab")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        child.wait_with_output()
+    };
+    let output = run()
+        .map_err(|err| DevstripError::tool("crontab", format!("failed to run crontab: {}", err)))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(DevstripError::tool(
+        "crontab",
+        format!(
+            "crontab exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    ))
+}
+
+fn write_file(path: &Path, text: &str) -> CoreResult<()> {
+    let temp = path.with_extension("tmp");
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&temp, text)?;
+        fs::rename(&temp, path)
+    };
+    write().map_err(|err| {
+        DevstripError::io(
+            path,
+            &err,
+            format!("Unable to write {}: {}", path.display(), err),
+        )
+    })
+}
+
+fn no_data_dir() -> DevstripError {
+    DevstripError::Io {
+        path: None,
+        message: "Unable to locate the data directory".to_string(),
+    }
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Quotes an `ExecStart=` word; `%` starts a specifier in unit files and
+/// `$` an environment variable.
+fn systemd_quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$")
+    )
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
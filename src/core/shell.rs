@@ -0,0 +1,444 @@
+use super::logs::stale_files;
+use super::{
+    age_cutoff, is_cancelled, is_excluded, report_found, safe_metadata, trash, Candidate,
+    CleanupStrategy, CoreResult, DevstripError, DevstripEvent, DiskUsage, Excludes, ScanConfig,
+    ScanControl,
+};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const HISTORY_FILES: &[&str] = &[
+    ".zsh_history",
+    ".bash_history",
+    ".local/share/fish/fish_history",
+];
+/// Histories above this size are offered for truncation to their most
+/// recent `HISTORY_KEEP_LINES` lines.
+const HISTORY_SIZE_LIMIT: u64 = 5 * 1024 * 1024;
+const HISTORY_KEEP_LINES: usize = 50_000;
+
+const RESURRECT_DIRS: &[&str] = &[".tmux/resurrect", ".local/share/tmux/resurrect"];
+const RESURRECT_KEEP: usize = 5;
+
+#[cfg(unix)]
+const SSH_SOCKET_DIRS: &[&str] = &[
+    ".ssh/sockets",
+    ".ssh/controlmasters",
+    ".ssh/cm",
+    ".ssh/control",
+];
+
+/// Small housekeeping targets in the home directory: oversized shell
+/// histories, piled-up tmux-resurrect saves, stale zsh completion dumps and
+/// SSH control sockets whose master is gone.
+pub(crate) fn collect_shell_clutter<F>(
+    home: &Path,
+    config: &ScanConfig,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let excludes = &config.exclude_paths;
+    let mut results = Vec::new();
+
+    for relative in HISTORY_FILES {
+        let path = home.join(relative);
+        if is_excluded(&path, excludes) {
+            continue;
+        }
+        let meta = match safe_metadata(&path) {
+            Some(meta) if meta.is_file() && meta.len() > HISTORY_SIZE_LIMIT => meta,
+            _ => continue,
+        };
+        reporter(DevstripEvent::Scanning { path: path.clone() });
+        let size = match truncatable_bytes(&path, HISTORY_KEEP_LINES) {
+            Some(size) if size > 0 => size,
+            _ => continue,
+        };
+        results.push(report_found(
+            reporter,
+            Candidate {
+                path,
+                size_bytes: size,
+                logical_bytes: size,
+                category: "Shell".to_string(),
+                reason: format!(
+                    "Oversized shell history (keep last {} lines)",
+                    HISTORY_KEEP_LINES
+                ),
+                last_used: meta.modified().ok(),
+                strategy: CleanupStrategy::TruncateHistory {
+                    keep_lines: HISTORY_KEEP_LINES,
+                },
+                deep_only: false,
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            },
+        ));
+    }
+
+    for relative in RESURRECT_DIRS {
+        if is_cancelled(control) {
+            return results;
+        }
+        let dir = home.join(relative);
+        if is_excluded(&dir, excludes) || !dir.is_dir() {
+            continue;
+        }
+        reporter(DevstripEvent::Scanning { path: dir.clone() });
+        if let Some(candidate) = stale_resurrect_saves(&dir, config) {
+            results.push(report_found(reporter, candidate));
+        }
+    }
+
+    results.extend(
+        stale_compdumps(home, config)
+            .into_iter()
+            .map(|candidate| report_found(reporter, candidate)),
+    );
+
+    #[cfg(unix)]
+    for dir in ssh_socket_dirs(home) {
+        if is_cancelled(control) {
+            return results;
+        }
+        if is_excluded(&dir, excludes) || !dir.is_dir() {
+            continue;
+        }
+        reporter(DevstripEvent::Scanning { path: dir.clone() });
+        results.extend(
+            dead_ssh_sockets(&dir, excludes)
+                .into_iter()
+                .map(|candidate| report_found(reporter, candidate)),
+        );
+    }
+
+    results
+}
+
+/// fish keeps its history as a YAML-like list; each entry starts with this
+/// and carries indented `when:` and `paths:` lines.
+const FISH_ENTRY_START: &[u8] = b"- cmd:";
+
+fn is_fish_history(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == "fish_history")
+}
+
+/// Byte offset where the last `keep_lines` lines start, or 0 to keep
+/// everything. A cut never lands
+/// inside a multi-line entry: a line ending in a backslash continues, and
+/// in a fish history only a `- cmd:` line starts an entry.
+fn history_cut(bytes: &[u8], keep_lines: usize, fish: bool) -> usize {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(
+            bytes
+                .iter()
+                .enumerate()
+                .filter(|(index, byte)| **byte == b'\n' && index + 1 < bytes.len())
+                .map(|(index, _)| index + 1),
+        )
+        .collect();
+    if line_starts.len() <= keep_lines {
+        return 0;
+    }
+    let mut index = line_starts.len() - keep_lines;
+    while index < line_starts.len() {
+        let start = line_starts[index];
+        let starts_entry = if fish {
+            bytes[start..].starts_with(FISH_ENTRY_START)
+        } else {
+            !(start >= 2 && bytes[start - 2] == b'\\')
+        };
+        if starts_entry {
+            return start;
+        }
+        index += 1;
+    }
+    // The kept lines all belong to one entry, or the file is not laid out
+    // as expected; either way there is nothing safe to cut.
+    0
+}
+
+/// Bytes a truncation to `keep_lines` lines would drop.
+pub(crate) fn truncatable_bytes(path: &Path, keep_lines: usize) -> Option<u64> {
+    let bytes = fs::read(path).ok()?;
+    Some(history_cut(&bytes, keep_lines, is_fish_history(path)) as u64)
+}
+
+/// Rewrites a history file to its last `keep_lines` lines, keeping its
+/// permissions. The new file is created with the history's mode, since it
+/// may hold secrets, and renamed into place unless the shell wrote to the
+/// history meanwhile; with `use_trash` the full history is moved to the
+/// trash first.
+pub(crate) fn truncate_history(path: &Path, keep_lines: usize, use_trash: bool) -> CoreResult<()> {
+    let io_error = |err: io::Error| DevstripError::io(path, &err, err.to_string());
+    let before = fs::metadata(path).map_err(io_error)?;
+    let bytes = fs::read(path).map_err(io_error)?;
+    let cut = history_cut(&bytes, keep_lines, is_fish_history(path));
+    if cut == 0 {
+        return Ok(());
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".devstrip-tmp");
+    let temp = path.with_file_name(temp_name);
+    let unchanged = || {
+        let now = fs::metadata(path)?;
+        Ok(now.len() == bytes.len() as u64 && now.modified()? == before.modified()?)
+    };
+    let written = write_private(&temp, &bytes[cut..], &before)
+        .and_then(|_| unchanged())
+        .map_err(io_error)
+        .and_then(|unchanged| {
+            if unchanged {
+                Ok(())
+            } else {
+                Err(DevstripError::file(
+                    path,
+                    "the history was written to while it was being truncated; it was left as it is",
+                ))
+            }
+        })
+        .and_then(|_| {
+            if use_trash {
+                trash::move_to_trash(path)
+            } else {
+                Ok(())
+            }
+        })
+        .and_then(|_| fs::rename(&temp, path).map_err(io_error));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Writes `contents` to a new file at `path` that is never more readable
+/// than the file `like` describes.
+fn write_private(path: &Path, contents: &[u8], like: &fs::Metadata) -> io::Result<()> {
+    let _ = fs::remove_file(path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(like.permissions().mode());
+    }
+    let mut file = options.open(path)?;
+    io::Write::write_all(&mut file, contents)?;
+    fs::set_permissions(path, like.permissions())
+}
+
+/// tmux-resurrect writes a save every few minutes with continuum enabled.
+/// The newest saves and whatever `last` points to are kept.
+fn stale_resurrect_saves(dir: &Path, config: &ScanConfig) -> Option<Candidate> {
+    let mut saves: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|name| name.starts_with("tmux_resurrect_"))
+                .unwrap_or(false)
+        })
+        .filter_map(|path| {
+            let meta = safe_metadata(&path)?;
+            meta.is_file()
+                .then_some(())
+                .and_then(|_| meta.modified().ok())
+                .map(|mtime| (mtime, path))
+        })
+        .collect();
+    saves.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+
+    let mut cutoff =
+        age_cutoff(config.category_min_age_days("Shell")).unwrap_or_else(SystemTime::now);
+    for (mtime, _) in saves.iter().take(RESURRECT_KEEP) {
+        cutoff = cutoff.min(*mtime);
+    }
+    if let Some(last) = fs::read_link(dir.join("last"))
+        .ok()
+        .map(|target| dir.join(target))
+        .and_then(|target| safe_metadata(&target))
+        .and_then(|meta| meta.modified().ok())
+    {
+        cutoff = cutoff.min(last);
+    }
+
+    let stale = stale_files(dir, cutoff);
+    let usage = stale
+        .iter()
+        .filter_map(|path| safe_metadata(path))
+        .fold(DiskUsage::default(), |acc, meta| {
+            acc.add(DiskUsage::of_file(&meta))
+        });
+    if usage.is_empty() {
+        return None;
+    }
+    let last_used = stale
+        .iter()
+        .filter_map(|path| safe_metadata(path).and_then(|meta| meta.modified().ok()))
+        .max();
+    Some(Candidate {
+        path: dir.to_path_buf(),
+        size_bytes: usage.allocated,
+        logical_bytes: usage.logical,
+        category: "Shell".to_string(),
+        reason: format!("Old tmux-resurrect saves ({} files)", stale.len()),
+        last_used,
+        strategy: CleanupStrategy::PruneFilesOlderThan { cutoff },
+        deep_only: config.too_recent_for_baseline(last_used),
+        recently_opened: None,
+        scanned: None,
+        size_pending: false,
+        size_incomplete: false,
+        matched_rule: None,
+    })
+}
+
+/// zsh leaves one `.zcompdump-<host>-<version>` per host and version it ever
+/// ran as; only the newest dump (and its compiled `.zwc`) is in use.
+fn stale_compdumps(home: &Path, config: &ScanConfig) -> Vec<Candidate> {
+    let mut dumps: Vec<(SystemTime, PathBuf)> = match fs::read_dir(home) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|name| name.starts_with(".zcompdump") && !name.ends_with(".zwc"))
+                    .unwrap_or(false)
+            })
+            .filter_map(|path| {
+                let meta = safe_metadata(&path)?;
+                if !meta.is_file() {
+                    return None;
+                }
+                Some((meta.modified().ok()?, path))
+            })
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    dumps.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+    let cutoff = age_cutoff(config.category_min_age_days("Shell"));
+
+    let mut results = Vec::new();
+    for (mtime, dump) in dumps.into_iter().skip(1) {
+        if cutoff.map(|limit| mtime >= limit).unwrap_or(false) {
+            continue;
+        }
+        let mut compiled = dump.clone().into_os_string();
+        compiled.push(".zwc");
+        for path in [dump, PathBuf::from(compiled)] {
+            if is_excluded(&path, &config.exclude_paths) {
+                continue;
+            }
+            let usage = match safe_metadata(&path) {
+                Some(meta) if meta.is_file() => DiskUsage::of_file(&meta),
+                _ => continue,
+            };
+            results.push(Candidate {
+                path,
+                size_bytes: usage.allocated,
+                logical_bytes: usage.logical,
+                category: "Shell".to_string(),
+                reason: "Stale zsh completion dump".to_string(),
+                last_used: Some(mtime),
+                strategy: CleanupStrategy::Remove,
+                deep_only: config.too_recent_for_baseline(Some(mtime)),
+                recently_opened: None,
+                scanned: None,
+                size_pending: false,
+                size_incomplete: false,
+                matched_rule: None,
+            });
+        }
+    }
+    results
+}
+
+/// Well-known socket folders plus the folders of any `ControlPath` set in
+/// `~/.ssh/config`.
+#[cfg(unix)]
+fn ssh_socket_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = SSH_SOCKET_DIRS.iter().map(|dir| home.join(dir)).collect();
+    if let Ok(config) = fs::read_to_string(home.join(".ssh/config")) {
+        for line in config.lines() {
+            let line = line.trim();
+            let mut words = line.splitn(2, |c: char| c.is_whitespace() || c == '=');
+            let key = words.next().unwrap_or_default();
+            if !key.eq_ignore_ascii_case("ControlPath") {
+                continue;
+            }
+            let value = words.next().unwrap_or_default().trim().trim_matches('"');
+            if value.eq_ignore_ascii_case("none") || value.is_empty() {
+                continue;
+            }
+            let expanded = match value.strip_prefix("~/") {
+                Some(rest) => home.join(rest),
+                None => PathBuf::from(value),
+            };
+            // Only the directory part is fixed; the file name holds tokens.
+            if let Some(parent) = expanded.parent() {
+                if !parent.to_string_lossy().contains('%') && !dirs.iter().any(|d| d == parent) {
+                    dirs.push(parent.to_path_buf());
+                }
+            }
+        }
+    }
+    dirs
+}
+
+/// Control sockets nothing listens on anymore; ssh leaves them behind when a
+/// master connection dies without cleaning up.
+#[cfg(unix)]
+fn dead_ssh_sockets(dir: &Path, excludes: &Excludes) -> Vec<Candidate> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    let mut results = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return results,
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|ft| ft.is_socket()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        if is_excluded(&path, excludes) {
+            continue;
+        }
+        let refused = matches!(
+            UnixStream::connect(&path),
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused
+        );
+        if !refused {
+            continue;
+        }
+        let last_used = safe_metadata(&path).and_then(|meta| meta.modified().ok());
+        results.push(Candidate {
+            path,
+            size_bytes: 0,
+            logical_bytes: 0,
+            category: "Shell".to_string(),
+            reason: "Dead SSH control socket".to_string(),
+            last_used,
+            strategy: CleanupStrategy::Remove,
+            deep_only: false,
+            recently_opened: None,
+            scanned: None,
+            size_pending: false,
+            size_incomplete: false,
+            matched_rule: None,
+        });
+    }
+    results
+}
@@ -0,0 +1,354 @@
+use super::{home_dir, linux, pathkey, safe_metadata, windows, DiskUsage};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_FILE: &str = "sizes.json";
+const CACHE_VERSION: u32 = 2;
+/// A directory or file changed this recently may change again within the
+/// same timestamp tick, which its modification time would not show.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+type Modified = (u64, u32);
+
+/// What one directory holds directly: the size of its files and the names
+/// of its subdirectories, valid for as long as the directory keeps the same
+/// identity and modification time and none of its files is newer than
+/// `newest_file`. A file rewritten in place leaves the directory's time
+/// alone, but not its own.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedDir {
+    id: Option<(u64, u64)>,
+    modified: Modified,
+    newest_file: Option<Modified>,
+    allocated: u64,
+    logical: u64,
+    subdirs: Vec<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Store {
+    version: u32,
+    dirs: HashMap<String, CachedDir>,
+}
+
+/// Directories listed so far in one scan.
+#[derive(Default)]
+struct Listed {
+    seen: HashMap<String, CachedDir>,
+    /// Directories that changed too recently to be kept for the next scan.
+    unsettled: HashMap<String, (DiskUsage, Vec<String>)>,
+    /// Directories that could not be read in full, so the sizes of the
+    /// trees holding them are short.
+    unreadable: Vec<PathBuf>,
+}
+
+/// The size cache of one scan. Every directory it lists is remembered
+/// until it ends, so trees that several detectors report, or that contain
+/// another candidate, are only read from disk once.
+pub(crate) struct Sizes {
+    /// Where the cache is kept; `None` when it only lives for this scan.
+    file: Option<PathBuf>,
+    /// What the earlier scan recorded, only read while this one runs.
+    earlier: HashMap<String, CachedDir>,
+    listed: RwLock<Listed>,
+}
+
+thread_local! {
+    /// The size cache of the scan the calling thread works for.
+    static CURRENT: RefCell<Option<Arc<Sizes>>> = const { RefCell::new(None) };
+}
+
+/// Identity and modification times of a directory and its files, read
+/// before it is listed.
+pub(crate) struct DirStamp {
+    key: String,
+    id: Option<(u64, u64)>,
+    modified: Modified,
+    newest_file: Option<Modified>,
+}
+
+/// The size cache for one scan on the calling thread, loaded from disk
+/// when the scan starts when `persist` is set. It is only consulted while
+/// a scan runs, so the check right before cleanup always measures the disk
+/// itself.
+pub(crate) struct ScanCache {
+    sizes: Arc<Sizes>,
+    outer: Option<Arc<Sizes>>,
+}
+
+impl ScanCache {
+    pub(crate) fn open(persist: bool) -> ScanCache {
+        let file = cache_dir()
+            .filter(|_| persist)
+            .map(|dir| dir.join(CACHE_FILE));
+        let earlier = file
+            .as_ref()
+            .and_then(|file| fs::read(file).ok())
+            .and_then(|bytes| serde_json::from_slice::<Store>(&bytes).ok())
+            .filter(|store| store.version == CACHE_VERSION)
+            .map(|store| store.dirs)
+            .unwrap_or_default();
+        let sizes = Arc::new(Sizes {
+            file,
+            earlier,
+            listed: RwLock::default(),
+        });
+        let outer = CURRENT.with_borrow_mut(|current| current.replace(sizes.clone()));
+        ScanCache { sizes, outer }
+    }
+
+    /// Writes back the directories this scan measured. Those it did not
+    /// reach are dropped, unless `partial` says the scan may have skipped
+    /// them: it was cancelled, or left sizes for later.
+    pub(crate) fn close(self, partial: bool) {
+        let Some(file) = &self.sizes.file else {
+            return;
+        };
+        let mut dirs = mem::take(&mut self.sizes.write().seen);
+        if partial {
+            for (key, dir) in &self.sizes.earlier {
+                dirs.entry(key.clone()).or_insert_with(|| dir.clone());
+            }
+        }
+        let store = Store {
+            version: CACHE_VERSION,
+            dirs,
+        };
+        let Some(parent) = file.parent() else {
+            return;
+        };
+        let temp = file.with_extension("json.tmp");
+        let _ = fs::create_dir_all(parent)
+            .and_then(|_| fs::write(&temp, serde_json::to_vec(&store).unwrap_or_default()))
+            .and_then(|_| fs::rename(&temp, file));
+    }
+}
+
+impl Drop for ScanCache {
+    fn drop(&mut self) {
+        CURRENT.set(self.outer.take());
+    }
+}
+
+/// The size cache of the calling thread's scan, for its worker threads
+/// to share with [`use_for`].
+pub(crate) fn current() -> Option<Arc<Sizes>> {
+    CURRENT.with_borrow(|current| current.clone())
+}
+
+/// Makes the calling worker thread use `sizes`.
+pub(crate) fn use_for(sizes: Option<Arc<Sizes>>) {
+    CURRENT.set(sizes);
+}
+
+/// The directories one walk lists, kept apart until it ends so the scan's
+/// cache is only locked for writing once per walk.
+pub(crate) struct Walk {
+    sizes: Option<Arc<Sizes>>,
+    seen: Vec<(String, CachedDir)>,
+    unsettled: Vec<(String, (DiskUsage, Vec<String>))>,
+    unreadable: Vec<PathBuf>,
+}
+
+impl Walk {
+    /// A walk for the calling thread's scan; one outside a scan neither
+    /// reads nor remembers anything.
+    pub(crate) fn start() -> Walk {
+        Walk {
+            sizes: current(),
+            seen: Vec::new(),
+            unsettled: Vec::new(),
+            unreadable: Vec::new(),
+        }
+    }
+
+    /// The files' size and the subdirectories of `dir` if this scan listed
+    /// it already.
+    pub(crate) fn listed(&self, dir: &Path) -> Option<(DiskUsage, Vec<PathBuf>)> {
+        let sizes = self.sizes.as_ref()?;
+        let key = dir.to_str()?;
+        let listed = sizes.read();
+        let (files, subdirs) = match listed.seen.get(key) {
+            Some(cached) => (cached.usage(), &cached.subdirs),
+            None => {
+                let (files, subdirs) = listed.unsettled.get(key)?;
+                (*files, subdirs)
+            }
+        };
+        Some((files, subdirs.iter().map(|name| dir.join(name)).collect()))
+    }
+
+    /// The stamp of `dir` when the cache kept on disk is in use and neither
+    /// the directory nor its files have changed in the last moments.
+    pub(crate) fn stamp(&self, dir: &Path) -> Option<DirStamp> {
+        self.sizes.as_ref()?.file.as_ref()?;
+        let key = dir.to_str()?.to_string();
+        let modified = settled(safe_metadata(dir)?.modified().ok()?)?;
+        let mut newest_file = None;
+        for entry in fs::read_dir(dir).ok()? {
+            let entry = entry.ok()?;
+            if entry.file_type().ok()?.is_dir() {
+                continue;
+            }
+            let file_modified = settled(entry.metadata().ok()?.modified().ok()?)?;
+            newest_file = newest_file.max(Some(file_modified));
+        }
+        Some(DirStamp {
+            key,
+            id: pathkey::file_id(dir),
+            modified,
+            newest_file,
+        })
+    }
+
+    /// The files' size and the subdirectories of `dir`, if neither it nor
+    /// its files changed since the scan that cached it.
+    pub(crate) fn lookup(
+        &mut self,
+        dir: &Path,
+        stamp: &DirStamp,
+    ) -> Option<(DiskUsage, Vec<PathBuf>)> {
+        let cached = self
+            .sizes
+            .as_ref()?
+            .earlier
+            .get(&stamp.key)
+            .filter(|cached| {
+                cached.id == stamp.id
+                    && cached.modified == stamp.modified
+                    && cached.newest_file == stamp.newest_file
+            })?
+            .clone();
+        let usage = cached.usage();
+        let subdirs = cached.subdirs.iter().map(|name| dir.join(name)).collect();
+        self.seen.push((stamp.key.clone(), cached));
+        Some((usage, subdirs))
+    }
+
+    /// Remembers what `dir` held directly when it was just listed, for the
+    /// rest of the scan and, with a `stamp`, for the next one.
+    pub(crate) fn record(
+        &mut self,
+        dir: &Path,
+        stamp: Option<DirStamp>,
+        files: DiskUsage,
+        subdirs: &[PathBuf],
+    ) {
+        if self.sizes.is_none() {
+            return;
+        }
+        let Some(key) = dir.to_str() else {
+            return;
+        };
+        let subdirs: Option<Vec<String>> = subdirs
+            .iter()
+            .map(|path| Some(path.file_name()?.to_str()?.to_string()))
+            .collect();
+        let Some(subdirs) = subdirs else {
+            return;
+        };
+        match stamp {
+            Some(stamp) => self.seen.push((
+                stamp.key,
+                CachedDir {
+                    id: stamp.id,
+                    modified: stamp.modified,
+                    newest_file: stamp.newest_file,
+                    allocated: files.allocated,
+                    logical: files.logical,
+                    subdirs,
+                },
+            )),
+            None => self.unsettled.push((key.to_string(), (files, subdirs))),
+        }
+    }
+
+    /// Notes that `dir` could not be read in full.
+    pub(crate) fn unreadable(&mut self, dir: &Path) {
+        if self.sizes.is_some() {
+            self.unreadable.push(dir.to_path_buf());
+        }
+    }
+}
+
+impl Drop for Walk {
+    fn drop(&mut self) {
+        let Some(sizes) = &self.sizes else {
+            return;
+        };
+        if self.seen.is_empty() && self.unsettled.is_empty() && self.unreadable.is_empty() {
+            return;
+        }
+        let mut listed = sizes.write();
+        listed.seen.extend(self.seen.drain(..));
+        listed.unsettled.extend(self.unsettled.drain(..));
+        listed.unreadable.append(&mut self.unreadable);
+    }
+}
+
+/// Whether the calling thread's scan could not read some directory at or
+/// below `path`.
+pub(crate) fn unreadable_below(path: &Path) -> bool {
+    current().is_some_and(|sizes| {
+        sizes
+            .read()
+            .unreadable
+            .iter()
+            .any(|dir| dir.starts_with(path))
+    })
+}
+
+impl Sizes {
+    fn read(&self) -> RwLockReadGuard<'_, Listed> {
+        self.listed
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Listed> {
+        self.listed
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CachedDir {
+    fn usage(&self) -> DiskUsage {
+        DiskUsage {
+            allocated: self.allocated,
+            logical: self.logical,
+        }
+    }
+}
+
+/// `modified` as seconds and nanoseconds since the epoch, unless it is so
+/// recent that a further change could keep the same time.
+fn settled(modified: SystemTime) -> Option<Modified> {
+    if SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default()
+        < SETTLE_TIME
+    {
+        return None;
+    }
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let home = home_dir()?;
+    let base = if cfg!(target_os = "macos") {
+        home.join("Library/Caches")
+    } else if cfg!(windows) {
+        windows::local_app_data(&home)
+    } else {
+        linux::cache_home(&home)
+    };
+    Some(base.join("devstrip"))
+}
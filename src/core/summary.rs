@@ -0,0 +1,79 @@
+use super::watch::display_root;
+use super::{home_dir, Candidate};
+use std::path::PathBuf;
+
+/// The targets of one category or under one scan root.
+#[derive(Clone, Debug)]
+pub struct GroupSummary {
+    pub name: String,
+    pub count: usize,
+    pub size_bytes: u64,
+    /// The least recently used target, when any has a known time.
+    pub oldest: Option<Candidate>,
+}
+
+impl GroupSummary {
+    fn add(&mut self, candidate: &Candidate) {
+        self.count += 1;
+        self.size_bytes = self.size_bytes.saturating_add(candidate.size_bytes);
+        if let Some(used) = candidate.last_used {
+            if self
+                .oldest
+                .as_ref()
+                .and_then(|oldest| oldest.last_used)
+                .is_none_or(|oldest| used < oldest)
+            {
+                self.oldest = Some(candidate.clone());
+            }
+        }
+    }
+}
+
+/// Totals per category, largest first.
+pub fn by_category(candidates: &[Candidate]) -> Vec<GroupSummary> {
+    group(candidates, |candidate| candidate.category.clone())
+}
+
+/// Totals per scan root, largest first. A target counts under the deepest
+/// root it is in; the caches in the home directory and other targets
+/// outside every root count under "other".
+pub fn by_root(candidates: &[Candidate], roots: &[PathBuf]) -> Vec<GroupSummary> {
+    let home = home_dir();
+    group(candidates, |candidate| {
+        roots
+            .iter()
+            .filter(|root| candidate.path.starts_with(root) && candidate.path != **root)
+            .max_by_key(|root| root.components().count())
+            .map(|root| display_root(root, home.as_deref()))
+            .unwrap_or_else(|| "other".to_string())
+    })
+}
+
+fn group<F>(candidates: &[Candidate], key: F) -> Vec<GroupSummary>
+where
+    F: Fn(&Candidate) -> String,
+{
+    let mut groups: Vec<GroupSummary> = Vec::new();
+    for candidate in candidates {
+        let name = key(candidate);
+        let index = match groups.iter().position(|group| group.name == name) {
+            Some(index) => index,
+            None => {
+                groups.push(GroupSummary {
+                    name,
+                    count: 0,
+                    size_bytes: 0,
+                    oldest: None,
+                });
+                groups.len() - 1
+            }
+        };
+        groups[index].add(candidate);
+    }
+    groups.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    groups
+}
@@ -0,0 +1,119 @@
+use super::{
+    last_used, measure_size, safe_metadata, Candidate, CleanupStrategy, CoreResult, DevstripError,
+    DiskUsage,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+const ARTIFACT_DIRS: &[&str] = &["deps", "build", "incremental", ".fingerprint", "examples"];
+const PROFILE_MARKER: &str = ".fingerprint";
+
+/// Finds per-crate artifacts inside a Cargo `target` directory that have not
+/// been touched since `cutoff`, cargo-sweep style, so the hot part of the
+/// incremental cache survives.
+pub(crate) fn collect_stale_artifacts(target: &Path, cutoff: SystemTime) -> Option<Candidate> {
+    let stale = stale_entries(target, cutoff);
+    if stale.is_empty() {
+        return None;
+    }
+    let usage = stale
+        .iter()
+        .map(|path| measure_size(path, None))
+        .fold(DiskUsage::default(), DiskUsage::add);
+    if usage.is_empty() {
+        return None;
+    }
+    let last_used = safe_metadata(target).and_then(|meta| last_used(&meta));
+    Some(Candidate {
+        path: target.to_path_buf(),
+        size_bytes: usage.allocated,
+        logical_bytes: usage.logical,
+        category: "Rust".to_string(),
+        reason: format!("Stale Cargo artifacts ({} entries)", stale.len()),
+        last_used,
+        strategy: CleanupStrategy::SweepCargoTarget { cutoff },
+        deep_only: false,
+        recently_opened: None,
+        scanned: None,
+        size_pending: false,
+        size_incomplete: false,
+        matched_rule: None,
+    })
+}
+
+/// Removes each stale artifact with `remove`, carrying on past failures and
+/// returning the first. Stops as soon as `cancel` is set.
+pub(crate) fn prune_stale_artifacts<F>(
+    target: &Path,
+    cutoff: SystemTime,
+    cancel: &AtomicBool,
+    mut remove: F,
+) -> CoreResult<()>
+where
+    F: FnMut(&Path) -> CoreResult<()>,
+{
+    let mut first_error = None;
+    for path in stale_entries(target, cutoff) {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(DevstripError::Cancelled);
+        }
+        match remove(&path) {
+            Err(DevstripError::Cancelled) => return Err(DevstripError::Cancelled),
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+            Ok(()) => {}
+        }
+    }
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn stale_entries(target: &Path, cutoff: SystemTime) -> Vec<PathBuf> {
+    let mut stale = Vec::new();
+    for profile in profile_dirs(target) {
+        for artifact_dir in ARTIFACT_DIRS {
+            let dir = profile.join(artifact_dir);
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let modified = safe_metadata(&path).and_then(|meta| last_used(&meta));
+                if matches!(modified, Some(mtime) if mtime < cutoff) {
+                    stale.push(path);
+                }
+            }
+        }
+    }
+    stale
+}
+
+fn profile_dirs(target: &Path) -> Vec<PathBuf> {
+    let mut profiles = Vec::new();
+    let mut pending = vec![(target.to_path_buf(), 0u8)];
+    while let Some((dir, depth)) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            if path.join(PROFILE_MARKER).is_dir() {
+                profiles.push(path);
+            } else if depth == 0 {
+                // Cross-compiled output lives one level deeper, under the target triple.
+                pending.push((path, depth + 1));
+            }
+        }
+    }
+    profiles
+}
@@ -0,0 +1,122 @@
+use super::{
+    is_cancelled, is_excluded, last_used, measure, report_found, safe_metadata, Candidate,
+    CleanupStrategy, CoreResult, DevstripError, DevstripEvent, Excludes, ScanControl,
+};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub(crate) fn find_in_path(tool: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(tool))
+        .find(|candidate| candidate.is_file())
+}
+
+pub(crate) fn run_tool(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub(crate) fn run_cleanup_command(program: &str, args: &[String]) -> CoreResult<()> {
+    let output = Command::new(program).args(args).output().map_err(|err| {
+        DevstripError::tool(program, format!("failed to run {}: {}", program, err))
+    })?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr.trim();
+    if detail.is_empty() {
+        Err(DevstripError::tool(
+            program,
+            format!("{} exited with {}", program, output.status),
+        ))
+    } else {
+        Err(DevstripError::tool(
+            program,
+            format!("{} exited with {}: {}", program, output.status, detail),
+        ))
+    }
+}
+
+pub(crate) fn collect_podman_storage<F>(
+    home: &Path,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let storage = home.join(".local/share/containers/storage");
+    if is_excluded(&storage, excludes) || !storage.is_dir() {
+        return Vec::new();
+    }
+    if find_in_path("podman").is_none() {
+        return Vec::new();
+    }
+    reporter(DevstripEvent::Scanning {
+        path: storage.to_path_buf(),
+    });
+    if is_cancelled(control) {
+        return Vec::new();
+    }
+
+    let dangling = run_tool(
+        "podman",
+        &["images", "--quiet", "--filter", "dangling=true"],
+    )
+    .map(|out| out.lines().filter(|line| !line.trim().is_empty()).count())
+    .unwrap_or(0);
+    let reclaimable = run_tool("podman", &["system", "df", "--format", "json"])
+        .and_then(|out| podman_reclaimable_bytes(&out));
+    // Measured right away even by a lazy scan: the size belongs to a
+    // podman command, which `measure_candidate` cannot size again.
+    let size = match reclaimable {
+        Some(bytes) => bytes,
+        None if dangling > 0 => measure::tree_usage(&storage, control).allocated,
+        None => 0,
+    };
+    if size == 0 {
+        return Vec::new();
+    }
+
+    let total = measure::tree_usage(&storage, control).allocated;
+    let last_used = safe_metadata(&storage).and_then(|meta| last_used(&meta));
+    vec![report_found(
+        reporter,
+        Candidate {
+            path: storage,
+            size_bytes: size,
+            logical_bytes: size,
+            category: "Containers".to_string(),
+            reason: format!(
+                "Podman storage ({} dangling image(s), {} on disk)",
+                dangling,
+                human_bytes::human_bytes(total as f64)
+            ),
+            last_used,
+            strategy: CleanupStrategy::command("podman", &["system", "prune", "--force"]),
+            deep_only: false,
+            recently_opened: None,
+            scanned: None,
+            size_pending: false,
+            size_incomplete: false,
+            matched_rule: None,
+        },
+    )]
+}
+
+fn podman_reclaimable_bytes(json: &str) -> Option<u64> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let total = value
+        .as_array()?
+        .iter()
+        .filter_map(|entry| entry.get("RawReclaimable").and_then(|v| v.as_u64()))
+        .sum();
+    Some(total)
+}
@@ -0,0 +1,143 @@
+use super::{login_home_dir, CleanupStrategy, CoreResult, DevstripError};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+static MOVING: Mutex<()> = Mutex::new(());
+
+/// Why `strategy` cannot move what it removes to the trash. A cleanup
+/// command deletes through its own tool, which has no trash to use.
+pub(crate) fn unsupported_reason(strategy: &CleanupStrategy) -> Option<String> {
+    match strategy {
+        CleanupStrategy::Command { .. } => Some(format!(
+            "cannot move to the trash what a cleanup command removes (this target: {})",
+            strategy.describe()
+        )),
+        _ => None,
+    }
+}
+
+/// Moves `path` into the user's trash so it can be restored. The move is a
+/// rename, so paths on another volume than the trash are refused rather
+/// than copied.
+pub(crate) fn move_to_trash(path: &Path) -> CoreResult<()> {
+    // A free name is only free until the move that takes it, so cleanups
+    // running side by side move one path at a time.
+    let _moving = MOVING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let name = path
+        .file_name()
+        .ok_or_else(|| DevstripError::file(path, format!("Invalid path: {}", path.display())))?;
+    let home = login_home_dir().ok_or_else(|| DevstripError::config(None, "HOME is not set"))?;
+    if cfg!(windows) {
+        return Err(DevstripError::config(
+            None,
+            "Moving to the Recycle Bin is not supported on Windows; run without --trash",
+        ));
+    }
+    if cfg!(target_os = "macos") {
+        let trash = home.join(".Trash");
+        let target = unique_target(&trash, name, |_| false);
+        rename_into_trash(path, &target)
+    } else {
+        freedesktop_trash(path, name, &home)
+    }
+}
+
+/// FreeDesktop trash: the file goes to `files/` and a `.trashinfo` record
+/// in `info/` lets file managers put it back.
+fn freedesktop_trash(path: &Path, name: &std::ffi::OsStr, home: &Path) -> CoreResult<()> {
+    let trash = super::linux::data_home(home).join("Trash");
+    let files = trash.join("files");
+    let info = trash.join("info");
+    fs::create_dir_all(&files)
+        .and_then(|_| fs::create_dir_all(&info))
+        .map_err(|err| {
+            DevstripError::io(
+                &trash,
+                &err,
+                format!("Unable to create {}: {}", trash.display(), err),
+            )
+        })?;
+
+    let absolute = super::canonicalize(path.parent().unwrap_or(Path::new("/")))
+        .map(|parent| parent.join(name))
+        .map_err(|err| {
+            DevstripError::io(
+                path,
+                &err,
+                format!("Unable to resolve {}: {}", path.display(), err),
+            )
+        })?;
+    let target = unique_target(&files, name, |candidate| {
+        info.join(info_name(candidate)).exists()
+    });
+    let target_name = target.file_name().unwrap_or(name).to_os_string();
+    let info_path = info.join(info_name(&target_name));
+    let record = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&absolute.to_string_lossy()),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+    fs::write(&info_path, record).map_err(|err| {
+        DevstripError::io(
+            &info_path,
+            &err,
+            format!("Unable to write {}: {}", info_path.display(), err),
+        )
+    })?;
+    rename_into_trash(path, &target).inspect_err(|_| {
+        let _ = fs::remove_file(&info_path);
+    })
+}
+
+fn rename_into_trash(path: &Path, target: &Path) -> CoreResult<()> {
+    fs::rename(path, target).map_err(|err| {
+        let message = if err.kind() == io::ErrorKind::CrossesDevices {
+            format!(
+                "{} is on a different volume than the trash; remove it without --trash",
+                path.display()
+            )
+        } else {
+            format!("Unable to move {} to the trash: {}", path.display(), err)
+        };
+        DevstripError::io(path, &err, message)
+    })
+}
+
+/// First free name in `dir`, appending " 2", " 3", ... like file managers do.
+fn unique_target<F>(dir: &Path, name: &std::ffi::OsStr, taken: F) -> PathBuf
+where
+    F: Fn(&OsString) -> bool,
+{
+    let mut candidate = name.to_os_string();
+    let mut counter = 2;
+    while dir.join(&candidate).symlink_metadata().is_ok() || taken(&candidate) {
+        candidate = name.to_os_string();
+        candidate.push(format!(" {}", counter));
+        counter += 1;
+    }
+    dir.join(candidate)
+}
+
+fn info_name(name: &OsString) -> OsString {
+    let mut info = name.clone();
+    info.push(".trashinfo");
+    info
+}
+
+fn percent_encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
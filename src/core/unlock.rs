@@ -0,0 +1,164 @@
+use super::windows;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "macos")]
+use std::process::{Command, Stdio};
+
+/// Removes the file, symlink or empty directory at `path`. When the OS
+/// refuses, whatever is in the way is unlocked and the removal retried:
+/// the read-only attribute on Windows; on Unix a parent directory without
+/// write permission (the Go module cache and Cargo's registry sources are
+/// made read-only on purpose), then immutable or append-only flags.
+/// Only the parent is touched when it lies inside `root`, the tree being
+/// removed. Every path that was changed is added to `unlocked`.
+pub(crate) fn remove(
+    path: &Path,
+    is_dir: bool,
+    root: &Path,
+    unlocked: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let attempt = || {
+        if is_dir {
+            fs::remove_dir(path)
+        } else {
+            windows::unlink(path)
+        }
+    };
+    let err = match attempt() {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => err,
+        result => return result,
+    };
+    let parent = path.parent().filter(|parent| parent.starts_with(root));
+    let writable = if cfg!(windows) {
+        unlock_if(windows::clear_readonly(path), path, unlocked)
+    } else {
+        parent.is_some_and(|parent| unlock_if(add_owner_write(parent, false), parent, unlocked))
+    };
+    let err = if writable {
+        match attempt() {
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => err,
+            result => return result,
+        }
+    } else {
+        err
+    };
+    let flagged = parent
+        .into_iter()
+        .chain([path])
+        .filter(|target| unlock_if(clear_immutable(target), target, unlocked))
+        .count();
+    if flagged > 0 {
+        attempt()
+    } else {
+        Err(err)
+    }
+}
+
+/// `fs::read_dir`, giving a directory inside the tree being removed read
+/// and write permission for its owner first if it lacks them.
+pub(crate) fn read_dir(dir: &Path, unlocked: &mut Vec<PathBuf>) -> io::Result<fs::ReadDir> {
+    match fs::read_dir(dir) {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied && !cfg!(windows) => {
+            if unlock_if(add_owner_write(dir, true), dir, unlocked) {
+                fs::read_dir(dir)
+            } else {
+                Err(err)
+            }
+        }
+        result => result,
+    }
+}
+
+fn unlock_if(changed: bool, path: &Path, unlocked: &mut Vec<PathBuf>) -> bool {
+    if changed && !unlocked.iter().any(|known| known == path) {
+        unlocked.push(path.to_path_buf());
+    }
+    changed
+}
+
+/// Adds owner write permission (and read and search when `readable` is
+/// set) to `dir`. Returns whether anything changed.
+#[cfg(unix)]
+fn add_owner_write(dir: &Path, readable: bool) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    let meta = match fs::symlink_metadata(dir) {
+        Ok(meta) if meta.is_dir() => meta,
+        _ => return false,
+    };
+    let wanted = if readable { 0o700 } else { 0o300 };
+    let mode = meta.permissions().mode();
+    if mode & wanted == wanted {
+        return false;
+    }
+    fs::set_permissions(dir, fs::Permissions::from_mode(mode | wanted)).is_ok()
+}
+
+#[cfg(not(unix))]
+fn add_owner_write(_dir: &Path, _readable: bool) -> bool {
+    false
+}
+
+/// Clears the user immutable and append-only flags on `path`, which keep
+/// even its owner from deleting it. On Linux only root may clear them, so
+/// this fails quietly for everyone else.
+#[cfg(target_os = "macos")]
+fn clear_immutable(path: &Path) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    const UF_IMMUTABLE: u32 = 0x2;
+    const UF_APPEND: u32 = 0x4;
+    let flags = match fs::symlink_metadata(path) {
+        Ok(meta) => meta.st_flags(),
+        Err(_) => return false,
+    };
+    flags & (UF_IMMUTABLE | UF_APPEND) != 0
+        && run_quietly("chflags", &["-h", "nouchg,nouappnd"], path)
+}
+
+/// Reads and writes the flags with the `FS_IOC_GETFLAGS` and
+/// `FS_IOC_SETFLAGS` ioctls. Symlinks have no flags of their own and are
+/// not opened.
+#[cfg(target_os = "linux")]
+fn clear_immutable(path: &Path) -> bool {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+    const FS_IMMUTABLE_FL: libc::c_int = 0x10;
+    const FS_APPEND_FL: libc::c_int = 0x20;
+    let file = match fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    let mut flags: libc::c_int = 0;
+    // SAFETY: the descriptor is open for the length of both calls, and
+    // each is passed a pointer to an int as the ioctls expect.
+    unsafe {
+        if libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) != 0
+            || flags & (FS_IMMUTABLE_FL | FS_APPEND_FL) == 0
+        {
+            return false;
+        }
+        let cleared = flags & !(FS_IMMUTABLE_FL | FS_APPEND_FL);
+        libc::ioctl(file.as_raw_fd(), libc::FS_IOC_SETFLAGS, &cleared) == 0
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn clear_immutable(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(target_os = "macos")]
+fn run_quietly(program: &str, args: &[&str], path: &Path) -> bool {
+    Command::new(program)
+        .args(args)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
@@ -0,0 +1,332 @@
+use super::git;
+use super::ignore;
+use super::logs::stale_files;
+use super::pathkey;
+use super::{
+    dotnet_project_file, gems, match_project_rule, measure_size, safe_metadata, shell, sweep,
+    Candidate, CleanupStrategy, DiskUsage, MatchedRule, BUILD_DIR_MANIFESTS,
+};
+use human_bytes::human_bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How far a target's size may drift between the scan and the cleanup, in
+/// percent of the scanned size, before it is skipped.
+const SIZE_TOLERANCE_PERCENT: u64 = 5;
+/// The drift always allowed, so a few files written to a small target do
+/// not count as a change.
+const SIZE_TOLERANCE_BYTES: u64 = 64 * 1024;
+
+/// Why a candidate was dropped when it was checked again right before
+/// cleanup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationFailure {
+    /// The path (or everything stale inside it) is gone.
+    Vanished,
+    /// The path no longer matches the rule that flagged it.
+    Reclassified,
+    /// The target grew or shrank by more than the tolerance since the
+    /// scan, so something is using it again.
+    Resized,
+    /// The path was modified or replaced after the scan.
+    Changed,
+}
+
+impl VerificationFailure {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VerificationFailure::Vanished => "vanished",
+            VerificationFailure::Reclassified => "reclassified",
+            VerificationFailure::Resized => "size changed >5%",
+            VerificationFailure::Changed => "changed",
+        }
+    }
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// What a candidate's path looked like when the scan finished. The device
+/// and inode tell a path that was deleted and recreated in the meantime
+/// apart from the one that was scanned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanStamp {
+    file_id: Option<(u64, u64)>,
+    modified: Option<SystemTime>,
+    /// Only recorded for files; a directory's contents are re-measured.
+    len: Option<u64>,
+}
+
+impl ScanStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        safe_metadata(path).map(|meta| Self::from_metadata(path, &meta))
+    }
+
+    /// The stamp of a target a cleanup stopped part-way through: what
+    /// removing a part changed is taken from the path now, while a path
+    /// deleted and created again keeps the stamp from the scan.
+    pub(crate) fn after_partial_removal(self, path: &Path) -> Self {
+        match Self::of(path) {
+            Some(now) if now.file_id == self.file_id => now,
+            _ => self,
+        }
+    }
+
+    fn from_metadata(path: &Path, meta: &fs::Metadata) -> Self {
+        ScanStamp {
+            file_id: pathkey::file_id(path),
+            modified: meta.modified().ok(),
+            len: meta.is_file().then_some(meta.len()),
+        }
+    }
+}
+
+/// A candidate's path together with the device and inode it had when the
+/// scan finished. Another file or directory created at the same path since
+/// gets a different ID.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CandidateId {
+    pub path: PathBuf,
+    pub file_id: Option<(u64, u64)>,
+}
+
+impl CandidateId {
+    /// The ID of whatever is at `path` right now.
+    pub fn of(path: &Path) -> Self {
+        CandidateId {
+            path: path.to_path_buf(),
+            file_id: pathkey::file_id(path),
+        }
+    }
+}
+
+impl Candidate {
+    /// The candidate's ID as of the scan, or just its path when the scan
+    /// recorded no device and inode for it.
+    pub fn id(&self) -> CandidateId {
+        CandidateId {
+            path: self.path.clone(),
+            file_id: self.scanned.and_then(|scanned| scanned.file_id),
+        }
+    }
+}
+
+/// A candidate that was re-classified and re-sized on disk just before
+/// cleanup. Only verified candidates reach a cleanup strategy.
+#[derive(Clone, Debug)]
+pub struct VerifiedCandidate {
+    candidate: Candidate,
+}
+
+impl VerifiedCandidate {
+    /// The candidate with `size_bytes` refreshed to the current size.
+    pub fn candidate(&self) -> &Candidate {
+        &self.candidate
+    }
+
+    pub fn into_candidate(self) -> Candidate {
+        self.candidate
+    }
+}
+
+/// Checks `candidate` against the disk again. Cleanup commands for external
+/// tools (`docker image rm`, `brew cleanup`, ...) check their own targets
+/// and pass through unchanged.
+pub fn verify(candidate: &Candidate) -> Result<VerifiedCandidate, (VerificationFailure, String)> {
+    let scanned_size = candidate.size_bytes;
+    if let CleanupStrategy::Command { .. } = candidate.strategy {
+        return Ok(VerifiedCandidate {
+            candidate: candidate.clone(),
+        });
+    }
+
+    let path = &candidate.path;
+    let meta = safe_metadata(path).ok_or_else(|| {
+        (
+            VerificationFailure::Vanished,
+            "no longer exists".to_string(),
+        )
+    })?;
+    let wants_link = candidate.strategy == CleanupStrategy::RemoveLink;
+    if meta.file_type().is_symlink() != wants_link {
+        return Err((
+            VerificationFailure::Reclassified,
+            if wants_link {
+                "is no longer a symbolic link".to_string()
+            } else {
+                "is now a symbolic link".to_string()
+            },
+        ));
+    }
+    if let Some(detail) = reclassified(candidate, meta.is_dir()) {
+        return Err((VerificationFailure::Reclassified, detail));
+    }
+    if let Some(detail) = changed(candidate, &meta) {
+        return Err((VerificationFailure::Changed, detail));
+    }
+
+    let usage = current_usage(candidate);
+    let current = usage.allocated;
+    if scanned_size > 0 && current == 0 {
+        return Err((
+            VerificationFailure::Vanished,
+            "nothing left to clean".to_string(),
+        ));
+    }
+    if !candidate.size_pending && !within_tolerance(scanned_size, current) {
+        return Err((
+            VerificationFailure::Resized,
+            format!(
+                "{} at scan time, {} now",
+                human_bytes(scanned_size as f64),
+                human_bytes(current as f64)
+            ),
+        ));
+    }
+
+    let mut candidate = candidate.clone();
+    candidate.size_bytes = usage.allocated;
+    candidate.logical_bytes = usage.logical;
+    candidate.size_pending = false;
+    Ok(VerifiedCandidate { candidate })
+}
+
+/// Whether `current` is close enough to `scanned` to clean what the scan
+/// showed.
+fn within_tolerance(scanned: u64, current: u64) -> bool {
+    let allowed = (scanned / 100)
+        .saturating_mul(SIZE_TOLERANCE_PERCENT)
+        .max(SIZE_TOLERANCE_BYTES);
+    scanned.abs_diff(current) <= allowed
+}
+
+/// Re-runs the checks that made the path a candidate in the first place,
+/// where they can be repeated from the path alone.
+fn reclassified(candidate: &Candidate, is_dir: bool) -> Option<String> {
+    let wants_dir = match candidate.strategy {
+        CleanupStrategy::SweepCargoTarget { .. }
+        | CleanupStrategy::PruneFilesOlderThan { .. }
+        | CleanupStrategy::EmptyDirectory
+        | CleanupStrategy::RemoveGemVersion => Some(true),
+        CleanupStrategy::TruncateHistory { .. } => Some(false),
+        _ => None,
+    };
+    if wants_dir.is_some_and(|wants_dir| wants_dir != is_dir) {
+        return Some(if is_dir {
+            "is now a directory".to_string()
+        } else {
+            "is no longer a directory".to_string()
+        });
+    }
+
+    let path = &candidate.path;
+    if let Some(file) = ignore::protecting_file(path) {
+        return Some(format!("it is now listed in {}", file.display()));
+    }
+    let parent = path.parent()?;
+    match candidate.matched_rule.as_ref()? {
+        MatchedRule::Project { name, .. } => {
+            if match_project_rule(name, parent).is_none() {
+                return Some("its project manifest is gone or it is now committed".to_string());
+            }
+        }
+        MatchedRule::BuildDir { name, manifest } => {
+            let manifests = BUILD_DIR_MANIFESTS
+                .iter()
+                .find(|(dir, _)| dir == name)
+                .map_or(&[][..], |(_, manifests)| *manifests);
+            if !manifests.iter().any(|m| parent.join(m).is_file()) {
+                return Some(format!("{} next to it is gone", manifest));
+            }
+            if git::has_tracked_files(path) {
+                return Some("it now contains files tracked by git".to_string());
+            }
+        }
+        MatchedRule::Dotnet { project, .. } => {
+            if dotnet_project_file(parent).is_none() {
+                return Some(format!("{} next to it is gone", project));
+            }
+        }
+    }
+    None
+}
+
+/// How the path differs from its scan stamp. Only checked for strategies
+/// that take the whole path: stale-file sweeps and history truncation
+/// expect their targets to keep being written to.
+fn changed(candidate: &Candidate, meta: &fs::Metadata) -> Option<String> {
+    if !matches!(
+        candidate.strategy,
+        CleanupStrategy::Remove
+            | CleanupStrategy::Compress
+            | CleanupStrategy::EmptyDirectory
+            | CleanupStrategy::RemoveGemVersion
+            | CleanupStrategy::RemoveLink
+    ) {
+        return None;
+    }
+    let scanned = candidate.scanned?;
+    let now = ScanStamp::from_metadata(&candidate.path, meta);
+    if scanned.file_id.is_some() && candidate.id() != CandidateId::of(&candidate.path) {
+        return Some("it was deleted and created again".to_string());
+    }
+    if let (Some(before), Some(after)) = (scanned.len, now.len) {
+        if before != after {
+            return Some(format!(
+                "{} at scan time, {} now",
+                human_bytes(before as f64),
+                human_bytes(after as f64)
+            ));
+        }
+    }
+    if scanned.modified != now.modified {
+        return Some("it was modified".to_string());
+    }
+    None
+}
+
+/// What the candidate's strategy would reclaim right now, measured the same
+/// way the scan measured it.
+pub(crate) fn current_usage(candidate: &Candidate) -> DiskUsage {
+    let path = &candidate.path;
+    match &candidate.strategy {
+        CleanupStrategy::Remove | CleanupStrategy::Compress | CleanupStrategy::EmptyDirectory => {
+            measure_size(path, None)
+        }
+        CleanupStrategy::SweepCargoTarget { cutoff } => {
+            sweep::collect_stale_artifacts(path, *cutoff)
+                .map(|stale| DiskUsage {
+                    allocated: stale.size_bytes,
+                    logical: stale.logical_bytes,
+                })
+                .unwrap_or_default()
+        }
+        CleanupStrategy::PruneFilesOlderThan { cutoff } => stale_files(path, *cutoff)
+            .iter()
+            .filter_map(|file| safe_metadata(file))
+            .fold(DiskUsage::default(), |acc, meta| {
+                acc.add(DiskUsage::of_file(&meta))
+            }),
+        CleanupStrategy::RemoveGemVersion => gems::version_usage(path, None),
+        CleanupStrategy::RemoveLink => safe_metadata(path)
+            .map(|meta| DiskUsage::of_file(&meta))
+            .unwrap_or_default(),
+        CleanupStrategy::TruncateHistory { keep_lines } => {
+            let dropped = shell::truncatable_bytes(path, *keep_lines).unwrap_or(0);
+            DiskUsage {
+                allocated: dropped,
+                logical: dropped,
+            }
+        }
+        CleanupStrategy::Command { .. } => DiskUsage {
+            allocated: candidate.size_bytes,
+            logical: candidate.logical_bytes,
+        },
+    }
+}
@@ -0,0 +1,283 @@
+use super::{
+    compare_versions, is_cancelled, is_excluded, last_used, linux, measure_size,
+    read_dir_reporting, report_found, safe_metadata, split_gem_dir_name, split_platform, Candidate,
+    CleanupStrategy, DevstripEvent, Excludes, ScanControl,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const WORKSPACE_STORAGE_DIRS: &[&str] = &[
+    "Library/Application Support/Code/User/workspaceStorage",
+    ".config/Code/User/workspaceStorage",
+];
+const EXTENSIONS_DIR: &str = ".vscode/extensions";
+/// Each profile but the default one lists its extensions in a folder here.
+const PROFILE_DIRS: &[&str] = &[
+    "Library/Application Support/Code/User/profiles",
+    ".config/Code/User/profiles",
+];
+
+/// Per-workspace state VS Code keeps after the workspace itself was deleted
+/// or moved. Remote workspaces are never flagged since their folders cannot
+/// be checked from here.
+pub(crate) fn collect_orphaned_workspace_storage<F>(
+    home: &Path,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    for relative in WORKSPACE_STORAGE_DIRS {
+        let base = linux::resolve(home, relative);
+        if is_excluded(&base, excludes) || !base.is_dir() {
+            continue;
+        }
+        reporter(DevstripEvent::Scanning { path: base.clone() });
+        let entries = match read_dir_reporting(&base, reporter) {
+            Some(entries) => entries,
+            None => continue,
+        };
+        for entry in entries.flatten() {
+            if is_cancelled(control) {
+                return results;
+            }
+            let path = entry.path();
+            if is_excluded(&path, excludes) {
+                continue;
+            }
+            let workspace = match workspace_location(&path.join("workspace.json")) {
+                Some(workspace) => workspace,
+                None => continue,
+            };
+            if workspace.exists() {
+                continue;
+            }
+            let usage = measure_size(&path, control);
+            if usage.is_empty() {
+                continue;
+            }
+            let last_used = safe_metadata(&path).and_then(|meta| last_used(&meta));
+            results.push(report_found(
+                reporter,
+                Candidate {
+                    path,
+                    size_bytes: usage.allocated,
+                    logical_bytes: usage.logical,
+                    category: "VSCode".to_string(),
+                    reason: format!("Workspace state for missing {}", workspace.display()),
+                    last_used,
+                    strategy: CleanupStrategy::Remove,
+                    deep_only: false,
+                    recently_opened: None,
+                    scanned: None,
+                    size_pending: false,
+                    size_incomplete: false,
+                    matched_rule: None,
+                },
+            ));
+        }
+    }
+    results
+}
+
+/// Folders VS Code has opened, each with when its workspace state was last
+/// written, which happens while the window is open.
+pub(crate) fn recent_workspaces(home: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut workspaces = Vec::new();
+    for relative in WORKSPACE_STORAGE_DIRS {
+        let base = linux::resolve(home, relative);
+        let entries = match fs::read_dir(&base) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let workspace = match workspace_location(&path.join("workspace.json")) {
+                Some(workspace) => workspace,
+                None => continue,
+            };
+            let state = safe_metadata(&path.join("state.vscdb"))
+                .or_else(|| safe_metadata(&path))
+                .and_then(|meta| last_used(&meta));
+            if let Some(opened) = state {
+                workspaces.push((workspace, opened));
+            }
+        }
+    }
+    workspaces
+}
+
+/// Extension versions left behind after VS Code installed another one for
+/// the same platform. The versions VS Code's extension lists point at are
+/// the installed ones, even after a downgrade; when no list names a version
+/// of an extension, its newest version is taken for the installed one.
+pub(crate) fn collect_superseded_extensions<F>(
+    home: &Path,
+    excludes: &Excludes,
+    reporter: &mut F,
+    control: Option<&ScanControl>,
+) -> Vec<Candidate>
+where
+    F: FnMut(DevstripEvent),
+{
+    let mut results = Vec::new();
+    let base = home.join(EXTENSIONS_DIR);
+    if is_excluded(&base, excludes) || !base.is_dir() {
+        return results;
+    }
+    reporter(DevstripEvent::Scanning { path: base.clone() });
+    let entries = match read_dir_reporting(&base, reporter) {
+        Some(entries) => entries,
+        None => return results,
+    };
+
+    let installed = installed_extensions(home, &base);
+    let mut by_id: HashMap<(String, String), Vec<(String, PathBuf)>> = HashMap::new();
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some((id, version)) = split_gem_dir_name(&name) {
+            // `rust-analyzer-0.3.1850-darwin-arm64` and `...-darwin-x64` are
+            // builds of one version, not two versions.
+            let (version, platform) = split_platform(&version);
+            by_id
+                .entry((id, platform))
+                .or_default()
+                .push((version, entry.path()));
+        }
+    }
+
+    for ((id, _), mut versions) in by_id {
+        if versions.len() < 2 {
+            continue;
+        }
+        versions.sort_by(|a, b| compare_versions(&b.0, &a.0));
+        let is_installed = |path: &Path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| installed.contains(name))
+        };
+        let kept = match versions.iter().find(|(_, path)| is_installed(path)) {
+            Some((version, _)) => version.clone(),
+            None => versions[0].0.clone(),
+        };
+        for (version, path) in versions {
+            if version == kept || is_installed(&path) {
+                continue;
+            }
+            if is_cancelled(control) {
+                return results;
+            }
+            if is_excluded(&path, excludes) {
+                continue;
+            }
+            let usage = measure_size(&path, control);
+            if usage.is_empty() {
+                continue;
+            }
+            let last_used = safe_metadata(&path).and_then(|meta| last_used(&meta));
+            results.push(report_found(
+                reporter,
+                Candidate {
+                    path,
+                    size_bytes: usage.allocated,
+                    logical_bytes: usage.logical,
+                    category: "VSCode".to_string(),
+                    reason: format!("{} {} (superseded by {})", id, version, kept),
+                    last_used,
+                    strategy: CleanupStrategy::Remove,
+                    deep_only: false,
+                    recently_opened: None,
+                    scanned: None,
+                    size_pending: false,
+                    size_incomplete: false,
+                    matched_rule: None,
+                },
+            ));
+        }
+    }
+    results
+}
+
+/// Folder names in `base` that an extension list of VS Code points at: the
+/// default profile's `extensions.json` next to them and each other
+/// profile's. Entries name their folder by `relativeLocation`, or in older
+/// lists by a `location` with the full path.
+fn installed_extensions(home: &Path, base: &Path) -> HashSet<String> {
+    let mut lists = vec![base.join("extensions.json")];
+    for relative in PROFILE_DIRS {
+        if let Ok(profiles) = fs::read_dir(linux::resolve(home, relative)) {
+            lists.extend(
+                profiles
+                    .flatten()
+                    .map(|profile| profile.path().join("extensions.json")),
+            );
+        }
+    }
+    let mut installed = HashSet::new();
+    for list in lists {
+        let Some(entries) = fs::read_to_string(&list)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Vec<serde_json::Value>>(&text).ok())
+        else {
+            continue;
+        };
+        for entry in entries {
+            let folder = entry
+                .get("relativeLocation")
+                .and_then(|location| location.as_str())
+                .or_else(|| {
+                    entry
+                        .get("location")?
+                        .get("path")?
+                        .as_str()?
+                        .rsplit('/')
+                        .next()
+                });
+            if let Some(folder) = folder {
+                installed.insert(folder.to_string());
+            }
+        }
+    }
+    installed
+}
+
+fn workspace_location(workspace_json: &Path) -> Option<PathBuf> {
+    let text = fs::read_to_string(workspace_json).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&text).ok()?;
+    let uri = value
+        .get("folder")
+        .or_else(|| value.get("workspace"))?
+        .as_str()?;
+    let path = uri.strip_prefix("file://")?;
+    let decoded = percent_decode(path)?;
+    if decoded.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(decoded))
+    }
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = text.get(index + 1..index + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            out.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
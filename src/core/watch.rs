@@ -0,0 +1,169 @@
+use super::{data_dir, home_dir, Candidate, CoreResult, DevstripError};
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HISTORY_FILE: &str = "watch-history.json";
+/// Snapshots kept in the history; with hourly passes, about two weeks.
+const HISTORY_LIMIT: usize = 336;
+
+/// The total size of every group a `devstrip watch` pass found, such as
+/// "node_modules across ~/Work".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WatchSnapshot {
+    /// RFC 3339 local time of the pass.
+    pub taken_at: String,
+    pub roots: Vec<PathBuf>,
+    pub groups: BTreeMap<String, u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GroupChange {
+    pub group: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl GroupChange {
+    pub fn delta(&self) -> i128 {
+        self.after as i128 - self.before as i128
+    }
+}
+
+impl WatchSnapshot {
+    /// Groups `candidates` by directory name and the scan root they are
+    /// under; targets outside every root, like the caches in the home
+    /// directory, count under `~`.
+    pub fn new(candidates: &[Candidate], roots: &[PathBuf]) -> Self {
+        let home = home_dir();
+        let mut groups: BTreeMap<String, u64> = BTreeMap::new();
+        for candidate in candidates {
+            let name = candidate
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| candidate.display_name());
+            let root = roots
+                .iter()
+                .filter(|root| candidate.path.starts_with(root) && candidate.path != **root)
+                .max_by_key(|root| root.components().count())
+                .map(|root| display_root(root, home.as_deref()))
+                .unwrap_or_else(|| "~".to_string());
+            let total = groups
+                .entry(format!("{} across {}", name, root))
+                .or_default();
+            *total = total.saturating_add(candidate.size_bytes);
+        }
+        WatchSnapshot {
+            taken_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            roots: roots.to_vec(),
+            groups,
+        }
+    }
+
+    pub fn taken_at(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.taken_at)
+            .ok()
+            .map(|time| time.with_timezone(&Local))
+    }
+
+    pub fn total(&self) -> u64 {
+        self.groups
+            .values()
+            .fold(0u64, |acc, size| acc.saturating_add(*size))
+    }
+
+    /// Groups that grew or shrank by at least `min_change` bytes since
+    /// `earlier`, the largest change first.
+    pub fn changes_since(&self, earlier: &WatchSnapshot, min_change: u64) -> Vec<GroupChange> {
+        let names: BTreeSet<&String> = earlier.groups.keys().chain(self.groups.keys()).collect();
+        let mut changes: Vec<GroupChange> = names
+            .into_iter()
+            .map(|group| GroupChange {
+                group: group.clone(),
+                before: earlier.groups.get(group).copied().unwrap_or(0),
+                after: self.groups.get(group).copied().unwrap_or(0),
+            })
+            .filter(|change| change.delta().unsigned_abs() >= u128::from(min_change.max(1)))
+            .collect();
+        changes.sort_by(|a, b| {
+            b.delta()
+                .unsigned_abs()
+                .cmp(&a.delta().unsigned_abs())
+                .then_with(|| a.group.cmp(&b.group))
+        });
+        changes
+    }
+}
+
+pub(crate) fn display_root(root: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| root.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => root.display().to_string(),
+    }
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// Earlier snapshots, oldest first; empty when nothing was recorded yet.
+pub fn load_history() -> CoreResult<Vec<WatchSnapshot>> {
+    let path = match history_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Vec::new()),
+    };
+    let text = fs::read(&path).map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!("Unable to read {}: {}", path.display(), err),
+        )
+    })?;
+    serde_json::from_slice(&text).map_err(|err| {
+        DevstripError::file(
+            &path,
+            format!(
+                "The watch history {} is damaged ({}); delete it to start over.",
+                path.display(),
+                err
+            ),
+        )
+    })
+}
+
+/// Appends `snapshot` to the history, dropping the oldest ones past the limit.
+pub fn record(snapshot: &WatchSnapshot) -> CoreResult<()> {
+    let path = history_path().ok_or_else(|| DevstripError::Io {
+        path: None,
+        message: "Unable to locate the data directory".to_string(),
+    })?;
+    let mut history = load_history()?;
+    history.push(snapshot.clone());
+    let excess = history.len().saturating_sub(HISTORY_LIMIT);
+    history.drain(..excess);
+    let text =
+        serde_json::to_vec(&history).map_err(|err| DevstripError::file(&path, err.to_string()))?;
+    let temp = path.with_extension("json.tmp");
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&temp, &text)?;
+        fs::rename(&temp, &path)
+    };
+    write().map_err(|err| {
+        DevstripError::io(
+            &path,
+            &err,
+            format!(
+                "Unable to save the watch history to {}: {}",
+                path.display(),
+                err
+            ),
+        )
+    })
+}
@@ -0,0 +1,129 @@
+use super::CleanupStrategy;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Caches under `%LOCALAPPDATA%`: (relative path, category, label, how it
+/// is cleaned). Tools that keep their caches in the profile itself
+/// (`.gradle`, `.nuget`) are found through the home-relative targets like
+/// on every other platform.
+const LOCAL_APP_DATA_CACHES: &[(&str, &str, &str, CleanupStrategy)] = &[
+    (
+        "npm-cache",
+        "Node",
+        "npm cache",
+        CleanupStrategy::EmptyDirectory,
+    ),
+    ("Yarn/Cache", "Node", "Yarn cache", CleanupStrategy::Remove),
+    ("pip/Cache", "Python", "pip cache", CleanupStrategy::Remove),
+    ("uv/cache", "Python", "uv cache", CleanupStrategy::Remove),
+    (
+        "NuGet/v3-cache",
+        ".NET",
+        "NuGet HTTP cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        "NuGet/plugins-cache",
+        ".NET",
+        "NuGet plugins cache",
+        CleanupStrategy::Remove,
+    ),
+    (
+        "Temp/NuGetScratch",
+        ".NET",
+        "NuGet scratch files",
+        CleanupStrategy::Remove,
+    ),
+    ("go-build", "Go", "Go build cache", CleanupStrategy::Remove),
+    (
+        "CrashDumps",
+        "Diagnostics",
+        "Crash dumps",
+        CleanupStrategy::Remove,
+    ),
+];
+
+/// `%LOCALAPPDATA%` for `home`. The variable is only trusted when it lies
+/// inside `home`, so `--home` pointed at another profile scans that
+/// profile's `AppData\Local` rather than the caller's.
+pub(crate) fn local_app_data(home: &Path) -> PathBuf {
+    std::env::var_os("LOCALAPPDATA")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| path.starts_with(home))
+        .unwrap_or_else(|| home.join("AppData").join("Local"))
+}
+
+/// The Windows-only cache targets for `home`.
+pub(crate) fn cache_targets(
+    home: &Path,
+) -> Vec<(PathBuf, &'static str, &'static str, CleanupStrategy)> {
+    let base = local_app_data(home);
+    LOCAL_APP_DATA_CACHES
+        .iter()
+        .map(|(relative, category, reason, strategy)| {
+            (base.join(relative), *category, *reason, strategy.clone())
+        })
+        .collect()
+}
+
+/// Drops the `\\?\` prefix `fs::canonicalize` puts on every path on
+/// Windows, so canonical paths still compare equal to (and `starts_with`)
+/// the plain paths users and environment variables give us. The standard
+/// library adds the prefix back itself for paths longer than `MAX_PATH`.
+pub(crate) fn strip_verbatim(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    let text = match path.to_str() {
+        Some(text) => text,
+        None => return path,
+    };
+    if let Some(share) = text.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", share));
+    }
+    match text.strip_prefix(r"\\?\") {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => PathBuf::from(rest),
+        _ => path,
+    }
+}
+
+/// Removes a file or symbolic link. A symbolic link or junction to a
+/// directory is removed with `remove_dir` on Windows, and never followed.
+#[cfg(windows)]
+pub(crate) fn unlink(path: &Path) -> io::Result<()> {
+    use std::os::windows::fs::FileTypeExt;
+    if fs::symlink_metadata(path)?.file_type().is_symlink_dir() {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn unlink(path: &Path) -> io::Result<()> {
+    fs::remove_file(path)
+}
+
+/// Clears the read-only attribute Windows puts on git pack files, NuGet
+/// packages and anything extracted from a zip that had it set, since it
+/// blocks deletion. Returns whether it was set.
+#[cfg(windows)]
+#[allow(clippy::permissions_set_readonly_false)]
+pub(crate) fn clear_readonly(path: &Path) -> bool {
+    let mut permissions = match fs::symlink_metadata(path) {
+        Ok(meta) => meta.permissions(),
+        Err(_) => return false,
+    };
+    if !permissions.readonly() {
+        return false;
+    }
+    permissions.set_readonly(false);
+    fs::set_permissions(path, permissions).is_ok()
+}
+
+#[cfg(not(windows))]
+pub(crate) fn clear_readonly(_path: &Path) -> bool {
+    false
+}
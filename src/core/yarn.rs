@@ -0,0 +1,22 @@
+use super::tools::{find_in_path, run_tool};
+use std::path::Path;
+
+/// Whether a Yarn Berry project commits its `.yarn/cache` (zero-install).
+/// `git ls-files` in the project answers it, so an ignored or never-added
+/// cache counts as regenerable. Inside a repository without `git` on the
+/// PATH, or when git fails, the cache is taken to be committed.
+pub(crate) fn cache_committed(project_root: &Path) -> bool {
+    let root = match super::canonicalize(project_root) {
+        Ok(root) => root,
+        Err(_) => return true,
+    };
+    if !root.ancestors().any(|dir| dir.join(".git").exists()) {
+        return false;
+    }
+    if find_in_path("git").is_none() {
+        return true;
+    }
+    let root_arg = root.to_string_lossy();
+    run_tool("git", &["-C", &root_arg, "ls-files", "--", ".yarn/cache"])
+        .is_none_or(|files| !files.trim().is_empty())
+}
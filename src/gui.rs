@@ -1,115 +1,265 @@
-use crate::core::{self, Candidate, ScanConfig};
+use crate::core::policy::{self, Policy};
+use crate::core::{
+    self, Candidate, CleanupOptions, CleanupOrder, CleanupStrategy, DevstripError, DevstripEvent,
+    Excludes, InstanceLock, ScanConfig, ScanEstimate, ScanJob, SkippedPath, SymlinkPolicy,
+};
 use gpui::{
-    div, prelude::*, px, size, App, Application, Bounds, ClickEvent, Context, Div, FlexDirection,
-    Overflow, Render, SharedString, Stateful, Window, WindowBounds, WindowOptions,
+    div, prelude::*, px, size, App, Application, Bounds, ClickEvent, Context, Div, ElementId,
+    FlexDirection, Overflow, Render, SharedString, Stateful, Window, WindowBounds, WindowOptions,
 };
 use human_bytes::human_bytes;
-use std::collections::BTreeSet;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// How often a running scan or cleanup hands its events to the view.
+/// Everything that arrived in between is applied as one batch with a single
+/// redraw, so a scan finding thousands of candidates does not lay the
+/// window out again for each one.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How many unreadable paths the results panel lists before collapsing the
+/// rest.
+const MAX_SKIPPED_LISTED: usize = 10;
+/// Rows rendered at once; deep scans can find thousands of candidates and
+/// laying them all out stalls the window.
+const RESULT_PAGE_SIZE: usize = 500;
+/// Repositories with uncommitted changes or commits this recent are left
+/// alone when "Skip active repositories" is on.
+const ACTIVE_REPO_DAYS: u64 = 7;
+/// Colors of the option toggles; the dry run and deep scan stand out from
+/// the rest.
+const OPTION_COLORS: ToggleColors = ToggleColors {
+    on: [0xEFF6FF, 0x1D4ED8, 0x1E3A8A],
+    off: [0xF3F4F6, 0x9CA3AF, 0x374151],
+};
+const DRY_RUN_COLORS: ToggleColors = ToggleColors {
+    on: [0xECFDF5, 0x047857, 0x065F46],
+    ..OPTION_COLORS
+};
+const DEEP_SCAN_COLORS: ToggleColors = ToggleColors {
+    on: [0xEDE9FE, 0x6D28D9, 0x4C1D95],
+    ..OPTION_COLORS
 };
+const CATEGORY_COLORS: ToggleColors = ToggleColors {
+    on: [0xEEF2FF, 0x4338CA, 0x312E81],
+    off: [0xF9FAFB, 0xD1D5DB, 0x374151],
+};
+
+/// Background, border and text colors of a checkbox row while it is on and
+/// while it is off.
+struct ToggleColors {
+    on: [u32; 3],
+    off: [u32; 3],
+}
 
 struct DevstripView {
     scanning: bool,
     cleaning: bool,
     dry_run: bool,
+    use_trash: bool,
     deep_scan: bool,
+    same_filesystem: bool,
+    skip_active_repos: bool,
+    /// List candidates first and measure them once the scan is done.
+    quick_scan: bool,
+    /// Scan at background CPU and disk priority.
+    low_impact: bool,
+    symlinks: SymlinkPolicy,
+    cleanup_order: CleanupOrder,
     status_line: String,
     info_message: Option<String>,
     error_message: Option<String>,
-    candidates: Vec<Candidate>,
-    all_candidates: Vec<Candidate>,
+    /// Rows share their candidate with `all_candidates` and the cleanup
+    /// queue, so filtering and cleaning never copy a candidate.
+    candidates: Vec<Arc<Candidate>>,
+    all_candidates: Vec<Arc<Candidate>>,
+    /// Rows of the previous scan that the running one has not found again,
+    /// by their index in `all_candidates`. They stay listed, marked as
+    /// sizes from the last scan, until it finds them or finishes.
+    stale_rows: HashMap<PathBuf, usize>,
+    /// Sizes the last scan measured, shown for candidates still waiting to
+    /// be measured.
+    previous_sizes: HashMap<PathBuf, u64>,
+    skipped_paths: Vec<SkippedPath>,
+    unmatched_excludes: Vec<PathBuf>,
     available_categories: BTreeSet<String>,
     selected_categories: BTreeSet<String>,
     category_filters_dirty: bool,
-    scan_cancel_flag: Option<Arc<AtomicBool>>,
+    scan_job: Option<Arc<ScanJob>>,
+    cleanup_cancel: Option<Arc<AtomicBool>>,
+    /// Held while a scan or cleanup runs; see `InstanceLock`.
+    instance_lock: Option<InstanceLock>,
     last_scan_cancelled: bool,
     show_cleanup_confirm: bool,
+    /// Set once the user confirmed a cleanup above the size cap a second
+    /// time.
+    force_large: bool,
     last_scan_config: Option<ScanConfig>,
+    /// Latest percentage and time left of the running scan, if known.
+    scan_estimate: Option<ScanEstimate>,
+    visible_limit: usize,
+    policy: Option<&'static Policy>,
 }
 
 impl DevstripView {
     fn new() -> Self {
+        let (policy, policy_error) = match policy::active_policy() {
+            Ok(policy) => (policy, None),
+            Err(err) => (None, Some(Self::describe_error(&err))),
+        };
         Self {
             scanning: false,
             cleaning: false,
             dry_run: true,
+            use_trash: policy.map(|policy| policy.require_trash).unwrap_or(false),
             deep_scan: false,
+            same_filesystem: false,
+            skip_active_repos: false,
+            quick_scan: false,
+            low_impact: false,
+            symlinks: SymlinkPolicy::default(),
+            cleanup_order: CleanupOrder::default(),
             status_line: "Ready to scan.".to_string(),
             info_message: Some(
                 "Press Scan to analyze your workspaces. Dry run mode is enabled by default."
                     .to_string(),
             ),
-            error_message: None,
+            error_message: policy_error,
             candidates: Vec::new(),
             all_candidates: Vec::new(),
+            stale_rows: HashMap::new(),
+            previous_sizes: HashMap::new(),
+            skipped_paths: Vec::new(),
+            unmatched_excludes: Vec::new(),
             available_categories: BTreeSet::new(),
             selected_categories: BTreeSet::new(),
             category_filters_dirty: false,
-            scan_cancel_flag: None,
+            scan_job: None,
+            cleanup_cancel: None,
+            instance_lock: None,
             last_scan_cancelled: false,
             show_cleanup_confirm: false,
+            force_large: false,
             last_scan_config: None,
+            scan_estimate: None,
+            visible_limit: RESULT_PAGE_SIZE,
+            policy,
         }
     }
 
-    fn start_scan(&mut self, cx: &mut Context<Self>) {
+    /// Scans every root, or with `incremental` only the roots that changed
+    /// since the last scan.
+    fn start_scan(&mut self, incremental: bool, cx: &mut Context<Self>) {
         if self.scanning {
             return;
         }
 
         self.scanning = true;
         self.cleaning = false;
-        self.status_line = "Scanning for cleanup targets...".to_string();
+        self.status_line = if incremental {
+            "Rescanning changed roots...".to_string()
+        } else {
+            "Scanning for cleanup targets...".to_string()
+        };
         self.error_message = None;
         self.info_message = None;
-        self.candidates.clear();
-        self.all_candidates.clear();
-        self.available_categories.clear();
-        self.scan_cancel_flag = None;
+        self.keep_previous_results();
+        self.skipped_paths.clear();
+        self.unmatched_excludes.clear();
+        self.visible_limit = RESULT_PAGE_SIZE;
+        self.scan_job = None;
         self.last_scan_cancelled = false;
         self.show_cleanup_confirm = false;
+        self.force_large = false;
+        self.scan_estimate = None;
         cx.notify();
 
-        let config = match Self::build_scan_config(self.deep_scan) {
+        let mut config = match Self::build_scan_config(
+            self.deep_scan,
+            self.same_filesystem,
+            self.skip_active_repos,
+            self.quick_scan,
+            self.low_impact,
+            self.symlinks,
+        ) {
             Ok(config) => config,
             Err(err) => {
                 self.scanning = false;
+                self.stale_rows.clear();
                 self.status_line = "Failed to build scan configuration.".to_string();
-                self.error_message = Some(err);
+                self.error_message = Some(Self::describe_error(&err));
                 cx.notify();
                 return;
             }
         };
 
+        match InstanceLock::acquire() {
+            Ok(lock) => self.instance_lock = Some(lock),
+            Err(err) => {
+                self.scanning = false;
+                self.stale_rows.clear();
+                self.status_line = "Another scan or cleanup is running.".to_string();
+                self.error_message = Some(Self::describe_error(&err));
+                cx.notify();
+                return;
+            }
+        }
+
+        config.incremental = incremental;
         self.last_scan_config = Some(config.clone());
 
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        self.scan_cancel_flag = Some(cancel_flag.clone());
+        let (event_tx, event_rx) = mpsc::channel::<DevstripEvent>();
+        let job = Arc::new(ScanJob::start_with_events(config, move |event| {
+            let _ = event_tx.send(event);
+        }));
+        self.scan_job = Some(job.clone());
 
-        let scan_task = cx.background_spawn({
-            let config = config.clone();
-            let cancel_flag = cancel_flag.clone();
-            async move { core::scan_with_cancel(&config, cancel_flag.as_ref()) }
-        });
+        let scan_task = cx.background_spawn(async move { job.wait() });
 
         cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(EVENT_POLL_INTERVAL).await;
+                let (events, finished) = drain_events(&event_rx);
+                let applied = this.update(cx, |this, cx| {
+                    if this.apply_events(events) {
+                        cx.notify();
+                    }
+                });
+                if applied.is_err() || finished {
+                    break;
+                }
+            }
+
             let candidates = scan_task.await;
             this.update(cx, move |this, cx| {
-                let was_cancelled = this
-                    .scan_cancel_flag
-                    .as_ref()
-                    .map(|flag| flag.load(Ordering::Relaxed))
-                    .unwrap_or(false);
+                let was_cancelled = this.scan_cancel_requested();
 
                 this.scanning = false;
-                this.scan_cancel_flag = None;
+                this.scan_job = None;
+                this.instance_lock = None;
                 this.last_scan_cancelled = was_cancelled;
-                this.all_candidates = candidates;
+                this.all_candidates = candidates.into_iter().map(Arc::new).collect();
+                this.stale_rows.clear();
                 this.sync_category_state();
                 this.apply_category_filter();
                 this.update_post_scan_messages(was_cancelled);
+                this.measure_pending_sizes(cx);
+                if !this.unmatched_excludes.is_empty() {
+                    let note = this
+                        .unmatched_excludes
+                        .iter()
+                        .map(|path| {
+                            format!("Exclude {} did not match any scanned path.", path.display())
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    this.info_message = Some(match this.info_message.take() {
+                        Some(info) => format!("{}\n{}", info, note),
+                        None => note,
+                    });
+                }
                 cx.notify();
             })
             .ok();
@@ -117,6 +267,51 @@ impl DevstripView {
         .detach();
     }
 
+    /// Marks the current results as stale for a new scan instead of
+    /// clearing them, and remembers their sizes.
+    fn keep_previous_results(&mut self) {
+        let mut sizes = HashMap::new();
+        for candidate in &self.all_candidates {
+            let size = if candidate.size_pending {
+                self.previous_sizes.get(&candidate.path).copied()
+            } else {
+                Some(candidate.size_bytes)
+            };
+            if let Some(size) = size {
+                sizes.insert(candidate.path.clone(), size);
+            }
+        }
+        self.previous_sizes = sizes;
+        self.stale_rows = self
+            .all_candidates
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| (candidate.path.clone(), index))
+            .collect();
+    }
+
+    /// Adds a candidate the running scan found, in place of its stale row
+    /// if it has one.
+    fn add_found(&mut self, candidate: Candidate) {
+        let candidate = Arc::new(candidate);
+        match self.stale_rows.remove(&candidate.path) {
+            Some(index) => self.all_candidates[index] = candidate,
+            None => self.all_candidates.push(candidate),
+        }
+    }
+
+    /// The size from the last scan to show for `candidate`, while its row
+    /// is stale or it waits to be measured.
+    fn last_scan_size(&self, candidate: &Candidate) -> Option<u64> {
+        if self.scanning && self.stale_rows.contains_key(&candidate.path) {
+            Some(candidate.size_bytes)
+        } else if candidate.size_pending {
+            self.previous_sizes.get(&candidate.path).copied()
+        } else {
+            None
+        }
+    }
+
     fn start_cleanup(&mut self, cx: &mut Context<Self>) {
         if self.cleaning || self.scanning {
             return;
@@ -133,6 +328,14 @@ impl DevstripView {
             cx.notify();
             return;
         }
+        if !self.dry_run && self.sizes_pending() {
+            self.info_message = Some(
+                "Some targets are still being measured. Clean once every size is known."
+                    .to_string(),
+            );
+            cx.notify();
+            return;
+        }
 
         if !self.dry_run && !self.show_cleanup_confirm {
             self.show_cleanup_confirm = true;
@@ -157,40 +360,121 @@ impl DevstripView {
             return;
         }
 
+        match InstanceLock::acquire() {
+            Ok(lock) => self.instance_lock = Some(lock),
+            Err(err) => {
+                self.show_cleanup_confirm = false;
+                self.status_line = "Another scan or cleanup is running.".to_string();
+                self.error_message = Some(Self::describe_error(&err));
+                cx.notify();
+                return;
+            }
+        }
+
         let dry_run = self.dry_run;
-        let candidates = self.candidates.clone();
+        let use_trash = self.use_trash;
+        let force_large = std::mem::take(&mut self.force_large);
+        let mut candidates = self.candidates.clone();
+        core::sort_for_cleanup(&mut candidates, self.cleanup_order);
         self.show_cleanup_confirm = false;
         self.cleaning = true;
         self.status_line = if dry_run {
-            format!("Simulating cleanup of {} target(s)...", candidates.len())
+            format!(
+                "Simulating cleanup of {} target(s), {}...",
+                candidates.len(),
+                self.cleanup_order.label()
+            )
+        } else if use_trash {
+            format!(
+                "Moving {} target(s) to the Trash, {}...",
+                candidates.len(),
+                self.cleanup_order.label()
+            )
         } else {
-            format!("Removing {} target(s)...", candidates.len())
+            format!(
+                "Removing {} target(s), {}...",
+                candidates.len(),
+                self.cleanup_order.label()
+            )
         };
         self.error_message = None;
         self.info_message = None;
         cx.notify();
 
-        let cleanup_task = cx.background_spawn(async move { core::cleanup(&candidates, dry_run) });
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cleanup_cancel = Some(Arc::clone(&cancel));
+        let queued = candidates.clone();
+        let (event_tx, event_rx) = mpsc::channel::<DevstripEvent>();
+        let cleanup_task = cx.background_spawn(async move {
+            let options = CleanupOptions {
+                dry_run,
+                trash: use_trash,
+                force_large,
+                workers: core::DEFAULT_CLEANUP_WORKERS,
+                ..CleanupOptions::default()
+            };
+            core::cleanup_with_cancel(&candidates, &options, &cancel, |event| {
+                let _ = event_tx.send(event);
+            })
+        });
 
         cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(EVENT_POLL_INTERVAL).await;
+                let (events, finished) = drain_events(&event_rx);
+                let applied = this.update(cx, |this, cx| {
+                    if this.apply_events(events) {
+                        cx.notify();
+                    }
+                });
+                if applied.is_err() || finished {
+                    break;
+                }
+            }
+
             let results = cleanup_task.await;
             this.update(cx, move |this, cx| {
                 this.cleaning = false;
+                this.cleanup_cancel = None;
+                this.instance_lock = None;
+                let attempted = results.len();
+                let not_attempted = queued[attempted..].to_vec();
 
                 let mut freed = 0u64;
                 let mut success_count = 0usize;
                 let mut failures = Vec::new();
                 let mut failure_messages = Vec::new();
+                let mut skipped_messages = Vec::new();
+                let mut unlocked_messages = Vec::new();
 
                 for result in results {
+                    if !result.unlocked.is_empty() {
+                        unlocked_messages.push(format!(
+                            "{} -> {} path(s), e.g. {}",
+                            result.candidate.display_name(),
+                            result.unlocked.len(),
+                            result.unlocked[0].display()
+                        ));
+                    }
                     if result.success {
                         success_count += 1;
                         freed = freed.saturating_add(result.candidate.size_bytes);
+                    } else if result.verification.is_some() {
+                        skipped_messages.push(format!(
+                            "{} -> {}",
+                            result.candidate.display_name(),
+                            result
+                                .error
+                                .as_ref()
+                                .map(ToString::to_string)
+                                .unwrap_or_default()
+                        ));
                     } else {
                         failures.push(result.candidate.clone());
                         let reason = result
                             .error
-                            .clone()
+                            .as_ref()
+                            .map(Self::describe_error)
                             .unwrap_or_else(|| "unknown error".to_string());
                         failure_messages.push(format!(
                             "{} -> {}",
@@ -223,6 +507,12 @@ impl DevstripView {
                     if failure_messages.is_empty() {
                         this.status_line = if success_count == 0 {
                             "Cleanup finished. Nothing was removed.".to_string()
+                        } else if use_trash {
+                            format!(
+                                "Cleanup finished: moved {} item(s) ({}) to the Trash.",
+                                success_count,
+                                Self::human_readable_size(freed)
+                            )
                         } else {
                             format!(
                                 "Cleanup finished: removed {} item(s) and reclaimed {}.",
@@ -243,6 +533,7 @@ impl DevstripView {
                     }
 
                     this.all_candidates = failures;
+                    this.all_candidates.extend(not_attempted.iter().cloned());
                     this.sync_category_state();
                     this.apply_category_filter();
 
@@ -273,6 +564,38 @@ impl DevstripView {
                     }
                 }
 
+                if !not_attempted.is_empty() {
+                    this.status_line = format!(
+                        "Cleanup stopped after {} of {} target(s); {} left untouched.",
+                        attempted,
+                        queued.len(),
+                        not_attempted.len()
+                    );
+                }
+
+                if !skipped_messages.is_empty() {
+                    let note = format!(
+                        "Skipped {} target(s) that changed since the scan; scan again to re-evaluate them:\n{}",
+                        skipped_messages.len(),
+                        skipped_messages.join("\n")
+                    );
+                    this.info_message = Some(match this.info_message.take() {
+                        Some(info) => format!("{}\n{}", info, note),
+                        None => note,
+                    });
+                }
+
+                if !unlocked_messages.is_empty() {
+                    let note = format!(
+                        "Cleared read-only or immutable flags to remove:\n{}",
+                        unlocked_messages.join("\n")
+                    );
+                    this.info_message = Some(match this.info_message.take() {
+                        Some(info) => format!("{}\n{}", info, note),
+                        None => note,
+                    });
+                }
+
                 cx.notify();
             })
             .ok();
@@ -280,6 +603,82 @@ impl DevstripView {
         .detach();
     }
 
+    /// Applies one batch of events; returns whether the view changed.
+    fn apply_events(&mut self, events: Vec<DevstripEvent>) -> bool {
+        let changed = !events.is_empty();
+        let mut found_new = false;
+        // Only the newest progress event of the batch makes it to the
+        // status line.
+        let mut progress = None;
+        for event in events {
+            match event {
+                DevstripEvent::CandidateFound(candidate) => {
+                    self.add_found(candidate);
+                    found_new = true;
+                }
+                DevstripEvent::PathSkipped(skipped) => {
+                    if !self
+                        .skipped_paths
+                        .iter()
+                        .any(|seen| seen.path == skipped.path)
+                    {
+                        self.skipped_paths.push(skipped);
+                    }
+                }
+                DevstripEvent::UnmatchedExclude { path } => {
+                    self.unmatched_excludes.push(path);
+                }
+                DevstripEvent::ScanEstimate(estimate) => {
+                    self.scan_estimate = Some(estimate);
+                }
+                DevstripEvent::Scanning { .. } | DevstripEvent::CleanupItemStarted { .. } => {
+                    progress = Some(event);
+                }
+                DevstripEvent::Warning(message) => {
+                    self.info_message = Some(message);
+                }
+                DevstripEvent::Error(message) => {
+                    self.error_message = Some(match self.error_message.take() {
+                        Some(existing) => format!("{}\n{}", existing, message),
+                        None => message,
+                    });
+                }
+                DevstripEvent::ScanStarted { .. }
+                | DevstripEvent::ScanProfile(_)
+                | DevstripEvent::ScanFinished { .. }
+                | DevstripEvent::CleanupItemFinished { .. }
+                | DevstripEvent::CleanupCancelled { .. } => {}
+            }
+        }
+
+        if found_new {
+            self.sync_category_state();
+            self.apply_category_filter();
+        }
+
+        let paused_or_stopping =
+            self.scan_cancel_requested() || self.scan_paused() || self.cleanup_cancel_requested();
+        if let Some(text) = progress
+            .filter(|_| !paused_or_stopping)
+            .and_then(|event| event.status_text())
+        {
+            self.status_line = if self.scanning {
+                match self.scan_estimate {
+                    Some(estimate) => format!(
+                        "{} ({} found, {})",
+                        text,
+                        self.all_candidates.len(),
+                        estimate.describe()
+                    ),
+                    None => format!("{} ({} found)", text, self.all_candidates.len()),
+                }
+            } else {
+                text
+            };
+        }
+        changed
+    }
+
     fn confirm_cleanup_dialog(&mut self, cx: &mut Context<Self>) {
         if self.cleaning || self.scanning {
             return;
@@ -287,14 +686,44 @@ impl DevstripView {
         if !self.show_cleanup_confirm {
             return;
         }
+        if self.sizes_pending() {
+            self.status_line = "Wait until every target is measured before cleaning.".to_string();
+            cx.notify();
+            return;
+        }
+        if !self.force_large && self.size_cap_error().is_some() {
+            self.force_large = true;
+            self.status_line = "Press Proceed again to confirm this large cleanup.".to_string();
+            cx.notify();
+            return;
+        }
         self.execute_cleanup(cx);
     }
 
+    /// Whether a target a quick scan listed is still waiting for its size;
+    /// the safety cap cannot be checked until it has one.
+    fn sizes_pending(&self) -> bool {
+        self.candidates
+            .iter()
+            .any(|candidate| candidate.size_pending)
+    }
+
+    /// Why the pending cleanup is larger than the safety cap allows, if it
+    /// is.
+    fn size_cap_error(&self) -> Option<DevstripError> {
+        let options = CleanupOptions {
+            dry_run: self.dry_run,
+            ..CleanupOptions::default()
+        };
+        options.size_cap_error(&self.candidates)
+    }
+
     fn cancel_cleanup_dialog(&mut self, cx: &mut Context<Self>) {
         if !self.show_cleanup_confirm {
             return;
         }
         self.show_cleanup_confirm = false;
+        self.force_large = false;
         self.status_line = "Cleanup cancelled.".to_string();
         self.info_message = Some("Dry run is off. Press Clean when ready.".to_string());
         cx.notify();
@@ -306,13 +735,35 @@ impl DevstripView {
             self.info_message =
                 Some("Dry run enabled. Cleanup will only simulate deletions.".to_string());
             self.show_cleanup_confirm = false;
+            self.force_large = false;
         } else {
             self.info_message = Some("Dry run disabled. Cleanup will delete files.".to_string());
         }
         cx.notify();
     }
 
+    fn toggle_trash(&mut self, cx: &mut Context<Self>) {
+        if self.use_trash && self.policy.map(|p| p.require_trash).unwrap_or(false) {
+            self.info_message =
+                Some("The managed policy requires moving targets to the Trash.".to_string());
+            cx.notify();
+            return;
+        }
+        self.use_trash = !self.use_trash;
+        self.info_message = Some(if self.use_trash {
+            "Cleanup will move targets to the Trash so they can be restored.".to_string()
+        } else {
+            "Cleanup will delete targets permanently.".to_string()
+        });
+        cx.notify();
+    }
+
     fn toggle_deep_scan(&mut self, cx: &mut Context<Self>) {
+        if !self.deep_scan && self.policy.map(|p| p.disable_deep_scan).unwrap_or(false) {
+            self.info_message = Some("Deep scan is disabled by the managed policy.".to_string());
+            cx.notify();
+            return;
+        }
         self.deep_scan = !self.deep_scan;
         if self.deep_scan {
             self.info_message = Some(
@@ -325,13 +776,141 @@ impl DevstripView {
         cx.notify();
     }
 
+    fn toggle_same_filesystem(&mut self, cx: &mut Context<Self>) {
+        self.same_filesystem = !self.same_filesystem;
+        self.info_message = Some(if self.same_filesystem {
+            "Future scans skip network shares and external volumes mounted inside the scanned folders."
+                .to_string()
+        } else {
+            "Future scans follow mounted volumes inside the scanned folders.".to_string()
+        });
+        cx.notify();
+    }
+
+    fn cycle_symlink_policy(&mut self, cx: &mut Context<Self>) {
+        self.symlinks = self.symlinks.next();
+        self.info_message = Some(
+            match self.symlinks {
+                SymlinkPolicy::Skip => "Future scans ignore symbolic links.",
+                SymlinkPolicy::Report => {
+                    "Future scans report symlinked build and cache directories without walking into other links."
+                }
+                SymlinkPolicy::Follow => {
+                    "Future scans report symlinked build and cache directories and walk into other linked directories."
+                }
+            }
+            .to_string(),
+        );
+        cx.notify();
+    }
+
+    fn toggle_quick_scan(&mut self, cx: &mut Context<Self>) {
+        self.quick_scan = !self.quick_scan;
+        self.info_message = Some(if self.quick_scan {
+            "Future scans list targets first and measure their sizes afterwards.".to_string()
+        } else {
+            "Future scans measure each target before listing it.".to_string()
+        });
+        cx.notify();
+    }
+
+    fn toggle_low_impact(&mut self, cx: &mut Context<Self>) {
+        self.low_impact = !self.low_impact;
+        self.info_message = Some(if self.low_impact {
+            "Future scans run at low priority so other work stays responsive.".to_string()
+        } else {
+            "Future scans run at normal priority.".to_string()
+        });
+        cx.notify();
+    }
+
+    /// Measures the candidates a quick scan listed without a size in the
+    /// background, filling in each row as it is done.
+    fn measure_pending_sizes(&mut self, cx: &mut Context<Self>) {
+        let pending: Vec<Candidate> = self
+            .all_candidates
+            .iter()
+            .filter(|candidate| candidate.size_pending)
+            .map(|candidate| (**candidate).clone())
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        let (measured_tx, measured_rx) = mpsc::channel::<Candidate>();
+        let measure_task = cx.background_spawn(async move {
+            for mut candidate in pending {
+                core::measure_candidate(&mut candidate);
+                if measured_tx.send(candidate).is_err() {
+                    break;
+                }
+            }
+        });
+
+        cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(EVENT_POLL_INTERVAL).await;
+                let mut measured = Vec::new();
+                let finished = loop {
+                    match measured_rx.try_recv() {
+                        Ok(candidate) => measured.push(candidate),
+                        Err(mpsc::TryRecvError::Empty) => break false,
+                        Err(mpsc::TryRecvError::Disconnected) => break true,
+                    }
+                };
+                let applied = this.update(cx, |this, cx| {
+                    if measured.is_empty() {
+                        return;
+                    }
+                    for candidate in measured {
+                        if let Some(row) = this
+                            .all_candidates
+                            .iter_mut()
+                            .find(|row| row.size_pending && row.id() == candidate.id())
+                        {
+                            *row = Arc::new(candidate);
+                        }
+                    }
+                    this.apply_category_filter();
+                    cx.notify();
+                });
+                if applied.is_err() || finished {
+                    break;
+                }
+            }
+            measure_task.await;
+        })
+        .detach();
+    }
+
+    fn toggle_skip_active_repos(&mut self, cx: &mut Context<Self>) {
+        self.skip_active_repos = !self.skip_active_repos;
+        self.info_message = Some(if self.skip_active_repos {
+            format!(
+                "Future scans skip git repositories with uncommitted changes or commits in the last {} days.",
+                ACTIVE_REPO_DAYS
+            )
+        } else {
+            "Future scans include actively developed repositories.".to_string()
+        });
+        cx.notify();
+    }
+
+    fn cycle_cleanup_order(&mut self, cx: &mut Context<Self>) {
+        self.cleanup_order = self.cleanup_order.next();
+        self.info_message = Some(format!(
+            "Cleanup will process targets {}.",
+            self.cleanup_order.label()
+        ));
+        cx.notify();
+    }
+
     fn stop_scan(&mut self, cx: &mut Context<Self>) {
         if !self.scanning {
             return;
         }
 
-        if let Some(flag) = &self.scan_cancel_flag {
-            if !flag.swap(true, Ordering::Relaxed) {
+        if let Some(job) = &self.scan_job {
+            if job.cancel() {
                 self.status_line = "Stopping scan...".to_string();
                 self.info_message = Some(
                     "Cancelling scan; partial results may appear once the operation stops."
@@ -342,10 +921,62 @@ impl DevstripView {
         }
     }
 
+    /// Asks the running cleanup to stop after the current item. A large
+    /// directory that is being removed stops part-way through.
+    fn stop_cleanup(&mut self, cx: &mut Context<Self>) {
+        let Some(cancel) = &self.cleanup_cancel else {
+            return;
+        };
+        if !cancel.swap(true, Ordering::Relaxed) {
+            self.status_line = "Stopping cleanup...".to_string();
+            cx.notify();
+        }
+    }
+
+    fn cleanup_cancel_requested(&self) -> bool {
+        self.cleanup_cancel
+            .as_ref()
+            .map(|cancel| cancel.load(Ordering::Relaxed))
+            .unwrap_or(false)
+    }
+
+    fn toggle_scan_pause(&mut self, cx: &mut Context<Self>) {
+        let Some(job) = &self.scan_job else {
+            return;
+        };
+        if job.is_cancelled() {
+            return;
+        }
+
+        if job.is_paused() {
+            job.resume();
+            self.status_line = "Scanning for cleanup targets...".to_string();
+            self.info_message = None;
+        } else {
+            job.pause();
+            let progress = job.progress();
+            self.status_line = format!(
+                "Scan paused after {} director(ies).",
+                progress.directories_scanned
+            );
+            self.info_message = Some(
+                "Press Resume to continue scanning, or Stop to keep partial results.".to_string(),
+            );
+        }
+        cx.notify();
+    }
+
     fn scan_cancel_requested(&self) -> bool {
-        self.scan_cancel_flag
+        self.scan_job
+            .as_ref()
+            .map(|job| job.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    fn scan_paused(&self) -> bool {
+        self.scan_job
             .as_ref()
-            .map(|flag| flag.load(Ordering::Relaxed))
+            .map(|job| job.is_paused())
             .unwrap_or(false)
     }
 
@@ -361,12 +992,14 @@ impl DevstripView {
         }
 
         self.category_filters_dirty = self.selected_categories != self.available_categories;
+        self.visible_limit = RESULT_PAGE_SIZE;
         self.apply_category_filter();
         if !self.scanning && !self.cleaning && self.last_scan_config.is_some() {
             self.update_post_scan_messages(self.last_scan_cancelled);
         }
         if self.show_cleanup_confirm {
             self.show_cleanup_confirm = false;
+            self.force_large = false;
         }
         cx.notify();
     }
@@ -475,9 +1108,16 @@ impl DevstripView {
         }
     }
 
-    fn build_scan_config(deep_scan: bool) -> Result<ScanConfig, String> {
+    fn build_scan_config(
+        deep_scan: bool,
+        same_filesystem: bool,
+        skip_active_repos: bool,
+        quick_scan: bool,
+        low_impact: bool,
+        symlinks: SymlinkPolicy,
+    ) -> Result<ScanConfig, DevstripError> {
         let extra: Vec<std::path::PathBuf> = Vec::new();
-        let excludes: Vec<std::path::PathBuf> = Vec::new();
+        let excludes = Excludes::default();
         let roots = core::default_roots(&extra, &excludes)?;
         let mut config = ScanConfig {
             roots,
@@ -486,6 +1126,22 @@ impl DevstripView {
             keep_latest_derived: 1,
             keep_latest_cache: 1,
             exclude_paths: excludes,
+            exclude_globs: Vec::new(),
+            cargo_sweep: false,
+            custom_rules: core::rules::load_user_rules().unwrap_or_default(),
+            extra_patterns: Vec::new(),
+            age_overrides: Vec::new(),
+            downloads_min_age_days: None,
+            baseline: None,
+            same_filesystem,
+            active_repo_days: skip_active_repos.then_some(ACTIVE_REPO_DAYS),
+            symlinks,
+            size_cache: true,
+            lazy_sizes: quick_scan,
+            low_priority: low_impact,
+            incremental: false,
+            profile: false,
+            keep_nested: false,
         };
 
         if deep_scan {
@@ -498,6 +1154,14 @@ impl DevstripView {
         Ok(config)
     }
 
+    /// The error followed by what the user can do about it.
+    fn describe_error(err: &DevstripError) -> String {
+        match err.hint() {
+            Some(hint) => format!("{} {}", err, hint),
+            None => err.to_string(),
+        }
+    }
+
     fn human_readable_size(bytes: u64) -> String {
         human_bytes(bytes as f64)
     }
@@ -575,68 +1239,25 @@ impl DevstripView {
         button.child(label.to_string())
     }
 
-    fn render_dry_run_toggle(&self, cx: &mut Context<Self>) -> Stateful<Div> {
-        let indicator = if self.dry_run { "[x]" } else { "[ ]" };
-        let (bg, border, text) = if self.dry_run {
-            (
-                gpui::rgb(0xECFDF5),
-                gpui::rgb(0x047857),
-                gpui::rgb(0x065F46),
-            )
-        } else {
-            (
-                gpui::rgb(0xF3F4F6),
-                gpui::rgb(0x9CA3AF),
-                gpui::rgb(0x374151),
-            )
-        };
-
-        div()
-            .id("dry-run-toggle")
-            .flex()
-            .gap_3()
-            .items_center()
-            .px_3()
-            .py_2()
-            .rounded_md()
-            .border_1()
-            .border_color(border)
-            .bg(bg)
-            .cursor_pointer()
-            .text_color(text)
-            .child(
-                div()
-                    .border_1()
-                    .border_color(border)
-                    .rounded_sm()
-                    .px_2()
-                    .py_1()
-                    .child(indicator.to_string()),
-            )
-            .child("Dry run (simulate cleanup)")
-            .on_click(cx.listener(|this, _event: &ClickEvent, _, cx| {
-                this.toggle_dry_run(cx);
-            }))
-    }
-
-    fn render_deep_scan_toggle(&self, cx: &mut Context<Self>) -> Stateful<Div> {
-        let indicator = if self.deep_scan { "[x]" } else { "[ ]" };
-        let (bg, border, text) = if self.deep_scan {
-            (
-                gpui::rgb(0xEDE9FE),
-                gpui::rgb(0x6D28D9),
-                gpui::rgb(0x4C1D95),
-            )
-        } else {
-            (
-                gpui::rgb(0xF3F4F6),
-                gpui::rgb(0x9CA3AF),
-                gpui::rgb(0x374151),
-            )
-        };
+    /// A checkbox row that calls `on_click` when clicked.
+    fn render_toggle<F>(
+        &self,
+        id: impl Into<ElementId>,
+        label: impl Into<SharedString>,
+        value: bool,
+        colors: &ToggleColors,
+        cx: &mut Context<Self>,
+        on_click: F,
+    ) -> Stateful<Div>
+    where
+        F: Fn(&mut Self, &mut Context<Self>) + 'static,
+    {
+        let indicator = if value { "[x]" } else { "[ ]" };
+        let [bg, border, text] = if value { colors.on } else { colors.off };
+        let (bg, border, text) = (gpui::rgb(bg), gpui::rgb(border), gpui::rgb(text));
 
         div()
-            .id("deep-scan-toggle")
+            .id(id)
             .flex()
             .gap_3()
             .items_center()
@@ -657,9 +1278,9 @@ impl DevstripView {
                     .py_1()
                     .child(indicator.to_string()),
             )
-            .child("Deep scan (--all)")
-            .on_click(cx.listener(|this, _event: &ClickEvent, _, cx| {
-                this.toggle_deep_scan(cx);
+            .child(label.into())
+            .on_click(cx.listener(move |this, _event: &ClickEvent, _, cx| {
+                on_click(this, cx);
             }))
     }
 
@@ -720,6 +1341,16 @@ impl DevstripView {
                 .child("This action cannot be undone."),
         );
 
+        if let Some(err) = self.size_cap_error() {
+            dialog = dialog.child(div().text_sm().text_color(gpui::rgb(0xB91C1C)).child(
+                if self.force_large {
+                    format!("{}. Press Proceed once more to remove it anyway.", err)
+                } else {
+                    format!("{}. Check the list before you proceed.", err)
+                },
+            ));
+        }
+
         let mut button_row = div().flex().gap_3();
         button_row = button_row.child(self.action_button("Proceed", true, cx, |this, cx| {
             this.confirm_cleanup_dialog(cx);
@@ -760,61 +1391,25 @@ impl DevstripView {
         }
 
         for category in &self.available_categories {
-            let selected = self.selected_categories.contains(category);
-            let indicator = if selected { "[x]" } else { "[ ]" };
-            let (bg, border, text) = if selected {
-                (
-                    gpui::rgb(0xEEF2FF),
-                    gpui::rgb(0x4338CA),
-                    gpui::rgb(0x312E81),
-                )
-            } else {
-                (
-                    gpui::rgb(0xF9FAFB),
-                    gpui::rgb(0xD1D5DB),
-                    gpui::rgb(0x374151),
-                )
-            };
-
-            let label = category.clone();
-            let toggle_value = category.clone();
             let element_id = SharedString::from(format!(
                 "category-{}",
-                label
+                category
                     .to_lowercase()
                     .chars()
                     .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
                     .collect::<String>()
             ));
-
-            block = block.child(
-                div()
-                    .id(element_id.clone())
-                    .flex()
-                    .gap_3()
-                    .items_center()
-                    .px_3()
-                    .py_2()
-                    .rounded_md()
-                    .border_1()
-                    .border_color(border)
-                    .bg(bg)
-                    .text_color(text)
-                    .cursor_pointer()
-                    .child(
-                        div()
-                            .border_1()
-                            .border_color(border)
-                            .rounded_sm()
-                            .px_2()
-                            .py_1()
-                            .child(indicator.to_string()),
-                    )
-                    .child(label.clone())
-                    .on_click(cx.listener(move |this, _event: &ClickEvent, _, cx| {
-                        this.toggle_category(&toggle_value, cx);
-                    })),
-            );
+            let toggle_value = category.clone();
+            block = block.child(self.render_toggle(
+                element_id,
+                category.clone(),
+                self.selected_categories.contains(category),
+                &CATEGORY_COLORS,
+                cx,
+                move |this, cx| {
+                    this.toggle_category(&toggle_value, cx);
+                },
+            ));
         }
 
         if self.selected_categories.is_empty() && self.category_filters_dirty {
@@ -829,8 +1424,27 @@ impl DevstripView {
         block
     }
 
-    fn candidate_row(index: usize, candidate: &Candidate) -> Div {
-        let (background_hex, accent_hex) = Self::size_palette(candidate.size_bytes);
+    fn candidate_row(index: usize, candidate: &Candidate, last_scan_size: Option<u64>) -> Div {
+        let (background_hex, accent_hex) =
+            Self::size_palette(last_scan_size.unwrap_or(candidate.size_bytes));
+        let size_text = match last_scan_size {
+            Some(size) if candidate.size_pending => format!(
+                "{} (last scan), measuring...",
+                Self::human_readable_size(size)
+            ),
+            Some(size) => format!("{} (last scan)", Self::human_readable_size(size)),
+            None if candidate.size_pending => "measuring...".to_string(),
+            None if candidate.size_incomplete => format!(
+                "{} (incomplete)",
+                Self::human_readable_size(candidate.size_bytes)
+            ),
+            None => Self::human_readable_size(candidate.size_bytes),
+        };
+        let size_hex = if last_scan_size.is_some() {
+            0x6B7280
+        } else {
+            accent_hex
+        };
 
         let mut row = div()
             .bg(gpui::rgb(background_hex))
@@ -856,8 +1470,8 @@ impl DevstripView {
             .child(
                 div()
                     .text_sm()
-                    .text_color(gpui::rgb(accent_hex))
-                    .child(Self::human_readable_size(candidate.size_bytes)),
+                    .text_color(gpui::rgb(size_hex))
+                    .child(size_text),
             );
 
         row = row.child(header);
@@ -876,6 +1490,27 @@ impl DevstripView {
                 .child(format!("Reason: {}", &candidate.reason)),
         );
 
+        if candidate.strategy != CleanupStrategy::Remove {
+            row = row.child(
+                div()
+                    .text_sm()
+                    .text_color(gpui::rgb(0x4B5563))
+                    .child(format!("Cleanup: {}", candidate.strategy.describe())),
+            );
+        }
+
+        if let Some(ide) = &candidate.recently_opened {
+            row = row.child(
+                div()
+                    .text_sm()
+                    .text_color(gpui::rgb(0x92400E))
+                    .child(format!(
+                        "Recently opened in {}; its caches will likely be rebuilt.",
+                        ide
+                    )),
+            );
+        }
+
         row.child(
             div()
                 .text_sm()
@@ -896,6 +1531,36 @@ impl DevstripView {
         }
     }
 
+    fn render_skipped_paths(skipped: &[SkippedPath]) -> Stateful<Div> {
+        let mut block = div()
+            .id("skipped-paths")
+            .flex()
+            .flex_col()
+            .gap_1()
+            .bg(gpui::rgb(0xFEF3C7))
+            .border_1()
+            .border_color(gpui::rgb(0xFCD34D))
+            .rounded_md()
+            .p_3()
+            .text_sm()
+            .text_color(gpui::rgb(0x78350F));
+
+        block = block.child(format!("{} path(s) could not be inspected:", skipped.len()));
+        for path in skipped.iter().take(MAX_SKIPPED_LISTED) {
+            block = block.child(format!("- {} ({})", path.path.display(), path.kind));
+        }
+        if skipped.len() > MAX_SKIPPED_LISTED {
+            block = block.child(format!(
+                "... and {} more",
+                skipped.len() - MAX_SKIPPED_LISTED
+            ));
+        }
+        if let Some(hint) = core::skipped_paths_hint(skipped) {
+            block = block.child(hint.to_string());
+        }
+        block
+    }
+
     fn render_roots(config: &ScanConfig) -> Stateful<Div> {
         let mut block = div()
             .id("last-scan-config")
@@ -993,15 +1658,41 @@ impl DevstripView {
 impl Render for DevstripView {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let can_scan = !self.scanning && !self.cleaning;
-        let can_clean = !self.scanning && !self.cleaning && !self.candidates.is_empty();
+        let can_clean = !self.scanning
+            && !self.cleaning
+            && !self.candidates.is_empty()
+            && (self.dry_run || !self.sizes_pending());
         let stop_enabled = self.scanning && !self.scan_cancel_requested();
+        let stop_cleanup_enabled = self.cleaning && !self.cleanup_cancel_requested();
 
         let scan_button = self.action_button("Scan", can_scan, cx, |this, cx| {
-            this.start_scan(cx);
+            this.start_scan(false, cx);
+        });
+        let can_rescan = can_scan && self.last_scan_config.is_some();
+        let rescan_button = self.action_button("Rescan", can_rescan, cx, |this, cx| {
+            this.start_scan(true, cx);
         });
 
-        let stop_button = self.action_button("Stop", stop_enabled, cx, |this, cx| {
-            this.stop_scan(cx);
+        let stop_button = self.action_button(
+            "Stop",
+            stop_enabled || stop_cleanup_enabled,
+            cx,
+            |this, cx| {
+                if this.cleaning {
+                    this.stop_cleanup(cx);
+                } else {
+                    this.stop_scan(cx);
+                }
+            },
+        );
+
+        let pause_label = if self.scan_paused() {
+            "Resume"
+        } else {
+            "Pause"
+        };
+        let pause_button = self.action_button(pause_label, stop_enabled, cx, |this, cx| {
+            this.toggle_scan_pause(cx);
         });
 
         let clean_button = self.action_button("Clean", can_clean, cx, |this, cx| {
@@ -1010,11 +1701,85 @@ impl Render for DevstripView {
 
         let mut buttons = div().flex().gap_3().flex_wrap();
         buttons = buttons.child(scan_button);
+        buttons = buttons.child(rescan_button);
+        buttons = buttons.child(pause_button);
         buttons = buttons.child(stop_button);
         buttons = buttons.child(clean_button);
 
-        let dry_run_control = self.render_dry_run_toggle(cx);
-        let deep_scan_control = self.render_deep_scan_toggle(cx);
+        let order_button = self.secondary_button(
+            &format!("Order: {}", self.cleanup_order.label()),
+            !self.cleaning,
+            cx,
+            |this, cx| {
+                this.cycle_cleanup_order(cx);
+            },
+        );
+
+        let symlinks_button = self.secondary_button(
+            &format!("Symlinks: {}", self.symlinks.label()),
+            !self.scanning,
+            cx,
+            |this, cx| {
+                this.cycle_symlink_policy(cx);
+            },
+        );
+
+        let dry_run_control = self.render_toggle(
+            "dry-run-toggle",
+            "Dry run (simulate cleanup)",
+            self.dry_run,
+            &DRY_RUN_COLORS,
+            cx,
+            Self::toggle_dry_run,
+        );
+        let trash_control = self.render_toggle(
+            "trash-toggle",
+            "Move to Trash (recoverable)",
+            self.use_trash,
+            &OPTION_COLORS,
+            cx,
+            Self::toggle_trash,
+        );
+        let deep_scan_control = self.render_toggle(
+            "deep-scan-toggle",
+            "Deep scan (--all)",
+            self.deep_scan,
+            &DEEP_SCAN_COLORS,
+            cx,
+            Self::toggle_deep_scan,
+        );
+        let same_filesystem_control = self.render_toggle(
+            "same-filesystem-toggle",
+            "Skip network and external volumes",
+            self.same_filesystem,
+            &OPTION_COLORS,
+            cx,
+            Self::toggle_same_filesystem,
+        );
+        let skip_active_repos_control = self.render_toggle(
+            "skip-active-repos-toggle",
+            "Skip active git repositories",
+            self.skip_active_repos,
+            &OPTION_COLORS,
+            cx,
+            Self::toggle_skip_active_repos,
+        );
+        let quick_scan_control = self.render_toggle(
+            "quick-scan-toggle",
+            "Quick scan (measure sizes afterwards)",
+            self.quick_scan,
+            &OPTION_COLORS,
+            cx,
+            Self::toggle_quick_scan,
+        );
+        let low_impact_control = self.render_toggle(
+            "low-impact-toggle",
+            "Low impact (background priority)",
+            self.low_impact,
+            &OPTION_COLORS,
+            cx,
+            Self::toggle_low_impact,
+        );
         let category_filters = self.render_category_filters(cx);
 
         let mut control_panel = div()
@@ -1037,9 +1802,30 @@ impl Render for DevstripView {
             "Scan for stale build outputs and caches, then selectively clean them up.".to_string(),
         ));
         control_panel = control_panel.child(self.render_project_link(cx));
+        if let Some(policy) = self.policy {
+            control_panel = control_panel.child(
+                div()
+                    .id("policy-banner")
+                    .bg(gpui::rgb(0xFEF3C7))
+                    .border_1()
+                    .border_color(gpui::rgb(0xFCD34D))
+                    .rounded_md()
+                    .p_3()
+                    .text_sm()
+                    .text_color(gpui::rgb(0x78350F))
+                    .child(policy.summary()),
+            );
+        }
         control_panel = control_panel.child(buttons);
+        control_panel = control_panel.child(order_button);
+        control_panel = control_panel.child(symlinks_button);
         control_panel = control_panel.child(dry_run_control);
+        control_panel = control_panel.child(trash_control);
         control_panel = control_panel.child(deep_scan_control);
+        control_panel = control_panel.child(same_filesystem_control);
+        control_panel = control_panel.child(skip_active_repos_control);
+        control_panel = control_panel.child(quick_scan_control);
+        control_panel = control_panel.child(low_impact_control);
         control_panel = control_panel.child(category_filters);
         if self.show_cleanup_confirm {
             control_panel = control_panel.child(self.render_cleanup_confirm(cx));
@@ -1089,6 +1875,10 @@ impl Render for DevstripView {
             results_panel = results_panel.child(Self::render_roots(config));
         }
 
+        if !self.skipped_paths.is_empty() {
+            results_panel = results_panel.child(Self::render_skipped_paths(&self.skipped_paths));
+        }
+
         let mut candidate_container = div().flex().flex_col().gap_3();
 
         {
@@ -1114,9 +1904,11 @@ impl Render for DevstripView {
                 scroll_area.child(div().text_sm().text_color(gpui::rgb(0x4B5563)).child(
                     "No scans yet. Choose Scan above to analyze your directories.".to_string(),
                 ));
-        } else if self.scanning {
+        } else if self.scanning && self.all_candidates.is_empty() {
             let message = if self.scan_cancel_requested() {
                 "Cancelling scan..."
+            } else if self.scan_paused() {
+                "Scan paused."
             } else {
                 "Scanning in progress..."
             };
@@ -1168,11 +1960,41 @@ impl Render for DevstripView {
             candidate_container = candidate_container.child(summary);
 
             let mut items = div().flex().flex_col().gap_3();
-            for (index, candidate) in self.candidates.iter().enumerate() {
-                items = items.child(Self::candidate_row(index, candidate));
+            for (index, candidate) in self.candidates.iter().take(self.visible_limit).enumerate() {
+                items = items.child(Self::candidate_row(
+                    index,
+                    candidate,
+                    self.last_scan_size(candidate),
+                ));
             }
 
             scroll_area = scroll_area.child(items);
+
+            let hidden = &self.candidates[visible_count.min(self.visible_limit)..];
+            if !hidden.is_empty() {
+                let next = hidden.len().min(RESULT_PAGE_SIZE);
+                let hidden_note = div()
+                    .text_sm()
+                    .text_color(gpui::rgb(0x4B5563))
+                    .child(format!(
+                        "{} more candidate(s) not shown, approx {}. They are still included in cleanup.",
+                        hidden.len(),
+                        Self::human_readable_size(core::scan_total_size(hidden))
+                    ));
+                let show_more =
+                    self.secondary_button(&format!("Show next {}", next), true, cx, |this, cx| {
+                        this.visible_limit = this.visible_limit.saturating_add(RESULT_PAGE_SIZE);
+                        cx.notify();
+                    });
+                scroll_area = scroll_area.child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap_2()
+                        .child(hidden_note)
+                        .child(show_more),
+                );
+            }
         }
 
         candidate_container = candidate_container.child(scroll_area);
@@ -1201,6 +2023,23 @@ impl Render for DevstripView {
     }
 }
 
+fn drain_events(rx: &mpsc::Receiver<DevstripEvent>) -> (Vec<DevstripEvent>, bool) {
+    let mut events = Vec::new();
+    loop {
+        match rx.try_recv() {
+            Ok(event) => {
+                let finished = matches!(event, DevstripEvent::ScanFinished { .. });
+                events.push(event);
+                if finished {
+                    return (events, true);
+                }
+            }
+            Err(mpsc::TryRecvError::Empty) => return (events, false),
+            Err(mpsc::TryRecvError::Disconnected) => return (events, true),
+        }
+    }
+}
+
 pub fn run() {
     Application::new().run(|cx: &mut App| {
         let bounds = Bounds::centered(None, size(px(960.0), px(640.0)), cx);
@@ -1,9 +1,17 @@
+use devstrip::core::sandbox;
+
 #[cfg(feature = "gui")]
 pub fn main() {
+    if sandbox::helper_requested() {
+        std::process::exit(sandbox::run_helper());
+    }
     devstrip::gui::run();
 }
 
 #[cfg(feature = "cli")]
 pub fn main() {
+    if sandbox::helper_requested() {
+        std::process::exit(sandbox::run_helper());
+    }
     devstrip::cli::run();
 }
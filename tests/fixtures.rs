@@ -0,0 +1,545 @@
+//! Replays the layouts in `tests/fixtures/*.fixture` and checks that a scan
+//! reports exactly the candidates each file expects.
+//!
+//! A fixture is a list of directives, one per line (`#` starts a comment):
+//!
+//! ```text
+//! scan   roots=home/Projects min-age-days=2 keep-latest-derived=1
+//! dir    home/Projects/app/node_modules/left-pad       age=40
+//! file   home/Projects/app/package.json  size=120      age=40
+//! link   home/Projects/app/vendor        target=shared/vendor
+//! expect home/Projects/app/node_modules  category=Project recent=VS%20Code
+//! reject home/Projects/app/src
+//! unmatched home/Projects/old-name
+//! ```
+//!
+//! Paths are relative to a scratch directory whose `home` folder stands in
+//! for the home directory. `age` is in days (default 30) and sets both the
+//! modification and access time of the entry itself; parent directories
+//! created implicitly get the default age.
+//! Files hold `size` filler bytes unless `text=...` gives their contents,
+//! or `hex=...` gives them as hex digits followed by `size` zero bytes;
+//! `%20` stands for a space in paths and values, and `%ROOT%` in `text`
+//! for the scratch directory. A `link` is a symbolic
+//! link to `target`, which is relative to the scratch directory.
+//! Every candidate inside the scratch directory must be matched by an
+//! `expect` line, and every `expect` must be reported. `recent` names the
+//! IDE that recently opened the candidate's project, if any, and `rule` the
+//! project rule the scan recorded for the pre-cleanup check (see
+//! `rule_name`). `unmatched`
+//! lists exactly the excludes the scan should report as never matching.
+//! When a fixture has `gone` or `survive` lines, the candidates are cleaned
+//! after the scan; each `gone` path must then be missing and each `survive`
+//! path still there.
+
+use devstrip::core::{
+    self, AgeOverride, Candidate, CleanupStrategy, DevstripEvent, Excludes, MatchedRule,
+    ScanConfig, ScanJob, SymlinkPolicy,
+};
+use std::collections::BTreeMap;
+use std::fs::{self, File, FileTimes};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_AGE_DAYS: u64 = 30;
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The home override is read from the environment, so fixtures run one at a
+/// time.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Default)]
+struct Fixture {
+    scan: Vec<(String, String)>,
+    entries: Vec<Entry>,
+    expected: BTreeMap<String, Expectation>,
+    rejected: Vec<String>,
+    unmatched: Vec<String>,
+    gone: Vec<String>,
+    survive: Vec<String>,
+}
+
+struct Entry {
+    path: String,
+    is_dir: bool,
+    size: usize,
+    text: Option<String>,
+    hex: Option<String>,
+    link: Option<String>,
+    age_days: u64,
+}
+
+#[derive(Default)]
+struct Expectation {
+    category: Option<String>,
+    strategy: Option<String>,
+    reason: Option<String>,
+    recent: Option<String>,
+    rule: Option<String>,
+}
+
+fn parse_fixture(name: &str, text: &str) -> Fixture {
+    let mut fixture = Fixture::default();
+    for (number, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let directive = words.next().unwrap_or_default();
+        let rest: Vec<&str> = words.collect();
+        let context = format!("{}.fixture:{}", name, number + 1);
+        let (path, options) = match directive {
+            "scan" => (None, rest.as_slice()),
+            _ => match rest.split_first() {
+                Some((path, options)) => (Some(path.to_string()), options),
+                None => panic!("{}: missing path", context),
+            },
+        };
+        let options: Vec<(String, String)> = options
+            .iter()
+            .map(|option| {
+                let (key, value) = option
+                    .split_once('=')
+                    .unwrap_or_else(|| panic!("{}: expected key=value, got {}", context, option));
+                (key.to_string(), value.replace("%20", " "))
+            })
+            .collect();
+        let option = |key: &str| {
+            options
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+        let path = path.map(|path| path.replace("%20", " "));
+        match directive {
+            "scan" => fixture.scan.extend(options.iter().cloned()),
+            "dir" | "file" | "link" => fixture.entries.push(Entry {
+                path: path.unwrap(),
+                is_dir: directive == "dir",
+                size: option("size").map_or(1024, |v| v.parse().expect("size")),
+                text: option("text"),
+                hex: option("hex"),
+                link: (directive == "link").then(|| option("target").expect("target")),
+                age_days: option("age").map_or(DEFAULT_AGE_DAYS, |v| v.parse().expect("age")),
+            }),
+            "expect" => {
+                fixture.expected.insert(
+                    path.unwrap(),
+                    Expectation {
+                        category: option("category"),
+                        strategy: option("strategy"),
+                        reason: option("reason"),
+                        recent: option("recent"),
+                        rule: option("rule"),
+                    },
+                );
+            }
+            "reject" => fixture.rejected.push(path.unwrap()),
+            "unmatched" => fixture.unmatched.push(path.unwrap()),
+            "gone" => fixture.gone.push(path.unwrap()),
+            "survive" => fixture.survive.push(path.unwrap()),
+            other => panic!("{}: unknown directive {}", context, other),
+        }
+    }
+    fixture
+}
+
+fn materialize(fixture: &Fixture, root: &Path) {
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("home")).unwrap();
+    let mut ages: BTreeMap<PathBuf, u64> = BTreeMap::new();
+    for entry in &fixture.entries {
+        let path = root.join(&entry.path);
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if !dir.starts_with(root) || dir == root {
+                break;
+            }
+            ages.entry(dir.to_path_buf()).or_insert(DEFAULT_AGE_DAYS);
+            parent = dir.parent();
+        }
+        if let Some(target) = &entry.link {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(root.join(target), &path).unwrap();
+            continue;
+        }
+        if entry.is_dir {
+            fs::create_dir_all(&path).unwrap();
+        } else {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let contents = match (&entry.text, &entry.hex) {
+                (Some(text), _) => text.replace("%ROOT%", &root.to_string_lossy()).into_bytes(),
+                (None, Some(hex)) => (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("hex"))
+                    .chain(std::iter::repeat_n(0, entry.size))
+                    .collect(),
+                (None, None) => vec![b'x'; entry.size],
+            };
+            fs::write(&path, contents).unwrap();
+        }
+        ages.insert(path, entry.age_days);
+    }
+    // Deepest first, so setting a child's time never bumps its parent.
+    let mut paths: Vec<(PathBuf, u64)> = ages.into_iter().collect();
+    paths.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+    let now = SystemTime::now();
+    for (path, age_days) in paths {
+        let time = now - DAY * age_days as u32;
+        File::open(&path)
+            .and_then(|file| file.set_times(FileTimes::new().set_accessed(time).set_modified(time)))
+            .unwrap_or_else(|err| panic!("set times on {}: {}", path.display(), err));
+    }
+}
+
+fn scan_config(fixture: &Fixture, root: &Path) -> ScanConfig {
+    let mut config = ScanConfig {
+        roots: vec![root.join("home/Projects")],
+        min_age_days: 2,
+        max_depth: 5,
+        keep_latest_derived: 1,
+        keep_latest_cache: 1,
+        exclude_paths: Excludes::default(),
+        exclude_globs: Vec::new(),
+        cargo_sweep: false,
+        custom_rules: Vec::new(),
+        extra_patterns: Vec::new(),
+        age_overrides: Vec::new(),
+        downloads_min_age_days: None,
+        baseline: None,
+        same_filesystem: false,
+        active_repo_days: None,
+        symlinks: SymlinkPolicy::Skip,
+        size_cache: false,
+        lazy_sizes: false,
+        low_priority: false,
+        incremental: false,
+        profile: false,
+        keep_nested: false,
+    };
+    for (key, value) in &fixture.scan {
+        match key.as_str() {
+            "roots" => config.roots = value.split(',').map(|r| root.join(r)).collect(),
+            "min-age-days" => config.min_age_days = value.parse().unwrap(),
+            "max-depth" => config.max_depth = value.parse().unwrap(),
+            "keep-latest-derived" => config.keep_latest_derived = value.parse().unwrap(),
+            "keep-latest-cache" => config.keep_latest_cache = value.parse().unwrap(),
+            "cargo-sweep" => config.cargo_sweep = value.parse().unwrap(),
+            "downloads-min-age-days" => config.downloads_min_age_days = value.parse().ok(),
+            "same-filesystem" => config.same_filesystem = value.parse().unwrap(),
+            "symlinks" => config.symlinks = value.parse().unwrap(),
+            "exclude" => config.exclude_paths = value.split(',').map(|p| root.join(p)).collect(),
+            "exclude-globs" => config.exclude_globs = value.split(',').map(String::from).collect(),
+            "patterns" => config.extra_patterns = value.split(',').map(String::from).collect(),
+            "ages" => {
+                config.age_overrides = value
+                    .split(',')
+                    .map(|age| {
+                        let (target, days) = age.split_once('=').expect("ages are TARGET=DAYS");
+                        AgeOverride {
+                            target: target.to_string(),
+                            days: days.parse().expect("age days"),
+                        }
+                    })
+                    .collect()
+            }
+            other => panic!("unknown scan option {}", other),
+        }
+    }
+    config
+}
+
+fn strategy_name(strategy: &CleanupStrategy) -> &'static str {
+    match strategy {
+        CleanupStrategy::Remove => "remove",
+        CleanupStrategy::Command { .. } => "command",
+        CleanupStrategy::SweepCargoTarget { .. } => "sweep",
+        CleanupStrategy::PruneFilesOlderThan { .. } => "prune",
+        CleanupStrategy::Compress => "compress",
+        CleanupStrategy::TruncateHistory { .. } => "truncate",
+        CleanupStrategy::EmptyDirectory => "empty",
+        CleanupStrategy::RemoveGemVersion => "gem",
+        CleanupStrategy::RemoveLink => "unlink",
+    }
+}
+
+/// `project:node_modules`, `project:.yarn/cache`, `build:target,Cargo.toml`,
+/// `dotnet:bin,App.csproj`, or `none`.
+fn rule_name(rule: Option<&MatchedRule>) -> String {
+    match rule {
+        Some(MatchedRule::Project { name, parent }) => match parent {
+            Some(parent) => format!("project:{}/{}", parent, name),
+            None => format!("project:{}", name),
+        },
+        Some(MatchedRule::BuildDir { name, manifest }) => format!("build:{},{}", name, manifest),
+        Some(MatchedRule::Dotnet { name, project }) => format!("dotnet:{},{}", name, project),
+        None => "none".to_string(),
+    }
+}
+
+fn relative_candidates(candidates: Vec<Candidate>, root: &Path) -> BTreeMap<String, Candidate> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let relative = candidate.path.strip_prefix(root).ok()?.to_path_buf();
+            Some((relative.to_string_lossy().into_owned(), candidate))
+        })
+        .collect()
+}
+
+fn run_fixture(name: &str) {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(format!("{}.fixture", name));
+    let text = fs::read_to_string(&source).unwrap();
+    let fixture = parse_fixture(name, &text);
+
+    let root =
+        std::env::temp_dir().join(format!("devstrip-fixture-{}-{}", std::process::id(), name));
+    materialize(&fixture, &root);
+    let root = fs::canonicalize(&root).unwrap();
+    std::env::set_var("DEVSTRIP_HOME_DIR", root.join("home"));
+    // Keep external CLIs (docker, podman, ...) out of the picture.
+    std::env::set_var("PATH", "");
+
+    let config = scan_config(&fixture, &root);
+    let mut unmatched = Vec::new();
+    let serial = core::scan_with_events(&config, |event| {
+        if let DevstripEvent::UnmatchedExclude { path } = event {
+            unmatched.push(
+                path.strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+        }
+    });
+    let parallel = relative_candidates(ScanJob::start(config).wait(), &root);
+    let mut problems = Vec::new();
+    if !fixture.gone.is_empty() || !fixture.survive.is_empty() {
+        let cleaned: Vec<Arc<Candidate>> = serial.iter().cloned().map(Arc::new).collect();
+        for result in core::cleanup(&cleaned, false) {
+            if let Some(error) = &result.error {
+                problems.push(format!(
+                    "cleaning {} failed: {}",
+                    result.candidate.display_name(),
+                    error
+                ));
+            }
+        }
+        for path in &fixture.gone {
+            if fs::symlink_metadata(root.join(path)).is_ok() {
+                problems.push(format!("{} is still there after cleanup", path));
+            }
+        }
+        for path in &fixture.survive {
+            if fs::symlink_metadata(root.join(path)).is_err() {
+                problems.push(format!("{} did not survive cleanup", path));
+            }
+        }
+    }
+    let serial = relative_candidates(serial, &root);
+    let _ = fs::remove_dir_all(&root);
+
+    for (path, candidate) in &serial {
+        match fixture.expected.get(path) {
+            None => problems.push(format!(
+                "unexpected candidate {} ({}: {})",
+                path, candidate.category, candidate.reason
+            )),
+            Some(expected) => {
+                if let Some(category) = &expected.category {
+                    if *category != candidate.category {
+                        problems.push(format!(
+                            "{}: category {} (expected {})",
+                            path, candidate.category, category
+                        ));
+                    }
+                }
+                if let Some(strategy) = &expected.strategy {
+                    let actual = strategy_name(&candidate.strategy);
+                    if strategy != actual {
+                        problems.push(format!(
+                            "{}: strategy {} (expected {})",
+                            path, actual, strategy
+                        ));
+                    }
+                }
+                if expected.recent != candidate.recently_opened {
+                    problems.push(format!(
+                        "{}: recently opened in {:?} (expected {:?})",
+                        path, candidate.recently_opened, expected.recent
+                    ));
+                }
+                if let Some(rule) = &expected.rule {
+                    let actual = rule_name(candidate.matched_rule.as_ref());
+                    if *rule != actual {
+                        problems.push(format!("{}: rule {} (expected {})", path, actual, rule));
+                    }
+                }
+                if let Some(reason) = &expected.reason {
+                    if !candidate.reason.contains(reason.as_str()) {
+                        problems.push(format!(
+                            "{}: reason {:?} does not mention {:?}",
+                            path, candidate.reason, reason
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    for path in fixture.expected.keys() {
+        if !serial.contains_key(path) {
+            problems.push(format!("missing candidate {}", path));
+        }
+    }
+    for path in &fixture.rejected {
+        if serial.contains_key(path) {
+            problems.push(format!("rejected path {} was reported", path));
+        }
+    }
+    if unmatched != fixture.unmatched {
+        problems.push(format!(
+            "unmatched excludes {:?} (expected {:?})",
+            unmatched, fixture.unmatched
+        ));
+    }
+    let serial_paths: Vec<&String> = serial.keys().collect();
+    let parallel_paths: Vec<&String> = parallel.keys().collect();
+    if serial_paths != parallel_paths {
+        problems.push(format!(
+            "parallel scan differs: {:?} vs {:?}",
+            parallel_paths, serial_paths
+        ));
+    }
+    assert!(
+        problems.is_empty(),
+        "{}.fixture:\n  {}",
+        name,
+        problems.join("\n  ")
+    );
+}
+
+#[test]
+fn npm() {
+    run_fixture("npm");
+}
+
+#[test]
+fn cargo() {
+    run_fixture("cargo");
+}
+
+#[test]
+fn cargo_sweep() {
+    run_fixture("sweep");
+}
+
+#[test]
+fn gradle() {
+    run_fixture("gradle");
+}
+
+#[test]
+fn xcode() {
+    run_fixture("xcode");
+}
+
+#[test]
+fn build_dir_manifests() {
+    run_fixture("manifests");
+}
+
+#[test]
+fn shell() {
+    run_fixture("shell");
+}
+
+#[test]
+fn devstripignore() {
+    run_fixture("devstripignore");
+}
+
+#[test]
+fn devstrip_toml() {
+    run_fixture("devstriptoml");
+}
+
+#[test]
+fn extra_patterns() {
+    run_fixture("patterns");
+}
+
+#[test]
+fn exclude_globs() {
+    run_fixture("excludeglobs");
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks() {
+    run_fixture("symlinks");
+}
+
+#[cfg(unix)]
+#[test]
+fn links_inside_removed_trees() {
+    run_fixture("removal");
+}
+
+#[test]
+fn unicode_paths() {
+    run_fixture("unicode");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linux() {
+    run_fixture("linux");
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn jetbrains_versions() {
+    run_fixture("jetbrains");
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn jetbrains_installed_versions() {
+    run_fixture("jetbrains-macos");
+}
+
+#[test]
+fn ide_workspaces() {
+    run_fixture("ide");
+}
+
+#[test]
+fn nested_roots() {
+    run_fixture("roots");
+}
+
+#[test]
+fn age_overrides() {
+    run_fixture("ages");
+}
+
+#[test]
+fn superseded_gems() {
+    run_fixture("gems");
+}
+
+#[test]
+fn core_dumps() {
+    run_fixture("coredumps");
+}
+
+#[test]
+fn vscode_extensions() {
+    run_fixture("vscode");
+}
@@ -0,0 +1,205 @@
+//! Exercises the sandboxed deletion helper and how a cleanup talks to it.
+//!
+//! A sandboxed cleanup starts the running executable again with
+//! `--devstrip-delete-helper`, so this test runs without the libtest
+//! harness: started with that flag, it acts as the helper. With
+//! `DEVSTRIP_TEST_HELPER_EXIT_AFTER=N` in its environment it answers `N`
+//! requests and then exits, the way a helper that crashes partway through
+//! would.
+
+use devstrip::core::{self, sandbox, Candidate, CleanupOptions, CleanupStrategy};
+use std::env;
+use std::fs;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+const EXIT_AFTER_ENV: &str = "DEVSTRIP_TEST_HELPER_EXIT_AFTER";
+
+type Test = fn(&Path);
+
+fn main() {
+    if sandbox::helper_requested() {
+        process::exit(run_helper());
+    }
+    let tests: &[(&str, Test)] = &[
+        ("nul_framed_requests", nul_framed_requests),
+        ("refused_replies", refused_replies),
+        ("helper_exits_partway", helper_exits_partway),
+        ("in_place_strategies_refused", in_place_strategies_refused),
+    ];
+    for (name, test) in tests {
+        let scratch = env::temp_dir().join(format!("devstrip-sandbox-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&scratch);
+        fs::create_dir_all(scratch.join("home")).unwrap();
+        let scratch = fs::canonicalize(&scratch).unwrap();
+        env::set_var("DEVSTRIP_HOME_DIR", scratch.join("home"));
+        test(&scratch);
+        let _ = fs::remove_dir_all(&scratch);
+        println!("test {} ... ok", name);
+    }
+}
+
+fn run_helper() -> i32 {
+    let limit = match env::var(EXIT_AFTER_ENV).ok().and_then(|n| n.parse().ok()) {
+        Some(limit) => limit,
+        None => return sandbox::run_helper(),
+    };
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    for _ in 0..limit {
+        let mut request = Vec::new();
+        if input.read_until(0, &mut request).unwrap_or(0) == 0 {
+            return 0;
+        }
+        request.pop();
+        let path = PathBuf::from(String::from_utf8(request).unwrap());
+        fs::remove_dir_all(&path).unwrap();
+        writeln!(output, "ok").unwrap();
+        output.flush().unwrap();
+    }
+    1
+}
+
+/// Sends each path to a fresh helper as one NUL-terminated request and
+/// returns its reply lines.
+fn ask_helper(paths: &[&Path]) -> Vec<String> {
+    let mut child = Command::new(env::current_exe().unwrap())
+        .arg(sandbox::HELPER_FLAG)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for path in paths {
+        stdin
+            .write_all(path.to_str().unwrap().as_bytes())
+            .and_then(|_| stdin.write_all(&[0]))
+            .unwrap();
+    }
+    drop(stdin);
+    let mut replies = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut replies)
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+    replies.lines().map(String::from).collect()
+}
+
+fn candidate(path: PathBuf, strategy: CleanupStrategy) -> Arc<Candidate> {
+    Arc::new(Candidate {
+        path,
+        size_bytes: 4096,
+        logical_bytes: 4096,
+        category: "Test".to_string(),
+        reason: "test target".to_string(),
+        last_used: None,
+        strategy,
+        deep_only: false,
+        recently_opened: None,
+        scanned: None,
+        size_pending: false,
+        size_incomplete: false,
+        matched_rule: None,
+    })
+}
+
+fn target(scratch: &Path, name: &str) -> PathBuf {
+    let dir = scratch.join("work").join(name);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("data.bin"), vec![b'x'; 4096]).unwrap();
+    dir
+}
+
+fn sandboxed() -> CleanupOptions {
+    CleanupOptions {
+        sandboxed: true,
+        workers: 1,
+        ..CleanupOptions::default()
+    }
+}
+
+/// A path may hold any byte but NUL, newlines included.
+fn nul_framed_requests(scratch: &Path) {
+    let plain = target(scratch, "plain");
+    let newline = target(scratch, "with\nnewline");
+    let replies = ask_helper(&[&plain, &newline]);
+    assert_eq!(replies, ["ok", "ok"]);
+    assert!(!plain.exists());
+    assert!(!newline.exists());
+}
+
+/// The helper checks every path itself, whatever the scanner sent.
+fn refused_replies(scratch: &Path) {
+    let home = scratch.join("home");
+    fs::create_dir_all(home.join("Documents")).unwrap();
+    let replies = ask_helper(&[
+        Path::new("relative/target"),
+        Path::new("/usr/devstrip-no-such-dir"),
+        &home,
+        &home.join("Documents"),
+    ]);
+    assert_eq!(replies.len(), 4, "{:?}", replies);
+    for reply in &replies {
+        assert!(reply.starts_with("err refused: "), "{:?}", replies);
+    }
+    assert!(home.join("Documents").is_dir());
+}
+
+/// Once the helper is gone, the remaining targets fail rather than being
+/// deleted some other way.
+fn helper_exits_partway(scratch: &Path) {
+    let targets: Vec<PathBuf> = ["first", "second", "third"]
+        .iter()
+        .map(|name| target(scratch, name))
+        .collect();
+    let candidates: Vec<Arc<Candidate>> = targets
+        .iter()
+        .map(|path| candidate(path.clone(), CleanupStrategy::Remove))
+        .collect();
+    env::set_var(EXIT_AFTER_ENV, "1");
+    let results = core::cleanup_with_events(&candidates, &sandboxed(), |_| {});
+    env::remove_var(EXIT_AFTER_ENV);
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0].success, "{:?}", results[0].error);
+    assert!(!targets[0].exists());
+    for (result, path) in results.iter().zip(&targets).skip(1) {
+        let error = result.error.as_ref().map(ToString::to_string);
+        let error = error.unwrap_or_default();
+        assert!(!result.success && error.contains("helper"), "{}", error);
+        assert!(path.join("data.bin").is_file());
+    }
+}
+
+/// Pruning or rewriting inside a target would happen in this process, so a
+/// sandboxed cleanup refuses it.
+fn in_place_strategies_refused(scratch: &Path) {
+    let logs = target(scratch, "logs");
+    let removed = target(scratch, "removed");
+    let cutoff = SystemTime::now() + Duration::from_secs(60);
+    let candidates = [
+        candidate(
+            logs.clone(),
+            CleanupStrategy::PruneFilesOlderThan { cutoff },
+        ),
+        candidate(removed.clone(), CleanupStrategy::Remove),
+    ];
+    let results = core::cleanup_with_events(&candidates, &sandboxed(), |_| {});
+
+    let error = results[0].error.as_ref().map(ToString::to_string);
+    let error = error.unwrap_or_default();
+    assert!(
+        error.starts_with("refused: sandboxed deletion only removes whole paths"),
+        "{}",
+        error
+    );
+    assert!(logs.join("data.bin").is_file());
+    assert!(results[1].success, "{:?}", results[1].error);
+    assert!(!removed.exists());
+}